@@ -30,6 +30,13 @@ impl Pool {
     }
 }
 
+/// Converts `input` between formats such as `json`, `ion` and `ion_binary`.
+#[wasm_bindgen]
+pub fn convert(input: &[u8], from: &str, to: &str) -> Option<Vec<u8>> {
+    let data = piqel::engine::loads_bytes(input, from).ok()?;
+    piqel::engine::dumps_bytes(data, to).ok()
+}
+
 #[wasm_bindgen]
 pub fn fact(n: u32) -> u32 {
     if n <= 1 {
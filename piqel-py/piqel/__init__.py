@@ -5,15 +5,19 @@ from .piqel import __version__
 from . import piqel
 
 
-LangType = Literal["json", "yaml", "toml", "xml", "csv"]
+LangType = Literal["json", "yaml", "toml", "xml", "ion", "csv"]
+BinaryLangType = Literal["ion_binary"]
 
 
 def load(data):
     return DataLake(data)
 
 
-def loads(s: str, from_type: LangType = "json"):
-    data = piqel.loads(s, from_type)
+def loads(s: Union[str, bytes], from_type: Union[LangType, BinaryLangType] = "json"):
+    if isinstance(s, bytes):
+        data = piqel.loads_bytes(s, from_type)
+    else:
+        data = piqel.loads(s, from_type)
     return DataLake(data)
 
 
@@ -33,6 +37,9 @@ class DataLake:
     def to(self, to_type: LangType = "json") -> str:
         return piqel.dumps(self.data, to_type)
 
+    def to_bytes(self, to_type: BinaryLangType = "ion_binary") -> bytes:
+        return piqel.dumps_bytes(self.data, to_type)
+
     def to_dict(self) -> dict:
         return self.data
 
@@ -1,3 +1,4 @@
+use pyo3::exceptions::PyValueError;
 use pyo3::prelude::*;
 use pyo3::types::*;
 use pythonize::{depythonize, pythonize};
@@ -20,6 +21,20 @@ fn piqel(py: Python, m: &PyModule) -> PyResult<()> {
         Ok(output)
     }
 
+    #[pyfn(m, "loads_bytes")]
+    fn loads_bytes(py: Python, input: &[u8], from: &str) -> PyResult<Py<PyAny>> {
+        let data = piqel::engine::loads_bytes(input, from).map_err(value_error)?;
+        let obj = pythonize(py, &data).map_err(value_error)?;
+        Ok(obj)
+    }
+
+    #[pyfn(m, "dumps_bytes")]
+    fn dumps_bytes(py: Python, obj: Py<PyAny>, to: &str) -> PyResult<Py<PyBytes>> {
+        let data = depythonize(obj.as_ref(py)).map_err(value_error)?;
+        let output = piqel::engine::dumps_bytes(data, to).map_err(value_error)?;
+        Ok(PyBytes::new(py, &output).into())
+    }
+
     #[pyfn(m, "query_evaluate")]
     fn query_evaluate(py: Python, obj: Py<PyAny>, q: &str) -> PyResult<Py<PyAny>> {
         let data = depythonize(obj.as_ref(py)).unwrap();
@@ -30,3 +45,8 @@ fn piqel(py: Python, m: &PyModule) -> PyResult<()> {
 
     Ok(())
 }
+
+// Raises an error of the library, or of converting a value to or from Python, as a ValueError.
+fn value_error(err: impl std::fmt::Display) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
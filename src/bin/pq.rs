@@ -1,24 +1,83 @@
 use std::convert::TryFrom;
-use std::io::{self, Read};
-use std::path::PathBuf;
+use std::ffi::OsStr;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
+use indexmap::IndexMap as Map;
 use structopt::StructOpt;
 
+use piqel::detect;
 use piqel::lang::{Lang, LangType};
 use piqel::parser;
-use piqel::planner::evaluate;
-use piqel::sql::Sql;
+use piqel::planner::{evaluate_rows_in, evaluate_with, explain, ExecOptions};
+use piqel::schema::{self, PqlType, Schema};
+use piqel::sql::{EvalMode, Sql, Statement};
+use piqel::value::csv_value::CsvOptions;
+use piqel::value::sqlite_value;
+use piqel::value::toml_value::{NullPolicy, TomlOptions};
+use piqel::value::{jsonl_value, PqlValue};
 
-fn read_from_stdin() -> anyhow::Result<String> {
-    let mut buf = String::new();
+fn read_from_stdin() -> anyhow::Result<Vec<u8>> {
+    let mut buf = Vec::new();
     let stdin = io::stdin();
     let mut handle = stdin.lock();
-    handle.read_to_string(&mut buf)?;
+    handle.read_to_end(&mut buf)?;
 
     Ok(buf)
 }
 
+// JSON Lines in and out is streamed: each line is queried and written as soon as it is read.
+fn stream_jsonl(
+    file_or_stdin: Option<PathBuf>,
+    sql: Option<Sql>,
+    mode: EvalMode,
+) -> anyhow::Result<()> {
+    let reader: Box<dyn BufRead> = if let Some(file) = file_or_stdin {
+        Box::new(BufReader::new(std::fs::File::open(file)?))
+    } else {
+        Box::new(BufReader::new(io::stdin()))
+    };
+    let mut rows = jsonl_value::rows(reader);
+    let stdout = io::stdout();
+    let mut handle = stdout.lock();
+    let mut emit = |value: PqlValue| -> anyhow::Result<()> {
+        writeln!(handle, "{}", jsonl_value::to_line(&value)?)?;
+        handle.flush()?;
+        Ok(())
+    };
+
+    if let Some(sql) = sql {
+        evaluate_rows_in(sql, rows, mode, emit)
+    } else {
+        rows.try_for_each(|row| row.and_then(&mut emit))
+    }
+}
+
+// The tables of a database are queried by name alongside the source: beside the fields of a
+// tuple, or beside the source itself, named `name`.
+fn with_tables(data: PqlValue, name: &str, tables: PqlValue) -> anyhow::Result<PqlValue> {
+    let mut fields = match data {
+        PqlValue::Object(map) => map,
+        data => {
+            let mut map = Map::new();
+            map.insert(name.to_owned(), data);
+            map
+        }
+    };
+    if let PqlValue::Object(tables) = tables {
+        for (k, v) in tables {
+            anyhow::ensure!(
+                !fields.contains_key(&k),
+                "the source and the database both have a collection named {}",
+                k
+            );
+            fields.insert(k, v);
+        }
+    }
+    Ok(PqlValue::Object(fields))
+}
+
 #[derive(StructOpt, Debug)]
 struct Opt {
     /// source text: file or standard input
@@ -29,12 +88,12 @@ struct Opt {
     #[structopt(short, long)]
     query: Option<String>,
 
-    /// target config file
-    #[structopt(short, long, possible_values(&["csv", "json", "toml", "yaml", "xml"]))]
+    /// format of the source. auto, the default, goes by the file extension, else by the content
+    #[structopt(short, long, possible_values(&["auto", "csv", "tsv", "json", "jsonl", "toml", "yaml", "xml", "ion", "ion_binary", "partiql", "msgpack", "cbor", "parquet", "arrow"]))]
     from: Option<String>,
 
-    /// target config file
-    #[structopt(short, long, possible_values(&["csv", "json", "toml", "yaml", "xml"]))]
+    /// format of the output; markdown, html and table render the rows as a table
+    #[structopt(short, long, possible_values(&["csv", "tsv", "json", "jsonl", "toml", "yaml", "xml", "ion", "ion_binary", "partiql", "msgpack", "cbor", "parquet", "arrow", "markdown", "html", "table"]))]
     to: Option<String>,
 
     /// sort keys of objects on output. it on works when --to option is json, currently
@@ -44,6 +103,70 @@ struct Opt {
     /// compact instead of pretty-printed output, only when outputting in JSON
     #[structopt(short, long)]
     compact: bool,
+
+    /// read non-integer numbers in JSON input as exact decimals instead of floats
+    #[structopt(long)]
+    decimal: bool,
+
+    /// field delimiter of CSV and TSV. defaults to ',' for CSV and a tab for TSV
+    #[structopt(short, long)]
+    delimiter: Option<char>,
+
+    /// quote character of CSV and TSV
+    #[structopt(long, default_value = "\"")]
+    quote: char,
+
+    /// CSV and TSV have no header row; columns are named _1, _2, ...
+    #[structopt(long)]
+    no_header: bool,
+
+    /// read every CSV and TSV field as a string instead of inferring numbers, booleans and nulls
+    #[structopt(long)]
+    no_infer: bool,
+
+    /// how to write nulls to TOML, which has no null
+    #[structopt(long, default_value = "omit", possible_values(&["omit", "empty", "error"]))]
+    toml_nulls: NullPolicy,
+
+    /// write a top-level value that is not a table, such as the rows of a query, under this key in TOML
+    #[structopt(long)]
+    toml_root: Option<String>,
+
+    /// SQLite database whose tables are queried by name. the source, if any, is read from a file, and named after it unless it is a tuple
+    #[structopt(long)]
+    db: Option<PathBuf>,
+
+    /// report on standard error which format the source was read as
+    #[structopt(short, long)]
+    verbose: bool,
+
+    /// print how the query is parsed, planned and optimized instead of running it, as EXPLAIN does
+    #[structopt(long)]
+    explain: bool,
+
+    /// number of threads the rows of the source are queried on, all of the cores with 0
+    #[structopt(short, long, default_value = "1")]
+    jobs: usize,
+
+    /// rows queried on several threads may come out of order, unless the query has ORDER BY
+    #[structopt(long)]
+    unordered: bool,
+
+    /// stop with an error at a type error, such as 'abc' + 1, instead of making it MISSING
+    #[structopt(long)]
+    strict: bool,
+
+    /// check the query against the shape of the source instead of running it: print the type of each field of its rows, paths that are never there and type errors
+    #[structopt(long)]
+    check: bool,
+
+    /// print the shape of the source, or of the result of the query, instead of the data
+    #[structopt(long)]
+    infer_schema: bool,
+
+    /// how --infer-schema prints the shape: partiql, the default, as a PartiQL type, json-schema as a JSON Schema, or stats as the types and counts of values at each path, in the output format
+    #[structopt(long, value_name = "format", default_value = "partiql", possible_values(&["partiql", "json-schema", "stats"]))]
+    schema_format: String,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -54,19 +177,130 @@ fn main() -> anyhow::Result<()> {
         to,
         sort_keys,
         compact,
+        decimal,
+        delimiter,
+        quote,
+        no_header,
+        no_infer,
+        toml_nulls,
+        toml_root,
+        db,
+        verbose,
+        explain: explain_only,
+        jobs,
+        unordered,
+        strict,
+        check,
+        infer_schema,
+        schema_format,
     } = Opt::from_args();
+    let mode = if strict {
+        EvalMode::Strict
+    } else {
+        EvalMode::Permissive
+    };
+
+    let sql = match query.as_deref().map(Statement::from_str).transpose()? {
+        Some(Statement::Explain(sql)) => {
+            println!("{}", explain(&sql));
+            return Ok(());
+        }
+        Some(Statement::Query(sql)) if explain_only => {
+            println!("{}", explain(&sql));
+            return Ok(());
+        }
+        Some(Statement::Query(sql)) => Some(sql),
+        None if explain_only => anyhow::bail!("--explain needs a query to explain"),
+        None if check => anyhow::bail!("--check needs a query to check"),
+        None => None,
+    };
+
+    let single_byte = |c: char| {
+        u8::try_from(c).map_err(|_| anyhow::anyhow!("'{}' is not a single byte character", c))
+    };
+    let csv = CsvOptions {
+        delimiter: delimiter.map(single_byte).transpose()?,
+        quote: single_byte(quote)?,
+        has_headers: !no_header,
+        infer_types: !no_infer,
+    };
+
+    let from = match from.as_deref() {
+        None | Some("auto") => {
+            let lang_type = file_or_stdin.as_deref().and_then(detect::from_extension);
+            if let (true, Some(lang_type)) = (verbose, &lang_type) {
+                eprintln!("pq: reading the source as {} by its file extension", lang_type);
+            }
+            lang_type
+        }
+        Some(lang_type) => Some(LangType::from_str(lang_type)?),
+    };
+
+    let is_jsonl_to_jsonl =
+        from == Some(LangType::Jsonl) && (to.is_none() || to.as_deref() == Some("jsonl"));
+    if is_jsonl_to_jsonl && db.is_none() && !check && !infer_schema {
+        return stream_jsonl(file_or_stdin, sql, mode);
+    }
+
     let _ = {
-        let input = if let Some(file) = file_or_stdin {
-            std::fs::read_to_string(file)?
+        // With a database, the source is only read from a file.
+        let no_source = file_or_stdin.is_none() && db.is_some();
+        let input = if let Some(file) = &file_or_stdin {
+            std::fs::read(file)?
+        } else if no_source {
+            Vec::default()
         } else {
             read_from_stdin()?
         };
 
-        let mut lang = if let Some(s_lang_type) = from {
-            let lang_type = LangType::from_str(&s_lang_type)?;
-            Lang::from_as(&input, lang_type)?
+        let read_as = |lang_type: LangType| {
+            if decimal && lang_type == LangType::Json {
+                Lang::from_as_json_decimal(std::str::from_utf8(&input)?)
+            } else if lang_type == LangType::Csv {
+                Lang::from_as_csv(std::str::from_utf8(&input)?, csv.to_owned())
+            } else if lang_type == LangType::Tsv {
+                Lang::from_as_tsv(std::str::from_utf8(&input)?, csv.to_owned())
+            } else {
+                // Columnar formats only read the columns the query uses.
+                let columns = sql.as_ref().and_then(Sql::source_columns);
+                Lang::from_bytes_as_columns(&input, lang_type, columns.as_ref())
+            }
+        };
+        let mut lang = if no_source {
+            Lang {
+                data: PqlValue::Object(Map::new()),
+                ..Lang::default()
+            }
+        } else if let Some(lang_type) = from {
+            read_as(lang_type)?
         } else {
-            Lang::from_str(&input)?
+            let detection = detect::detect(&input)?;
+            if verbose {
+                eprintln!(
+                    "pq: detected the source as {} with {} confidence",
+                    detection.lang.from, detection.confidence
+                );
+            }
+            match detection.lang.from {
+                // Read again with the options that apply to these formats.
+                LangType::Json if decimal => read_as(LangType::Json)?,
+                LangType::Csv | LangType::Tsv => read_as(detection.lang.from)?,
+                _ => detection.lang,
+            }
+        };
+        lang.csv = csv;
+        if let Some(db) = &db {
+            let name = file_or_stdin
+                .as_deref()
+                .and_then(Path::file_stem)
+                .and_then(OsStr::to_str)
+                .unwrap_or_default();
+            let tables = sqlite_value::from_path(db)?;
+            lang.data = with_tables(std::mem::take(&mut lang.data), name, tables)?;
+        }
+        lang.toml = TomlOptions {
+            nulls: toml_nulls,
+            root_key: toml_root,
         };
 
         if let Some(t) = to {
@@ -76,17 +310,50 @@ fn main() -> anyhow::Result<()> {
             }
         }
 
-        if let Some(q) = query {
-            let sql = Sql::from_str(&q)?;
-            let result = evaluate(sql, lang.data);
-            lang.data = result;
+        if let (Some(sql), true) = (&sql, check) {
+            let report = schema::check(sql, &PqlType::of(&lang.data));
+            println!("{}", report);
+            if report.has_errors() {
+                anyhow::bail!("the query has type errors");
+            }
+            return Ok(());
+        }
+
+        if let Some(sql) = sql {
+            let options = ExecOptions {
+                threads: jobs,
+                preserve_order: !unordered,
+                mode,
+                ..ExecOptions::default()
+            };
+            let result = evaluate_with(sql, std::mem::take(&mut lang.data), &options)?;
+            lang.set_result(result);
+        }
+
+        if infer_schema {
+            let schema = Schema::infer(&lang.data);
+            match schema_format.as_str() {
+                "json-schema" => {
+                    let json = schema.to_type().to_json_schema();
+                    match compact {
+                        true => println!("{}", serde_json::to_string(&json)?),
+                        false => println!("{}", serde_json::to_string_pretty(&json)?),
+                    }
+                    return Ok(());
+                }
+                "stats" => lang.set_result(schema.stats()),
+                _ => {
+                    println!("{:#}", schema.to_type());
+                    return Ok(());
+                }
+            }
         }
 
         if lang.to == LangType::Json && sort_keys {
             lang.sort_keys();
         }
 
-        lang.print(compact);
+        lang.print(compact)?;
     };
 
     Ok(())
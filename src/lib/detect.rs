@@ -0,0 +1,291 @@
+//! Guessing the format of an input from its file extension or its content.
+//!
+//! `detect` tries every format in turn and keeps the most confident parse. A parse that is
+//! merely possible, such as a plain string read as a YAML scalar, is not taken as a match.
+
+use std::path::Path;
+
+use parse_display::Display;
+
+use crate::lang::{Lang, LangType};
+use crate::value::csv_value::CsvOptions;
+use crate::value::PqlValue;
+
+/// How strongly the content of an input points to a format.
+#[derive(Display, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[display(style = "snake_case")]
+pub enum Confidence {
+    /// Parses as a collection in a lenient format such as YAML, Ion text or CSV.
+    Medium,
+    /// Parses in a strict format such as JSON, TOML or XML.
+    High,
+    /// Starts with the magic bytes or version marker of a format.
+    Certain,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    pub lang: Lang,
+    pub confidence: Confidence,
+}
+
+/// The format a file name suggests, if its extension is known.
+pub fn from_extension(path: &Path) -> Option<LangType> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let lang_type = match extension.as_str() {
+        "json" => LangType::Json,
+        "jsonl" | "ndjson" => LangType::Jsonl,
+        "yaml" | "yml" => LangType::Yaml,
+        "toml" => LangType::Toml,
+        "xml" => LangType::Xml,
+        "ion" => LangType::Ion,
+        "10n" => LangType::IonBinary,
+        "partiql" | "pql" => LangType::Partiql,
+        "csv" => LangType::Csv,
+        "tsv" | "tab" => LangType::Tsv,
+        "msgpack" | "mpk" => LangType::Msgpack,
+        "cbor" => LangType::Cbor,
+        #[cfg(feature = "columnar")]
+        "parquet" => LangType::Parquet,
+        #[cfg(feature = "columnar")]
+        "arrow" | "feather" | "ipc" => LangType::Arrow,
+        _ => return None,
+    };
+    Some(lang_type)
+}
+
+/// Detects the format of `input` from its content.
+/// Fails with the reason each format was rejected if none of them matches.
+pub fn detect(input: &[u8]) -> anyhow::Result<Detection> {
+    if let Some(detection) = magic(input) {
+        return detection;
+    }
+
+    let candidates = match std::str::from_utf8(input) {
+        Ok(_) => vec![
+            LangType::Json,
+            LangType::Jsonl,
+            LangType::Partiql,
+            LangType::Ion,
+            LangType::Toml,
+            LangType::Xml,
+            LangType::Yaml,
+            LangType::Csv,
+            LangType::Tsv,
+        ],
+        Err(_) => vec![LangType::Msgpack, LangType::Cbor],
+    };
+    let mut best: Option<Detection> = None;
+    let mut rejections = vec![];
+    for lang_type in candidates {
+        match sniff(input, &lang_type) {
+            Ok(detection) => {
+                if !matches!(&best, Some(best) if best.confidence >= detection.confidence) {
+                    best = Some(detection);
+                }
+            }
+            Err(err) => rejections.push(format!("  {}: {}", lang_type, err)),
+        }
+        // Only an Ion version marker is more certain, and the strict formats never parse one.
+        if matches!(&best, Some(best) if best.confidence >= Confidence::High) {
+            break;
+        }
+    }
+    best.ok_or_else(|| {
+        anyhow::anyhow!(
+            "could not detect the input format; tried:\n{}",
+            rejections.join("\n")
+        )
+    })
+}
+
+fn magic(input: &[u8]) -> Option<anyhow::Result<Detection>> {
+    let lang_type = if input.starts_with(&crate::value::ion_binary::VERSION_MARKER) {
+        LangType::IonBinary
+    } else if input.starts_with(&crate::value::cbor_value::MAGIC) {
+        LangType::Cbor
+    } else {
+        columnar_magic(input)?
+    };
+    let lang = Lang::from_bytes_as(input, lang_type);
+    Some(lang.map(|lang| Detection {
+        lang,
+        confidence: Confidence::Certain,
+    }))
+}
+
+#[cfg(feature = "columnar")]
+fn columnar_magic(input: &[u8]) -> Option<LangType> {
+    if input.starts_with(&crate::value::parquet_value::MAGIC) {
+        Some(LangType::Parquet)
+    } else if input.starts_with(&crate::value::arrow_value::MAGIC) {
+        Some(LangType::Arrow)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(feature = "columnar"))]
+fn columnar_magic(_input: &[u8]) -> Option<LangType> {
+    None
+}
+
+// Parses `input` as `lang_type`, failing with a reason when it does not parse or is implausible.
+fn sniff(input: &[u8], lang_type: &LangType) -> anyhow::Result<Detection> {
+    let text = std::str::from_utf8(input).unwrap_or_default();
+    let trimmed = text.trim_start_matches('\u{feff}').trim_start();
+    let (lang, confidence) = match lang_type {
+        LangType::Json => (Lang::from_as_json(text)?, Confidence::High),
+        LangType::Jsonl => {
+            let lines = text.lines().filter(|line| !line.trim().is_empty()).count();
+            anyhow::ensure!(lines > 1, "not several lines of JSON");
+            (Lang::from_as_jsonl(text)?, Confidence::High)
+        }
+        LangType::Partiql => match Lang::from_as_partiql(text) {
+            Ok(lang) => (lang, Confidence::High),
+            // The parser's own error traces every alternative it tried, which is too long here.
+            Err(_) => anyhow::bail!("fail to parse input as partiql"),
+        },
+        LangType::Ion if trimmed.starts_with("$ion_1_0") => {
+            (Lang::from_as_ion(text)?, Confidence::Certain)
+        }
+        LangType::Ion => {
+            anyhow::ensure!(
+                trimmed.starts_with(['{', '[', '(']),
+                "no Ion struct, list or version marker"
+            );
+            (Lang::from_as_ion(text)?, Confidence::Medium)
+        }
+        LangType::Toml => {
+            let lang = Lang::from_as_toml(text)?;
+            anyhow::ensure!(is_collection(&lang.data), "no TOML keys");
+            (lang, Confidence::High)
+        }
+        LangType::Xml => {
+            anyhow::ensure!(trimmed.starts_with('<'), "does not start with an XML tag");
+            (Lang::from_as_xml(text)?, Confidence::High)
+        }
+        LangType::Yaml => {
+            let lang = Lang::from_as_yaml(text)?;
+            anyhow::ensure!(
+                is_collection(&lang.data),
+                "only parses as a plain YAML scalar"
+            );
+            (lang, Confidence::Medium)
+        }
+        LangType::Csv | LangType::Tsv => {
+            let delimiter = if lang_type == &LangType::Csv {
+                ','
+            } else {
+                '\t'
+            };
+            let header = trimmed.lines().next().unwrap_or_default();
+            anyhow::ensure!(
+                header.contains(delimiter) && trimmed.lines().nth(1).is_some(),
+                "no header and rows separated by {:?}",
+                delimiter
+            );
+            let lang = if lang_type == &LangType::Csv {
+                Lang::from_as_csv(text, CsvOptions::default())?
+            } else {
+                Lang::from_as_tsv(text, CsvOptions::default())?
+            };
+            (lang, Confidence::Medium)
+        }
+        LangType::Msgpack | LangType::Cbor => {
+            let lang = Lang::from_bytes_as(input, lang_type.to_owned())?;
+            anyhow::ensure!(is_collection(&lang.data), "no map or array");
+            (lang, Confidence::Medium)
+        }
+        _ => anyhow::bail!("not detected from content"),
+    };
+    Ok(Detection { lang, confidence })
+}
+
+fn is_collection(value: &PqlValue) -> bool {
+    match value.to_owned().unbag() {
+        PqlValue::Object(map) => !map.is_empty(),
+        PqlValue::Array(_) => true,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::{detect, from_extension, Confidence};
+    use crate::lang::{Lang, LangType};
+    use crate::value::{cbor_value, msgpack_value};
+
+    #[test]
+    fn detects_formats() -> anyhow::Result<()> {
+        let cases = vec![
+            (r#"{ "a": 1 }"#, LangType::Json, Confidence::High),
+            (
+                "{\"a\": 1}\n{\"a\": 2}\n",
+                LangType::Jsonl,
+                Confidence::High,
+            ),
+            ("<< { 'a': 1 } >>", LangType::Partiql, Confidence::High),
+            ("$ion_1_0 { a: 1 }", LangType::Ion, Confidence::Certain),
+            ("{ a: hello }", LangType::Ion, Confidence::Medium),
+            ("a = 1\n[b]\nc = 'x'\n", LangType::Toml, Confidence::High),
+            ("<a><b>1</b></a>", LangType::Xml, Confidence::High),
+            ("a: 1\nb:\n  - x\n", LangType::Yaml, Confidence::Medium),
+            ("a,b\n1,2\n", LangType::Csv, Confidence::Medium),
+            ("a\tb\n1\t2\n", LangType::Tsv, Confidence::Medium),
+        ];
+        for (input, lang_type, confidence) in cases {
+            let detection = detect(input.as_bytes())?;
+            assert_eq!(detection.lang.from, lang_type, "{}", input);
+            assert_eq!(detection.confidence, confidence, "{}", input);
+        }
+
+        let bytes = [0x81, 0xa1, b'a', 0x01];
+        assert_eq!(detect(&bytes)?.lang.from, LangType::Msgpack);
+        Ok(())
+    }
+
+    #[test]
+    fn detects_binary_output() -> anyhow::Result<()> {
+        let data = Lang::from_as_json(r#"[{ "a": 1, "b": [true, "x"] }, { "a": 2.5 }]"#)?.data;
+        let cases = vec![
+            (cbor_value::to_bytes(&data)?, LangType::Cbor),
+            (msgpack_value::to_bytes(&data)?, LangType::Msgpack),
+        ];
+        for (bytes, lang_type) in cases {
+            let detection = detect(&bytes)?;
+            assert_eq!(detection.lang.from, lang_type);
+            assert_eq!(detection.lang.data, data, "{}", lang_type);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn plain_text_is_not_detected() {
+        let err = detect(b"hello world").unwrap_err().to_string();
+        assert!(err.starts_with("could not detect the input format; tried:\n  json: "));
+        assert!(
+            err.contains("\n  xml: does not start with an XML tag"),
+            "{}",
+            err
+        );
+        assert!(
+            err.contains("\n  yaml: only parses as a plain YAML scalar"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn extensions() {
+        assert_eq!(from_extension(Path::new("a/b.YML")), Some(LangType::Yaml));
+        assert_eq!(
+            from_extension(Path::new("log.ndjson")),
+            Some(LangType::Jsonl)
+        );
+        assert_eq!(from_extension(Path::new("notes.txt")), None);
+        assert_eq!(from_extension(Path::new("Makefile")), None);
+    }
+}
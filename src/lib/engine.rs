@@ -9,12 +9,12 @@ pub fn evaluate(sql: &str, input: &str, from: &str, to: &str) -> anyhow::Result<
     let from_lang_type = LangType::from_str(&from)?;
     let to_lang_type = LangType::from_str(&to)?;
     let mut lang = Lang::from_as(&input, from_lang_type)?;
+    lang.to = to_lang_type;
 
     let sql = Sql::from_str(&sql)?;
 
-    let result = planner::evaluate(sql, lang.data);
-    lang.to = to_lang_type;
-    lang.data = result;
+    let result = planner::evaluate(sql, std::mem::take(&mut lang.data));
+    lang.set_result(result);
     let output = lang.to_string(true)?;
 
     Ok(output)
@@ -36,6 +36,42 @@ pub fn dumps(data: PqlValue, to: &str) -> anyhow::Result<String> {
     Ok(output)
 }
 
+/// Like `loads`, but also accepts binary formats such as `ion_binary`.
+pub fn loads_bytes(input: &[u8], from: &str) -> anyhow::Result<PqlValue> {
+    let from_lang_type = LangType::from_str(from)?;
+    let lang = Lang::from_bytes_as(input, from_lang_type)?;
+    let value = lang.data;
+    Ok(value)
+}
+
+/// Like `dumps`, but also writes binary formats such as `ion_binary`.
+pub fn dumps_bytes(data: PqlValue, to: &str) -> anyhow::Result<Vec<u8>> {
+    let lang = Lang {
+        data,
+        to: LangType::from_str(to)?,
+        ..Lang::default()
+    };
+    let output = lang.to_bytes(true)?;
+    Ok(output)
+}
+
+/// Like `evaluate`, but also reads and writes binary formats.
+/// Columnar inputs such as `parquet` only read the columns the query uses.
+pub fn evaluate_bytes(sql: &str, input: &[u8], from: &str, to: &str) -> anyhow::Result<Vec<u8>> {
+    let from_lang_type = LangType::from_str(from)?;
+    let to_lang_type = LangType::from_str(to)?;
+    let sql = Sql::from_str(sql)?;
+    let columns = sql.source_columns();
+    let mut lang = Lang::from_bytes_as_columns(input, from_lang_type, columns.as_ref())?;
+    lang.to = to_lang_type;
+
+    let result = planner::evaluate(sql, std::mem::take(&mut lang.data));
+    lang.set_result(result);
+    let output = lang.to_bytes(true)?;
+
+    Ok(output)
+}
+
 pub fn query_evaluate(data: PqlValue, sql: &str) -> anyhow::Result<PqlValue> {
     let sql = Sql::from_str(&sql)?;
     let data = PqlValue::from(data);
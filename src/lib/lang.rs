@@ -1,23 +1,46 @@
+use std::collections::HashSet;
 use std::str::FromStr;
 
 use parse_display::{Display, FromStr};
 
-#[cfg(feature = "table")]
-use polars::prelude::CsvReader;
-#[cfg(feature = "table")]
-use polars::prelude::*;
-
-use crate::value::{BPqlValue, PqlValue, TomlValue};
+use crate::value::csv_value::CsvOptions;
+use crate::value::toml_value::TomlOptions;
+use crate::value::PqlValue;
 
 #[derive(Display, FromStr, PartialEq, Clone, Debug)]
 #[display(style = "snake_case")]
 pub enum LangType {
     Json,
+    /// JSON Lines, one JSON value per line, read as the rows of a bag
+    Jsonl,
     Yaml,
     Toml,
     Xml,
-    #[cfg(feature = "table")]
+    /// Amazon Ion text
+    Ion,
+    /// Amazon Ion binary, read and written with `Lang::from_bytes_as` and `Lang::to_bytes`
+    IonBinary,
+    /// PartiQL value text, as in `samples/*.env`
+    Partiql,
+    /// MessagePack, a binary format read and written like `IonBinary`
+    Msgpack,
+    /// CBOR, a binary format read and written like `IonBinary`
+    Cbor,
     Csv,
+    /// Tab-separated values, the CSV dialect with a tab delimiter
+    Tsv,
+    /// A GitHub Flavored Markdown table, for output only
+    Markdown,
+    /// An HTML table, for output only
+    Html,
+    /// Columns aligned for a terminal, for output only
+    Table,
+    /// Apache Parquet, a binary format read and written like `IonBinary`
+    #[cfg(feature = "columnar")]
+    Parquet,
+    /// Apache Arrow IPC file, a binary format read and written like `IonBinary`
+    #[cfg(feature = "columnar")]
+    Arrow,
 }
 
 impl Default for LangType {
@@ -26,6 +49,18 @@ impl Default for LangType {
     }
 }
 
+impl LangType {
+    /// Whether the format is binary, so that it can only be read and written as bytes.
+    pub fn is_binary(&self) -> bool {
+        match self {
+            Self::IonBinary | Self::Msgpack | Self::Cbor => true,
+            #[cfg(feature = "columnar")]
+            Self::Parquet | Self::Arrow => true,
+            _ => false,
+        }
+    }
+}
+
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct Lang {
     pub data: PqlValue,
@@ -33,172 +68,390 @@ pub struct Lang {
     pub from: LangType,
     pub to: LangType,
     pub colnames: Vec<String>,
+    /// The CSV or TSV dialect to read and write with
+    pub csv: CsvOptions,
+    /// How to write TOML
+    pub toml: TomlOptions,
 }
 
 impl FromStr for Lang {
     type Err = anyhow::Error;
 
+    /// Detects the format of `input`; see `detect::detect`.
     fn from_str(input: &str) -> anyhow::Result<Self> {
-        if let Ok(this) = Self::from_as_json(input) {
-            Ok(this)
-        } else if let Ok(this) = Self::from_as_toml(input) {
-            Ok(this)
-        } else if let Ok(this) = Self::from_as_xml(input) {
-            Ok(this)
-        } else if let Ok(this) = Self::from_as_xml(input) {
-            Ok(this)
-        } else {
-            anyhow::bail!("not supported")
-        }
+        Ok(crate::detect::detect(input.as_bytes())?.lang)
     }
 }
 
 impl Lang {
     pub fn from_as(input: &str, lnag_type: LangType) -> anyhow::Result<Self> {
         match lnag_type {
-            #[cfg(feature = "table")]
-            LangType::Csv => Self::from_as_csv(input),
+            LangType::Csv => Self::from_as_csv(input, CsvOptions::default()),
+            LangType::Tsv => Self::from_as_tsv(input, CsvOptions::default()),
             LangType::Json => Self::from_as_json(input),
+            LangType::Jsonl => Self::from_as_jsonl(input),
             LangType::Toml => Self::from_as_toml(input),
             LangType::Yaml => Self::from_as_yaml(input),
             LangType::Xml => Self::from_as_xml(input),
+            LangType::Ion => Self::from_as_ion(input),
+            LangType::IonBinary => Self::from_as_ion_binary(input.as_bytes()),
+            LangType::Partiql => Self::from_as_partiql(input),
+            LangType::Msgpack | LangType::Cbor => {
+                anyhow::bail!("{} is a binary format, use Lang::from_bytes_as", lnag_type)
+            }
+            LangType::Markdown | LangType::Html | LangType::Table => {
+                anyhow::bail!("{} is an output format only", lnag_type)
+            }
+            #[cfg(feature = "columnar")]
+            LangType::Parquet | LangType::Arrow => {
+                anyhow::bail!("{} is a binary format, use Lang::from_bytes_as", lnag_type)
+            }
         }
     }
 
-    #[cfg(feature = "table")]
-    pub fn from_as_csv(input: &str) -> anyhow::Result<Self> {
-        if let Ok(data) = csvstr_to_pqlv(input) {
+    /// Like `from_as`, but also accepts binary formats.
+    pub fn from_bytes_as(input: &[u8], lang_type: LangType) -> anyhow::Result<Self> {
+        match lang_type {
+            LangType::IonBinary => Self::from_as_ion_binary(input),
+            LangType::Msgpack => Self::from_as_msgpack(input),
+            LangType::Cbor => Self::from_as_cbor(input),
+            #[cfg(feature = "columnar")]
+            LangType::Parquet => Self::from_as_parquet(input, None),
+            #[cfg(feature = "columnar")]
+            LangType::Arrow => Self::from_as_arrow(input, None),
+            _ => Self::from_as(std::str::from_utf8(input)?, lang_type),
+        }
+    }
+
+    /// Like `from_bytes_as`, but columnar formats only read `columns`, or all of them for `None`.
+    #[cfg_attr(not(feature = "columnar"), allow(unused_variables))]
+    pub fn from_bytes_as_columns(
+        input: &[u8],
+        lang_type: LangType,
+        columns: Option<&HashSet<String>>,
+    ) -> anyhow::Result<Self> {
+        match lang_type {
+            #[cfg(feature = "columnar")]
+            LangType::Parquet => Self::from_as_parquet(input, columns),
+            #[cfg(feature = "columnar")]
+            LangType::Arrow => Self::from_as_arrow(input, columns),
+            _ => Self::from_bytes_as(input, lang_type),
+        }
+    }
+
+    /// Like `from_str`, but also recognizes binary formats by their magic bytes.
+    pub fn from_bytes(input: &[u8]) -> anyhow::Result<Self> {
+        Ok(crate::detect::detect(input)?.lang)
+    }
+
+    pub fn from_as_csv(input: &str, csv: CsvOptions) -> anyhow::Result<Self> {
+        let data = crate::value::csv_value::from_str(input, &csv)?;
+        Ok(Self {
+            data,
+            text: input.to_string(),
+            from: LangType::Csv,
+            to: LangType::Csv,
+            colnames: Vec::default(),
+            csv,
+            toml: TomlOptions::default(),
+        })
+    }
+
+    pub fn from_as_tsv(input: &str, csv: CsvOptions) -> anyhow::Result<Self> {
+        let data = crate::value::csv_value::from_str(input, &csv.or_tab())?;
+        Ok(Self {
+            data,
+            text: input.to_string(),
+            from: LangType::Tsv,
+            to: LangType::Tsv,
+            colnames: Vec::default(),
+            csv,
+            toml: TomlOptions::default(),
+        })
+    }
+
+    pub fn from_as_json(input: &str) -> anyhow::Result<Self> {
+        if let Ok(data) = serde_json::from_str::<serde_json::value::Value>(input) {
+            // Json does not distinguish between Float and Int. For this reason, it it parsed once with serde_json::value::Value, not crate::value::PqlValue.
             Ok(Self {
-                data,
+                data: crate::value::json_value::to_pqlvalue(data)?,
                 text: input.to_string(),
-                from: LangType::Csv,
-                to: LangType::Csv,
+                from: LangType::Json,
+                to: LangType::Json,
                 colnames: Vec::default(),
+                csv: CsvOptions::default(),
+                toml: TomlOptions::default(),
             })
         } else {
-            anyhow::bail!("fail to parse input as csv");
+            anyhow::bail!("fail to parse input as json");
         }
     }
 
-    pub fn from_as_json(input: &str) -> anyhow::Result<Self> {
+    /// Parses JSON, reading non-integer numbers as exact decimals instead of floats.
+    pub fn from_as_json_decimal(input: &str) -> anyhow::Result<Self> {
         if let Ok(data) = serde_json::from_str::<serde_json::value::Value>(input) {
-            // Json does not distinguish between Float and Int. For this reason, it it parsed once with serde_json::value::Value, not crate::value::PqlValue.
             Ok(Self {
-                data: crate::value::json_value::to_pqlvalue(data),
+                data: crate::value::json_value::to_pqlvalue_as_decimal(data)?,
                 text: input.to_string(),
                 from: LangType::Json,
                 to: LangType::Json,
                 colnames: Vec::default(),
+                csv: CsvOptions::default(),
+                toml: TomlOptions::default(),
             })
         } else {
             anyhow::bail!("fail to parse input as json");
         }
     }
 
+    pub fn from_as_jsonl(input: &str) -> anyhow::Result<Self> {
+        let data = crate::value::jsonl_value::from_str(input)?;
+        Ok(Self {
+            data,
+            text: input.to_string(),
+            from: LangType::Jsonl,
+            to: LangType::Jsonl,
+            colnames: Vec::default(),
+            csv: CsvOptions::default(),
+            toml: TomlOptions::default(),
+        })
+    }
+
     pub fn from_as_toml(input: &str) -> anyhow::Result<Self> {
-        if let Ok(data) = toml::from_str::<PqlValue>(input) {
+        if let Ok(value) = toml::from_str::<toml::Value>(input) {
+            // Parsed as toml::Value first so that native datetimes are not lost.
+            let data = crate::value::toml_value::to_pqlvalue(value);
             Ok(Self {
                 data,
                 text: input.to_string(),
                 from: LangType::Toml,
                 to: LangType::Toml,
                 colnames: Vec::default(),
+                csv: CsvOptions::default(),
+                toml: TomlOptions::default(),
             })
         } else {
             anyhow::bail!("fail to parse input as toml");
         }
     }
 
+    pub fn from_as_ion(input: &str) -> anyhow::Result<Self> {
+        let data = crate::value::ion_value::from_str(input)?;
+        Ok(Self {
+            data,
+            text: input.to_string(),
+            from: LangType::Ion,
+            to: LangType::Ion,
+            colnames: Vec::default(),
+            csv: CsvOptions::default(),
+            toml: TomlOptions::default(),
+        })
+    }
+
+    pub fn from_as_ion_binary(input: &[u8]) -> anyhow::Result<Self> {
+        let data = crate::value::ion_binary::from_bytes(input)?;
+        Ok(Self {
+            data,
+            text: String::default(),
+            from: LangType::IonBinary,
+            to: LangType::IonBinary,
+            colnames: Vec::default(),
+            csv: CsvOptions::default(),
+            toml: TomlOptions::default(),
+        })
+    }
+
+    /// Reads a Parquet file. Only `columns` are read, or all of them for `None`.
+    #[cfg(feature = "columnar")]
+    pub fn from_as_parquet(
+        input: &[u8],
+        columns: Option<&HashSet<String>>,
+    ) -> anyhow::Result<Self> {
+        let data = crate::value::parquet_value::from_bytes(input, columns)?;
+        Ok(Self {
+            data,
+            text: String::default(),
+            from: LangType::Parquet,
+            to: LangType::Parquet,
+            colnames: Vec::default(),
+            csv: CsvOptions::default(),
+            toml: TomlOptions::default(),
+        })
+    }
+
+    /// Reads an Arrow IPC file. Only `columns` are read, or all of them for `None`.
+    #[cfg(feature = "columnar")]
+    pub fn from_as_arrow(
+        input: &[u8],
+        columns: Option<&HashSet<String>>,
+    ) -> anyhow::Result<Self> {
+        let data = crate::value::arrow_value::from_bytes(input, columns)?;
+        Ok(Self {
+            data,
+            text: String::default(),
+            from: LangType::Arrow,
+            to: LangType::Arrow,
+            colnames: Vec::default(),
+            csv: CsvOptions::default(),
+            toml: TomlOptions::default(),
+        })
+    }
+
+    pub fn from_as_msgpack(input: &[u8]) -> anyhow::Result<Self> {
+        let data = crate::value::msgpack_value::from_bytes(input)?;
+        Ok(Self {
+            data,
+            text: String::default(),
+            from: LangType::Msgpack,
+            to: LangType::Msgpack,
+            colnames: Vec::default(),
+            csv: CsvOptions::default(),
+            toml: TomlOptions::default(),
+        })
+    }
+
+    pub fn from_as_cbor(input: &[u8]) -> anyhow::Result<Self> {
+        let data = crate::value::cbor_value::from_bytes(input)?;
+        Ok(Self {
+            data,
+            text: String::default(),
+            from: LangType::Cbor,
+            to: LangType::Cbor,
+            colnames: Vec::default(),
+            csv: CsvOptions::default(),
+            toml: TomlOptions::default(),
+        })
+    }
+
+    pub fn from_as_partiql(input: &str) -> anyhow::Result<Self> {
+        let data = crate::value::partiql_value::from_str(input)?;
+        Ok(Self {
+            data,
+            text: input.to_string(),
+            from: LangType::Partiql,
+            to: LangType::Partiql,
+            colnames: Vec::default(),
+            csv: CsvOptions::default(),
+            toml: TomlOptions::default(),
+        })
+    }
+
     pub fn from_as_xml(input: &str) -> anyhow::Result<Self> {
-        if let Ok(data) = quick_xml::de::from_str::<PqlValue>(input) {
-            Ok(Self {
-                data,
-                text: input.to_string(),
-                from: LangType::Xml,
-                to: LangType::Xml,
-                colnames: Vec::default(),
-            })
-        } else {
-            anyhow::bail!("fail to parse input as xml");
-        }
+        let data = crate::value::xml_value::from_str(input)?;
+        Ok(Self {
+            data,
+            text: input.to_string(),
+            from: LangType::Xml,
+            to: LangType::Xml,
+            colnames: Vec::default(),
+            csv: CsvOptions::default(),
+            toml: TomlOptions::default(),
+        })
     }
 
+    /// Reads a YAML document, or a stream of several as a bag of them.
     pub fn from_as_yaml(input: &str) -> anyhow::Result<Self> {
-        if let Ok(data) = serde_yaml::from_str::<PqlValue>(input) {
+        // Parsed with yaml_rust rather than serde_yaml so that numbers keep their source text.
+        if let Ok(data) = crate::value::yaml_value::from_str(input) {
             Ok(Self {
                 data,
                 text: input.to_string(),
                 from: LangType::Yaml,
                 to: LangType::Yaml,
                 colnames: Vec::default(),
+                csv: CsvOptions::default(),
+                toml: TomlOptions::default(),
             })
         } else {
             anyhow::bail!("fail to parse input as yaml");
         }
     }
 
+    /// Replaces the data with the result of a query, to be written instead of the source text.
+    /// The result is kept a bag for the formats that tell bags apart from arrays.
+    pub fn set_result(&mut self, result: PqlValue) {
+        self.data = match result {
+            PqlValue::Array(array) if matches!(self.to, LangType::Partiql | LangType::Yaml) => {
+                PqlValue::bag(array)
+            }
+            result => result,
+        };
+        self.text = String::default();
+    }
+
     pub fn sort_keys(&mut self) {
-        let json = serde_json::to_string(&self.data).unwrap();
-        let bdata = serde_json::from_str::<BPqlValue>(&json).unwrap();
-        let bjson = serde_json::to_string(&bdata).unwrap();
-        let data = serde_json::from_str::<PqlValue>(&bjson).unwrap();
-        self.data = data;
+        self.data = self.data.to_owned().sort_keys();
     }
 
     pub fn to_string(&self, compact: bool) -> anyhow::Result<String> {
         let output = match (&self.to, &self.from == &self.to) {
-            #[cfg(feature = "table")]
-            (LangType::Csv, _) => {
-                // To pad missing values with null, serialize them to json, deserialize them with polars, and write them to csv from there.
-                let sss = match &self.data {
-                    PqlValue::Array(array) => array
-                        .iter()
-                        .map(|v| serde_json::to_string(&v).unwrap())
-                        .collect::<Vec<String>>()
-                        .join("\n"),
-                    _ => anyhow::bail!("must array"),
-                };
-                let c = std::io::Cursor::new(&sss);
-                let mut df = JsonReader::new(c).infer_schema(Some(100)).finish()?;
-
-                let mut v = Vec::new();
-                CsvWriter::new(&mut v)
-                    .has_headers(true)
-                    .with_delimiter(b',')
-                    .finish(&mut df)?;
-                let s = String::from_utf8(v)?;
-                s
+            (LangType::Csv, _) => crate::value::csv_value::to_string(&self.data, &self.csv)?,
+            (LangType::Tsv, _) => {
+                crate::value::csv_value::to_string(&self.data, &self.csv.or_tab())?
+            }
+            (LangType::Json, _) if compact => self.data.to_jsonc()?,
+            (LangType::Json, _) => self.data.to_jsonp()?,
+            (LangType::Jsonl, _) => crate::value::jsonl_value::to_string(&self.data)?,
+            (LangType::Ion, _) => crate::value::ion_value::to_string(&self.data, compact),
+            (LangType::Partiql, _) => {
+                crate::value::partiql_value::to_string(&self.data, compact)
+            }
+            (LangType::Markdown, _) => crate::value::tabular_value::to_markdown(&self.data),
+            (LangType::Html, _) => crate::value::tabular_value::to_html(&self.data),
+            (LangType::Table, _) => crate::value::tabular_value::to_table(&self.data),
+            (LangType::IonBinary, _) => {
+                anyhow::bail!("ion_binary is a binary format, use Lang::to_bytes")
+            }
+            (LangType::Msgpack, _) | (LangType::Cbor, _) => {
+                anyhow::bail!("{} is a binary format, use Lang::to_bytes", self.to)
             }
-            (LangType::Json, _) if compact => serde_json::to_string(&self.data).unwrap(),
-            (LangType::Json, _) => serde_json::to_string_pretty(&self.data).unwrap(),
-            (_, true) => self.text.to_owned(),
-            (LangType::Toml, _) => {
-                let v = TomlValue::from(self.data.to_owned());
-                toml::to_string_pretty(&v).unwrap()
+            #[cfg(feature = "columnar")]
+            (LangType::Parquet, _) | (LangType::Arrow, _) => {
+                anyhow::bail!("{} is a binary format, use Lang::to_bytes", self.to)
             }
-            (LangType::Yaml, _) => serde_yaml::to_string(&self.data)
-                .unwrap()
-                .trim_start_matches("---\n")
-                .to_string(),
-            (LangType::Xml, _) => quick_xml::se::to_string(&self.data).unwrap(),
+            (_, true) if !self.text.is_empty() => self.text.to_owned(),
+            (LangType::Toml, _) => crate::value::toml_value::to_string(&self.data, &self.toml)?,
+            (LangType::Yaml, _) => crate::value::yaml_value::to_string(&self.data)?,
+            (LangType::Xml, _) => crate::value::xml_value::to_string(&self.data, compact)?,
         };
 
         Ok(output)
     }
 
+    pub fn to_bytes(&self, compact: bool) -> anyhow::Result<Vec<u8>> {
+        match self.to {
+            LangType::IonBinary => Ok(crate::value::ion_binary::to_bytes(&self.data)),
+            LangType::Msgpack => crate::value::msgpack_value::to_bytes(&self.data),
+            LangType::Cbor => crate::value::cbor_value::to_bytes(&self.data),
+            #[cfg(feature = "columnar")]
+            LangType::Parquet => crate::value::parquet_value::to_bytes(&self.data),
+            #[cfg(feature = "columnar")]
+            LangType::Arrow => crate::value::arrow_value::to_bytes(&self.data),
+            _ => Ok(self.to_string(compact)?.into_bytes()),
+        }
+    }
+
     #[cfg(feature = "cli")]
     pub fn print(&self, compact: bool) -> anyhow::Result<()> {
+        if self.to.is_binary() {
+            use std::io::Write;
+            std::io::stdout().write_all(&self.to_bytes(compact)?)?;
+            return Ok(());
+        }
         let output = self.to_string(compact)?;
 
         if atty::is(atty::Stream::Stdout) {
             let bytes = output.as_bytes().to_vec();
             let lang_type = self.to.to_string();
 
-            bat::PrettyPrinter::new()
+            // bat has no syntax for some formats such as Ion; print those plainly.
+            let printed = bat::PrettyPrinter::new()
                 .language(&lang_type)
                 .input(bat::Input::from_bytes(&bytes))
-                .print()
-                .unwrap();
+                .print();
+            if printed.is_err() {
+                println!("{}", output);
+            }
         } else {
             println!("{}", &output);
         }
@@ -206,9 +459,54 @@ impl Lang {
     }
 }
 
-#[cfg(feature = "table")]
-fn csvstr_to_pqlv(input: &str) -> anyhow::Result<PqlValue> {
-    let c = std::io::Cursor::new(input.to_owned());
-    let df = CsvReader::new(c).infer_schema(Some(100)).finish()?;
-    Ok(PqlValue::from(df))
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+
+    use super::Lang;
+    use crate::value::PqlValue;
+
+    #[test]
+    fn oversized_integers_are_exact() -> anyhow::Result<()> {
+        let big = PqlValue::Decimal(BigDecimal::from_str("123456789012345678901234567890")?);
+
+        let lang = Lang::from_as_json(r#"{ "n": 123456789012345678901234567890, "m": 1 }"#)?;
+        assert_eq!(lang.data.to_owned().get("n"), Some(big.to_owned()));
+        assert_eq!(lang.data.to_owned().get("m"), Some(PqlValue::from(1)));
+        assert_eq!(
+            lang.data.to_jsonc()?,
+            r#"{"n":123456789012345678901234567890,"m":1}"#
+        );
+
+        let lang = Lang::from_as_yaml("n: 123456789012345678901234567890\nm: -1\nf: 1.5\n")?;
+        assert_eq!(lang.data.to_owned().get("n"), Some(big));
+        assert_eq!(lang.data.to_owned().get("m"), Some(PqlValue::from(-1)));
+        assert_eq!(lang.data.get("f"), Some(PqlValue::from(1.5)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn toml_datetimes_round_trip() -> anyhow::Result<()> {
+        let input = "at = 2021-07-01T09:30:00.5+09:00\non = 2021-07-01\nopens = 09:30:00\n";
+        let mut lang = Lang::from_as_toml(input)?;
+        assert!(matches!(lang.data.to_owned().get("at"), Some(PqlValue::DateTime(_))));
+        assert!(matches!(lang.data.to_owned().get("on"), Some(PqlValue::Date(_))));
+        assert!(matches!(lang.data.to_owned().get("opens"), Some(PqlValue::Time(_))));
+
+        lang.text = String::default();
+        lang.from = crate::lang::LangType::Json;
+        assert_eq!(lang.to_string(false)?, input);
+        Ok(())
+    }
+
+    #[test]
+    fn query_results_replace_the_source() -> anyhow::Result<()> {
+        let mut lang = Lang::from_as_yaml("- a: 1\n- a: 2\n")?;
+        lang.set_result(Lang::from_as_json(r#"[{ "a": 2 }]"#)?.data);
+        assert_eq!(lang.to_string(false)?, "a: 2\n");
+        Ok(())
+    }
 }
@@ -1,10 +1,11 @@
 #![feature(box_patterns)]
+pub mod detect;
 pub mod engine;
 pub mod lang;
 pub mod models;
 pub mod parser;
 pub mod planner;
+pub mod schema;
 pub mod pqlir_parser;
 pub mod sql;
-pub mod utils;
 pub mod value;
@@ -69,7 +69,12 @@ pub fn left_join<'a>(input: &'a str) -> IResult<&'a str, Vec<Field>> {
 pub fn parse_where(input: &str) -> IResult<&str, WhereCond> {
     preceded(
         tag_no_case("WHERE"),
-        alt((parse_where_eq, parse_where_like)),
+        alt((
+            parse_where_eq,
+            parse_where_compare,
+            parse_where_eq_expr,
+            parse_where_like,
+        )),
     )(input)
 }
 
@@ -86,6 +91,48 @@ pub fn parse_where_eq(input: &str) -> IResult<&str, WhereCond> {
     Ok((input, res))
 }
 
+pub fn parse_where_compare(input: &str) -> IResult<&str, WhereCond> {
+    let (input, (expr, op, right)) = preceded(
+        multispace0,
+        tuple((
+            parse_expr,
+            preceded(
+                multispace0,
+                alt((
+                    tag("<="),
+                    tag(">="),
+                    tag("!="),
+                    tag("<>"),
+                    tag("<"),
+                    tag(">"),
+                )),
+            ),
+            preceded(multispace0, parse_value),
+        )),
+    )(input)?;
+    let res = match op {
+        "<=" => WhereCond::Le { expr, right },
+        ">=" => WhereCond::Ge { expr, right },
+        "<" => WhereCond::Lt { expr, right },
+        ">" => WhereCond::Gt { expr, right },
+        _ => WhereCond::Neq { expr, right },
+    };
+    Ok((input, res))
+}
+
+pub fn parse_where_eq_expr(input: &str) -> IResult<&str, WhereCond> {
+    let (input, (expr, _, right)) = preceded(
+        multispace0,
+        tuple((
+            parse_expr,
+            preceded(multispace0, tag("=")),
+            preceded(multispace0, parse_expr),
+        )),
+    )(input)?;
+    let res = WhereCond::EqExpr { expr, right };
+    Ok((input, res))
+}
+
 pub fn parse_where_like(input: &str) -> IResult<&str, WhereCond> {
     let (input, (expr, _, s)) = preceded(
         multispace0,
@@ -146,8 +193,9 @@ pub fn offset(input: &str) -> IResult<&str, u64> {
 mod tests {
     use std::str::FromStr;
 
-    use super::from;
-    use crate::sql::Field;
+    use super::{from, parse_where};
+    use crate::sql::{Expr, Field, WhereCond};
+    use crate::value::PqlValue;
 
     #[test]
     fn parse_from() -> anyhow::Result<()> {
@@ -163,4 +211,69 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn parse_where_comparisons() -> anyhow::Result<()> {
+        let expr = Expr::from_str("price")?;
+        let right = PqlValue::from(10.);
+        let cases = vec![
+            (
+                "WHERE price < 10",
+                WhereCond::Lt {
+                    expr: expr.to_owned(),
+                    right: right.to_owned(),
+                },
+            ),
+            (
+                "WHERE price <= 10",
+                WhereCond::Le {
+                    expr: expr.to_owned(),
+                    right: right.to_owned(),
+                },
+            ),
+            (
+                "WHERE price > 10",
+                WhereCond::Gt {
+                    expr: expr.to_owned(),
+                    right: right.to_owned(),
+                },
+            ),
+            (
+                "WHERE price >= 10",
+                WhereCond::Ge {
+                    expr: expr.to_owned(),
+                    right: right.to_owned(),
+                },
+            ),
+            (
+                "WHERE price != 10",
+                WhereCond::Neq {
+                    expr: expr.to_owned(),
+                    right: right.to_owned(),
+                },
+            ),
+            (
+                "WHERE price <> 10",
+                WhereCond::Neq {
+                    expr: expr.to_owned(),
+                    right: right.to_owned(),
+                },
+            ),
+        ];
+        for (input, cond) in cases {
+            assert_eq!(parse_where(input)?, ("", cond), "{}", input);
+        }
+
+        assert_eq!(
+            parse_where("WHERE o.user_id = u.id")?,
+            (
+                "",
+                WhereCond::EqExpr {
+                    expr: Expr::from_str("o.user_id")?,
+                    right: Expr::from_str("u.id")?,
+                }
+            )
+        );
+        Ok(())
+    }
 }
@@ -1,6 +1,8 @@
 use nom::branch::alt;
 use nom::bytes::complete::escaped;
+use nom::bytes::complete::is_not;
 use nom::bytes::complete::tag;
+use nom::bytes::complete::tag_no_case;
 use nom::bytes::complete::take_while;
 use nom::character::complete::alphanumeric1;
 use nom::character::complete::char;
@@ -8,15 +10,17 @@ use nom::character::complete::digit1;
 use nom::character::complete::multispace0;
 use nom::character::complete::one_of;
 use nom::character::complete::space1;
-use nom::combinator::cut;
+use nom::combinator::{cut, opt};
 use nom::error::{ErrorKind, ParseError};
-use nom::multi::many1;
+use nom::multi::{many0, many1};
 use nom::number::complete::recognize_float;
 use nom::sequence::delimited;
-use nom::sequence::{preceded, terminated};
+use nom::sequence::{preceded, terminated, tuple};
 use nom::{IResult, InputLength};
 
 use crate::sql::Expr;
+use crate::value::datetime::{self, PqlTime};
+use crate::value::PqlValue;
 
 pub fn eof<I: Copy + InputLength, E: ParseError<I>>(input: I) -> IResult<I, I, E> {
     if input.input_len() == 0 {
@@ -79,6 +83,51 @@ fn parse_str<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, &'a str
     )(i)
 }
 
+/// A single-quoted text such as a date-time literal or format pattern, where `''` escapes a quote.
+pub fn text(input: &str) -> IResult<&str, String> {
+    let (input, parts) = preceded(
+        char('\''),
+        cut(terminated(
+            many0(alt((is_not("'"), escaped_quote))),
+            char('\''),
+        )),
+    )(input)?;
+
+    Ok((input, parts.concat()))
+}
+
+fn escaped_quote(input: &str) -> IResult<&str, &str> {
+    let (input, _) = tag("''")(input)?;
+    Ok((input, "'"))
+}
+
+pub fn datetime_literal(input: &str) -> IResult<&str, PqlValue> {
+    let (rest, (keyword, s)) = tuple((
+        alt((
+            tag_no_case("TIMESTAMP"),
+            terminated(
+                tag_no_case("TIME"),
+                opt(preceded(space1, tag_no_case("WITH TIME ZONE"))),
+            ),
+            tag_no_case("DATE"),
+        )),
+        preceded(multispace0, text),
+    ))(input)?;
+
+    let value = match keyword.to_uppercase().as_str() {
+        "TIMESTAMP" => datetime::parse_timestamp(&s).map(PqlValue::DateTime),
+        "TIME" => s.parse::<PqlTime>().map(PqlValue::Time),
+        _ => datetime::parse_date(&s).map(PqlValue::Date),
+    };
+    match value {
+        Ok(value) => Ok((rest, value)),
+        Err(_) => Err(nom::Err::Failure(ParseError::from_error_kind(
+            input,
+            ErrorKind::Verify,
+        ))),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::float_number;
@@ -1,13 +1,15 @@
 use collect_mac::collect;
 use indexmap::IndexMap as Map;
 use nom::branch::alt;
-use nom::bytes::complete::{tag, tag_no_case};
+use nom::bytes::complete::{is_not, tag, tag_no_case};
 use nom::character::complete::alphanumeric1;
 use nom::character::complete::char;
 use nom::character::complete::multispace0;
+use nom::character::complete::one_of;
 use nom::combinator::map;
+use nom::combinator::not;
 use nom::combinator::opt;
-use nom::multi::separated_list1;
+use nom::multi::many0;
 use nom::sequence::delimited;
 use nom::sequence::preceded;
 use nom::sequence::tuple;
@@ -16,6 +18,7 @@ use nom::IResult;
 pub use crate::parser;
 pub use crate::parser::elements;
 pub use crate::parser::elements::string_allowed_in_field;
+use crate::parser::keywords;
 pub use crate::parser::whitespace;
 use crate::pqlir_parser;
 pub use crate::sql::clause;
@@ -28,10 +31,7 @@ use crate::value::PqlValue;
 pub fn pqlvalue_as_field(input: &str) -> IResult<&str, Field> {
     let (input, (value, alias)) = tuple((
         pqlir_parser::root,
-        opt(preceded(
-            opt(preceded(multispace0, tag_no_case("AS"))),
-            preceded(multispace0, alphanumeric1),
-        )),
+        opt(alias),
     ))(input)?;
 
     let field = Field {
@@ -41,6 +41,14 @@ pub fn pqlvalue_as_field(input: &str) -> IResult<&str, Field> {
     Ok((input, field))
 }
 
+// A bare alias must not take the keyword of the next clause, as in `SELECT msg WHERE ...`.
+fn alias(input: &str) -> IResult<&str, &str> {
+    preceded(
+        opt(preceded(multispace0, tag_no_case("AS"))),
+        preceded(multispace0, preceded(not(keywords::sql_keyword), alphanumeric1)),
+    )(input)
+}
+
 pub fn parse_field(input: &str) -> IResult<&str, Field> {
     alt((expr_as_field, pqlvalue_as_field, selector_as_field))(input)
 }
@@ -74,10 +82,7 @@ pub fn pqlvalue_with_alias_to_pql_value(input: &str) -> IResult<&str, PqlValue>
 pub fn selector_as_field(input: &str) -> IResult<&str, Field> {
     let (input, (selector, alias)) = tuple((
         parse_selector,
-        opt(preceded(
-            opt(preceded(multispace0, tag_no_case("AS"))),
-            preceded(multispace0, alphanumeric1),
-        )),
+        opt(alias),
     ))(input)?;
 
     let field = Field {
@@ -90,10 +95,7 @@ pub fn selector_as_field(input: &str) -> IResult<&str, Field> {
 pub fn projection(input: &str) -> IResult<&str, (Selector, Option<String>)> {
     let (input, (selector, opt_alias)) = tuple((
         parse_selector,
-        opt(preceded(
-            opt(preceded(multispace0, tag_no_case("AS"))),
-            preceded(multispace0, alphanumeric1),
-        )),
+        opt(alias),
     ))(input)?;
     Ok((input, (selector, opt_alias.map(String::from))))
 }
@@ -102,10 +104,7 @@ pub fn expr_as_field(input: &str) -> IResult<&str, Field> {
     // The math::parse must be placed after the parse_path_as_expr to prevent the inf keyword from being parsed.
     let (input, (expr, alias)) = tuple((
         parse_expr,
-        opt(preceded(
-            opt(preceded(multispace0, tag_no_case("AS"))),
-            preceded(multispace0, alphanumeric1),
-        )),
+        opt(alias),
     ))(input)?;
 
     let field = Field {
@@ -143,25 +142,48 @@ pub fn parse_alias_in_from_clause(input: &str) -> IResult<&str, String> {
 }
 
 pub fn parse_selector(input: &str) -> IResult<&str, Selector> {
-    pub fn selecotrnode_with_index<'a>(input: &str) -> IResult<&str, Vec<SelectorNode>> {
-        let (input, (s, opt_i)) = tuple((
-            string_allowed_in_field,
-            opt(delimited(char('['), elements::integer, char(']'))),
-        ))(input)?;
-
-        let mut nodes = vec![];
-        nodes.push(SelectorNode::String(s));
-        if let Some(i) = opt_i {
-            nodes.push(SelectorNode::Number(i as i64));
-        };
-
-        Ok((input, nodes))
+    // `@attr` and `#text` name the attributes and text of an XML element.
+    fn node_name(input: &str) -> IResult<&str, String> {
+        let (input, (prefix, name)) = tuple((opt(one_of("@#")), string_allowed_in_field))(input)?;
+        Ok((input, prefix.into_iter().chain(name.chars()).collect()))
+    }
+
+    // A quoted name such as `"dc:title"` may contain any character but `"`, and cannot start a path.
+    fn quoted_name(input: &str) -> IResult<&str, String> {
+        map(delimited(char('"'), is_not("\""), char('"')), String::from)(input)
+    }
+
+    fn selecotrnode_with_index(
+        node: fn(&str) -> IResult<&str, String>,
+    ) -> impl FnMut(&str) -> IResult<&str, Vec<SelectorNode>> {
+        move |input| {
+            let (input, (s, opt_i)) = tuple((
+                node,
+                opt(delimited(char('['), elements::integer, char(']'))),
+            ))(input)?;
+
+            let mut nodes = vec![];
+            nodes.push(SelectorNode::String(s));
+            if let Some(i) = opt_i {
+                nodes.push(SelectorNode::Number(i as i64));
+            };
+
+            Ok((input, nodes))
+        }
     }
 
-    let (input, (opt_dot, vec_nodes)) = tuple((
+    let (input, (opt_dot, head, tail)) = tuple((
         opt(char('.')),
-        separated_list1(char('.'), selecotrnode_with_index),
+        selecotrnode_with_index(node_name),
+        many0(preceded(
+            char('.'),
+            alt((
+                selecotrnode_with_index(node_name),
+                selecotrnode_with_index(quoted_name),
+            )),
+        )),
     ))(input)?;
+    let vec_nodes = std::iter::once(head).chain(tail);
 
     let mut nodes = vec![];
     if let Some(_dot) = opt_dot {
@@ -179,6 +201,7 @@ pub fn parse_path_as_expr<'a>(input: &'a str) -> IResult<&'a str, Expr> {
 
 #[cfg(test)]
 mod tests {
+    use super::parse_field;
     use super::parse_selector;
     use crate::parser;
     use crate::sql::Selector;
@@ -228,4 +251,15 @@ mod tests {
         assert_eq!(value, expected);
         Ok(())
     }
+
+    #[test]
+    fn bare_alias_is_not_a_keyword() -> anyhow::Result<()> {
+        let (rest, field) = parse_field("msg WHERE level = 'error'")?;
+        assert_eq!(field.alias, None);
+        assert_eq!(rest.trim_start(), "WHERE level = 'error'");
+
+        let (_, field) = parse_field("msg message")?;
+        assert_eq!(field.alias, Some("message".to_owned()));
+        Ok(())
+    }
 }
@@ -1,28 +1,46 @@
+use std::str::FromStr;
+
 use nom::branch::alt;
 use nom::bytes::complete::{tag, tag_no_case};
-use nom::character::complete::char;
-use nom::combinator::cut;
-use nom::sequence::{preceded, terminated, tuple};
+use nom::character::complete::{alpha1, char};
+use nom::combinator::{cut, map, map_res, opt};
+use nom::sequence::{delimited, preceded, terminated, tuple};
 use nom::IResult;
 
 use crate::sql::Expr;
 use crate::sql::Func;
+use crate::value::datetime::DateTimePart;
 
+use crate::parser::elements::{comma, text};
 use crate::parser::{parse_expr, whitespace};
 
 pub fn function(input: &str) -> IResult<&str, Expr> {
+    let (input, func) = preceded(
+        whitespace,
+        alt((
+            extract,
+            date_add,
+            date_diff,
+            to_string,
+            to_timestamp,
+            utcnow,
+            unary_function,
+        )),
+    )(input)?;
+
+    Ok((input, Expr::Func(Box::new(func))))
+}
+
+fn unary_function(input: &str) -> IResult<&str, Func> {
     let (input, (funcname, _, expr)) = tuple((
-        preceded(
-            whitespace,
-            alt((
-                tag_no_case("count"),
-                tag_no_case("upper"),
-                tag_no_case("lower"),
-                tag_no_case("ceil"),
-                tag_no_case("floor"),
-                tag_no_case("round"),
-            )),
-        ),
+        alt((
+            tag_no_case("count"),
+            tag_no_case("upper"),
+            tag_no_case("lower"),
+            tag_no_case("ceil"),
+            tag_no_case("floor"),
+            tag_no_case("round"),
+        )),
         char('('),
         cut(terminated(
             preceded(whitespace, parse_expr),
@@ -36,7 +54,91 @@ pub fn function(input: &str) -> IResult<&str, Expr> {
         _ => todo!(),
     };
 
-    let res = Expr::Func(Box::new(func));
+    Ok((input, func))
+}
+
+fn args<'a, O, F>(name: &'static str, inner: F) -> impl FnMut(&'a str) -> IResult<&'a str, O>
+where
+    F: FnMut(&'a str) -> IResult<&'a str, O>,
+{
+    preceded(
+        tuple((tag_no_case(name), whitespace, char('('))),
+        cut(terminated(
+            delimited(whitespace, inner, whitespace),
+            char(')'),
+        )),
+    )
+}
+
+fn datetime_part(input: &str) -> IResult<&str, DateTimePart> {
+    map_res(
+        alt((
+            tag_no_case("timezone_hour"),
+            tag_no_case("timezone_minute"),
+            alpha1,
+        )),
+        |s: &str| DateTimePart::from_str(&s.to_lowercase()),
+    )(input)
+}
+
+fn pattern(input: &str) -> IResult<&str, Expr> {
+    map(text, |s| Expr::from(s.as_str()))(input)
+}
+
+fn extract(input: &str) -> IResult<&str, Func> {
+    map(
+        args(
+            "extract",
+            tuple((
+                datetime_part,
+                delimited(whitespace, tag_no_case("from"), whitespace),
+                parse_expr,
+            )),
+        ),
+        |(part, _, expr)| Func::Extract(part, expr),
+    )(input)
+}
+
+fn date_add(input: &str) -> IResult<&str, Func> {
+    map(
+        args(
+            "date_add",
+            tuple((datetime_part, comma, parse_expr, comma, parse_expr)),
+        ),
+        |(part, _, quantity, _, expr)| Func::DateAdd(part, quantity, expr),
+    )(input)
+}
+
+fn date_diff(input: &str) -> IResult<&str, Func> {
+    map(
+        args(
+            "date_diff",
+            tuple((datetime_part, comma, parse_expr, comma, parse_expr)),
+        ),
+        |(part, _, from, _, to)| Func::DateDiff(part, from, to),
+    )(input)
+}
+
+fn to_string(input: &str) -> IResult<&str, Func> {
+    map(
+        args("to_string", tuple((parse_expr, comma, pattern))),
+        |(expr, _, pattern)| Func::ToString(expr, pattern),
+    )(input)
+}
+
+fn to_timestamp(input: &str) -> IResult<&str, Func> {
+    map(
+        args(
+            "to_timestamp",
+            tuple((parse_expr, opt(preceded(comma, pattern)))),
+        ),
+        |(expr, pattern)| Func::ToTimestamp(expr, pattern),
+    )(input)
+}
 
-    Ok((input, res))
+fn utcnow(input: &str) -> IResult<&str, Func> {
+    map(
+        tuple((tag_no_case("utcnow"), whitespace, tag("("), whitespace, tag(")"))),
+        |_| Func::UtcNow,
+    )(input)
 }
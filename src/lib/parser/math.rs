@@ -7,6 +7,7 @@ use nom::sequence::{delimited, tuple};
 use nom::IResult;
 
 use crate::sql::Expr;
+use crate::value::PqlValue;
 
 use crate::parser;
 
@@ -69,6 +70,8 @@ pub fn parse_path_or_num(input: &str) -> IResult<&str, Expr> {
         space0,
         alt((
             parser::float_number,
+            map(parser::elements::datetime_literal, Expr::Value),
+            map(parser::elements::text, |s| Expr::Value(PqlValue::Str(s))),
             parser::func::function,
             parser::parse_path_as_expr,
         )),
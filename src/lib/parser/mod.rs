@@ -21,6 +21,7 @@ pub use expressions::parse_path_as_expr;
 
 pub fn parse_value(input: &str) -> IResult<&str, PqlValue> {
     alt((
+        elements::datetime_literal,
         map(elements::string, |s| PqlValue::Str(s.to_string())),
         map(double, |f| PqlValue::Float(OrderedFloat(f as f64))),
     ))(input)
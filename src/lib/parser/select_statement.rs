@@ -1,18 +1,21 @@
-use nom::character::complete::multispace0;
+use nom::bytes::complete::tag_no_case;
+use nom::character::complete::{char, multispace0, multispace1};
 use nom::{
     branch::alt,
     combinator::opt,
-    sequence::{preceded, tuple},
+    sequence::{preceded, terminated, tuple},
     IResult,
 };
 
 use crate::parser::clauses;
+use crate::parser::elements;
 
-use crate::sql::Sql;
+use crate::sql::{Sql, Statement};
 
 pub fn from_str(input: &str) -> anyhow::Result<Sql> {
     match parse_planner_sql(input) {
-        Ok((_, sql)) => Ok(sql),
+        Ok(("", sql)) => Ok(sql),
+        Ok((rest, _)) => anyhow::bail!("cannot parse the query at: {}", rest.trim()),
         Err(nom::Err::Incomplete(_needed)) => {
             anyhow::bail!("needed")
         }
@@ -29,8 +32,35 @@ pub fn from_str(input: &str) -> anyhow::Result<Sql> {
     }
 }
 
+pub fn statement_from_str(input: &str) -> anyhow::Result<Statement> {
+    match parse_explain(input) {
+        Ok((rest, _)) => Ok(Statement::Explain(from_str(rest)?)),
+        Err(_) => Ok(Statement::Query(from_str(input)?)),
+    }
+}
+
+pub fn parse_explain(input: &str) -> IResult<&str, &str> {
+    terminated(
+        preceded(multispace0, tag_no_case("EXPLAIN")),
+        multispace1,
+    )(input)
+}
+
+/// Parses a query with its clauses in either order. Unless one of them reads the whole input, the
+/// query is read as far as it can be, and the rest is left.
 pub fn parse_planner_sql(input: &str) -> IResult<&str, Sql> {
-    alt((parse_sql21, parse_sql22))(input)
+    alt((
+        terminated(parse_sql21, end_of_query),
+        terminated(parse_sql22, end_of_query),
+        parse_sql21,
+    ))(input)
+}
+
+fn end_of_query(input: &str) -> IResult<&str, &str> {
+    preceded(
+        tuple((multispace0, opt(char(';')), multispace0)),
+        elements::eof,
+    )(input)
 }
 
 pub fn parse_sql21(input: &str) -> IResult<&str, Sql> {
@@ -0,0 +1,405 @@
+//! Collections registered by name, with indexes over paths of their rows, and queries over them
+//! that read the indexes instead of every row where they can.
+//!
+//! A query reads a registered collection by its name, as in `FROM orders o`. A WHERE clause that
+//! compares a path of such a row with a literal, as in `o.id = 2` or `o.total >= 100`, or with a
+//! value bound before it, as the keys of a join in `o.user_id = u.id`, is looked up in an index on
+//! that path, when the collection has one that can look it up.
+
+use std::sync::Arc;
+
+use indexmap::IndexMap as Map;
+
+use crate::planner::explain::explain_with;
+use crate::planner::index::{Index, IndexKind, Probe};
+use crate::planner::optimizer::{columns, map_input, prune_columns, variables};
+use crate::planner::physical_plan::{elements, into_row};
+use crate::planner::{optimize, ExecOptions, LogicalPlan, PhysicalPlan, Source};
+use crate::sql::{Expr, Field, Selector, SelectorNode, Sql, WhereCond};
+use crate::value::PqlValue;
+
+#[derive(Debug, Default)]
+struct Collection {
+    rows: Arc<Vec<PqlValue>>,
+    indexes: Vec<Arc<Index>>,
+}
+
+#[derive(Debug, Default)]
+pub struct Catalog {
+    collections: Map<String, Collection>,
+}
+
+impl Catalog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers the elements of `data` as the rows of the collection `name`. The indexes of a
+    /// collection registered before are rebuilt over the new rows.
+    pub fn register(&mut self, name: &str, data: PqlValue) {
+        let rows = Arc::new(elements(data));
+        let indexes = match self.collections.get(name) {
+            Some(collection) => collection
+                .indexes
+                .iter()
+                .map(|index| {
+                    Arc::new(Index::build(
+                        name,
+                        Arc::clone(&rows),
+                        index.path.to_owned(),
+                        index.kind(),
+                    ))
+                })
+                .collect(),
+            None => vec![],
+        };
+        self.collections
+            .insert(name.to_owned(), Collection { rows, indexes });
+    }
+
+    /// Builds an index of `kind` over `path` of the rows of the collection `name`, such as `id`
+    /// or `address.city`, in place of any index of the same kind over that path.
+    pub fn create_index(&mut self, name: &str, path: &str, kind: IndexKind) -> anyhow::Result<()> {
+        let collection = match self.collections.get_mut(name) {
+            Some(collection) => collection,
+            None => anyhow::bail!("no collection named {}", name),
+        };
+        let index = Index::new(name, Arc::clone(&collection.rows), path, kind)?;
+        collection
+            .indexes
+            .retain(|other| other.path != index.path || other.kind() != kind);
+        collection.indexes.push(Arc::new(index));
+        Ok(())
+    }
+
+    /// The plan of `sql` over the registered collections.
+    pub fn plan(&self, sql: Sql) -> PhysicalPlan<'static> {
+        let plan = LogicalPlan::from(sql);
+        let source = plan.source(PqlValue::Missing);
+        let plan = self.use_indexes(optimize(PhysicalPlan::new(plan, source)));
+        // The collections that are still scanned, rather than looked up, are read as a document.
+        let names = columns(&plan);
+        let mut document = Map::new();
+        for (name, collection) in &self.collections {
+            if names.as_ref().is_none_or(|names| names.contains(name)) {
+                let rows = collection.rows.as_ref().to_owned();
+                document.insert(name.to_owned(), PqlValue::Array(rows));
+            }
+        }
+        prune_columns(with_data(plan, PqlValue::Object(document)))
+    }
+
+    pub fn evaluate(&self, sql: Sql) -> anyhow::Result<PqlValue> {
+        self.evaluate_with(sql, &ExecOptions::default())
+    }
+
+    /// Evaluates `sql` over the registered collections, as `options` says.
+    pub fn evaluate_with(&self, sql: Sql, options: &ExecOptions) -> anyhow::Result<PqlValue> {
+        let rows = self
+            .plan(sql)
+            .execute_with(options)?
+            .map(|tuple| tuple.map(into_row))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(PqlValue::Array(rows))
+    }
+
+    /// Explains `sql` as `explain` does, with the indexes its plan reads.
+    pub fn explain(&self, sql: &Sql) -> String {
+        explain_with(sql, &self.plan(sql.to_owned()))
+    }
+
+    // Replaces each filter over a join of a registered collection with a lookup in an index of
+    // the collection, where it has one for the condition.
+    fn use_indexes<'a>(&self, plan: PhysicalPlan<'a>) -> PhysicalPlan<'a> {
+        match plan {
+            PhysicalPlan::Filter {
+                input:
+                    box PhysicalPlan::Join {
+                        input,
+                        field,
+                        outer: false,
+                    },
+                cond,
+            } => match self.index_for(&input, &field, &cond) {
+                Some((alias, index, probe)) => PhysicalPlan::IndexLookup {
+                    input: Box::new(self.use_indexes(*input)),
+                    alias,
+                    index,
+                    probe,
+                },
+                None => PhysicalPlan::Filter {
+                    input: Box::new(self.use_indexes(PhysicalPlan::Join {
+                        input,
+                        field,
+                        outer: false,
+                    })),
+                    cond,
+                },
+            },
+            plan => map_input(plan, |input| self.use_indexes(input)),
+        }
+    }
+
+    // The index that looks up the rows of the join of `field` over `input` that satisfy `cond`.
+    fn index_for(
+        &self,
+        input: &PhysicalPlan,
+        field: &Field,
+        cond: &WhereCond,
+    ) -> Option<(String, Arc<Index>, Probe)> {
+        let alias = field.alias.to_owned()?;
+        let name = match &field.expr {
+            Expr::Selector(selector) => match selector.to_vec().as_slice() {
+                [SelectorNode::String(name)] => name.to_owned(),
+                _ => return None,
+            },
+            _ => return None,
+        };
+        // A variable of the same name, bound before, is what the name refers to.
+        if binds(input, &name) {
+            return None;
+        }
+        let collection = self.collections.get(&name)?;
+
+        let candidates = match cond {
+            WhereCond::Eq { expr, right } => vec![(expr, Probe::Eq(right.to_owned()))],
+            WhereCond::Lt { expr, right } => vec![(expr, Probe::Lt(right.to_owned()))],
+            WhereCond::Le { expr, right } => vec![(expr, Probe::Le(right.to_owned()))],
+            WhereCond::Gt { expr, right } => vec![(expr, Probe::Gt(right.to_owned()))],
+            WhereCond::Ge { expr, right } => vec![(expr, Probe::Ge(right.to_owned()))],
+            // The key of the other side is evaluated before the row of the collection is bound.
+            WhereCond::EqExpr { expr, right } => vec![
+                (expr, Probe::Key(right.to_owned())),
+                (right, Probe::Key(expr.to_owned())),
+            ]
+            .into_iter()
+            .filter(|(_, probe)| match probe {
+                Probe::Key(other) => {
+                    variables(other).is_some_and(|variables| !variables.contains(&alias))
+                }
+                _ => false,
+            })
+            .collect(),
+            WhereCond::Neq { .. } | WhereCond::Like { .. } => vec![],
+        };
+        candidates.into_iter().find_map(|(expr, probe)| {
+            let path = path_of(expr, &alias)?;
+            let index = collection
+                .indexes
+                .iter()
+                .filter(|index| index.path == path && index.supports(&probe))
+                .min_by_key(|index| index.kind() != IndexKind::Hash)?;
+            Some((alias.to_owned(), Arc::clone(index), probe))
+        })
+    }
+}
+
+// The path of a row bound to `alias` that `expr` reads, as `id` in `o.id`.
+fn path_of(expr: &Expr, alias: &str) -> Option<Selector> {
+    match expr {
+        Expr::Selector(selector) => match selector.split_first()? {
+            (SelectorNode::String(first), path) if first == alias && path.len() > 0 => Some(path),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+// Whether a join of `plan` binds `name`.
+fn binds(plan: &PhysicalPlan, name: &str) -> bool {
+    match plan {
+        PhysicalPlan::Scan { .. } => false,
+        PhysicalPlan::Join { input, field, .. } => {
+            field.alias.as_deref() == Some(name) || binds(input, name)
+        }
+        PhysicalPlan::IndexLookup { input, alias, .. } => alias == name || binds(input, name),
+        PhysicalPlan::Filter { input, .. }
+        | PhysicalPlan::Project { input, .. }
+        | PhysicalPlan::Sort { input, .. }
+        | PhysicalPlan::Limit { input, .. }
+        | PhysicalPlan::Guard { input, .. } => binds(input, name),
+    }
+}
+
+// Reads `data` in place of what the scan of `plan` reads.
+fn with_data<'a>(plan: PhysicalPlan<'a>, data: PqlValue) -> PhysicalPlan<'a> {
+    match plan {
+        PhysicalPlan::Scan {
+            source: Source::Rows(_),
+            columns,
+        } => PhysicalPlan::Scan {
+            source: Source::Rows(data),
+            columns,
+        },
+        PhysicalPlan::Scan { columns, .. } => PhysicalPlan::Scan {
+            source: Source::Document(data),
+            columns,
+        },
+        plan => map_input(plan, move |input| with_data(input, data)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::Catalog;
+    use crate::planner::{evaluate, IndexKind, PhysicalPlan};
+    use crate::sql::Sql;
+    use crate::value::{json_value, PqlValue};
+
+    // Integers as JSON has them, and a key that is a float.
+    fn data() -> anyhow::Result<PqlValue> {
+        json_value::to_pqlvalue(serde_json::from_str(
+            r#"{
+                "users": [
+                    { "id": 1, "name": "ann" },
+                    { "id": 2, "name": "bob" },
+                    { "id": 3, "name": "cat" }
+                ],
+                "orders": [
+                    { "id": 10, "user_id": 2, "total": 30 },
+                    { "id": 11, "user_id": 1.0, "total": 120 },
+                    { "id": 12, "user_id": 2, "total": 75.5 },
+                    { "id": 13, "total": 5 },
+                    { "id": 14, "user_id": 3, "total": 100 }
+                ]
+            }"#,
+        )?)
+    }
+
+    fn catalog(kind: IndexKind) -> anyhow::Result<Catalog> {
+        let data = data()?;
+        let mut catalog = Catalog::new();
+        for name in ["users", "orders"] {
+            catalog.register(name, data.to_owned().get(name).unwrap_or_default());
+        }
+        catalog.create_index("users", "id", kind)?;
+        catalog.create_index("orders", "user_id", kind)?;
+        catalog.create_index("orders", "total", kind)?;
+        Ok(catalog)
+    }
+
+    fn has_lookup(plan: &PhysicalPlan) -> bool {
+        match plan {
+            PhysicalPlan::Scan { .. } => false,
+            PhysicalPlan::IndexLookup { .. } => true,
+            PhysicalPlan::Join { input, .. }
+            | PhysicalPlan::Filter { input, .. }
+            | PhysicalPlan::Project { input, .. }
+            | PhysicalPlan::Sort { input, .. }
+            | PhysicalPlan::Limit { input, .. }
+            | PhysicalPlan::Guard { input, .. } => has_lookup(input),
+        }
+    }
+
+    #[test]
+    fn same_rows_as_scans() -> anyhow::Result<()> {
+        let cases = vec![
+            ("SELECT u.name AS name FROM users u WHERE u.id = 2", true, true),
+            ("SELECT o.id AS id FROM orders o WHERE o.total >= 75", false, true),
+            ("SELECT o.id AS id FROM orders o WHERE o.total < 75", false, true),
+            ("SELECT o.id AS id FROM orders o WHERE o.total <= 75", false, true),
+            ("SELECT o.id AS id FROM orders o WHERE o.total > 'a'", false, true),
+            (
+                "SELECT u.name AS name, o.id AS id FROM users u, orders o WHERE o.user_id = u.id",
+                true,
+                true,
+            ),
+            (
+                "SELECT u.name AS name, o.total AS total FROM orders o, users u WHERE u.id = o.user_id",
+                true,
+                true,
+            ),
+            // No index is on the name.
+            ("SELECT u.id AS id FROM users u WHERE u.name = 'bob'", false, false),
+        ];
+        for kind in [IndexKind::Hash, IndexKind::Ordered] {
+            let catalog = catalog(kind)?;
+            for (query, by_hash, by_order) in &cases {
+                let sql = Sql::from_str(query)?;
+                let uses_index = match kind {
+                    IndexKind::Hash => *by_hash,
+                    IndexKind::Ordered => *by_order,
+                };
+                assert_eq!(
+                    has_lookup(&catalog.plan(sql.to_owned())),
+                    uses_index,
+                    "{} {:?}",
+                    query,
+                    kind
+                );
+                assert_eq!(
+                    catalog.evaluate(sql.to_owned())?,
+                    evaluate(sql, data()?),
+                    "{} {:?}",
+                    query,
+                    kind
+                );
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn looks_up_integers_by_value() -> anyhow::Result<()> {
+        for kind in [IndexKind::Hash, IndexKind::Ordered] {
+            let catalog = catalog(kind)?;
+            let sql = Sql::from_str("SELECT u.name AS name FROM users u WHERE u.id = 2")?;
+            assert_eq!(
+                catalog.evaluate(sql)?,
+                PqlValue::from_str("[{ 'name': 'bob' }]")?,
+                "{:?}",
+                kind
+            );
+            // The order of a user whose id is the float `1.0` is found by the integer `1`.
+            let sql = Sql::from_str(
+                "SELECT u.name AS name, o.id AS id FROM users u, orders o WHERE o.user_id = u.id",
+            )?;
+            let ann =
+                json_value::to_pqlvalue(serde_json::from_str(r#"{ "name": "ann", "id": 11 }"#)?)?;
+            match catalog.evaluate(sql)? {
+                PqlValue::Array(rows) => assert!(rows.contains(&ann), "{:?} {:?}", rows, kind),
+                rows => panic!("{:?}", rows),
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn explains_lookups() -> anyhow::Result<()> {
+        let catalog = catalog(IndexKind::Hash)?;
+        let sql = Sql::from_str(
+            "SELECT u.name AS name, o.id AS id FROM users u, orders o WHERE o.user_id = u.id",
+        )?;
+        let explained = catalog.explain(&sql);
+        assert!(
+            explained.contains("IndexLookup o.user_id = u.id [hash index on orders.user_id]"),
+            "{}",
+            explained
+        );
+        // The orders are looked up, and are left out of the document.
+        assert!(
+            explained.contains("Scan document [o, u, users]"),
+            "{}",
+            explained
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rebuilds_indexes() -> anyhow::Result<()> {
+        let mut catalog = catalog(IndexKind::Hash)?;
+        catalog.register("users", PqlValue::from_str("[{ 'id': 2, 'name': 'new' }]")?);
+        let sql = Sql::from_str("SELECT u.name AS name FROM users u WHERE u.id = 2")?;
+        assert!(has_lookup(&catalog.plan(sql.to_owned())));
+        assert_eq!(
+            catalog.evaluate(sql)?,
+            PqlValue::from_str("[{ 'name': 'new' }]")?
+        );
+        assert!(catalog
+            .create_index("nothing", "id", IndexKind::Hash)
+            .is_err());
+        Ok(())
+    }
+}
@@ -1,27 +1,57 @@
 pub use crate::planner::LogicalPlan;
 pub use crate::sql::clause::Limit;
 pub use crate::sql::clause::OrderBy;
+use indexmap::IndexMap as Map;
+
+use crate::planner::ExecOptions;
+
 use crate::sql::Env;
-use crate::sql::Expr;
 use crate::sql::Sql;
+use crate::sql::DOCS;
 use crate::value::PqlValue;
 
-pub fn evaluate<'a>(sql: Sql, data: PqlValue) -> PqlValue {
-    let mut env = Env::default();
-    env.insert("", &Expr::from(data));
-    let plan = LogicalPlan::from(sql);
-    let result = plan.execute(&mut env);
-    result
+pub fn evaluate(sql: Sql, data: PqlValue) -> PqlValue {
+    let data = name_collection(&sql, data.unbag());
+    LogicalPlan::from(sql).execute(&mut Env::from(data))
+}
+
+/// Evaluates like `evaluate`, as `options` says.
+pub fn evaluate_with(sql: Sql, data: PqlValue, options: &ExecOptions) -> anyhow::Result<PqlValue> {
+    let data = name_collection(&sql, data.unbag());
+    LogicalPlan::from(sql).execute_with(&mut Env::from(data), options)
+}
+
+// A collection, such as the documents of a YAML stream, is named `docs` when the first source of
+// the FROM clause is that name, unless its rows have a field of that name. Any other name is
+// looked up in the data as it is.
+pub(crate) fn name_collection(sql: &Sql, data: PqlValue) -> PqlValue {
+    if !sql.reads_docs() {
+        return data;
+    }
+    let is_named = match &data {
+        PqlValue::Array(rows) => !rows
+            .iter()
+            .any(|row| matches!(row, PqlValue::Object(map) if map.contains_key(DOCS))),
+        _ => false,
+    };
+    if is_named {
+        let mut map = Map::new();
+        map.insert(DOCS.to_owned(), data);
+        PqlValue::Object(map)
+    } else {
+        data
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::str::FromStr;
 
+    use super::evaluate;
     use crate::planner::LogicalPlan;
     use crate::sql::Env;
     use crate::sql::Sql;
-    use crate::value::PqlValue;
+    use crate::value::{json_value, PqlValue};
 
     #[test]
     fn test_rename() -> anyhow::Result<()> {
@@ -61,4 +91,19 @@ LIMIT 2
 
         Ok(())
     }
+
+    #[test]
+    fn reads_the_input_as_docs() -> anyhow::Result<()> {
+        let data = json_value::to_pqlvalue(serde_json::from_str(r#"[{ "a": 1 }, { "a": 2 }]"#)?)?;
+        assert_eq!(
+            evaluate(Sql::from_str("SELECT x.a FROM docs x")?, data.to_owned()),
+            json_value::to_pqlvalue(serde_json::from_str(r#"[{ "a": 1 }, { "a": 2 }]"#)?)?
+        );
+        // Any other name is not the input, so a misspelt one reads nothing.
+        assert_eq!(
+            evaluate(Sql::from_str("SELECT x.a FROM typo x")?, data),
+            PqlValue::from_str("[{}, {}]")?
+        );
+        Ok(())
+    }
 }
@@ -0,0 +1,386 @@
+//! `EXPLAIN`: how a query is read and run, as an indented tree of the parsed `Sql`, its
+//! `LogicalPlan` and the optimized `PhysicalPlan`.
+//!
+//! Each expression of the plans is followed by its full path, with every alias of the FROM and
+//! LEFT JOIN clauses replaced by the path it is bound to, as in `o.id => orders.id`.
+
+use crate::planner::index::Probe;
+use crate::planner::{optimize, LogicalPlan, PhysicalPlan, Source};
+use crate::sql::{Env, Expr, Field, Func, Sql, WhereCond};
+use crate::value::{partiql_value, PqlValue};
+
+/// The plan of `sql`, one node to a line, each indented by two spaces below its parent.
+pub fn explain(sql: &Sql) -> String {
+    let plan = LogicalPlan::from(sql.to_owned());
+    let source = plan.source(PqlValue::Missing);
+    explain_with(sql, &optimize(PhysicalPlan::new(plan, source)))
+}
+
+// The plan of `sql`, with `physical` as the plan it runs.
+pub(crate) fn explain_with(sql: &Sql, physical: &PhysicalPlan) -> String {
+    let mut tree = Tree::default();
+    tree.push(0, "Sql");
+    tree.sql(1, sql);
+
+    let plan = LogicalPlan::from(sql.to_owned());
+    let env = aliases(&plan);
+    tree.push(0, "LogicalPlan");
+    tree.logical_plan(1, &plan, &env);
+
+    tree.push(0, "PhysicalPlan");
+    tree.physical_plan(1, physical, &env);
+    tree.0.join("\n")
+}
+
+// The path each alias is bound to, expanded against the aliases before it.
+fn aliases(plan: &LogicalPlan) -> Env {
+    let mut env = Env::default();
+    for field in plan.drains.iter().flat_map(|drain| drain.0.iter()) {
+        if let (Some(alias), Expr::Selector(_)) = (&field.alias, &field.expr) {
+            let path = field.expr.expand_fullpath(&env);
+            env.insert(alias, &path);
+        }
+    }
+    env
+}
+
+#[derive(Default)]
+struct Tree(Vec<String>);
+
+impl Tree {
+    fn push(&mut self, depth: usize, line: impl AsRef<str>) {
+        self.0
+            .push(format!("{}{}", "  ".repeat(depth), line.as_ref()));
+    }
+
+    fn sql(&mut self, depth: usize, sql: &Sql) {
+        let clauses = vec![
+            ("SELECT", &sql.select_clause),
+            ("FROM", &sql.from_clause),
+            ("LEFT JOIN", &sql.left_join_clause),
+        ];
+        for (keyword, fields) in clauses {
+            if !fields.is_empty() {
+                self.push(depth, keyword);
+                for field in fields {
+                    self.push(depth + 1, field_text(field));
+                }
+            }
+        }
+        if let Some(cond) = &sql.where_clause {
+            self.push(depth, format!("WHERE {}", cond_text(cond)));
+        }
+        if let Some(order_by) = &sql.orderby {
+            self.push(depth, format!("ORDER BY {}", order_by_text(order_by)));
+        }
+        if let Some(limit) = &sql.limit {
+            self.push(depth, format!("LIMIT {}", limit_text(limit)));
+        }
+    }
+
+    fn logical_plan(&mut self, depth: usize, plan: &LogicalPlan, env: &Env) {
+        for (i, drain) in plan.drains.iter().enumerate() {
+            self.push(depth, if i == 0 { "Drain" } else { "Drain (LEFT JOIN)" });
+            for field in &drain.0 {
+                self.push(depth + 1, expanded(&field_text(field), &field.expr, env));
+            }
+        }
+        if let Some(cond) = &plan.filter.0 {
+            let line = cond_expanded(cond, env);
+            self.push(depth, format!("Filter {}", line));
+        }
+        self.push(depth, "Projection");
+        for field in &plan.project.0 {
+            self.push(depth + 1, expanded(&field_text(field), &field.expr, env));
+        }
+        if let Some(order_by) = &plan.order_by {
+            self.push(depth, format!("ORDER BY {}", order_by_text(order_by)));
+        }
+        if let Some(limit) = &plan.limit {
+            self.push(depth, format!("LIMIT {}", limit_text(limit)));
+        }
+    }
+
+    fn physical_plan(&mut self, depth: usize, plan: &PhysicalPlan, env: &Env) {
+        let input = match plan {
+            PhysicalPlan::Scan { source, columns } => {
+                let kind = match source {
+                    Source::Document(_) => "document",
+                    Source::Rows(_) => "rows",
+                    Source::Stream(_) => "stream",
+                    Source::Tuples(_) => "tuples",
+                };
+                match columns {
+                    Some(columns) => {
+                        let columns = columns.iter().cloned().collect::<Vec<_>>().join(", ");
+                        self.push(depth, format!("Scan {} [{}]", kind, columns))
+                    }
+                    None => self.push(depth, format!("Scan {}", kind)),
+                }
+                return;
+            }
+            PhysicalPlan::Join {
+                input,
+                field,
+                outer,
+            } => {
+                let join = if *outer { "LeftJoin" } else { "Join" };
+                let line = expanded(&field_text(field), &field.expr, env);
+                self.push(depth, format!("{} {}", join, line));
+                input
+            }
+            PhysicalPlan::Filter { input, cond } => {
+                let line = cond_expanded(cond, env);
+                self.push(depth, format!("Filter {}", line));
+                input
+            }
+            PhysicalPlan::IndexLookup {
+                input,
+                alias,
+                index,
+                probe,
+            } => {
+                let path = format!("{}.{}", alias, index.path.to_string());
+                let (op, value) = match probe {
+                    Probe::Eq(value) => ("=", value_text(value)),
+                    Probe::Key(expr) => ("=", expr_text(expr)),
+                    Probe::Lt(value) => ("<", value_text(value)),
+                    Probe::Le(value) => ("<=", value_text(value)),
+                    Probe::Gt(value) => (">", value_text(value)),
+                    Probe::Ge(value) => (">=", value_text(value)),
+                };
+                self.push(
+                    depth,
+                    format!(
+                        "IndexLookup {} {} {} [{} index on {}.{}]",
+                        path,
+                        op,
+                        value,
+                        index.kind_name(),
+                        index.collection,
+                        index.path.to_string()
+                    ),
+                );
+                input
+            }
+            PhysicalPlan::Project { input, projection } => {
+                let fields = projection
+                    .0
+                    .iter()
+                    .map(|field| expanded(&field_text(field), &field.expr, env))
+                    .collect::<Vec<_>>();
+                self.push(depth, format!("Project {}", fields.join(", ")));
+                input
+            }
+            PhysicalPlan::Sort { input, order_by } => {
+                self.push(depth, format!("Sort {}", order_by_text(order_by)));
+                input
+            }
+            PhysicalPlan::Limit { input, limit } => {
+                self.push(depth, format!("Limit {}", limit_text(limit)));
+                input
+            }
+            // The limits of an execution are not part of its plan.
+            PhysicalPlan::Guard { input, .. } => return self.physical_plan(depth, input, env),
+        };
+        self.physical_plan(depth + 1, input, env);
+    }
+}
+
+// `text` followed by the full path of `expr`, unless it has no path to expand.
+fn expanded(text: &str, expr: &Expr, env: &Env) -> String {
+    match expr {
+        Expr::Star | Expr::Value(_) | Expr::Sql(_) => text.to_owned(),
+        expr => format!("{} => {}", text, expr_text(&expr.expand_fullpath(env))),
+    }
+}
+
+fn field_text(field: &Field) -> String {
+    match &field.alias {
+        Some(alias) => format!("{} AS {}", expr_text(&field.expr), alias),
+        None => expr_text(&field.expr),
+    }
+}
+
+fn cond_text(cond: &WhereCond) -> String {
+    let (expr, op, right) = match cond {
+        WhereCond::Eq { expr, right } => (expr, "=", value_text(right)),
+        WhereCond::Neq { expr, right } => (expr, "!=", value_text(right)),
+        WhereCond::Lt { expr, right } => (expr, "<", value_text(right)),
+        WhereCond::Le { expr, right } => (expr, "<=", value_text(right)),
+        WhereCond::Gt { expr, right } => (expr, ">", value_text(right)),
+        WhereCond::Ge { expr, right } => (expr, ">=", value_text(right)),
+        WhereCond::Like { expr, right } => (
+            expr,
+            "LIKE",
+            value_text(&PqlValue::Str(right.to_owned())),
+        ),
+        WhereCond::EqExpr { expr, right } => (expr, "=", expr_text(right)),
+    };
+    format!("{} {} {}", expr_text(expr), op, right)
+}
+
+// A condition followed by the full paths of its expressions.
+fn cond_expanded(cond: &WhereCond, env: &Env) -> String {
+    match cond {
+        WhereCond::EqExpr { expr, right } => format!(
+            "{} => {} = {}",
+            cond_text(cond),
+            expr_text(&expr.to_owned().expand_fullpath(env)),
+            expr_text(&right.to_owned().expand_fullpath(env))
+        ),
+        cond => expanded(&cond_text(cond), &cond.as_expr(), env),
+    }
+}
+
+fn order_by_text(order_by: &crate::sql::clause::OrderBy) -> String {
+    let direction = if order_by.is_asc { "ASC" } else { "DESC" };
+    format!("{} {}", order_by.label, direction)
+}
+
+fn limit_text(limit: &crate::sql::clause::Limit) -> String {
+    format!("{} OFFSET {}", limit.limit, limit.offset)
+}
+
+fn value_text(value: &PqlValue) -> String {
+    partiql_value::to_string(value, true)
+}
+
+fn expr_text(expr: &Expr) -> String {
+    // An operand that is itself an operation is parenthesized.
+    let operand = |expr: &Expr| match expr {
+        Expr::Add(..)
+        | Expr::Sub(..)
+        | Expr::Mul(..)
+        | Expr::Div(..)
+        | Expr::Rem(..)
+        | Expr::Exp(..) => format!("({})", expr_text(expr)),
+        expr => expr_text(expr),
+    };
+    let (left, op, right) = match expr {
+        Expr::Star => return "*".to_owned(),
+        Expr::Selector(selector) => return selector.to_string(),
+        Expr::Value(value) => return value_text(value),
+        Expr::Func(func) => return func_text(func),
+        Expr::Sql(_) => return "(subquery)".to_owned(),
+        Expr::Add(left, right) => (left, "+", right),
+        Expr::Sub(left, right) => (left, "-", right),
+        Expr::Mul(left, right) => (left, "*", right),
+        Expr::Div(left, right) => (left, "/", right),
+        Expr::Rem(left, right) => (left, "%", right),
+        Expr::Exp(left, right) => (left, "^", right),
+    };
+    format!("{} {} {}", operand(left), op, operand(right))
+}
+
+fn func_text(func: &Func) -> String {
+    match func {
+        Func::Count(expr) => format!("COUNT({})", expr_text(expr)),
+        Func::Upper(expr) => format!("UPPER({})", expr_text(expr)),
+        Func::Extract(part, expr) => format!("EXTRACT({} FROM {})", part, expr_text(expr)),
+        Func::DateAdd(part, a, b) => {
+            format!("DATE_ADD({}, {}, {})", part, expr_text(a), expr_text(b))
+        }
+        Func::DateDiff(part, a, b) => {
+            format!("DATE_DIFF({}, {}, {})", part, expr_text(a), expr_text(b))
+        }
+        Func::ToString(a, b) => format!("TO_STRING({}, {})", expr_text(a), expr_text(b)),
+        Func::ToTimestamp(a, Some(b)) => {
+            format!("TO_TIMESTAMP({}, {})", expr_text(a), expr_text(b))
+        }
+        Func::ToTimestamp(a, None) => format!("TO_TIMESTAMP({})", expr_text(a)),
+        Func::UtcNow => "UTCNOW()".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::explain;
+    use crate::sql::{Sql, Statement};
+
+    #[test]
+    fn explains_plans() -> anyhow::Result<()> {
+        let statement = Statement::from_str(
+            "EXPLAIN SELECT o.id AS id, i.sku FROM orders o, o.items i WHERE o.id = 2 LIMIT 3",
+        )?;
+        let sql = match statement {
+            Statement::Explain(sql) => sql,
+            statement => panic!("{:?}", statement),
+        };
+        let expected = r#"Sql
+  SELECT
+    o.id AS id
+    i.sku
+  FROM
+    orders AS o
+    o.items AS i
+  WHERE o.id = 2e0
+  LIMIT 3 OFFSET 0
+LogicalPlan
+  Drain
+    orders AS o => orders
+    o.items AS i => orders.items
+  Drain (LEFT JOIN)
+  Filter o.id = 2e0 => orders.id
+  Projection
+    o.id AS id => orders.id
+    i.sku => orders.items.sku
+  LIMIT 3 OFFSET 0
+PhysicalPlan
+  Project o.id AS id => orders.id, i.sku => orders.items.sku
+    Limit 3 OFFSET 0
+      Join o.items AS i => orders.items
+        Filter o.id = 2e0 => orders.id
+          Join orders AS o => orders
+            Scan document [i, o, orders]"#;
+        assert_eq!(explain(&sql), expected);
+        Ok(())
+    }
+
+    #[test]
+    fn explains_rows() -> anyhow::Result<()> {
+        let sql = Sql::from_str("SELECT UPPER(name) AS name, price * 2 ORDER BY name DESC")?;
+        let expected = r#"Sql
+  SELECT
+    UPPER(name) AS name
+    price * 2e0
+  ORDER BY name DESC
+LogicalPlan
+  Drain
+  Drain (LEFT JOIN)
+  Projection
+    UPPER(name) AS name => UPPER(name)
+    price * 2e0 => price * 2e0
+  ORDER BY name DESC
+PhysicalPlan
+  Sort name DESC
+    Project UPPER(name) AS name => UPPER(name), price * 2e0 => price * 2e0
+      Scan rows [name, price]"#;
+        assert_eq!(explain(&sql), expected);
+
+        assert!(matches!(
+            Statement::from_str("SELECT explained")?,
+            Statement::Query(_)
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn explains_only_whole_queries() -> anyhow::Result<()> {
+        let err =
+            Statement::from_str("EXPLAIN SELECT x.n AS n FROM big x WHERE x.n + 1 > 2 * 3 LIMIT 2")
+                .unwrap_err();
+        assert_eq!(err.to_string(), "cannot parse the query at: * 3 LIMIT 2");
+
+        let statement =
+            Statement::from_str("EXPLAIN SELECT x.n AS n FROM big x WHERE x.n + 1 > 6 LIMIT 2")?;
+        let sql = match statement {
+            Statement::Explain(sql) => sql,
+            statement => panic!("{:?}", statement),
+        };
+        assert!(explain(&sql).contains("Limit 2 OFFSET 0"));
+        Ok(())
+    }
+}
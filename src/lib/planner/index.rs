@@ -0,0 +1,358 @@
+//! In-memory indexes over a path of the rows of a collection.
+//!
+//! An index keeps the value of its path in each row, its key, and finds the rows whose key
+//! matches a value without reading the others. A hash index finds the rows whose key equals a
+//! value; an ordered index finds those whose key is less or greater than a value as well. Either
+//! finds exactly the rows that the condition it stands for keeps, in the order of the collection.
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use bigdecimal::BigDecimal;
+
+use crate::sql::{Env, Expr, Selector};
+use crate::value::{float_to_decimal, BPqlValue, PqlValue};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexKind {
+    /// Finds the rows whose key equals a value.
+    Hash,
+    /// Finds the rows whose key equals, or is less or greater than, a value.
+    Ordered,
+}
+
+/// What an index looks up: the rows whose key compares to a value as a WHERE clause would.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Probe {
+    Eq(PqlValue),
+    /// Keys equal to the value of an expression evaluated in each tuple of the input, as the key
+    /// of a join. A missing or null key equals nothing.
+    Key(Expr),
+    Lt(PqlValue),
+    Le(PqlValue),
+    Gt(PqlValue),
+    Ge(PqlValue),
+}
+
+enum Entries {
+    /// The rows of each key, apart from keys that are collections, which have no hash.
+    Hash(HashMap<BPqlValue, Vec<usize>>),
+    /// The rows whose key has an order, sorted by their key.
+    Ordered(Vec<usize>),
+}
+
+pub struct Index {
+    pub collection: String,
+    pub path: Selector,
+    rows: Arc<Vec<PqlValue>>,
+    keys: Vec<PqlValue>,
+    entries: Entries,
+}
+
+impl fmt::Debug for Index {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Index({} index on {}.{})",
+            self.kind_name(),
+            self.collection,
+            self.path.to_string()
+        )
+    }
+}
+
+impl Index {
+    /// Indexes `rows`, the rows of `collection`, on `path`, as `p` in `SELECT * FROM c WHERE p`.
+    pub fn new(
+        collection: &str,
+        rows: Arc<Vec<PqlValue>>,
+        path: &str,
+        kind: IndexKind,
+    ) -> anyhow::Result<Self> {
+        let path = match Expr::from_str(path) {
+            Ok(Expr::Selector(selector)) if selector.len() > 0 => selector,
+            _ => anyhow::bail!(
+                "an index is on a path, such as `id` or `address.city`: {}",
+                path
+            ),
+        };
+        Ok(Self::build(collection, rows, path, kind))
+    }
+
+    // Indexes `rows` on `path`, which is known to be a path.
+    pub(crate) fn build(
+        collection: &str,
+        rows: Arc<Vec<PqlValue>>,
+        path: Selector,
+        kind: IndexKind,
+    ) -> Self {
+        let keys = rows
+            .iter()
+            .map(|row| Expr::Selector(path.to_owned()).eval(&Env::from(row.to_owned())))
+            .collect::<Vec<_>>();
+        let entries = match kind {
+            IndexKind::Hash => {
+                let mut map = HashMap::<BPqlValue, Vec<usize>>::new();
+                for (i, key) in keys.iter().enumerate() {
+                    if is_hashable(key) {
+                        map.entry(hash_key(key)).or_default().push(i);
+                    }
+                }
+                Entries::Hash(map)
+            }
+            IndexKind::Ordered => {
+                let mut sorted = (0..keys.len())
+                    .filter(|&i| rank(&keys[i]).is_some())
+                    .collect::<Vec<_>>();
+                sorted.sort_by(|&a, &b| order(&keys[a], &keys[b]));
+                Entries::Ordered(sorted)
+            }
+        };
+        Self {
+            collection: collection.to_owned(),
+            path,
+            rows,
+            keys,
+            entries,
+        }
+    }
+
+    pub fn kind(&self) -> IndexKind {
+        match self.entries {
+            Entries::Hash(_) => IndexKind::Hash,
+            Entries::Ordered(_) => IndexKind::Ordered,
+        }
+    }
+
+    pub(crate) fn kind_name(&self) -> &'static str {
+        match self.kind() {
+            IndexKind::Hash => "hash",
+            IndexKind::Ordered => "ordered",
+        }
+    }
+
+    /// Whether the index can look up `probe`. Only an ordered index looks up ranges.
+    pub fn supports(&self, probe: &Probe) -> bool {
+        matches!(probe, Probe::Eq(_) | Probe::Key(_)) || self.kind() == IndexKind::Ordered
+    }
+
+    /// The rows whose key matches `probe`, evaluated in `env`, in the order of the collection.
+    pub fn lookup(&self, probe: &Probe, env: &Env) -> Vec<PqlValue> {
+        let mut found = match probe {
+            Probe::Eq(value) => self.equal(value),
+            Probe::Key(expr) => match expr.to_owned().eval(env) {
+                PqlValue::Missing | PqlValue::Null => vec![],
+                value => self.equal(&value),
+            },
+            Probe::Lt(value) => self.range(value, |ordering| ordering == Ordering::Less),
+            Probe::Le(value) => self.range(value, |ordering| ordering != Ordering::Greater),
+            Probe::Gt(value) => self.range(value, |ordering| ordering == Ordering::Greater),
+            Probe::Ge(value) => self.range(value, |ordering| ordering != Ordering::Less),
+        };
+        found.sort_unstable();
+        found.into_iter().map(|i| self.rows[i].to_owned()).collect()
+    }
+
+    // The rows whose key is equal to `value`.
+    fn equal(&self, value: &PqlValue) -> Vec<usize> {
+        match &self.entries {
+            Entries::Hash(map) if is_hashable(value) => {
+                map.get(&hash_key(value)).cloned().unwrap_or_default()
+            }
+            Entries::Ordered(_) if rank(value).is_some() => {
+                let mut found = self.range(value, |ordering| ordering == Ordering::Equal);
+                // Values of the same kind that do not compare have the same place in the order.
+                found.retain(|&i| self.keys[i].equals(value));
+                found
+            }
+            _ => (0..self.keys.len())
+                .filter(|&i| self.keys[i].equals(value))
+                .collect(),
+        }
+    }
+
+    // The rows of an ordered index whose key compares to `value` as `is_match` says.
+    fn range(&self, value: &PqlValue, is_match: impl Fn(Ordering) -> bool) -> Vec<usize> {
+        let sorted = match &self.entries {
+            Entries::Ordered(sorted) => sorted,
+            Entries::Hash(_) => unreachable!("a hash index looks up no range"),
+        };
+        let rank = match rank(value) {
+            Some(rank) => rank,
+            None => return vec![],
+        };
+        // The keys of the same kind as `value`, then those less than, equal to, and greater than it.
+        let start = sorted.partition_point(|&i| self::rank(&self.keys[i]) < Some(rank));
+        let end = sorted.partition_point(|&i| self::rank(&self.keys[i]) <= Some(rank));
+        let same = &sorted[start..end];
+        let less = same.partition_point(|&i| self.compare(i, value) == Ordering::Less);
+        let not_greater = same.partition_point(|&i| self.compare(i, value) != Ordering::Greater);
+        let mut found = vec![];
+        for (ordering, slice) in [
+            (Ordering::Less, &same[..less]),
+            (Ordering::Equal, &same[less..not_greater]),
+            (Ordering::Greater, &same[not_greater..]),
+        ] {
+            if is_match(ordering) {
+                found.extend_from_slice(slice);
+            }
+        }
+        found
+    }
+
+    fn compare(&self, i: usize, value: &PqlValue) -> Ordering {
+        self.keys[i].compare(value).unwrap_or(Ordering::Equal)
+    }
+}
+
+// Whether `value` has a hash, as every value but a collection does.
+fn is_hashable(value: &PqlValue) -> bool {
+    match value {
+        PqlValue::Array(_) | PqlValue::Object(_) => false,
+        PqlValue::Annotated(_, value) => is_hashable(value),
+        _ => true,
+    }
+}
+
+// The key of `value` in a hash index. A number of any type is the decimal it is worth, so that
+// `2`, `2e0` and `2.0`, which are equal, are one key.
+fn hash_key(value: &PqlValue) -> BPqlValue {
+    let decimal = match value {
+        PqlValue::Int(int) => Some(BigDecimal::from(*int)),
+        PqlValue::Float(float) => float_to_decimal(float.0),
+        PqlValue::Decimal(decimal) => Some(decimal.to_owned()),
+        _ => None,
+    };
+    match decimal {
+        Some(decimal) => BPqlValue::Decimal(decimal.normalized()),
+        None => BPqlValue::from(value.to_owned()),
+    }
+}
+
+// The kind of a value that has an order, among the values it compares to.
+fn rank(value: &PqlValue) -> Option<u8> {
+    match value {
+        PqlValue::Int(_) | PqlValue::Float(_) | PqlValue::Decimal(_) => Some(0),
+        PqlValue::Str(_) => Some(1),
+        PqlValue::Symbol(_) => Some(2),
+        PqlValue::Boolean(_) => Some(3),
+        PqlValue::DateTime(_) => Some(4),
+        PqlValue::Date(_) => Some(5),
+        PqlValue::Time(_) => Some(6),
+        PqlValue::Annotated(_, value) => rank(value),
+        _ => None,
+    }
+}
+
+// The order of two keys that have one: by kind, then as they compare.
+fn order(a: &PqlValue, b: &PqlValue) -> Ordering {
+    rank(a)
+        .cmp(&rank(b))
+        .then_with(|| a.compare(b).unwrap_or(Ordering::Equal))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::sync::Arc;
+
+    use super::{Index, IndexKind, Probe};
+    use crate::sql::{Env, Expr};
+    use crate::value::{json_value, PqlValue};
+
+    fn rows() -> anyhow::Result<Arc<Vec<PqlValue>>> {
+        let rows = vec![
+            "{ 'id': 3, 'name': 'c' }",
+            "{ 'id': 1, 'name': 'a' }",
+            "{ 'name': 'none' }",
+            "{ 'id': 'x', 'name': 'str' }",
+            "{ 'id': 2, 'name': 'b' }",
+            "{ 'id': 1, 'name': 'a2' }",
+        ]
+        .into_iter()
+        .map(PqlValue::from_str)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(Arc::new(rows))
+    }
+
+    fn names(rows: Vec<PqlValue>) -> Vec<String> {
+        rows.into_iter()
+            .filter_map(|row| match row.get("name") {
+                Some(PqlValue::Str(name)) => Some(name),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn looks_up_equal_keys() -> anyhow::Result<()> {
+        let env = Env::default();
+        for kind in [IndexKind::Hash, IndexKind::Ordered] {
+            let index = Index::new("t", rows()?, "id", kind)?;
+            let one = index.lookup(&Probe::Eq(PqlValue::from_str("1")?), &env);
+            assert_eq!(names(one), vec!["a", "a2"], "{:?}", kind);
+            let x = index.lookup(&Probe::Eq(PqlValue::from("x")), &env);
+            assert_eq!(names(x), vec!["str"], "{:?}", kind);
+            let missing = index.lookup(&Probe::Eq(PqlValue::from_str("9")?), &env);
+            assert!(missing.is_empty());
+
+            // A row without the key is found by a MISSING literal, but a missing key of a join
+            // equals nothing.
+            let none = index.lookup(&Probe::Eq(PqlValue::Missing), &env);
+            assert_eq!(names(none), vec!["none"], "{:?}", kind);
+            let row = Env::from(PqlValue::from_str("{ 'id': 1 }")?);
+            let key = index.lookup(&Probe::Key(Expr::from_str("absent")?), &row);
+            assert!(key.is_empty());
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn looks_up_numbers_of_any_type() -> anyhow::Result<()> {
+        let env = Env::default();
+        let rows = json_value::to_pqlvalue(serde_json::from_str(
+            r#"[{ "id": 2, "name": "int" }, { "id": 2.0, "name": "float" }, { "id": 3 }]"#,
+        )?)?;
+        let rows = match rows {
+            PqlValue::Array(rows) => Arc::new(rows),
+            _ => unreachable!(),
+        };
+        for kind in [IndexKind::Hash, IndexKind::Ordered] {
+            let index = Index::new("t", rows.to_owned(), "id", kind)?;
+            for two in [
+                PqlValue::from(2.),
+                PqlValue::Int(2),
+                PqlValue::from_str("2")?,
+            ] {
+                let found = index.lookup(&Probe::Eq(two.to_owned()), &env);
+                assert_eq!(names(found), vec!["int", "float"], "{:?} {:?}", kind, two);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn looks_up_ranges() -> anyhow::Result<()> {
+        let env = Env::default();
+        let index = Index::new("t", rows()?, "id", IndexKind::Ordered)?;
+        let two = PqlValue::from(2.);
+        let cases = vec![
+            (Probe::Lt(two.to_owned()), vec!["a", "a2"]),
+            (Probe::Le(two.to_owned()), vec!["a", "b", "a2"]),
+            (Probe::Gt(two.to_owned()), vec!["c"]),
+            (Probe::Ge(two.to_owned()), vec!["c", "b"]),
+            (Probe::Gt(PqlValue::from("a")), vec!["str"]),
+        ];
+        for (probe, expected) in cases {
+            assert_eq!(names(index.lookup(&probe, &env)), expected, "{:?}", probe);
+        }
+
+        let hash = Index::new("t", rows()?, "id", IndexKind::Hash)?;
+        assert!(!hash.supports(&Probe::Lt(two)));
+        assert!(Index::new("t", rows()?, "1 + 1", IndexKind::Hash).is_err());
+        Ok(())
+    }
+}
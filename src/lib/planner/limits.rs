@@ -0,0 +1,270 @@
+//! Limits on the resources a query uses: when it must be done, whether it was cancelled, how many
+//! tuples its operators produce and roughly how many bytes of them it holds.
+//!
+//! Each operator checks the limits as it produces a tuple, so that a query that exceeds one stops
+//! with an `ExecError` as soon as it does, instead of running until it is done.
+
+use std::mem;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Instant;
+
+use parse_display::Display;
+
+use crate::planner::optimizer::map_input;
+use crate::planner::{ExecOptions, PhysicalPlan, Source};
+use crate::sql::{Env, Expr};
+use crate::value::PqlValue;
+
+/// Why a query was stopped before it was done.
+#[derive(Display, Debug, Clone, PartialEq, Eq)]
+pub enum ExecError {
+    #[display("the query ran past its deadline")]
+    Timeout,
+    #[display("the query was cancelled")]
+    Cancelled,
+    #[display("the query produced more than {max} rows")]
+    TooManyRows { max: usize },
+    #[display("the query held more than about {max} bytes of rows")]
+    OutOfMemory { max: usize },
+}
+
+impl std::error::Error for ExecError {}
+
+/// Cancels the queries it is given to from another thread. A query stops with
+/// `ExecError::Cancelled` at the next tuple one of its operators produces.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl PartialEq for CancelToken {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The limits of one execution of a plan, with what it has used of them so far. It is shared
+/// by the operators of the plan, on every thread they run on.
+#[derive(Debug)]
+pub struct Limits {
+    deadline: Option<Instant>,
+    cancel: Option<CancelToken>,
+    max_rows: Option<usize>,
+    max_memory: Option<usize>,
+    rows: AtomicUsize,
+    memory: AtomicUsize,
+}
+
+impl Limits {
+    pub fn new(options: &ExecOptions) -> Self {
+        Self {
+            deadline: options.deadline,
+            cancel: options.cancel.to_owned(),
+            max_rows: options.max_rows,
+            max_memory: options.max_memory,
+            rows: AtomicUsize::new(0),
+            memory: AtomicUsize::new(0),
+        }
+    }
+
+    /// Whether there is no limit to check.
+    pub fn is_unlimited(&self) -> bool {
+        self.deadline.is_none()
+            && self.cancel.is_none()
+            && self.max_rows.is_none()
+            && self.max_memory.is_none()
+    }
+
+    /// Stops a query that is past its deadline or cancelled.
+    pub fn check(&self) -> Result<(), ExecError> {
+        if self.cancel.as_ref().is_some_and(CancelToken::is_cancelled) {
+            return Err(ExecError::Cancelled);
+        }
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            return Err(ExecError::Timeout);
+        }
+        Ok(())
+    }
+
+    /// Counts `tuple` as produced, and as held until the end of the query if `held`.
+    pub fn admit(&self, tuple: &Env, held: bool) -> Result<(), ExecError> {
+        self.check()?;
+        let rows = self.rows.fetch_add(1, Ordering::Relaxed) + 1;
+        if let Some(max) = self.max_rows {
+            if rows > max {
+                return Err(ExecError::TooManyRows { max });
+            }
+        }
+        if let (Some(max), true) = (self.max_memory, held) {
+            let size = tuple_size(tuple);
+            if self.memory.fetch_add(size, Ordering::Relaxed) + size > max {
+                return Err(ExecError::OutOfMemory { max });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Puts a check of `limits` above every operator of `plan`. The tuples that ORDER BY sorts are
+/// held until the end of the query, and so are those of the result when `held`. Tuples that are
+/// already bound, such as those of a chunk run in parallel, were checked as they were produced.
+pub fn guard<'a>(plan: PhysicalPlan<'a>, limits: &Arc<Limits>, held: bool) -> PhysicalPlan<'a> {
+    if limits.is_unlimited()
+        || matches!(
+            plan,
+            PhysicalPlan::Scan {
+                source: Source::Tuples(_),
+                ..
+            }
+        )
+    {
+        return plan;
+    }
+    let sorts = matches!(plan, PhysicalPlan::Sort { .. });
+    let plan = map_input(plan, |input| guard(input, limits, sorts));
+    PhysicalPlan::Guard {
+        input: Box::new(plan),
+        limits: Arc::clone(limits),
+        held,
+    }
+}
+
+// The approximate size in memory of the values a tuple binds, not counting those of the tuple
+// it was produced from, which it shares.
+fn tuple_size(tuple: &Env) -> usize {
+    tuple
+        .bindings()
+        .map(|(name, expr)| {
+            name.len()
+                + match expr {
+                    Expr::Value(value) => value_size(value),
+                    _ => mem::size_of::<Expr>(),
+                }
+        })
+        .sum()
+}
+
+fn value_size(value: &PqlValue) -> usize {
+    mem::size_of::<PqlValue>()
+        + match value {
+            PqlValue::Str(string) | PqlValue::Symbol(string) => string.len(),
+            PqlValue::Blob(bytes) | PqlValue::Clob(bytes) => bytes.len(),
+            PqlValue::Annotated(annotations, value) => {
+                annotations.iter().map(String::len).sum::<usize>() + value_size(value)
+            }
+            PqlValue::Array(array) => array.iter().map(value_size).sum(),
+            PqlValue::Object(map) => map
+                .iter()
+                .map(|(key, value)| key.len() + value_size(value))
+                .sum(),
+            _ => 0,
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+    use std::time::{Duration, Instant};
+
+    use super::{CancelToken, ExecError};
+    use crate::planner::{evaluate_with, ExecOptions};
+    use crate::sql::Sql;
+    use crate::value::PqlValue;
+
+    fn data() -> anyhow::Result<PqlValue> {
+        let numbers = (0..200).map(|i| format!("{}", i)).collect::<Vec<_>>();
+        PqlValue::from_str(&format!("{{ 'a': [{0}], 'b': [{0}] }}", numbers.join(", ")))
+    }
+
+    fn error(sql: &str, options: ExecOptions) -> anyhow::Result<ExecError> {
+        let sql = Sql::from_str(sql)?;
+        let err = evaluate_with(sql, data()?, &options).unwrap_err();
+        err.downcast::<ExecError>()
+    }
+
+    const CARTESIAN: &str = "SELECT x, y FROM a x, b y";
+
+    #[test]
+    fn stops_at_limits() -> anyhow::Result<()> {
+        for threads in [1, 4] {
+            let options = ExecOptions {
+                threads,
+                ..ExecOptions::default()
+            };
+            assert_eq!(
+                error(
+                    CARTESIAN,
+                    ExecOptions {
+                        max_rows: Some(1000),
+                        ..options.to_owned()
+                    }
+                )?,
+                ExecError::TooManyRows { max: 1000 }
+            );
+            assert_eq!(
+                error(
+                    "SELECT x, y FROM a x, b y ORDER BY x",
+                    ExecOptions {
+                        max_memory: Some(10_000),
+                        ..options.to_owned()
+                    }
+                )?,
+                ExecError::OutOfMemory { max: 10_000 }
+            );
+            assert_eq!(
+                error(
+                    CARTESIAN,
+                    ExecOptions {
+                        deadline: Some(Instant::now() - Duration::from_secs(1)),
+                        ..options.to_owned()
+                    }
+                )?,
+                ExecError::Timeout
+            );
+            let cancel = CancelToken::new();
+            cancel.cancel();
+            assert_eq!(
+                error(
+                    CARTESIAN,
+                    ExecOptions {
+                        cancel: Some(cancel),
+                        ..options.to_owned()
+                    }
+                )?,
+                ExecError::Cancelled
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn runs_within_limits() -> anyhow::Result<()> {
+        let sql = Sql::from_str("SELECT x FROM a x WHERE x = 3")?;
+        let options = ExecOptions {
+            deadline: Some(Instant::now() + Duration::from_secs(60)),
+            cancel: Some(CancelToken::new()),
+            max_rows: Some(1000),
+            max_memory: Some(1 << 20),
+            ..ExecOptions::default()
+        };
+        let unlimited = evaluate_with(sql.to_owned(), data()?, &ExecOptions::default())?;
+        assert_eq!(evaluate_with(sql, data()?, &options)?, unlimited);
+        Ok(())
+    }
+}
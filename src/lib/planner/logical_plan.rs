@@ -1,16 +1,20 @@
 use crate::planner::drain::Drain;
 use crate::planner::filter::Filter;
+use crate::planner::optimize;
+use crate::planner::ExecOptions;
+use crate::planner::physical_plan::{into_row, PhysicalPlan, Source};
 use crate::planner::project::Projection;
 use crate::sql::clause::Limit;
 use crate::sql::clause::OrderBy;
 use crate::sql::Env;
+use crate::sql::Expr;
 
 use crate::sql::Sql;
-use crate::value::BPqlValue;
 use crate::value::PqlValue;
 
 #[derive(Debug, Default)]
 pub struct LogicalPlan {
+    /// The sources of the FROM clause, then those of the LEFT JOIN clause.
     pub drains: Vec<Drain>,
     pub filter: Filter,
     pub project: Projection,
@@ -31,44 +35,33 @@ impl From<Sql> for LogicalPlan {
 }
 
 impl LogicalPlan {
-    pub fn execute(self, env: &mut Env) -> PqlValue {
-        for drain in self.drains {
-            drain.execute(env);
-        }
-
-        self.filter.execute(env);
-
-        let mut list = self.project.execute(&env);
-
-        if let Some(orderby) = &self.order_by {
-            let mut list_with_key = list
-                .into_iter()
-                .filter_map(|record| {
-                    record
-                        .to_owned()
-                        .get(&orderby.label)
-                        .map(|value| (BPqlValue::from(value), record))
-                })
-                .collect::<Vec<_>>();
-            list_with_key.sort_by(|x, y| {
-                if orderby.is_asc {
-                    x.0.partial_cmp(&y.0).unwrap()
-                } else {
-                    y.0.partial_cmp(&x.0).unwrap()
-                }
-            });
-            list = list_with_key
-                .into_iter()
-                .map(|(_k, v)| v)
-                .collect::<Vec<_>>();
+    /// Reads `data` as a single document when the FROM clause names what the query reads,
+    /// and as a collection of rows otherwise.
+    pub fn source<'a>(&self, data: PqlValue) -> Source<'a> {
+        if self.drains.iter().all(|drain| drain.0.is_empty()) {
+            Source::Rows(data)
+        } else {
+            Source::Document(data)
         }
+    }
 
-        if let Some(limit_clause) = &self.limit {
-            let (_, values) = list.split_at(limit_clause.offset as usize);
-            let (values, _) = values.split_at(limit_clause.limit as usize);
-            list = values.to_owned();
-        }
+    /// Runs the plan over the value bound to `""` in `env`.
+    pub fn execute(self, env: &mut Env) -> PqlValue {
+        self.execute_with(env, &ExecOptions::default())
+            .expect("rows in memory are read without error")
+    }
 
-        PqlValue::Array(list)
+    /// Runs the plan like `execute`, as `options` says.
+    pub fn execute_with(self, env: &mut Env, options: &ExecOptions) -> anyhow::Result<PqlValue> {
+        let data = match env.remove("") {
+            Some(Expr::Value(data)) => data,
+            _ => PqlValue::Missing,
+        };
+        let source = self.source(data);
+        let rows = optimize(PhysicalPlan::new(self, source))
+            .execute_with(options)?
+            .map(|tuple| tuple.map(into_row))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(PqlValue::Array(rows))
     }
 }
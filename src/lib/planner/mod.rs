@@ -1,15 +1,32 @@
+mod catalog;
 mod drain;
 mod eval;
+mod explain;
 pub mod filter;
+mod index;
+mod limits;
 mod logical_plan;
+pub mod optimizer;
+mod options;
+mod parallel;
+mod physical_plan;
 pub mod project;
+mod stream;
 
 pub use crate::sql::clause::Limit;
 pub use crate::sql::clause::OrderBy;
 pub use crate::sql::WhereCond;
 
+pub use catalog::Catalog;
 pub use drain::Drain;
-pub use eval::evaluate;
+pub use eval::{evaluate, evaluate_with};
+pub use explain::explain;
 pub use filter::Filter;
+pub use index::{Index, IndexKind, Probe};
+pub use limits::{CancelToken, ExecError, Limits};
 pub use logical_plan::LogicalPlan;
+pub use optimizer::optimize;
+pub use options::ExecOptions;
+pub use physical_plan::{PhysicalPlan, Source};
 pub use project::Projection;
+pub use stream::{evaluate_rows, evaluate_rows_in};
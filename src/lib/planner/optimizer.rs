@@ -0,0 +1,493 @@
+//! Rewrites of a plan, applied between planning and execution, that keep its result but do less
+//! work to produce it:
+//!
+//! - Arithmetic over literals is computed once, while planning.
+//! - WHERE moves below the joins it does not depend on, so that it drops a tuple before the
+//!   collections nested in it are unnested.
+//! - LIMIT moves below SELECT, which projects a single tuple for each of its input, so that only
+//!   the tuples it keeps are projected.
+//! - The scan keeps only the fields of a row that the query refers to.
+
+use std::collections::BTreeSet;
+
+use crate::planner::index::Probe;
+use crate::planner::PhysicalPlan;
+use crate::planner::Projection;
+use crate::sql::Env;
+use crate::sql::EvalError;
+use crate::sql::Expr;
+use crate::sql::Field;
+use crate::sql::WhereCond;
+use crate::value::PqlValue;
+
+/// Applies every rule to `plan`.
+pub fn optimize(plan: PhysicalPlan) -> PhysicalPlan {
+    let plan = fold_constants(plan);
+    let plan = push_down_predicates(plan);
+    let plan = push_down_limits(plan);
+    prune_columns(plan)
+}
+
+/// Replaces arithmetic over numeric literals with its result.
+pub fn fold_constants(plan: PhysicalPlan) -> PhysicalPlan {
+    match plan {
+        PhysicalPlan::Join {
+            input,
+            field,
+            outer,
+        } => PhysicalPlan::Join {
+            input: Box::new(fold_constants(*input)),
+            field: Field {
+                expr: fold(field.expr),
+                alias: field.alias,
+            },
+            outer,
+        },
+        PhysicalPlan::Filter { input, cond } => PhysicalPlan::Filter {
+            input: Box::new(fold_constants(*input)),
+            cond: cond.map_exprs(fold),
+        },
+        PhysicalPlan::Project { input, projection } => PhysicalPlan::Project {
+            input: Box::new(fold_constants(*input)),
+            projection: Projection(
+                projection
+                    .0
+                    .into_iter()
+                    .map(|field| Field {
+                        expr: fold(field.expr),
+                        alias: field.alias,
+                    })
+                    .collect(),
+            ),
+        },
+        plan => map_input(plan, fold_constants),
+    }
+}
+
+/// Moves a filter below each join that binds no variable of its condition.
+pub fn push_down_predicates(plan: PhysicalPlan) -> PhysicalPlan {
+    match plan {
+        PhysicalPlan::Filter {
+            input:
+                box PhysicalPlan::Join {
+                    input,
+                    field,
+                    outer,
+                },
+            cond,
+        } if !depends_on(&cond, &field) => PhysicalPlan::Join {
+            input: Box::new(push_down_predicates(PhysicalPlan::Filter { input, cond })),
+            field,
+            outer,
+        },
+        plan => map_input(plan, push_down_predicates),
+    }
+}
+
+/// Moves a limit below the projection it limits.
+pub fn push_down_limits(plan: PhysicalPlan) -> PhysicalPlan {
+    match plan {
+        PhysicalPlan::Limit {
+            input: box PhysicalPlan::Project { input, projection },
+            limit,
+        } => PhysicalPlan::Project {
+            input: Box::new(push_down_limits(PhysicalPlan::Limit { input, limit })),
+            projection,
+        },
+        plan => map_input(plan, push_down_limits),
+    }
+}
+
+/// Restricts the scan to the fields that the paths of the query start with, unless it refers
+/// to the whole row, as with `SELECT *`.
+pub fn prune_columns(plan: PhysicalPlan) -> PhysicalPlan {
+    match columns(&plan) {
+        Some(columns) => with_columns(plan, columns),
+        None => plan,
+    }
+}
+
+fn fold(expr: Expr) -> Expr {
+    match expr {
+        Expr::Add(box left, box right) => {
+            fold_binary(Expr::Add, left, right, PqlValue::checked_add)
+        }
+        Expr::Sub(box left, box right) => {
+            fold_binary(Expr::Sub, left, right, PqlValue::checked_sub)
+        }
+        Expr::Mul(box left, box right) => {
+            fold_binary(Expr::Mul, left, right, PqlValue::checked_mul)
+        }
+        Expr::Div(box left, box right) => {
+            fold_binary(Expr::Div, left, right, PqlValue::checked_div)
+        }
+        Expr::Rem(box left, box right) => {
+            fold_binary(Expr::Rem, left, right, PqlValue::checked_rem)
+        }
+        Expr::Exp(box left, box right) => {
+            fold_binary(Expr::Exp, left, right, PqlValue::checked_powf)
+        }
+        expr => expr,
+    }
+}
+
+fn fold_binary(
+    make: fn(Box<Expr>, Box<Expr>) -> Expr,
+    left: Expr,
+    right: Expr,
+    op: fn(PqlValue, PqlValue) -> Result<PqlValue, EvalError>,
+) -> Expr {
+    match (fold(left), fold(right)) {
+        (Expr::Value(left), Expr::Value(right)) if is_number(&left) && is_number(&right) => {
+            // An error, such as a division by zero, is left to execution to report or not as its
+            // mode says.
+            match op(left.to_owned(), right.to_owned()) {
+                Ok(value) => Expr::Value(value),
+                Err(_) => make(Box::new(Expr::Value(left)), Box::new(Expr::Value(right))),
+            }
+        }
+        (left, right) => make(Box::new(left), Box::new(right)),
+    }
+}
+
+// Arithmetic over other values is left to execution, which decides what it means.
+fn is_number(value: &PqlValue) -> bool {
+    matches!(
+        value,
+        PqlValue::Int(_) | PqlValue::Float(_) | PqlValue::Decimal(_)
+    )
+}
+
+// Whether `cond` may read the variable that `field` binds. A condition that cannot tell which
+// variables it reads depends on all of them.
+fn depends_on(cond: &WhereCond, field: &Field) -> bool {
+    let alias = match &field.alias {
+        Some(alias) => alias,
+        None => return true,
+    };
+    cond.exprs()
+        .into_iter()
+        .any(|expr| variables(expr).is_none_or(|variables| variables.contains(alias)))
+}
+
+// The names that the paths of `expr` start with: variables, or fields of the row.
+pub(crate) fn variables(expr: &Expr) -> Option<BTreeSet<String>> {
+    let names = expr.source_columns(&Env::default())?;
+    // A path from the root of the input, like `.a`, starts with no name at all.
+    if names.contains("") {
+        return None;
+    }
+    Some(names.into_iter().collect())
+}
+
+// Every name the paths of `plan` start with. A name that is a variable is kept as well, which
+// costs nothing when the row has no such field.
+pub(crate) fn columns(plan: &PhysicalPlan) -> Option<BTreeSet<String>> {
+    let (input, exprs) = match plan {
+        PhysicalPlan::Scan { .. } => return Some(BTreeSet::default()),
+        PhysicalPlan::Join { input, field, .. } => (input, vec![field.expr.to_owned()]),
+        PhysicalPlan::Filter { input, cond } => {
+            (input, cond.exprs().into_iter().cloned().collect())
+        }
+        PhysicalPlan::IndexLookup { input, probe, .. } => match probe {
+            Probe::Key(expr) => (input, vec![expr.to_owned()]),
+            _ => (input, vec![]),
+        },
+        PhysicalPlan::Project { input, projection } => (
+            input,
+            projection
+                .0
+                .iter()
+                .map(|field| field.expr.to_owned())
+                .collect(),
+        ),
+        // ORDER BY and LIMIT read the projected tuples, not the row.
+        PhysicalPlan::Sort { input, .. }
+        | PhysicalPlan::Limit { input, .. }
+        | PhysicalPlan::Guard { input, .. } => (input, vec![]),
+    };
+    let mut columns = self::columns(input)?;
+    for expr in exprs {
+        columns.extend(variables(&expr)?);
+    }
+    Some(columns)
+}
+
+fn with_columns(plan: PhysicalPlan, columns: BTreeSet<String>) -> PhysicalPlan {
+    match plan {
+        PhysicalPlan::Scan { source, .. } => PhysicalPlan::Scan {
+            source,
+            columns: Some(columns),
+        },
+        plan => map_input(plan, move |input| with_columns(input, columns)),
+    }
+}
+
+// Rewrites the input of `plan` with `f`.
+pub(crate) fn map_input<'a>(
+    plan: PhysicalPlan<'a>,
+    f: impl FnOnce(PhysicalPlan<'a>) -> PhysicalPlan<'a>,
+) -> PhysicalPlan<'a> {
+    match plan {
+        PhysicalPlan::Scan { .. } => plan,
+        PhysicalPlan::Join {
+            input,
+            field,
+            outer,
+        } => PhysicalPlan::Join {
+            input: Box::new(f(*input)),
+            field,
+            outer,
+        },
+        PhysicalPlan::Filter { input, cond } => PhysicalPlan::Filter {
+            input: Box::new(f(*input)),
+            cond,
+        },
+        PhysicalPlan::IndexLookup {
+            input,
+            alias,
+            index,
+            probe,
+        } => PhysicalPlan::IndexLookup {
+            input: Box::new(f(*input)),
+            alias,
+            index,
+            probe,
+        },
+        PhysicalPlan::Project { input, projection } => PhysicalPlan::Project {
+            input: Box::new(f(*input)),
+            projection,
+        },
+        PhysicalPlan::Sort { input, order_by } => PhysicalPlan::Sort {
+            input: Box::new(f(*input)),
+            order_by,
+        },
+        PhysicalPlan::Limit { input, limit } => PhysicalPlan::Limit {
+            input: Box::new(f(*input)),
+            limit,
+        },
+        PhysicalPlan::Guard {
+            input,
+            limits,
+            held,
+        } => PhysicalPlan::Guard {
+            input: Box::new(f(*input)),
+            limits,
+            held,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeSet;
+    use std::str::FromStr;
+
+    use super::{fold_constants, optimize, prune_columns, push_down_limits, push_down_predicates};
+    use crate::planner::{LogicalPlan, PhysicalPlan, Source};
+    use crate::sql::{Expr, Field, Sql, WhereCond};
+    use crate::value::PqlValue;
+
+    fn plan_of(query: &str) -> anyhow::Result<PhysicalPlan<'static>> {
+        let sql = Sql::from_str(query)?;
+        Ok(PhysicalPlan::new(
+            LogicalPlan::from(sql),
+            Source::Rows(PqlValue::Missing),
+        ))
+    }
+
+    #[test]
+    fn folds_constants() -> anyhow::Result<()> {
+        let plan = fold_constants(plan_of(
+            "SELECT 2 * 3 + a AS x, 2 ^ 3 AS y WHERE 1 + 1 = 2",
+        )?);
+        match plan {
+            PhysicalPlan::Project {
+                input:
+                    box PhysicalPlan::Filter {
+                        cond: WhereCond::Eq { expr, .. },
+                        ..
+                    },
+                projection,
+            } => {
+                assert_eq!(expr, Expr::Value(PqlValue::from(2.)));
+                match &projection.0[..] {
+                    [Field {
+                        expr: Expr::Add(box Expr::Value(six), box Expr::Selector(_)),
+                        ..
+                    }, Field {
+                        expr: Expr::Value(eight),
+                        ..
+                    }] => {
+                        assert_eq!(six, &PqlValue::from(6.));
+                        assert_eq!(eight, &PqlValue::from(8.));
+                    }
+                    fields => panic!("{:?}", fields),
+                }
+            }
+            plan => panic!("{:?}", plan),
+        }
+
+        // Arithmetic over a path is left to execution.
+        let plan = fold_constants(plan_of("SELECT 1 + a AS x")?);
+        assert!(matches!(
+            plan,
+            PhysicalPlan::Project { projection, .. }
+                if matches!(projection.0[0].expr, Expr::Add(..))
+        ));
+        // So is an error, which strict mode reports and permissive mode makes MISSING.
+        let plan = fold_constants(plan_of("SELECT 1 / 0 AS x")?);
+        assert!(matches!(
+            plan,
+            PhysicalPlan::Project { projection, .. }
+                if matches!(projection.0[0].expr, Expr::Div(..))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn pushes_predicates_below_joins() -> anyhow::Result<()> {
+        let plan = push_down_predicates(plan_of(
+            "SELECT o.id AS id FROM orders o, o.items i WHERE o.id = 2",
+        )?);
+        assert!(matches!(
+            plan,
+            PhysicalPlan::Project {
+                input:
+                    box PhysicalPlan::Join {
+                        input:
+                            box PhysicalPlan::Filter {
+                                input: box PhysicalPlan::Join { .. },
+                                ..
+                            },
+                        ..
+                    },
+                ..
+            }
+        ));
+
+        // A condition on the innermost variable stays above its join.
+        let plan = push_down_predicates(plan_of(
+            "SELECT o.id AS id FROM orders o, o.items i WHERE i.sku = 'a'",
+        )?);
+        assert!(matches!(
+            plan,
+            PhysicalPlan::Project {
+                input: box PhysicalPlan::Filter {
+                    input: box PhysicalPlan::Join { .. },
+                    ..
+                },
+                ..
+            }
+        ));
+
+        // A condition on a field of the row goes down to the scan, even below an outer join.
+        let plan = push_down_predicates(plan_of(
+            "SELECT o.id AS id FROM orders o LEFT JOIN o.items i WHERE region = 'eu'",
+        )?);
+        assert!(matches!(
+            plan,
+            PhysicalPlan::Project {
+                input: box PhysicalPlan::Join {
+                    input: box PhysicalPlan::Join {
+                        input: box PhysicalPlan::Filter {
+                            input: box PhysicalPlan::Scan { .. },
+                            ..
+                        },
+                        ..
+                    },
+                    ..
+                },
+                ..
+            }
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn pushes_limits_below_projections() -> anyhow::Result<()> {
+        let plan = push_down_limits(plan_of("SELECT a WHERE b = 1 LIMIT 2")?);
+        assert!(matches!(
+            plan,
+            PhysicalPlan::Project {
+                input: box PhysicalPlan::Limit {
+                    input: box PhysicalPlan::Filter { .. },
+                    ..
+                },
+                ..
+            }
+        ));
+
+        // The tuples to keep are only known once they are sorted.
+        let plan = push_down_limits(plan_of("SELECT a ORDER BY a LIMIT 2")?);
+        assert!(matches!(
+            plan,
+            PhysicalPlan::Limit {
+                input: box PhysicalPlan::Sort { .. },
+                ..
+            }
+        ));
+        Ok(())
+    }
+
+    fn scan_columns(plan: &PhysicalPlan) -> Option<BTreeSet<String>> {
+        match plan {
+            PhysicalPlan::Scan { columns, .. } => columns.to_owned(),
+            PhysicalPlan::Join { input, .. }
+            | PhysicalPlan::Filter { input, .. }
+            | PhysicalPlan::IndexLookup { input, .. }
+            | PhysicalPlan::Project { input, .. }
+            | PhysicalPlan::Sort { input, .. }
+            | PhysicalPlan::Limit { input, .. }
+            | PhysicalPlan::Guard { input, .. } => scan_columns(input),
+        }
+    }
+
+    #[test]
+    fn prunes_columns() -> anyhow::Result<()> {
+        let plan = prune_columns(plan_of(
+            "SELECT name, UPPER(city) AS city WHERE price = 1 ORDER BY name",
+        )?);
+        let expected = vec!["city", "name", "price"]
+            .into_iter()
+            .map(String::from)
+            .collect::<BTreeSet<_>>();
+        assert_eq!(scan_columns(&plan), Some(expected));
+
+        let plan = prune_columns(plan_of("SELECT e.name AS name FROM hr.employees e")?);
+        let expected = vec!["e", "hr"]
+            .into_iter()
+            .map(String::from)
+            .collect::<BTreeSet<_>>();
+        assert_eq!(scan_columns(&plan), Some(expected));
+
+        assert_eq!(scan_columns(&prune_columns(plan_of("SELECT *")?)), None);
+        Ok(())
+    }
+
+    #[test]
+    fn keeps_results() -> anyhow::Result<()> {
+        let data = PqlValue::from_str(
+            r#"[
+                { 'a': 1, 'b': 1, 'tags': ['x', 'y'], 'wide': 'unused' },
+                { 'a': 2, 'b': 2, 'tags': ['z'], 'wide': 'unused' },
+                { 'a': 3, 'b': 1, 'tags': [], 'wide': 'unused' }
+            ]"#,
+        )?;
+        let sql = Sql::from_str("SELECT a * (1 + 1) AS a2, t FROM tags t WHERE b = 1 LIMIT 2")?;
+        let run = |plan: PhysicalPlan| -> anyhow::Result<PqlValue> {
+            Ok(PqlValue::from(
+                plan.rows().collect::<anyhow::Result<Vec<_>>>()?,
+            ))
+        };
+        let source = || Source::Rows(data.to_owned());
+        assert_eq!(
+            run(optimize(PhysicalPlan::new(
+                LogicalPlan::from(sql.to_owned()),
+                source()
+            )))?,
+            run(PhysicalPlan::new(LogicalPlan::from(sql), source()))?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,41 @@
+use std::time::Instant;
+
+use crate::planner::CancelToken;
+use crate::sql::EvalMode;
+
+/// How a query is run, and the limits it stops at with an `ExecError`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExecOptions {
+    /// The number of threads the rows of a collection are split over, all of the available
+    /// cores with 0. A single thread reads the rows lazily, and stops at LIMIT.
+    pub threads: usize,
+    /// Whether rows computed in parallel keep the order of their input when there is no
+    /// ORDER BY. Otherwise, chunks of rows come in the order they are done.
+    pub preserve_order: bool,
+    /// When the query stops with `ExecError::Timeout`.
+    pub deadline: Option<Instant>,
+    /// Stops the query with `ExecError::Cancelled` once it is cancelled.
+    pub cancel: Option<CancelToken>,
+    /// The most tuples the operators of the query produce in all, from those read to those of
+    /// the result, before it stops with `ExecError::TooManyRows`.
+    pub max_rows: Option<usize>,
+    /// The most bytes, approximately, of the tuples the query holds until it is done, those it
+    /// sorts and those of its result, before it stops with `ExecError::OutOfMemory`.
+    pub max_memory: Option<usize>,
+    /// Whether a type error, such as `'abc' + 1`, is MISSING or stops the query.
+    pub mode: EvalMode,
+}
+
+impl Default for ExecOptions {
+    fn default() -> Self {
+        Self {
+            threads: 1,
+            preserve_order: true,
+            deadline: None,
+            cancel: None,
+            max_rows: None,
+            max_memory: None,
+            mode: EvalMode::Permissive,
+        }
+    }
+}
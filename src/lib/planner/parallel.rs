@@ -0,0 +1,272 @@
+//! Parallel execution of a plan.
+//!
+//! The operators that work on one tuple at a time, joins, filters and projections, run over
+//! chunks of the tuples of their input on a thread pool. Their input is the rows of the source,
+//! or for a document, the elements of the first collection of its FROM clause, or the rows an
+//! index looks up. ORDER BY and LIMIT then run over the tuples of every chunk. A LIMIT without
+//! an ORDER BY, which stops pulling as soon as it has its tuples, is run on a single thread
+//! instead.
+
+use std::sync::{Arc, Mutex};
+
+use rayon::ThreadPoolBuilder;
+
+use crate::planner::limits::guard;
+use crate::planner::physical_plan::Tuples;
+use crate::planner::{ExecOptions, Limits, PhysicalPlan, Projection, Source};
+use crate::sql::{Env, Field, WhereCond};
+
+const CHUNK_SIZE: usize = 1024;
+
+// An operator that works on one tuple at a time, without its input.
+enum Step {
+    Join { field: Field, outer: bool },
+    Filter(WhereCond),
+    Project(Projection),
+}
+
+impl Step {
+    fn plan<'a>(&self, input: PhysicalPlan<'a>) -> PhysicalPlan<'a> {
+        let input = Box::new(input);
+        match self {
+            Self::Join { field, outer } => PhysicalPlan::Join {
+                input,
+                field: field.to_owned(),
+                outer: *outer,
+            },
+            Self::Filter(cond) => PhysicalPlan::Filter {
+                input,
+                cond: cond.to_owned(),
+            },
+            Self::Project(projection) => PhysicalPlan::Project {
+                input,
+                projection: projection.to_owned(),
+            },
+        }
+    }
+}
+
+impl<'a> PhysicalPlan<'a> {
+    /// Executes the plan on `options.threads` threads, within the limits and in the mode of `options`.
+    pub fn execute_with(self, options: &ExecOptions) -> anyhow::Result<Tuples<'a>> {
+        let limits = Arc::new(Limits::new(options));
+        if options.threads == 1 || self.stops_early() {
+            return Ok(guard(self, &limits, true).execute_in(options.mode));
+        }
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(options.threads)
+            .build()?;
+        let plan = self.parallelize(&pool, options, &limits)?;
+        Ok(guard(plan, &limits, true).execute_in(options.mode))
+    }
+
+    // Whether a LIMIT stops pulling from operators that would otherwise run in parallel.
+    fn stops_early(&self) -> bool {
+        match self {
+            Self::Scan { .. } => false,
+            Self::Limit { input, .. } => !matches!(**input, Self::Sort { .. }),
+            Self::Join { input, .. }
+            | Self::Filter { input, .. }
+            | Self::IndexLookup { input, .. }
+            | Self::Project { input, .. }
+            | Self::Sort { input, .. }
+            | Self::Guard { input, .. } => input.stops_early(),
+        }
+    }
+
+    // Whether the plan produces a single tuple, the one of a document.
+    fn is_single(&self) -> bool {
+        match self {
+            Self::Scan {
+                source: Source::Document(_),
+                ..
+            } => true,
+            Self::Filter { input, .. } => input.is_single(),
+            _ => false,
+        }
+    }
+
+    // Replaces the operators that work on one tuple at a time with the tuples they produce.
+    fn parallelize(
+        self,
+        pool: &rayon::ThreadPool,
+        options: &ExecOptions,
+        limits: &Arc<Limits>,
+    ) -> anyhow::Result<Self> {
+        let plan = match self {
+            Self::Scan { .. } => self,
+            Self::Sort { input, order_by } => Self::Sort {
+                input: Box::new(input.parallelize(pool, options, limits)?),
+                order_by,
+            },
+            Self::Limit { input, limit } => Self::Limit {
+                input: Box::new(input.parallelize(pool, options, limits)?),
+                limit,
+            },
+            plan => {
+                let mut steps = vec![];
+                let input = plan.split(&mut steps);
+                if steps.is_empty() {
+                    return Ok(input);
+                }
+                // The steps were taken from the top down, and are applied from the bottom up.
+                steps.reverse();
+                Self::Scan {
+                    source: Source::Tuples(run(input, &steps, pool, options, limits)?),
+                    columns: None,
+                }
+            }
+        };
+        Ok(plan)
+    }
+
+    // Takes the operators that work on one tuple at a time off the top of the plan, and returns
+    // what is left, whose tuples they are run over.
+    fn split(self, steps: &mut Vec<Step>) -> Self {
+        match self {
+            Self::Join {
+                input,
+                field,
+                outer,
+            } if !input.is_single() => {
+                steps.push(Step::Join { field, outer });
+                input.split(steps)
+            }
+            Self::Filter { input, cond } if !input.is_single() => {
+                steps.push(Step::Filter(cond));
+                input.split(steps)
+            }
+            Self::Project { input, projection } if !input.is_single() => {
+                steps.push(Step::Project(projection));
+                input.split(steps)
+            }
+            plan => plan,
+        }
+    }
+}
+
+// Runs `steps` over chunks of the tuples of `input`, which is read on the current thread.
+fn run(
+    input: PhysicalPlan,
+    steps: &[Step],
+    pool: &rayon::ThreadPool,
+    options: &ExecOptions,
+    limits: &Arc<Limits>,
+) -> anyhow::Result<Vec<Env>> {
+    let done = Mutex::new(vec![]);
+    let mut tuples = guard(input, limits, false).execute_in(options.mode);
+    pool.in_place_scope(|scope| -> anyhow::Result<()> {
+        for index in 0.. {
+            limits.check()?;
+            let chunk = tuples
+                .by_ref()
+                .take(CHUNK_SIZE)
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            if chunk.is_empty() {
+                break;
+            }
+            let done = &done;
+            scope.spawn(move |_| {
+                let input = PhysicalPlan::Scan {
+                    source: Source::Tuples(chunk),
+                    columns: None,
+                };
+                let plan = steps.iter().fold(input, |input, step| step.plan(input));
+                // The tuples of a chunk are held until every chunk is done.
+                let plan = guard(plan, limits, true);
+                let tuples = plan.execute_in(options.mode).collect::<Vec<_>>();
+                done.lock().unwrap().push((index, tuples));
+            });
+        }
+        Ok(())
+    })?;
+
+    let mut done = done.into_inner().unwrap();
+    if options.preserve_order {
+        done.sort_by_key(|(index, _)| *index);
+    }
+    done.into_iter()
+        .flat_map(|(_, tuples)| tuples)
+        .collect::<anyhow::Result<Vec<_>>>()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use indexmap::IndexMap as Map;
+
+    use crate::planner::{evaluate, evaluate_with, ExecOptions};
+    use crate::sql::Sql;
+    use crate::value::{BPqlValue, PqlValue};
+
+    fn parallel(preserve_order: bool) -> ExecOptions {
+        ExecOptions {
+            threads: 4,
+            preserve_order,
+            ..ExecOptions::default()
+        }
+    }
+
+    fn rows(n: i64) -> PqlValue {
+        let rows = (0..n)
+            .map(|i| {
+                let mut row = Map::new();
+                row.insert("n".to_owned(), PqlValue::from(i));
+                let parity = if i % 2 == 0 { "even" } else { "odd" };
+                row.insert("parity".to_owned(), PqlValue::from(parity));
+                let tags = vec![PqlValue::from("a"), PqlValue::from("b")];
+                row.insert("tags".to_owned(), PqlValue::from(tags));
+                PqlValue::from(row)
+            })
+            .collect::<Vec<_>>();
+        PqlValue::from(rows)
+    }
+
+    #[test]
+    fn same_rows_as_a_single_thread() -> anyhow::Result<()> {
+        let mut document = Map::new();
+        document.insert("rows".to_owned(), rows(5000));
+        let document = PqlValue::Object(document);
+        let cases = vec![
+            ("SELECT n * 2 AS n2 WHERE parity = 'odd'", rows(5000)),
+            ("SELECT n ORDER BY n DESC LIMIT 3 OFFSET 1", rows(5000)),
+            ("SELECT n LIMIT 5", rows(5000)),
+            (
+                "SELECT r.n AS n, t FROM rows r, r.tags t WHERE r.parity = 'even'",
+                document,
+            ),
+        ];
+        for (query, data) in cases {
+            let sql = Sql::from_str(query)?;
+            let expected = evaluate(sql.to_owned(), data.to_owned());
+            assert_eq!(
+                evaluate_with(sql, data, &parallel(true))?,
+                expected,
+                "{}",
+                query
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn unordered_rows() -> anyhow::Result<()> {
+        let sql = Sql::from_str("SELECT n WHERE parity = 'even'")?;
+        let sorted = |value: PqlValue| match value {
+            PqlValue::Array(rows) => {
+                let mut rows = rows
+                    .into_iter()
+                    .filter_map(|row| row.get("n").map(BPqlValue::from))
+                    .collect::<Vec<_>>();
+                rows.sort();
+                rows
+            }
+            value => panic!("{:?}", value),
+        };
+        let expected = evaluate(sql.to_owned(), rows(5000));
+        let result = evaluate_with(sql, rows(5000), &parallel(false))?;
+        assert_eq!(sorted(result), sorted(expected));
+        Ok(())
+    }
+}
@@ -0,0 +1,423 @@
+//! The physical plan: a tree of operators that pull binding tuples from their input one at a
+//! time, so that nothing is read or computed before it is needed.
+//!
+//! A binding tuple is an `Env`. Its row, the value unqualified paths are looked up in, is bound
+//! to `""`, and each source of the FROM clause binds its own alias. Only ORDER BY holds on to
+//! every tuple; LIMIT stops pulling from its input once it is satisfied.
+
+use std::cmp::Ordering;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::planner::index::{Index, Probe};
+use crate::planner::Limits;
+use crate::planner::LogicalPlan;
+use crate::planner::Projection;
+use crate::sql::clause::Limit;
+use crate::sql::clause::OrderBy;
+use crate::sql::Env;
+use crate::sql::EvalMode;
+use crate::sql::Expr;
+use crate::sql::Field;
+use crate::sql::WhereCond;
+use crate::value::BPqlValue;
+use crate::value::PqlValue;
+
+pub type Tuples<'a> = Box<dyn Iterator<Item = anyhow::Result<Env>> + 'a>;
+
+/// Where a plan reads its rows from.
+pub enum Source<'a> {
+    /// The whole input as a single row, for a query whose FROM clause names what it reads.
+    Document(PqlValue),
+    /// Each element of a collection, or a value that is not a collection as a single row.
+    Rows(PqlValue),
+    /// Rows read as they are pulled, such as the lines of a JSON Lines file.
+    Stream(Box<dyn Iterator<Item = anyhow::Result<PqlValue>> + 'a>),
+    /// Binding tuples that are already bound, such as those of a chunk run in parallel.
+    Tuples(Vec<Env>),
+}
+
+impl fmt::Debug for Source<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Document(_) => f.write_str("Document"),
+            Self::Rows(_) => f.write_str("Rows"),
+            Self::Stream(_) => f.write_str("Stream"),
+            Self::Tuples(_) => f.write_str("Tuples"),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PhysicalPlan<'a> {
+    /// Reads the rows of `source`, keeping only the fields named in `columns` of a row that is a
+    /// tuple.
+    Scan {
+        source: Source<'a>,
+        columns: Option<BTreeSet<String>>,
+    },
+    /// Binds each element of the collection `field` evaluates to in a tuple of `input` to its
+    /// alias, which is the last step of its path by default. An `outer` join keeps a tuple whose
+    /// collection is empty, with the alias MISSING.
+    Join {
+        input: Box<Self>,
+        field: Field,
+        outer: bool,
+    },
+    Filter {
+        input: Box<Self>,
+        cond: WhereCond,
+    },
+    /// Binds each row of an indexed collection whose key matches `probe` in a tuple of `input`
+    /// to `alias`, as a join over the collection followed by the filter the probe stands for.
+    IndexLookup {
+        input: Box<Self>,
+        alias: String,
+        index: Arc<Index>,
+        probe: Probe,
+    },
+    /// Replaces each tuple with its projection.
+    Project {
+        input: Box<Self>,
+        projection: Projection,
+    },
+    Sort {
+        input: Box<Self>,
+        order_by: OrderBy,
+    },
+    Limit {
+        input: Box<Self>,
+        limit: Limit,
+    },
+    /// Checks `limits` for each tuple of `input`, and ends with the error of the first limit it
+    /// exceeds. A `held` tuple counts towards the memory of the query.
+    Guard {
+        input: Box<Self>,
+        limits: Arc<Limits>,
+        held: bool,
+    },
+}
+
+impl<'a> PhysicalPlan<'a> {
+    pub fn new(plan: LogicalPlan, source: Source<'a>) -> Self {
+        let mut physical = Self::Scan {
+            source,
+            columns: None,
+        };
+        // The sources of the FROM clause come first, then those of LEFT JOIN.
+        let mut position = 0;
+        for (i, drain) in plan.drains.into_iter().enumerate() {
+            for field in drain.0 {
+                position += 1;
+                let (alias, expr) = field.rename(position);
+                physical = Self::Join {
+                    input: Box::new(physical),
+                    field: Field {
+                        expr,
+                        alias: Some(alias),
+                    },
+                    outer: i > 0,
+                };
+            }
+        }
+        if let Some(cond) = plan.filter.0 {
+            physical = Self::Filter {
+                input: Box::new(physical),
+                cond: *cond,
+            };
+        }
+        physical = Self::Project {
+            input: Box::new(physical),
+            projection: plan.project,
+        };
+        if let Some(order_by) = plan.order_by {
+            physical = Self::Sort {
+                input: Box::new(physical),
+                order_by,
+            };
+        }
+        if let Some(limit) = plan.limit {
+            physical = Self::Limit {
+                input: Box::new(physical),
+                limit,
+            };
+        }
+        physical
+    }
+
+    pub fn execute(self) -> Tuples<'a> {
+        self.execute_in(EvalMode::Permissive)
+    }
+
+    /// The tuples of the plan, with type errors in its expressions handled as `mode` says.
+    pub fn execute_in(self, mode: EvalMode) -> Tuples<'a> {
+        match self {
+            Self::Scan {
+                source: Source::Tuples(tuples),
+                ..
+            } => Box::new(tuples.into_iter().map(Ok)),
+            Self::Scan { source, columns } => {
+                let rows: Box<dyn Iterator<Item = anyhow::Result<PqlValue>> + 'a> = match source {
+                    Source::Document(value) => Box::new(std::iter::once(Ok(value))),
+                    Source::Rows(value) => Box::new(elements(value).into_iter().map(Ok)),
+                    Source::Stream(rows) => rows,
+                    Source::Tuples(_) => unreachable!(),
+                };
+                Box::new(rows.map(move |row| {
+                    row.map(|row| match (row, &columns) {
+                        (PqlValue::Object(mut map), Some(columns)) => {
+                            map.retain(|key, _| columns.contains(key));
+                            Env::from(PqlValue::Object(map))
+                        }
+                        (row, _) => Env::from(row),
+                    })
+                }))
+            }
+            Self::Join {
+                input,
+                field,
+                outer,
+            } => Box::new(input.execute_in(mode).flat_map(move |tuple| -> Tuples<'a> {
+                let tuple = match tuple {
+                    Ok(tuple) => Arc::new(tuple),
+                    Err(err) => return Box::new(std::iter::once(Err(err))),
+                };
+                let mut values = match field.expr.to_owned().eval_in(&tuple, mode) {
+                    Ok(value) => elements(value),
+                    Err(err) => return Box::new(std::iter::once(Err(err))),
+                };
+                if values.is_empty() && outer {
+                    values.push(PqlValue::Missing);
+                }
+                let alias = field.alias.to_owned().unwrap_or_default();
+                Box::new(values.into_iter().map(move |value| {
+                    let mut env = Env::nested(Arc::clone(&tuple));
+                    env.bind(&alias, value);
+                    Ok(env)
+                }))
+            })),
+            Self::Filter { input, cond } => {
+                Box::new(input.execute_in(mode).filter_map(move |tuple| match tuple {
+                    Ok(env) => match cond.eval_in(&env, mode) {
+                        Ok(true) => Some(Ok(env)),
+                        Ok(false) => None,
+                        Err(err) => Some(Err(err)),
+                    },
+                    Err(err) => Some(Err(err)),
+                }))
+            }
+            Self::IndexLookup {
+                input,
+                alias,
+                index,
+                probe,
+            } => Box::new(input.execute_in(mode).flat_map(move |tuple| -> Tuples<'a> {
+                let tuple = match tuple {
+                    Ok(tuple) => Arc::new(tuple),
+                    Err(err) => return Box::new(std::iter::once(Err(err))),
+                };
+                let rows = index.lookup(&probe, &tuple);
+                let alias = alias.to_owned();
+                Box::new(rows.into_iter().map(move |row| {
+                    let mut env = Env::nested(Arc::clone(&tuple));
+                    env.bind(&alias, row);
+                    Ok(env)
+                }))
+            })),
+            Self::Project { input, projection } => {
+                Box::new(input.execute_in(mode).map(move |tuple| {
+                    tuple.and_then(|env| projection.execute_in(&env, mode).map(Env::from))
+                }))
+            }
+            Self::Sort { input, order_by } => {
+                let tuples = match input.execute_in(mode).collect::<anyhow::Result<Vec<_>>>() {
+                    Ok(tuples) => tuples,
+                    Err(err) => return Box::new(std::iter::once(Err(err))),
+                };
+                // A row without the label has no place in the order and is left out.
+                let mut keyed = tuples
+                    .into_iter()
+                    .filter_map(|env| match env.get_ref("") {
+                        Some(Expr::Value(row)) => {
+                            row.to_owned().get(&order_by.label).map(|key| (key, env))
+                        }
+                        _ => None,
+                    })
+                    .collect::<Vec<_>>();
+                keyed.sort_by(|x, y| {
+                    if order_by.is_asc {
+                        sort_order(&x.0, &y.0)
+                    } else {
+                        sort_order(&y.0, &x.0)
+                    }
+                });
+                Box::new(keyed.into_iter().map(|(_, env)| Ok(env)))
+            }
+            Self::Limit { input, limit } => Box::new(
+                input
+                    .execute_in(mode)
+                    .skip(limit.offset as usize)
+                    .take(limit.limit as usize),
+            ),
+            Self::Guard {
+                input,
+                limits,
+                held,
+            } => {
+                let mut stopped = false;
+                Box::new(input.execute_in(mode).map_while(move |tuple| {
+                    if stopped {
+                        return None;
+                    }
+                    let checked = tuple.and_then(|env| match limits.admit(&env, held) {
+                        Ok(()) => Ok(env),
+                        Err(err) => Err(err.into()),
+                    });
+                    stopped = checked.is_err();
+                    Some(checked)
+                }))
+            }
+        }
+    }
+
+    /// The rows of the result, as they are produced.
+    pub fn rows(self) -> impl Iterator<Item = anyhow::Result<PqlValue>> + 'a {
+        self.execute().map(|tuple| tuple.map(into_row))
+    }
+}
+
+// The row of a tuple of the result.
+pub(crate) fn into_row(mut env: Env) -> PqlValue {
+    match env.remove("") {
+        Some(Expr::Value(row)) => row,
+        _ => PqlValue::Missing,
+    }
+}
+
+// The order of ORDER BY keys: values that compare, as numbers of any type do, by how they compare,
+// and any others by their kind and then their contents.
+fn sort_order(a: &PqlValue, b: &PqlValue) -> Ordering {
+    a.compare(b).unwrap_or_else(|| {
+        let key = |value: &PqlValue| match value {
+            PqlValue::Annotated(_, value) => BPqlValue::from(value.as_ref().to_owned()),
+            value => BPqlValue::from(value.to_owned()),
+        };
+        key(a).cmp(&key(b))
+    })
+}
+
+// The elements a FROM source ranges over. A value that is not a collection is a single element.
+pub(crate) fn elements(value: PqlValue) -> Vec<PqlValue> {
+    match value {
+        PqlValue::Missing => vec![],
+        PqlValue::Array(array) => array,
+        value if value.is_bag() => match value {
+            PqlValue::Annotated(_, box PqlValue::Array(array)) => array,
+            _ => unreachable!(),
+        },
+        value => vec![value],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+    use std::str::FromStr;
+
+    use super::{PhysicalPlan, Source};
+    use crate::planner::LogicalPlan;
+    use crate::sql::Sql;
+    use crate::value::{json_value, PqlValue};
+
+    #[test]
+    fn limit_stops_pulling() -> anyhow::Result<()> {
+        let pulled = Cell::new(0);
+        let rows = (0..1000).map(|i| {
+            pulled.set(pulled.get() + 1);
+            PqlValue::from_str(&format!("{{ 'n': {} }}", i))
+        });
+        let sql = Sql::from_str("SELECT n LIMIT 2 OFFSET 3")?;
+        let plan = PhysicalPlan::new(LogicalPlan::from(sql), Source::Stream(Box::new(rows)));
+        let result = plan.rows().collect::<anyhow::Result<Vec<_>>>()?;
+
+        assert_eq!(
+            PqlValue::from(result),
+            PqlValue::from_str("[{ 'n': 3 }, { 'n': 4 }]")?
+        );
+        assert_eq!(pulled.get(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn joins_nested_collections() -> anyhow::Result<()> {
+        let data = PqlValue::from_str(
+            r#"{
+                'orders': [
+                    { 'id': 1, 'items': [{ 'sku': 'a' }, { 'sku': 'b' }] },
+                    { 'id': 2, 'items': [] },
+                    { 'id': 3, 'items': [{ 'sku': 'c' }] }
+                ]
+            }"#,
+        )?;
+        let sql =
+            Sql::from_str("SELECT o.id AS id, i.sku AS sku FROM orders o, o.items i LIMIT 2")?;
+        let plan = PhysicalPlan::new(LogicalPlan::from(sql), Source::Document(data.to_owned()));
+        assert_eq!(
+            PqlValue::from(plan.rows().collect::<anyhow::Result<Vec<_>>>()?),
+            PqlValue::from_str("[{ 'id': 1, 'sku': 'a' }, { 'id': 1, 'sku': 'b' }]")?
+        );
+
+        // A source without an alias is named after the last step of its path.
+        let sql = Sql::from_str("SELECT orders.id AS id FROM orders WHERE orders.id = 2")?;
+        let plan = PhysicalPlan::new(LogicalPlan::from(sql), Source::Document(data.to_owned()));
+        assert_eq!(
+            PqlValue::from(plan.rows().collect::<anyhow::Result<Vec<_>>>()?),
+            PqlValue::from_str("[{ 'id': 2 }]")?
+        );
+
+        let sql =
+            Sql::from_str("SELECT o.id AS id, i.sku AS sku FROM orders o LEFT JOIN o.items i")?;
+        let plan = PhysicalPlan::new(LogicalPlan::from(sql), Source::Document(data));
+        assert_eq!(
+            PqlValue::from(plan.rows().collect::<anyhow::Result<Vec<_>>>()?),
+            PqlValue::from_str(
+                "[{ 'id': 1, 'sku': 'a' }, { 'id': 1, 'sku': 'b' }, { 'id': 2 }, { 'id': 3, 'sku': 'c' }]"
+            )?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn sorts_keys_of_any_type() -> anyhow::Result<()> {
+        let rows = json_value::to_pqlvalue(serde_json::from_str(
+            r#"[
+                { "k": 2.5 },
+                { "k": [1, 2] },
+                { "k": 3 },
+                { "k": { "a": 1 } },
+                { "k": "x" },
+                { "k": 1 },
+                { "k": [1] }
+            ]"#,
+        )?)?;
+        let sql = Sql::from_str("SELECT k ORDER BY k")?;
+        let plan = PhysicalPlan::new(LogicalPlan::from(sql), Source::Rows(rows));
+        // Numbers are in the order of what they are worth, whatever their type.
+        assert_eq!(
+            PqlValue::from(plan.rows().collect::<anyhow::Result<Vec<_>>>()?),
+            json_value::to_pqlvalue(serde_json::from_str(
+                r#"[
+                    { "k": "x" },
+                    { "k": 1 },
+                    { "k": 2.5 },
+                    { "k": 3 },
+                    { "k": [1] },
+                    { "k": [1, 2] },
+                    { "k": { "a": 1 } }
+                ]"#,
+            )?)?
+        );
+        Ok(())
+    }
+}
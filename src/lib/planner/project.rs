@@ -1,420 +1,141 @@
-use collect_mac::collect;
 use indexmap::IndexMap as Map;
-use itertools::Itertools;
 
 use crate::sql::Env;
+use crate::sql::EvalMode;
 use crate::sql::Expr;
 use crate::sql::Field;
-use crate::sql::Selector;
 use crate::value::PqlValue;
 
+/// The SELECT clause, evaluated once for each binding tuple.
 #[derive(Debug, Default, Clone)]
 pub struct Projection(pub Vec<Field>);
 
 impl Projection {
-    pub fn execute(self, env: &Env) -> Vec<PqlValue> {
-        let v = self
-            .0
-            .iter()
-            .map(|field| {
-                let field = field.expand_fullpath(&env);
-                let (alias, expr) = field.rename();
-                let value = expr.eval(env);
-                (alias, value)
-            })
-            .collect::<Map<String, PqlValue>>();
-        let v = Rows::from(PqlValue::Object(v));
-        let v = Records::from(v);
-        v.into_list()
+    /// The output tuple of the binding tuple `env`. A field whose value is MISSING is left out.
+    pub fn execute(&self, env: &Env) -> PqlValue {
+        self.execute_in(env, EvalMode::Permissive)
+            .unwrap_or_else(|_| PqlValue::Object(Map::new()))
     }
-}
-
-impl PqlValue {
-    pub fn project_by_selector(
-        &self,
-        alias: Option<String>,
-        selector: &Selector,
-    ) -> (String, Self) {
-        let value = self.select_by_selector(&selector);
-        let key = alias.clone().unwrap_or({
-            let last = selector.to_vec().last().unwrap().to_string();
-            last
-        });
-        (key, value)
-    }
-
-    pub fn select_by_fields(&self, field_list: &[Field], env: &Env) -> Option<Self> {
-        let mut new_map = Map::<String, Self>::new();
 
-        for field in field_list {
-            match &field.expr {
-                Expr::Selector(selector) => {
-                    let value = self.select_by_selector(&selector);
-                    let key = field.alias.clone().unwrap_or({
-                        let last = selector.to_vec().last().unwrap().to_string();
-                        last
-                    });
-                    new_map.insert(key, value);
-                }
-                _ => {
-                    let value = field.to_owned().expr.eval(&env);
-                    let key = field.alias.clone().unwrap_or_default();
-                    new_map.insert(key, value);
+    /// The output tuple of the binding tuple `env`, with type errors handled as `mode` says.
+    pub fn execute_in(&self, env: &Env, mode: EvalMode) -> anyhow::Result<PqlValue> {
+        let mut tuple = Map::new();
+        for (i, field) in self.0.iter().enumerate() {
+            if field.expr == Expr::Star {
+                tuple.extend(star(env));
+                continue;
+            }
+            let (alias, expr) = field.to_owned().rename(i + 1);
+            match expr.eval_in(env, mode)? {
+                PqlValue::Missing => {}
+                value => {
+                    tuple.insert(alias, value);
                 }
             }
         }
-
-        Some(Self::Object(new_map))
-    }
-}
-
-#[derive(Debug, Default, Clone)]
-pub struct Rows {
-    data: Map<String, Vec<PqlValue>>,
-    size: usize,
-    keys: Vec<String>,
-}
-
-impl From<PqlValue> for Rows {
-    fn from(value: PqlValue) -> Self {
-        let mut size = 0;
-
-        let data = match value {
-            PqlValue::Object(record) => record
-                .into_iter()
-                .map(|(key, val)| match val {
-                    PqlValue::Array(array) => {
-                        if size == 0 {
-                            size = array.len();
-                        }
-                        (key, array)
-                    }
-                    _ => {
-                        size = 1;
-                        (key, vec![val])
-                    }
-                })
-                .collect::<Map<String, Vec<PqlValue>>>(),
-            _ => {
-                dbg!(&value);
-                unreachable!()
-            }
-        };
-
-        let keys = data.keys().map(String::from).collect();
-        Self { data, size, keys }
+        Ok(PqlValue::Object(tuple))
     }
 }
 
-impl From<Rows> for PqlValue {
-    fn from(records: Rows) -> Self {
-        let array = records
-            .data
-            .into_iter()
-            .map(|(k, v)| {
-                PqlValue::Object(collect! {
-                    as Map<String, PqlValue>:
-                    k => PqlValue::Array(v)
-                })
-            })
-            .collect::<Vec<_>>();
-        PqlValue::Array(array)
-    }
-}
-
-#[derive(Debug, Default, Clone)]
-pub struct Records(pub Vec<Map<String, Vec<PqlValue>>>);
-
-impl From<Rows> for Records {
-    fn from(rows: Rows) -> Self {
-        let records = {
-            let mut records = Vec::<Map<String, Vec<PqlValue>>>::new();
-            for i in 0..rows.size {
-                let mut record = Map::<String, Vec<PqlValue>>::new();
-                for key in &rows.keys {
-                    if let Some(value) = rows.data.get(key.as_str()).unwrap().get(i) {
-                        let v: Vec<PqlValue> = value.to_owned().flatten().into();
-                        record.insert(key.to_string(), v);
-                    } else {
-                        dbg!(&record);
-                    }
-                }
-                records.push(record);
+// `*` is every field of the tuples the FROM clause binds, or of the row when there is no FROM.
+// A variable bound to a value that is not a tuple is a field `_n`, for the `n`th variable.
+fn star(env: &Env) -> Map<String, PqlValue> {
+    let mut variables = env.variables();
+    let row = variables.shift_remove("");
+    let values = if variables.is_empty() {
+        row.into_iter().collect::<Vec<_>>()
+    } else {
+        variables.values().cloned().collect::<Vec<_>>()
+    };
+
+    let mut fields = Map::new();
+    for (i, expr) in values.into_iter().enumerate() {
+        match expr {
+            Expr::Value(PqlValue::Object(map)) => fields.extend(map.to_owned()),
+            Expr::Value(PqlValue::Missing) => {}
+            Expr::Value(value) => {
+                fields.insert(format!("_{}", i + 1), value.to_owned());
             }
-            records
-        };
-        Self(records)
-    }
-}
-
-impl From<Records> for PqlValue {
-    fn from(records: Records) -> Self {
-        Self::Array(
-            records
-                .0
-                .into_iter()
-                .map(|obj| {
-                    Self::Object(
-                        obj.into_iter()
-                            .map(|(k, v)| (k, Self::Array(v)))
-                            .collect::<Map<String, _>>(),
-                    )
-                })
-                .collect::<Vec<_>>(),
-        )
-    }
-}
-
-impl Records {
-    pub fn into_list(self) -> Vec<PqlValue> {
-        self.0
-            .into_iter()
-            .map(|record| {
-                let record = record
-                    .into_iter()
-                    .filter_map(|(k, v)| if !v.is_empty() { Some((k, v)) } else { None })
-                    .collect::<Map<String, Vec<PqlValue>>>();
-
-                let keys = record.keys();
-                let it = record.values().into_iter().multi_cartesian_product();
-                it.map(|prod| {
-                    let map = keys
-                        .clone()
-                        .into_iter()
-                        .zip(prod.into_iter())
-                        .flat_map(|(key, p)| {
-                            p.to_owned()
-                                .then_if_not_missing()
-                                .map(|val| (key.to_owned(), val))
-                        })
-                        .collect::<Map<String, _>>();
-                    let v = PqlValue::Object(map);
-                    v
-                })
-                .collect::<Vec<PqlValue>>()
-            })
-            .flatten()
-            .collect::<Vec<PqlValue>>()
+            _ => {}
+        }
     }
+    fields
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Records;
-    use super::Rows;
-    use crate::planner::LogicalPlan;
-    use crate::sql::Env;
-    use crate::sql::Expr;
-    use crate::sql::Selector;
-    use crate::sql::Sql;
+    use std::str::FromStr;
 
+    use crate::planner::evaluate;
+    use crate::sql::Sql;
     use crate::value::PqlValue;
-    use indexmap::IndexMap as Map;
-    use std::str::FromStr;
 
     #[test]
-    fn test_convert_coloumnar_to_rowwise() -> anyhow::Result<()> {
-        let form0 = PqlValue::from_str(
-            r#"
-{
-  "projectName": [
-    [
-      "AWS Redshift security",
-      "AWS Aurora security"
-    ],
-    [
-      "AWS Redshift security"
-    ]
-  ],
-  "employeeName": [
-    "Bob Smith",
-    "Jane Smith"
-  ]
-}
-"#,
-        )?;
-        let form1 = PqlValue::from_str(
-            r#"
-[
-  {
-    "projectName": [
-      [
-        "AWS Redshift security",
-        "AWS Aurora security"
-      ],
-      [
-        "AWS Redshift security"
-      ]
-    ]
-  },
-  {
-    "employeeName": [
-      "Bob Smith",
-      "Jane Smith"
-    ]
-  }
-]
-"#,
-        )?;
-        let form2 = PqlValue::from_str(
-            r#"
-[
-  {
-    "projectName": [
-      "AWS Redshift security",
-      "AWS Aurora security"
-    ],
-    "employeeName": [
-      "Bob Smith"
-    ]
-  },
-  {
-    "projectName": [
-      "AWS Redshift security"
-    ],
-    "employeeName": [
-      "Jane Smith"
-    ]
-  }
-]
-"#,
-        )?;
-        let form3 = PqlValue::from_str(
+    fn test_project_a_missing_value() -> anyhow::Result<()> {
+        let data = PqlValue::from_str(
             r#"
 [
-  {
-    "projectName": "AWS Redshift security",
-    "employeeName": "Bob Smith"
-  },
-  {
-    "projectName": "AWS Aurora security",
-    "employeeName": "Bob Smith"
-  },
-  {
-    "projectName": "AWS Redshift security",
-    "employeeName": "Jane Smith"
-  }
+    { 'id': 3, 'name': 'Bob Smith' },
+    { 'id': 4, 'name': 'Susan Smith', 'title': 'Dev Mgr' },
+    { 'id': 6, 'name': 'Jane Smith', 'title': 'Software Eng 2'}
 ]
 "#,
         )?;
+        let sql = Sql::from_str(r#"SELECT id, name, title"#)?;
 
-        let rows = Rows::from(form0.to_owned());
-        assert_eq!(PqlValue::from(rows.to_owned()), form1);
-
-        let records = Records::from(rows);
-        assert_eq!(PqlValue::from(records.to_owned()), form2);
-
-        let list = records.into_list();
-        assert_eq!(PqlValue::from(list.to_owned()), form3);
-
+        assert_eq!(evaluate(sql, data.to_owned()), data);
         Ok(())
     }
 
     #[test]
-    fn test_convert_matrix() -> anyhow::Result<()> {
-        let form0 = PqlValue::from_str(
-            r#"
-{
-    "id": [
-        1,
-        3
-    ],
-    "x": [
-        [
-            [2, 4],
-            [6]
-        ],
-        [
-            [8]
-        ]
-    ]
-}
-"#,
-        )?;
-        let form3 = PqlValue::from_str(
-            r#"
+    fn test_project_a_missing_value2() -> anyhow::Result<()> {
+        let data = PqlValue::from(vec![
+            PqlValue::from_str(r#"{ 'id': 3, 'name': 'Bob Smith' }"#)?,
+            PqlValue::Missing,
+            PqlValue::from_str(r#"{ 'id': 6, 'name': 'Jane Smith', 'title': 'Software Eng 2'}"#)?,
+        ]);
+        let sql = Sql::from_str(r#"SELECT id, name, title"#)?;
+
+        assert_eq!(
+            evaluate(sql, data),
+            PqlValue::from_str(
+                r#"
 [
-  {
-    "id": 1,
-    "x": 2
-  },
-  {
-    "id": 1,
-    "x": 4
-  },
-  {
-    "id": 1,
-    "x": 6
-  },
-  {
-    "id": 3,
-    "x": 8
-  }
+    { 'id': 3, 'name': 'Bob Smith' },
+    {},
+    { 'id': 6, 'name': 'Jane Smith', 'title': 'Software Eng 2'}
 ]
-"#,
-        )?;
-
-        let rows = Rows::from(form0.to_owned());
-        let v = PqlValue::from(rows.to_owned());
-        v.print();
-        // assert_eq!(PqlValue::from(rows.to_owned()), form1);
-
-        let records = Records::from(rows);
-        let _v = PqlValue::from(records.to_owned());
-        dbg!(&records);
-        // v.print();
-        // assert_eq!(PqlValue::from(records.to_owned()), form2);
-
-        let list = records.into_list();
-        assert_eq!(PqlValue::from(list.to_owned()), form3);
-
+                "#,
+            )?
+        );
         Ok(())
     }
 
     #[test]
-    fn test_project_a_missing_value() -> anyhow::Result<()> {
-        let env = Env::from(PqlValue::from_str(
+    fn pairs_values_of_the_same_binding() -> anyhow::Result<()> {
+        // Each matrix has rows of different lengths, which a columnar projection would mis-pair.
+        let data = PqlValue::from_str(
             r#"
-[
-    { 'id': 3, 'name': 'Bob Smith' },
-    { 'id': 4, 'name': 'Susan Smith', 'title': 'Dev Mgr' },
-    { 'id': 6, 'name': 'Jane Smith', 'title': 'Software Eng 2'}
-]
+{
+    'matrices': [
+        { 'id': 1, 'matrix': [[2, 4], [6]] },
+        { 'id': 3, 'matrix': [[8]] }
+    ]
+}
 "#,
-        )?);
-        let sql = Sql::from_str(r#"SELECT id, name, title"#)?;
-        let logical_plan = LogicalPlan::from(sql);
-        dbg!(&logical_plan);
-
-        let name = Expr::from(Selector::from("title")).eval(&env);
-        dbg!(&name);
-
-        let v = logical_plan
-            .project
-            .0
-            .iter()
-            .map(|field| {
-                let field = field.expand_fullpath(&env);
-                let (alias, expr) = field.rename();
-                let value = expr.eval(&env);
-                dbg!(&alias, &value);
-                (alias, value)
-            })
-            .collect::<Map<String, PqlValue>>();
-
-        let v = Rows::from(PqlValue::Object(v));
-        let v = Records::from(v);
-        let v = v.into_list();
-        let v = PqlValue::from(v);
+        )?;
+        let sql =
+            Sql::from_str("SELECT t.id AS id, x, y FROM matrices AS t, t.matrix AS y, y AS x")?;
 
         assert_eq!(
-            PqlValue::from(v),
+            evaluate(sql, data),
             PqlValue::from_str(
                 r#"
 [
-    { 'id': 3, 'name': 'Bob Smith' },
-    { 'id': 4, 'name': 'Susan Smith', 'title': 'Dev Mgr' },
-    { 'id': 6, 'name': 'Jane Smith', 'title': 'Software Eng 2'}
+    { 'id': 1, 'x': 2, 'y': [2, 4] },
+    { 'id': 1, 'x': 4, 'y': [2, 4] },
+    { 'id': 1, 'x': 6, 'y': [6] },
+    { 'id': 3, 'x': 8, 'y': [8] }
 ]
                 "#,
             )?
@@ -423,57 +144,36 @@ mod tests {
     }
 
     #[test]
-    fn test_project_a_missing_value2() -> anyhow::Result<()> {
-        let env = Env::from(PqlValue::from(vec![
-            PqlValue::from_str(
-                r#"
-    { 'id': 3, 'name': 'Bob Smith' }
-            "#,
-            )?,
-            PqlValue::Missing,
-            PqlValue::from_str(
-                r#"
-    { 'id': 6, 'name': 'Jane Smith', 'title': 'Software Eng 2'}
-            "#,
-            )?,
-        ]));
-        let sql = Sql::from_str(r#"SELECT id, name, title"#)?;
-        let logical_plan = LogicalPlan::from(sql);
-        dbg!(&logical_plan);
-
-        let name = Expr::from(Selector::from("title")).eval(&env);
-        dbg!(&name);
-
-        let v = logical_plan
-            .project
-            .0
-            .iter()
-            .map(|field| {
-                let field = field.expand_fullpath(&env);
-                let (alias, expr) = field.rename();
-                let value = expr.eval(&env);
-                dbg!(&alias, &value);
-                (alias, value)
-            })
-            .collect::<Map<String, PqlValue>>();
-
-        let v = Rows::from(PqlValue::Object(v));
-        let v = Records::from(v);
-        let v = v.into_list();
-        let v = PqlValue::from(v);
+    fn names_and_stars() -> anyhow::Result<()> {
+        let data = PqlValue::from_str(
+            r#"
+{
+    'people': [{ 'name': 'a', 'tags': ['x', 'y'] }],
+    'pets': [{ 'pet': 'cat' }]
+}
+"#,
+        )?;
+        let sql = Sql::from_str("SELECT p.tags, 2 * 3 FROM people p")?;
+        assert_eq!(
+            evaluate(sql, data.to_owned()),
+            PqlValue::from_str("[{ 'tags': ['x', 'y'], '_2': 6 }]")?
+        );
 
+        let sql = Sql::from_str("SELECT * FROM people p, pets q, p.tags t")?;
         assert_eq!(
-            PqlValue::from(v),
+            evaluate(sql, data.to_owned()),
             PqlValue::from_str(
                 r#"
 [
-    { 'id': 3, 'name': 'Bob Smith' },
-    {},
-    { 'id': 6, 'name': 'Jane Smith', 'title': 'Software Eng 2'}
+    { 'name': 'a', 'tags': ['x', 'y'], 'pet': 'cat', '_3': 'x' },
+    { 'name': 'a', 'tags': ['x', 'y'], 'pet': 'cat', '_3': 'y' }
 ]
                 "#,
             )?
         );
+
+        let sql = Sql::from_str("SELECT *")?;
+        assert_eq!(evaluate(sql, data.to_owned()), PqlValue::from(vec![data]));
         Ok(())
     }
 }
@@ -0,0 +1,82 @@
+use crate::planner::eval::name_collection;
+use crate::planner::physical_plan::into_row;
+use crate::planner::{optimize, LogicalPlan, PhysicalPlan, Source};
+use crate::sql::EvalMode;
+use crate::sql::Sql;
+use crate::value::PqlValue;
+
+/// Evaluates `sql` against one row at a time and passes each result to `emit` as soon as it is produced,
+/// so that a stream of rows is queried in constant memory.
+/// LIMIT stops reading once it is satisfied; ORDER BY needs every row and reads them all first.
+pub fn evaluate_rows<'a, I, F>(sql: Sql, rows: I, emit: F) -> anyhow::Result<()>
+where
+    I: Iterator<Item = anyhow::Result<PqlValue>> + 'a,
+    F: FnMut(PqlValue) -> anyhow::Result<()>,
+{
+    evaluate_rows_in(sql, rows, EvalMode::Permissive, emit)
+}
+
+/// Evaluates `sql` against one row at a time like `evaluate_rows`, with type errors handled as
+/// `mode` says.
+pub fn evaluate_rows_in<'a, I, F>(
+    sql: Sql,
+    rows: I,
+    mode: EvalMode,
+    mut emit: F,
+) -> anyhow::Result<()>
+where
+    I: Iterator<Item = anyhow::Result<PqlValue>> + 'a,
+    F: FnMut(PqlValue) -> anyhow::Result<()>,
+{
+    let named = sql.to_owned();
+    // Each row is a document of its own, which FROM may name like the collection of a whole input.
+    let rows = rows.map(move |row| {
+        row.map(
+            |row| match name_collection(&named, PqlValue::Array(vec![row.unbag()])) {
+                PqlValue::Array(mut rows) => rows.remove(0),
+                named => named,
+            },
+        )
+    });
+    let plan = optimize(PhysicalPlan::new(
+        LogicalPlan::from(sql),
+        Source::Stream(Box::new(rows)),
+    ));
+    for tuple in plan.execute_in(mode) {
+        emit(into_row(tuple?))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::evaluate_rows;
+    use crate::sql::Sql;
+    use crate::value::PqlValue;
+
+    #[test]
+    fn emits_rows_as_they_match() -> anyhow::Result<()> {
+        let sql = Sql::from_str("SELECT msg WHERE level = 'error' LIMIT 2 OFFSET 1")?;
+        let rows = (0..).map(|i| {
+            let level = if i % 2 == 0 { "error" } else { "info" };
+            PqlValue::from_str(&format!("{{ 'level': '{}', 'msg': 'm{}' }}", level, i))
+        });
+
+        let mut emitted = vec![];
+        // The input never ends, so this only returns because LIMIT stops reading.
+        evaluate_rows(sql, rows, |value| {
+            emitted.push(value);
+            Ok(())
+        })?;
+        assert_eq!(
+            emitted,
+            vec![
+                PqlValue::from_str("{ 'msg': 'm2' }")?,
+                PqlValue::from_str("{ 'msg': 'm4' }")?,
+            ]
+        );
+        Ok(())
+    }
+}
@@ -1,59 +1,176 @@
+use std::str::FromStr;
+
+use bigdecimal::BigDecimal;
 use indexmap::IndexMap;
 use ordered_float::OrderedFloat;
 
-use nom::character::complete::multispace0;
 pub use nom::error::convert_error;
 pub use nom::error::VerboseError;
 use nom::{
     branch::alt,
-    bytes::complete::{escaped, tag},
-    character::complete::{alphanumeric1, char, one_of, space1},
-    combinator::{cut, map, opt, value},
+    bytes::complete::{is_not, tag, tag_no_case, take_until, take_while, take_while1},
+    character::complete::{char, digit1, multispace1, one_of},
+    combinator::{cut, map, map_opt, opt, recognize, value},
     error::{context, ContextError, ParseError},
-    multi::separated_list0,
-    number::complete::double,
-    sequence::{delimited, preceded, separated_pair, terminated},
-    IResult, Parser,
+    multi::{many0_count, separated_list0},
+    sequence::{pair, preceded, separated_pair, terminated, tuple},
+    IResult,
 };
 
+use crate::value::datetime::{self, PqlTime};
+use crate::value::ion_value;
 use crate::value::PqlValue;
 
-fn parse_str<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, &'a str, E> {
-    escaped(alt((alphanumeric1, space1)), '\\', one_of("\"n\\"))(i)
+/// Whitespace and `--` or `/* */` comments.
+fn sp<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, usize, E> {
+    many0_count(alt((
+        multispace1,
+        recognize(pair(tag("--"), opt(is_not("\r\n")))),
+        recognize(tuple((tag("/*"), take_until("*/"), tag("*/")))),
+    )))(i)
 }
 
-fn boolean<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, bool, E> {
-    // This is a parser that returns `true` if it sees the string "true", and
-    // an error otherwise
-    let parse_true = value(true, tag("true"));
-
-    // This is a parser that returns `false` if it sees the string "false", and
-    // an error otherwise
-    let parse_false = value(false, tag("false"));
+fn keyword<'a, E: ParseError<&'a str>>(
+    word: &'static str,
+) -> impl FnMut(&'a str) -> IResult<&'a str, &'a str, E> {
+    terminated(
+        tag_no_case(word),
+        nom::combinator::not(take_while1(|c: char| c.is_ascii_alphanumeric() || c == '_')),
+    )
+}
 
-    // `alt` combines the two parsers. It returns the result of the first
-    // successful parser, or an error
-    alt((parse_true, parse_false))(input)
+fn boolean<'a, E: ParseError<&'a str>>(input: &'a str) -> IResult<&'a str, bool, E> {
+    alt((value(true, keyword("true")), value(false, keyword("false"))))(input)
 }
 
 fn null<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
-) -> IResult<&'a str, &'a str, E> {
-    context("null", alt((tag("null"), tag("NULL"))))(i)
+) -> IResult<&'a str, PqlValue, E> {
+    context(
+        "null",
+        alt((
+            value(PqlValue::Null, keyword("null")),
+            value(PqlValue::Missing, keyword("missing")),
+        )),
+    )(i)
+}
+
+// Quotes are escaped either by doubling them, as in SQL, or with a backslash.
+fn quoted<'a, E: ParseError<&'a str>>(
+    quote: char,
+) -> impl Fn(&'a str) -> IResult<&'a str, String, E> {
+    move |i: &'a str| {
+        let (i, _) = char(quote)(i)?;
+        let mut s = String::new();
+        let mut chars = i.char_indices().peekable();
+        while let Some((pos, c)) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => s.push('\n'),
+                    Some((_, 't')) => s.push('\t'),
+                    Some((_, 'r')) => s.push('\r'),
+                    Some((_, c)) => s.push(c),
+                    None => break,
+                },
+                _ if c == quote => {
+                    if let Some((_, next)) = chars.peek() {
+                        if *next == quote {
+                            chars.next();
+                            s.push(quote);
+                            continue;
+                        }
+                    }
+                    return Ok((&i[pos + c.len_utf8()..], s));
+                }
+                _ => s.push(c),
+            }
+        }
+        Err(nom::Err::Failure(E::from_error_kind(
+            i,
+            nom::error::ErrorKind::Char,
+        )))
+    }
 }
 
 fn string<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
-) -> IResult<&'a str, &'a str, E> {
+) -> IResult<&'a str, String, E> {
+    context("string", alt((quoted('\''), quoted('"'))))(i)
+}
+
+// `3` is an INT, `3.5` a DECIMAL and `3.5e0` a FLOAT.
+fn number<'a, E: ParseError<&'a str>>(i: &'a str) -> IResult<&'a str, PqlValue, E> {
+    map_opt(
+        recognize(tuple((
+            opt(one_of("+-")),
+            digit1,
+            opt(pair(char('.'), opt(digit1))),
+            opt(tuple((one_of("eE"), opt(one_of("+-")), digit1))),
+        ))),
+        |s: &str| {
+            if s.contains(['e', 'E']) {
+                s.parse::<f64>()
+                    .ok()
+                    .map(|f| PqlValue::Float(OrderedFloat(f)))
+            } else if s.contains('.') {
+                BigDecimal::from_str(s.trim_end_matches('.'))
+                    .ok()
+                    .map(PqlValue::Decimal)
+            } else {
+                s.parse::<i64>().ok().map(PqlValue::Int).or_else(|| {
+                    BigDecimal::from_str(s.trim_start_matches('+'))
+                        .ok()
+                        .map(PqlValue::Decimal)
+                })
+            }
+        },
+    )(i)
+}
+
+fn datetime<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    i: &'a str,
+) -> IResult<&'a str, PqlValue, E> {
+    let with_time_zone = tuple((
+        multispace1,
+        tag_no_case("with"),
+        multispace1,
+        tag_no_case("time"),
+        multispace1,
+        tag_no_case("zone"),
+    ));
     context(
-        "string",
+        "datetime",
         alt((
-            preceded(char('"'), cut(terminated(parse_str, char('"')))),
-            preceded(char('\''), cut(terminated(parse_str, char('\'')))),
+            map_opt(preceded(pair(keyword("timestamp"), sp), string), |s| {
+                datetime::parse_timestamp(&s).ok().map(PqlValue::DateTime)
+            }),
+            map_opt(
+                preceded(tuple((keyword("time"), opt(with_time_zone), sp)), string),
+                |s| PqlTime::from_str(&s).ok().map(PqlValue::Time),
+            ),
+            map_opt(preceded(pair(keyword("date"), sp), string), |s| {
+                datetime::parse_date(&s).ok().map(PqlValue::Date)
+            }),
         )),
     )(i)
 }
 
+/// An Ion value between backticks, for types PartiQL has no literal for.
+fn ion<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    i: &'a str,
+) -> IResult<&'a str, PqlValue, E> {
+    context(
+        "ion",
+        map_opt(
+            preceded(
+                char('`'),
+                cut(terminated(take_while(|c| c != '`'), char('`'))),
+            ),
+            |s| ion_value::from_str(s).ok(),
+        ),
+    )(i)
+}
+
 fn bag<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
 ) -> IResult<&'a str, Vec<PqlValue>, E> {
@@ -62,8 +179,8 @@ fn bag<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
         preceded(
             tag("<<"),
             cut(terminated(
-                separated_list0(preceded(multispace0, char(',')), json_value),
-                preceded(multispace0, tag(">>")),
+                separated_list0(preceded(sp, char(',')), json_value),
+                preceded(sp, tag(">>")),
             )),
         ),
     )(i)
@@ -77,8 +194,8 @@ fn array<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
         preceded(
             tag("["),
             cut(terminated(
-                separated_list0(preceded(multispace0, char(',')), json_value),
-                preceded(multispace0, tag("]")),
+                separated_list0(preceded(sp, char(',')), json_value),
+                preceded(sp, tag("]")),
             )),
         ),
     )(i)
@@ -86,10 +203,10 @@ fn array<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
 
 fn key_value<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
-) -> IResult<&'a str, (&'a str, PqlValue), E> {
+) -> IResult<&'a str, (String, PqlValue), E> {
     separated_pair(
-        preceded(multispace0, string),
-        cut(preceded(multispace0, char(':'))),
+        preceded(sp, string),
+        cut(preceded(sp, char(':'))),
         json_value,
     )(i)
 }
@@ -103,53 +220,84 @@ fn hash<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
             char('{'),
             cut(terminated(
                 map(
-                    separated_list0(preceded(multispace0, char(',')), key_value),
-                    |tuple_vec| {
-                        tuple_vec
-                            .into_iter()
-                            .map(|(k, v)| (String::from(k), v))
-                            .collect()
-                    },
+                    separated_list0(preceded(sp, char(',')), key_value),
+                    |tuple_vec| tuple_vec.into_iter().collect(),
                 ),
-                preceded(multispace0, char('}')),
+                preceded(sp, char('}')),
             )),
         ),
     )(i)
 }
 
+/// Parses a value, keeping bags apart from arrays as `PqlValue::bag`.
 pub fn json_value<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
 ) -> IResult<&'a str, PqlValue, E> {
     preceded(
-        multispace0,
+        sp,
         alt((
-            map(null, |_s| PqlValue::Null),
+            null,
             map(hash, PqlValue::Object),
             map(array, PqlValue::Array),
-            map(bag, PqlValue::Array),
-            map(string, |s| PqlValue::Str(String::from(s))),
-            map(double, |f| PqlValue::Float(OrderedFloat(f as f64))),
+            map(bag, PqlValue::bag),
+            map(string, PqlValue::Str),
+            datetime,
+            ion,
+            number,
             map(boolean, PqlValue::Boolean),
         )),
     )(i)
 }
 
+/// Parses a value the way queries and tests have always read it: bags as arrays and numbers as floats.
 pub fn root<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
     i: &'a str,
 ) -> IResult<&'a str, PqlValue, E> {
-    delimited(multispace0, json_value, opt(multispace0))(i)
+    map(root_with_bags, legacy)(i)
+}
+
+fn legacy(value: PqlValue) -> PqlValue {
+    match value.unbag() {
+        PqlValue::Int(int) => PqlValue::Float(OrderedFloat(int as f64)),
+        PqlValue::Decimal(decimal) => {
+            // Through the text, since `BigDecimal::to_f64` does not round correctly.
+            PqlValue::Float(OrderedFloat(
+                decimal.to_string().parse().unwrap_or(f64::NAN),
+            ))
+        }
+        PqlValue::Array(array) => PqlValue::Array(array.into_iter().map(legacy).collect()),
+        PqlValue::Object(map) => {
+            PqlValue::Object(map.into_iter().map(|(k, v)| (k, legacy(v))).collect())
+        }
+        value => value,
+    }
+}
+
+pub fn root_with_bags<'a, E: ParseError<&'a str> + ContextError<&'a str>>(
+    i: &'a str,
+) -> IResult<&'a str, PqlValue, E> {
+    terminated(json_value, sp)(i)
 }
 
 pub fn pql_value(input: &str) -> anyhow::Result<PqlValue> {
-    // let re = regex::Regex::new(r"(^|\n)\s*--[\w\s\.{}]*?\n").unwrap();
-    let re = regex::Regex::new(r"--[\w\s\.{}]*?\n").unwrap();
-    let input = re.replace_all(input, "");
-
-    match root::<VerboseError<&str>>(&input) {
-        Ok((_, r)) => Ok(r),
-        Err(_err) => {
-            anyhow::bail!("failed")
+    pql_value_with_bags(input).map(legacy)
+}
+
+/// Like `pql_value`, but keeps bags, so that they are written back as `<< >>`,
+/// and tells INT, DECIMAL (`3.5`) and FLOAT (`3.5e0`) apart.
+pub fn pql_value_with_bags(input: &str) -> anyhow::Result<PqlValue> {
+    match root_with_bags::<VerboseError<&str>>(input) {
+        Ok(("", r)) => Ok(r),
+        Ok((rest, _)) => {
+            anyhow::bail!(
+                "unexpected text: {}",
+                rest.lines().next().unwrap_or_default()
+            )
+        }
+        Err(nom::Err::Error(err)) | Err(nom::Err::Failure(err)) => {
+            anyhow::bail!("{}", convert_error(input, err))
         }
+        Err(err) => anyhow::bail!("{}", err),
     }
 }
 
@@ -0,0 +1,566 @@
+//! Checks a query against the type of the data it reads, before it runs.
+//!
+//! The checker infers the type of every expression of the query from the schema, as the query
+//! would evaluate it over any value of that type. A path that is never there is a warning, and
+//! an operator or a function applied to values it is not defined for is a type error, as it
+//! would be in strict mode. Where only some of the values it may be applied to are of the wrong
+//! types, it is a warning, as those are MISSING in permissive mode.
+
+use std::fmt;
+
+use indexmap::IndexMap as Map;
+use parse_display::Display;
+
+use crate::schema::PqlType;
+use crate::sql::{EvalError, Expr, Func, Selector, SelectorNode, Sql, WhereCond, DOCS};
+use crate::value::datetime::DateTimePart;
+use crate::value::PqlValue;
+
+#[derive(Display, Debug, Clone, Copy, PartialEq, Eq)]
+#[display(style = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+/// Something wrong with a query, found before it runs.
+#[derive(Display, Debug, Clone, PartialEq, Eq)]
+#[display("{severity}: {message}")]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// What checking a query found: the name and type of each field of its rows, and what is wrong
+/// with it.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TypeCheck {
+    pub columns: Vec<(String, PqlType)>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl TypeCheck {
+    /// Whether the query has a type error, which stops it in strict mode.
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == Severity::Error)
+    }
+}
+
+impl fmt::Display for TypeCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut lines = self
+            .columns
+            .iter()
+            .map(|(name, t)| format!("{}: {}", name, t))
+            .collect::<Vec<_>>();
+        lines.extend(self.diagnostics.iter().map(Diagnostic::to_string));
+        write!(f, "{}", lines.join("\n"))
+    }
+}
+
+/// Checks `sql` against `schema`, the type of the data it is run over: a document when the query
+/// has a FROM clause, and a collection of rows otherwise.
+pub fn check(sql: &Sql, schema: &PqlType) -> TypeCheck {
+    let has_from = !sql.from_clause.is_empty() || !sql.left_join_clause.is_empty();
+    let schema = &name_collection(sql, schema);
+    let mut checker = Checker {
+        row: if has_from {
+            schema.to_owned()
+        } else {
+            schema.elements()
+        },
+        variables: Map::new(),
+        diagnostics: vec![],
+    };
+
+    for (field, outer) in sql
+        .from_clause
+        .iter()
+        .map(|field| (field, false))
+        .chain(sql.left_join_clause.iter().map(|field| (field, true)))
+    {
+        let mut t = checker.expr(&field.expr).elements();
+        if outer {
+            t = t.union(PqlType::Missing);
+        }
+        match &field.alias {
+            Some(alias) => {
+                checker.variables.insert(alias.to_owned(), t);
+            }
+            None => checker.row = t,
+        }
+    }
+    if let Some(cond) = &sql.where_clause {
+        checker.cond(cond);
+    }
+
+    let mut columns = vec![];
+    for (i, field) in sql.select_clause.iter().enumerate() {
+        if field.expr == Expr::Star {
+            columns.extend(checker.star());
+            continue;
+        }
+        let t = checker.expr(&field.expr);
+        let (name, _) = field.to_owned().rename(i + 1);
+        columns.push((name, t));
+    }
+    if let Some(order_by) = &sql.orderby {
+        let is_column = columns
+            .iter()
+            .any(|(name, t)| name == &order_by.label || *t == PqlType::Any && name == "*");
+        if !is_column {
+            checker.warn(format!(
+                "ORDER BY {} is not a field of the result, which leaves out every row",
+                order_by.label
+            ));
+        }
+    }
+
+    TypeCheck {
+        columns,
+        diagnostics: checker.diagnostics,
+    }
+}
+
+struct Checker {
+    // The type of the row that paths that do not start with a variable are in.
+    row: PqlType,
+    variables: Map<String, PqlType>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl Checker {
+    fn report(&mut self, severity: Severity, message: String) {
+        let diagnostic = Diagnostic { severity, message };
+        if !self.diagnostics.contains(&diagnostic) {
+            self.diagnostics.push(diagnostic);
+        }
+    }
+
+    fn warn(&mut self, message: String) {
+        self.report(Severity::Warning, message)
+    }
+
+    fn expr(&mut self, expr: &Expr) -> PqlType {
+        match expr {
+            Expr::Star | Expr::Sql(_) => PqlType::Any,
+            Expr::Selector(selector) => self.path(selector),
+            Expr::Value(value) => PqlType::of(value),
+            Expr::Func(func) => self.func(func),
+            Expr::Add(a, b) => self.arithmetic("+", a, b),
+            Expr::Sub(a, b) => self.arithmetic("-", a, b),
+            Expr::Mul(a, b) => self.arithmetic("*", a, b),
+            Expr::Div(a, b) => self.arithmetic("/", a, b),
+            Expr::Rem(a, b) => self.arithmetic("%", a, b),
+            Expr::Exp(a, b) => self.arithmetic("^", a, b),
+        }
+    }
+
+    fn path(&mut self, selector: &Selector) -> PqlType {
+        let t = match selector.split_first() {
+            Some((head, tail)) => match self.variables.get(&head.to_string()) {
+                Some(t) => t.path(&tail),
+                None => self.row.path(selector),
+            },
+            None => self.row.to_owned(),
+        };
+        if t.is_always_missing() {
+            self.warn(format!(
+                "the path {} is always MISSING",
+                selector.to_string()
+            ));
+        }
+        t
+    }
+
+    fn arithmetic(&mut self, op: &str, a: &Expr, b: &Expr) -> PqlType {
+        let args = [self.expr(a), self.expr(b)];
+        if matches!(op, "/" | "%") && matches!(b, Expr::Value(value) if is_zero(value)) {
+            self.report(Severity::Error, EvalError::DivisionByZero.to_string());
+        }
+        self.apply(op, &args, &|args| match (op, args) {
+            (_, [a, b]) if !a.is_number() || !b.is_number() => None,
+            ("^", _) => Some(PqlType::Float),
+            (_, [PqlType::Float, _] | [_, PqlType::Float]) => Some(PqlType::Float),
+            (_, [PqlType::Decimal, _] | [_, PqlType::Decimal]) => Some(PqlType::Decimal),
+            // Two INTs are divided as floats.
+            ("/", _) => Some(PqlType::Float),
+            _ => Some(PqlType::Int),
+        })
+    }
+
+    fn func(&mut self, func: &Func) -> PqlType {
+        let is_datetime =
+            |t: &PqlType| matches!(t, PqlType::Timestamp | PqlType::Date | PqlType::Time);
+        let is_timestamp = |t: &PqlType| matches!(t, PqlType::Timestamp | PqlType::Date);
+        match func {
+            Func::Count(expr) => {
+                self.expr(expr);
+                let err = EvalError::Unsupported("COUNT outside of an aggregate query".to_owned());
+                self.report(Severity::Error, err.to_string());
+                PqlType::Missing
+            }
+            Func::Upper(expr) => {
+                let args = [self.expr(expr)];
+                self.apply("UPPER", &args, &|args| match args {
+                    [PqlType::String] => Some(PqlType::String),
+                    _ => None,
+                })
+            }
+            Func::Extract(part, expr) => {
+                let args = [self.expr(expr)];
+                let part = *part;
+                self.apply("EXTRACT", &args, &|args| match args {
+                    [t] if is_datetime(t) && part == DateTimePart::Second => Some(PqlType::Decimal),
+                    [t] if is_datetime(t) => Some(PqlType::Int),
+                    _ => None,
+                })
+            }
+            Func::DateAdd(_, quantity, expr) => {
+                let args = [self.expr(quantity), self.expr(expr)];
+                self.apply("DATE_ADD", &args, &|args| match args {
+                    [n, t] if n.is_number() && is_timestamp(t) => Some(t.to_owned()),
+                    _ => None,
+                })
+            }
+            Func::DateDiff(_, from, to) => {
+                let args = [self.expr(from), self.expr(to)];
+                self.apply("DATE_DIFF", &args, &|args| match args {
+                    [a, b] if is_timestamp(a) && is_timestamp(b) => Some(PqlType::Int),
+                    _ => None,
+                })
+            }
+            Func::ToString(expr, pattern) => {
+                let args = [self.expr(expr), self.expr(pattern)];
+                self.apply("TO_STRING", &args, &|args| match args {
+                    [t, PqlType::String] if is_datetime(t) => Some(PqlType::String),
+                    _ => None,
+                })
+            }
+            Func::ToTimestamp(expr, pattern) => {
+                let mut args = vec![self.expr(expr)];
+                if let Some(pattern) = pattern {
+                    args.push(self.expr(pattern));
+                }
+                self.apply("TO_TIMESTAMP", &args, &|args| match args {
+                    [PqlType::String | PqlType::Timestamp] | [PqlType::String, PqlType::String] => {
+                        Some(PqlType::Timestamp)
+                    }
+                    _ => None,
+                })
+            }
+            Func::UtcNow => PqlType::Timestamp,
+        }
+    }
+
+    // The type of applying `op` to arguments of the types `args`, reporting the types of the
+    // arguments it is not defined for, whose result is MISSING.
+    fn apply(
+        &mut self,
+        op: &str,
+        args: &[PqlType],
+        f: &dyn Fn(&[PqlType]) -> Option<PqlType>,
+    ) -> PqlType {
+        let mut undefined = vec![];
+        // Comparisons are not applied to the elements of lists, which do not compare.
+        let elementwise = !matches!(op, "<" | "<=" | ">" | ">=");
+        let t = apply(args, elementwise, f, &mut undefined);
+        if let Some(operands) = undefined.first() {
+            let err = EvalError::Type {
+                op: op.to_owned(),
+                operands: operands.to_owned(),
+            };
+            if t.is_always_missing() {
+                self.report(Severity::Error, err.to_string());
+            } else {
+                self.warn(format!("{}, which is MISSING", err));
+            }
+        }
+        t
+    }
+
+    fn cond(&mut self, cond: &WhereCond) {
+        let (op, t, right) = match cond {
+            WhereCond::Eq { expr, .. } | WhereCond::Neq { expr, .. } => {
+                self.expr(expr);
+                return;
+            }
+            WhereCond::EqExpr { expr, right } => {
+                self.expr(expr);
+                self.expr(right);
+                return;
+            }
+            WhereCond::Like { expr, .. } => {
+                let args = [self.expr(expr)];
+                self.apply("LIKE", &args, &|args| match args {
+                    [PqlType::String] => Some(PqlType::Bool),
+                    _ => None,
+                });
+                return;
+            }
+            WhereCond::Lt { expr, right } => ("<", self.expr(expr), right),
+            WhereCond::Le { expr, right } => ("<=", self.expr(expr), right),
+            WhereCond::Gt { expr, right } => (">", self.expr(expr), right),
+            WhereCond::Ge { expr, right } => (">=", self.expr(expr), right),
+        };
+        let args = [t, PqlType::of(right)];
+        self.apply(op, &args, &|args| match args {
+            [a, b] if a.is_number() && b.is_number() => Some(PqlType::Bool),
+            [a, b] if a == b && !matches!(a, PqlType::List(_) | PqlType::Struct(_)) => {
+                Some(PqlType::Bool)
+            }
+            _ => None,
+        });
+    }
+
+    // The fields `*` selects: those of each variable, or of the row without one.
+    fn star(&self) -> Vec<(String, PqlType)> {
+        let values = if self.variables.is_empty() {
+            vec![&self.row]
+        } else {
+            self.variables.values().collect()
+        };
+        let mut columns = Map::new();
+        for (i, t) in values.into_iter().enumerate() {
+            let mut rest = PqlType::nothing();
+            let mut fields = None;
+            for member in t.members() {
+                match member {
+                    PqlType::Any => return vec![("*".to_owned(), PqlType::Any)],
+                    PqlType::Struct(map) => fields = Some(map),
+                    PqlType::Missing => {}
+                    member => rest = rest.union(member.to_owned()),
+                }
+            }
+            let is_struct = fields.is_some();
+            for (key, t) in fields.into_iter().flatten() {
+                let t = match rest.members().is_empty() {
+                    true => t.to_owned(),
+                    false => t.to_owned().union(PqlType::Missing),
+                };
+                columns.insert(key.to_owned(), t);
+            }
+            if !rest.members().is_empty() {
+                let t = match is_struct {
+                    true => rest.union(PqlType::Missing),
+                    false => rest,
+                };
+                columns.insert(format!("_{}", i + 1), t);
+            }
+        }
+        columns.into_iter().collect()
+    }
+}
+
+// Applies `f` to each combination of the types the arguments may have. MISSING and NULL
+// arguments make the result MISSING and NULL, and those that are lists apply `f` to their
+// elements when `elementwise`, as the operators of values do.
+fn apply(
+    args: &[PqlType],
+    elementwise: bool,
+    f: &dyn Fn(&[PqlType]) -> Option<PqlType>,
+    undefined: &mut Vec<String>,
+) -> PqlType {
+    let combinations = args.iter().fold(vec![vec![]], |combinations, arg| {
+        combinations
+            .into_iter()
+            .flat_map(|combination: Vec<PqlType>| {
+                arg.members().into_iter().map(move |member| {
+                    let mut combination = combination.to_owned();
+                    combination.push(member.to_owned());
+                    combination
+                })
+            })
+            .collect::<Vec<_>>()
+    });
+    let mut result = PqlType::nothing();
+    for combination in combinations {
+        let t = if combination.contains(&PqlType::Any) {
+            PqlType::Any
+        } else if combination.contains(&PqlType::Missing) {
+            PqlType::Missing
+        } else if combination.contains(&PqlType::Null) {
+            PqlType::Null
+        } else if elementwise && combination.iter().any(|t| matches!(t, PqlType::List(_))) {
+            let elements = combination
+                .iter()
+                .map(|t| match t {
+                    PqlType::List(element) => element.as_ref().to_owned(),
+                    t => t.to_owned(),
+                })
+                .collect::<Vec<_>>();
+            PqlType::List(Box::new(apply(&elements, elementwise, f, undefined)))
+        } else {
+            f(&combination).unwrap_or_else(|| {
+                let names = combination.iter().map(PqlType::name).collect::<Vec<_>>();
+                undefined.push(names.join(" and "));
+                PqlType::Missing
+            })
+        };
+        result = result.union(t);
+    }
+    result
+}
+
+// The type of the data as the query reads it: a collection of rows is named `docs` as
+// `planner::eval::name_collection` names it, when none of its rows may have a field of that name.
+fn name_collection(sql: &Sql, schema: &PqlType) -> PqlType {
+    match schema {
+        PqlType::List(element)
+            if sql.reads_docs()
+                && element
+                    .field(&SelectorNode::String(DOCS.to_owned()))
+                    .members()
+                    .into_iter()
+                    .all(|t| *t == PqlType::Missing) =>
+        {
+            let mut fields = Map::new();
+            fields.insert(DOCS.to_owned(), schema.to_owned());
+            PqlType::Struct(fields)
+        }
+        schema => schema.to_owned(),
+    }
+}
+
+fn is_zero(value: &PqlValue) -> bool {
+    match value {
+        PqlValue::Int(int) => *int == 0,
+        PqlValue::Float(float) => float.0 == 0.,
+        PqlValue::Decimal(decimal) => *decimal == 0.into(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{check, Severity};
+    use crate::schema::PqlType;
+    use crate::sql::Sql;
+    use crate::value::{json_value, PqlValue};
+
+    fn schema() -> anyhow::Result<PqlType> {
+        let data = json_value::to_pqlvalue(serde_json::from_str(
+            r#"{
+                "users": [
+                    { "id": 1, "name": "a", "age": 30, "tags": ["x"] },
+                    { "id": 2, "name": "b", "nickname": "bee", "tags": [] }
+                ]
+            }"#,
+        )?)?;
+        Ok(PqlType::of(&data))
+    }
+
+    fn messages(sql: &str) -> anyhow::Result<Vec<String>> {
+        let report = check(&Sql::from_str(sql)?, &schema()?);
+        Ok(report.diagnostics.iter().map(ToString::to_string).collect())
+    }
+
+    #[test]
+    fn infers_the_types_of_fields() -> anyhow::Result<()> {
+        let sql = Sql::from_str(
+            "SELECT u.id, UPPER(u.name) AS name, u.age * 2 AS double, u.nickname, u.tags \
+             FROM users AS u WHERE u.age > 20",
+        )?;
+        let report = check(&sql, &schema()?);
+        let columns = report
+            .columns
+            .iter()
+            .map(|(name, t)| format!("{}: {}", name, t))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            columns,
+            vec![
+                "id: INT",
+                "name: STRING",
+                "double: UNION<FLOAT, MISSING>",
+                "nickname: UNION<STRING, MISSING>",
+                "tags: LIST<STRING>",
+            ]
+        );
+        assert!(report.diagnostics.is_empty(), "{:?}", report.diagnostics);
+
+        let report = check(&Sql::from_str("SELECT * FROM users AS u")?, &schema()?);
+        let names = report
+            .columns
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["id", "name", "age", "tags", "nickname"]);
+        Ok(())
+    }
+
+    #[test]
+    fn names_a_collection_docs() -> anyhow::Result<()> {
+        let rows = PqlType::of(&json_value::to_pqlvalue(serde_json::from_str(
+            r#"[{ "id": 1 }, { "id": 2 }]"#,
+        )?)?);
+        let report = check(&Sql::from_str("SELECT d.id FROM docs d")?, &rows);
+        assert_eq!(report.to_string(), "id: INT");
+        // Rows that have a field of that name are read as they are.
+        let rows = PqlType::of(&json_value::to_pqlvalue(serde_json::from_str(
+            r#"[{ "docs": [{ "id": "a" }] }]"#,
+        )?)?);
+        let report = check(&Sql::from_str("SELECT d.id FROM docs d")?, &rows);
+        assert_eq!(report.to_string(), "id: LIST<STRING>");
+        Ok(())
+    }
+
+    #[test]
+    fn reports_missing_paths_and_type_errors() -> anyhow::Result<()> {
+        assert_eq!(
+            messages("SELECT u.nmae FROM users AS u")?,
+            vec!["warning: the path u.nmae is always MISSING"]
+        );
+        assert_eq!(
+            messages("SELECT u.name + 1 AS x FROM users AS u")?,
+            vec!["error: type error: + is not defined for string and float"]
+        );
+        assert_eq!(
+            messages("SELECT UPPER(u.id) AS x FROM users AS u WHERE u.name < 3")?,
+            vec![
+                "error: type error: < is not defined for string and float",
+                "error: type error: UPPER is not defined for int",
+            ]
+        );
+        // Only some of the values are of the wrong type.
+        let sql = Sql::from_str("SELECT u.nickname + 1 AS x FROM users AS u")?;
+        let data = PqlValue::from_str("{ 'users': [{ 'nickname': 'a' }, { 'nickname': 1 }] }")?;
+        let report = check(&sql, &PqlType::of(&data));
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].severity, Severity::Warning);
+        assert!(!report.has_errors());
+
+        assert_eq!(
+            messages("SELECT u.id FROM users AS u ORDER BY name")?,
+            vec!["warning: ORDER BY name is not a field of the result, which leaves out every row"]
+        );
+        // A string operand is parsed from the text of the query as any other.
+        let sql = Sql::from_str("SELECT 'abc' + 1 AS w, u.nope FROM users AS u")?;
+        let report = check(&sql, &schema()?);
+        let columns = report
+            .columns
+            .iter()
+            .map(|(name, t)| format!("{}: {}", name, t))
+            .collect::<Vec<_>>();
+        assert_eq!(columns, vec!["w: MISSING", "nope: MISSING"]);
+        assert_eq!(
+            report
+                .diagnostics
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec![
+                "error: type error: + is not defined for string and float",
+                "warning: the path u.nope is always MISSING",
+            ]
+        );
+        // Nothing is known of data of any type.
+        let report = check(&Sql::from_str("SELECT a.b + 1 AS x FROM t")?, &PqlType::Any);
+        assert!(report.diagnostics.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+//! Infers the shape of data from the values in it.
+//!
+//! A `Schema` is built by observing values, and records at each path the types seen there, how
+//! often the path is there at all, how many distinct values it has, and how long the lists at it
+//! are. Its type is the `PqlType` of every value observed.
+
+use std::collections::HashSet;
+
+use indexmap::IndexMap as Map;
+
+use crate::schema::PqlType;
+use crate::value::{BPqlValue, PqlValue};
+
+/// The most distinct values of a path that are counted; past it, there are too many to tell.
+pub const MAX_DISTINCT: usize = 1000;
+
+/// The values observed at a path of the data.
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    /// How many values were seen.
+    pub count: usize,
+    /// How many values of each type other than a list or a tuple were seen, NULL included.
+    pub scalars: Vec<(PqlType, usize)>,
+    /// The lists seen, and their elements.
+    pub lists: Option<Box<Lists>>,
+    /// The tuples seen, and their fields.
+    pub tuples: Option<Box<Tuples>>,
+    distinct: HashSet<BPqlValue>,
+    too_many_distinct: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Lists {
+    pub count: usize,
+    pub min_len: usize,
+    pub max_len: usize,
+    pub total_len: usize,
+    pub elements: Schema,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Tuples {
+    pub count: usize,
+    /// The values of each field, in the order the fields were first seen. A field is missing
+    /// from the tuples it was not seen in.
+    pub fields: Map<String, Schema>,
+}
+
+impl Schema {
+    /// The schema of the values of `value` and of every path in it.
+    pub fn infer(value: &PqlValue) -> Self {
+        let mut schema = Self::default();
+        schema.observe(value);
+        schema
+    }
+
+    /// Adds `value` to what was seen.
+    pub fn observe(&mut self, value: &PqlValue) {
+        match value {
+            PqlValue::Missing => return,
+            PqlValue::Annotated(_, value) => return self.observe(value),
+            _ => {}
+        }
+        self.count += 1;
+        match value {
+            PqlValue::Array(array) => {
+                let lists = self.lists.get_or_insert_with(|| {
+                    Box::new(Lists {
+                        min_len: array.len(),
+                        ..Lists::default()
+                    })
+                });
+                lists.count += 1;
+                lists.min_len = lists.min_len.min(array.len());
+                lists.max_len = lists.max_len.max(array.len());
+                lists.total_len += array.len();
+                for element in array {
+                    lists.elements.observe(element);
+                }
+            }
+            PqlValue::Object(map) => {
+                let tuples = self.tuples.get_or_insert_with(Box::default);
+                tuples.count += 1;
+                for (key, value) in map {
+                    tuples
+                        .fields
+                        .entry(key.to_owned())
+                        .or_default()
+                        .observe(value);
+                }
+            }
+            value => {
+                let t = PqlType::of(value);
+                match self.scalars.iter_mut().find(|(seen, _)| *seen == t) {
+                    Some((_, count)) => *count += 1,
+                    None => self.scalars.push((t, 1)),
+                }
+                if !self.too_many_distinct {
+                    self.distinct.insert(BPqlValue::from(value.to_owned()));
+                    if self.distinct.len() > MAX_DISTINCT {
+                        self.too_many_distinct = true;
+                        self.distinct.clear();
+                    }
+                }
+            }
+        }
+    }
+
+    /// How many distinct values other than lists and tuples were seen, unless there are more than
+    /// `MAX_DISTINCT`.
+    pub fn distinct(&self) -> Option<usize> {
+        match self.too_many_distinct {
+            true => None,
+            false => Some(self.distinct.len()),
+        }
+    }
+
+    /// How many NULLs were seen.
+    pub fn nulls(&self) -> usize {
+        self.scalars
+            .iter()
+            .find(|(t, _)| *t == PqlType::Null)
+            .map_or(0, |(_, count)| *count)
+    }
+
+    /// The type of every value seen. A field of a tuple that some tuples do not have may be
+    /// MISSING.
+    pub fn to_type(&self) -> PqlType {
+        let mut t = self
+            .scalars
+            .iter()
+            .fold(PqlType::nothing(), |t, (scalar, _)| {
+                t.union(scalar.to_owned())
+            });
+        if let Some(lists) = &self.lists {
+            t = t.union(PqlType::List(Box::new(lists.elements.to_type())));
+        }
+        if let Some(tuples) = &self.tuples {
+            let fields = tuples
+                .fields
+                .iter()
+                .map(|(key, field)| {
+                    let t = match field.count < tuples.count {
+                        true => field.to_type().union(PqlType::Missing),
+                        false => field.to_type(),
+                    };
+                    (key.to_owned(), t)
+                })
+                .collect();
+            t = t.union(PqlType::Struct(fields));
+        }
+        t
+    }
+
+    /// A row for each path of the data: its type, how many values it has, how many of them are
+    /// NULL, how many times it is missing from a tuple, how many distinct values it has, and the
+    /// shortest and longest lists at it. Paths are written as in JSONPath, from `$` for the data.
+    pub fn stats(&self) -> PqlValue {
+        let mut rows = vec![];
+        self.collect_stats("$".to_owned(), None, &mut rows);
+        PqlValue::Array(rows)
+    }
+
+    fn collect_stats(&self, path: String, tuples: Option<usize>, rows: &mut Vec<PqlValue>) {
+        let optional = |n: Option<usize>| n.map_or(PqlValue::Null, |n| PqlValue::from(n as i64));
+        let mut row = Map::new();
+        row.insert("path".to_owned(), PqlValue::from(path.as_str()));
+        row.insert(
+            "type".to_owned(),
+            PqlValue::from(self.to_type().to_string().as_str()),
+        );
+        row.insert("count".to_owned(), PqlValue::from(self.count as i64));
+        row.insert("nulls".to_owned(), PqlValue::from(self.nulls() as i64));
+        row.insert(
+            "missing".to_owned(),
+            PqlValue::from(tuples.map_or(0, |tuples| tuples - self.count) as i64),
+        );
+        row.insert("distinct".to_owned(), optional(self.distinct()));
+        let lists = self.lists.as_deref();
+        row.insert(
+            "min_length".to_owned(),
+            optional(lists.map(|lists| lists.min_len)),
+        );
+        row.insert(
+            "max_length".to_owned(),
+            optional(lists.map(|lists| lists.max_len)),
+        );
+        rows.push(PqlValue::Object(row));
+
+        if let Some(lists) = lists {
+            lists
+                .elements
+                .collect_stats(format!("{}[*]", path), None, rows);
+        }
+        if let Some(tuples) = &self.tuples {
+            for (key, field) in &tuples.fields {
+                field.collect_stats(format!("{}.{}", path, key), Some(tuples.count), rows);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Schema;
+    use crate::schema::PqlType;
+    use crate::value::{json_value, PqlValue};
+
+    fn data() -> anyhow::Result<PqlValue> {
+        json_value::to_pqlvalue(serde_json::from_str(
+            r#"[
+                { "id": 1, "name": "a", "tags": ["x", "y"], "score": null },
+                { "id": 2, "tags": [], "score": 1.5 },
+                { "id": 3, "name": "c", "tags": ["x"], "score": "n/a" }
+            ]"#,
+        )?)
+    }
+
+    #[test]
+    fn infers_types() -> anyhow::Result<()> {
+        let data = data()?;
+        let schema = Schema::infer(&data);
+        assert_eq!(schema.to_type(), PqlType::of(&data));
+        assert_eq!(
+            schema.to_type().to_string(),
+            "LIST<STRUCT<id: INT, name: UNION<STRING, MISSING>, tags: LIST<STRING>, \
+             score: UNION<NULL, FLOAT, STRING>>>"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn counts_values() -> anyhow::Result<()> {
+        let schema = Schema::infer(&data()?);
+        let rows = match schema.stats() {
+            PqlValue::Array(rows) => rows,
+            _ => unreachable!(),
+        };
+        let stats = |path: &str| {
+            rows.iter()
+                .find(|row| (*row).to_owned().get("path") == Some(PqlValue::from(path)))
+                .cloned()
+                .unwrap_or_default()
+        };
+        let get = |path: &str, key: &str| stats(path).get(key).unwrap_or_default();
+
+        assert_eq!(get("$", "count"), PqlValue::from(1));
+        assert_eq!(get("$", "max_length"), PqlValue::from(3));
+        assert_eq!(get("$[*].name", "count"), PqlValue::from(2));
+        assert_eq!(get("$[*].name", "missing"), PqlValue::from(1));
+        assert_eq!(get("$[*].score", "nulls"), PqlValue::from(1));
+        assert_eq!(get("$[*].tags", "min_length"), PqlValue::from(0));
+        assert_eq!(get("$[*].tags", "max_length"), PqlValue::from(2));
+        assert_eq!(get("$[*].tags[*]", "count"), PqlValue::from(3));
+        assert_eq!(get("$[*].tags[*]", "distinct"), PqlValue::from(2));
+        assert_eq!(get("$[*].id", "distinct"), PqlValue::from(3));
+        assert_eq!(get("$[*].id", "min_length"), PqlValue::Null);
+        Ok(())
+    }
+}
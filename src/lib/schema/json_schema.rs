@@ -0,0 +1,129 @@
+use serde_json::{json, Map, Value};
+
+use crate::schema::PqlType;
+
+const DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+impl PqlType {
+    /// The JSON Schema that the values of this type validate against, as they are written in
+    /// JSON. The fields of a struct that are never MISSING are required.
+    pub fn to_json_schema(&self) -> Value {
+        let mut schema = json_schema(self);
+        if let Value::Object(map) = &mut schema {
+            map.insert("$schema".to_owned(), json!(DIALECT));
+        }
+        schema
+    }
+}
+
+fn json_schema(t: &PqlType) -> Value {
+    match t {
+        // A union without MISSING, which is not a value but the absence of one.
+        PqlType::Union(_) | PqlType::Missing => {
+            let members = t
+                .members()
+                .into_iter()
+                .filter(|member| **member != PqlType::Missing)
+                .map(json_schema)
+                .collect::<Vec<_>>();
+            match members.as_slice() {
+                [] => json!({}),
+                [schema] => schema.to_owned(),
+                _ => union(members),
+            }
+        }
+        PqlType::Any => json!({}),
+        PqlType::Null => json!({ "type": "null" }),
+        PqlType::Bool => json!({ "type": "boolean" }),
+        PqlType::Int => json!({ "type": "integer" }),
+        PqlType::Float | PqlType::Decimal => json!({ "type": "number" }),
+        PqlType::String | PqlType::Symbol | PqlType::Clob => json!({ "type": "string" }),
+        PqlType::Timestamp => json!({ "type": "string", "format": "date-time" }),
+        PqlType::Date => json!({ "type": "string", "format": "date" }),
+        PqlType::Time => json!({ "type": "string", "format": "time" }),
+        PqlType::Blob => json!({ "type": "string", "contentEncoding": "base64" }),
+        PqlType::List(element) => match element.members().is_empty() {
+            true => json!({ "type": "array" }),
+            false => json!({ "type": "array", "items": json_schema(element) }),
+        },
+        PqlType::Struct(fields) => {
+            let properties = fields
+                .iter()
+                .map(|(key, t)| (key.to_owned(), json_schema(t)))
+                .collect::<Map<_, _>>();
+            let required = fields
+                .iter()
+                .filter(|(_, t)| !t.is_optional())
+                .map(|(key, _)| json!(key))
+                .collect::<Vec<_>>();
+            json!({ "type": "object", "properties": properties, "required": required })
+        }
+    }
+}
+
+// The schema of a value that is valid against any of `schemas`, with a list of types for those
+// that only have one.
+fn union(schemas: Vec<Value>) -> Value {
+    let types = schemas
+        .iter()
+        .map(|schema| match schema.as_object() {
+            Some(map) if map.len() == 1 => map.get("type").cloned(),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>();
+    match types {
+        Some(types) => json!({ "type": types }),
+        None => json!({ "anyOf": schemas }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::schema::PqlType;
+    use crate::value::json_value;
+
+    #[test]
+    fn exports_json_schema() -> anyhow::Result<()> {
+        let data = json_value::to_pqlvalue(serde_json::from_str(
+            r#"[
+                { "id": 1, "name": "a", "tags": ["x"], "score": null },
+                { "id": 2, "tags": [], "score": 1.5, "meta": { "at": "2021" } }
+            ]"#,
+        )?)?;
+        let expected = json!({
+            "type": "array",
+            "items": {
+                "type": "object",
+                "properties": {
+                    "id": { "type": "integer" },
+                    "name": { "type": "string" },
+                    "tags": { "type": "array", "items": { "type": "string" } },
+                    "score": { "type": ["null", "number"] },
+                    "meta": {
+                        "type": "object",
+                        "properties": { "at": { "type": "string" } },
+                        "required": ["at"]
+                    }
+                },
+                "required": ["id", "tags", "score"]
+            },
+            "$schema": "https://json-schema.org/draft/2020-12/schema"
+        });
+        assert_eq!(PqlType::of(&data).to_json_schema(), expected);
+
+        let mixed = PqlType::Int.union(PqlType::List(Box::new(PqlType::Int)));
+        assert_eq!(
+            mixed.to_json_schema(),
+            json!({
+                "anyOf": [
+                    { "type": "integer" },
+                    { "type": "array", "items": { "type": "integer" } }
+                ],
+                "$schema": "https://json-schema.org/draft/2020-12/schema"
+            })
+        );
+        Ok(())
+    }
+}
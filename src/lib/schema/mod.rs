@@ -0,0 +1,10 @@
+//! The types of data, and of the queries that read it.
+
+mod check;
+mod infer;
+mod json_schema;
+mod pql_type;
+
+pub use check::{check, Diagnostic, Severity, TypeCheck};
+pub use infer::{Lists, Schema, Tuples, MAX_DISTINCT};
+pub use pql_type::PqlType;
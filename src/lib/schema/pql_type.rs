@@ -0,0 +1,316 @@
+use std::fmt;
+
+use indexmap::IndexMap as Map;
+
+use crate::sql::{Selector, SelectorNode};
+use crate::value::PqlValue;
+
+/// The type of a value, or of all the values an expression may have.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PqlType {
+    /// Any value, as that of data whose shape is not known.
+    Any,
+    Missing,
+    Null,
+    Bool,
+    Int,
+    Float,
+    Decimal,
+    String,
+    Symbol,
+    Timestamp,
+    Date,
+    Time,
+    Blob,
+    Clob,
+    /// A list or a bag, of elements of a type.
+    List(Box<PqlType>),
+    /// A tuple of these fields. A field that may be absent is a union with MISSING.
+    Struct(Map<String, PqlType>),
+    /// A value of any of these types, of which there is at most one list and one struct.
+    /// With none, there is no value at all, as in an empty list.
+    Union(Vec<PqlType>),
+}
+
+impl PqlType {
+    /// The type of `value`, where the elements of a list have the union of their types.
+    pub fn of(value: &PqlValue) -> Self {
+        match value {
+            PqlValue::Missing => Self::Missing,
+            PqlValue::Null => Self::Null,
+            PqlValue::Str(_) => Self::String,
+            PqlValue::Boolean(_) => Self::Bool,
+            PqlValue::Int(_) => Self::Int,
+            PqlValue::Float(_) => Self::Float,
+            PqlValue::Decimal(_) => Self::Decimal,
+            PqlValue::DateTime(_) => Self::Timestamp,
+            PqlValue::Date(_) => Self::Date,
+            PqlValue::Time(_) => Self::Time,
+            PqlValue::Symbol(_) => Self::Symbol,
+            PqlValue::Blob(_) => Self::Blob,
+            PqlValue::Clob(_) => Self::Clob,
+            PqlValue::Annotated(_, value) => Self::of(value),
+            PqlValue::Array(array) => Self::List(Box::new(
+                array
+                    .iter()
+                    .fold(Self::nothing(), |t, value| t.union(Self::of(value))),
+            )),
+            PqlValue::Object(map) => Self::Struct(
+                map.iter()
+                    .map(|(key, value)| (key.to_owned(), Self::of(value)))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// The type of no value.
+    pub fn nothing() -> Self {
+        Self::Union(vec![])
+    }
+
+    /// The type of the values of either type. The lists of both have the union of their element
+    /// types, and their structs the union of their fields, which are MISSING where one has none.
+    pub fn union(self, other: Self) -> Self {
+        let mut members: Vec<Self> = vec![];
+        for member in self.into_members().into_iter().chain(other.into_members()) {
+            match member {
+                Self::Any => return Self::Any,
+                Self::List(element) => {
+                    match members.iter_mut().find(|t| matches!(t, Self::List(_))) {
+                        Some(Self::List(existing)) => {
+                            let merged = std::mem::replace(existing.as_mut(), Self::nothing());
+                            **existing = merged.union(*element);
+                        }
+                        _ => members.push(Self::List(element)),
+                    }
+                }
+                Self::Struct(fields) => {
+                    match members.iter_mut().find(|t| matches!(t, Self::Struct(_))) {
+                        Some(Self::Struct(existing)) => {
+                            *existing = merge_fields(std::mem::take(existing), fields)
+                        }
+                        _ => members.push(Self::Struct(fields)),
+                    }
+                }
+                member if !members.contains(&member) => members.push(member),
+                _ => {}
+            }
+        }
+        if members.len() == 1 {
+            members.remove(0)
+        } else {
+            Self::Union(members)
+        }
+    }
+
+    /// The types a value of this type may have, none of which is a union.
+    pub fn members(&self) -> Vec<&Self> {
+        match self {
+            Self::Union(members) => members.iter().collect(),
+            t => vec![t],
+        }
+    }
+
+    fn into_members(self) -> Vec<Self> {
+        match self {
+            Self::Union(members) => members,
+            t => vec![t],
+        }
+    }
+
+    /// Whether a value of this type may be MISSING.
+    pub fn is_optional(&self) -> bool {
+        self.members()
+            .into_iter()
+            .any(|t| matches!(t, Self::Any | Self::Missing))
+    }
+
+    /// Whether a value of this type is always MISSING, or a list of values that are.
+    pub fn is_always_missing(&self) -> bool {
+        match self {
+            Self::Missing => true,
+            Self::List(element) => element.is_always_missing(),
+            Self::Union(members) => {
+                !members.is_empty() && members.iter().all(Self::is_always_missing)
+            }
+            _ => false,
+        }
+    }
+
+    /// The name of the type in messages, as `PqlValue::type_name` names that of a value.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Any => "any",
+            Self::Missing => "missing",
+            Self::Null => "null",
+            Self::Bool => "bool",
+            Self::Int => "int",
+            Self::Float => "float",
+            Self::Decimal => "decimal",
+            Self::String => "string",
+            Self::Symbol => "symbol",
+            Self::Timestamp => "timestamp",
+            Self::Date => "date",
+            Self::Time => "time",
+            Self::Blob => "blob",
+            Self::Clob => "clob",
+            Self::List(_) => "list",
+            Self::Struct(_) => "struct",
+            Self::Union(_) => "union",
+        }
+    }
+
+    pub fn is_number(&self) -> bool {
+        matches!(self, Self::Int | Self::Float | Self::Decimal)
+    }
+
+    /// The type of a step of a path into a value of this type. A field of a list is that field
+    /// of each of its elements, and a field of anything but a tuple is MISSING.
+    pub fn field(&self, node: &SelectorNode) -> Self {
+        match (self, node) {
+            (Self::Any, _) => Self::Any,
+            (Self::Union(members), _) => members
+                .iter()
+                .fold(Self::nothing(), |t, member| t.union(member.field(node))),
+            (Self::Struct(fields), SelectorNode::String(name)) => {
+                fields.get(name).cloned().unwrap_or(Self::Missing)
+            }
+            // The list may be too short to have the element.
+            (Self::List(element), SelectorNode::Number(_)) => {
+                element.as_ref().to_owned().union(Self::Missing)
+            }
+            (Self::List(element), SelectorNode::String(_)) => {
+                Self::List(Box::new(element.field(node)))
+            }
+            _ => Self::Missing,
+        }
+    }
+
+    /// The type of the value at `path` in a value of this type.
+    pub fn path(&self, path: &Selector) -> Self {
+        path.to_vec()
+            .iter()
+            .fold(self.to_owned(), |t, node| t.field(node))
+    }
+
+    /// The type of the values FROM binds in turn to a variable over a value of this type: the
+    /// elements of a list, none of MISSING, and any other value itself.
+    pub fn elements(&self) -> Self {
+        match self {
+            Self::List(element) => element.as_ref().to_owned(),
+            Self::Missing => Self::nothing(),
+            Self::Union(members) => members
+                .iter()
+                .fold(Self::nothing(), |t, member| t.union(member.elements())),
+            t => t.to_owned(),
+        }
+    }
+}
+
+fn merge_fields(a: Map<String, PqlType>, mut b: Map<String, PqlType>) -> Map<String, PqlType> {
+    let mut fields = a
+        .into_iter()
+        .map(|(key, t)| {
+            let other = b.shift_remove(&key).unwrap_or(PqlType::Missing);
+            (key, t.union(other))
+        })
+        .collect::<Map<_, _>>();
+    for (key, t) in b {
+        fields.insert(key, t.union(PqlType::Missing));
+    }
+    fields
+}
+
+/// The type as PartiQL writes it, such as `STRUCT<id: INT, tags: LIST<STRING>>`. The alternate
+/// form, `{:#}`, writes each field of a struct on a line of its own.
+impl fmt::Display for PqlType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.written(f.alternate().then_some(0)))
+    }
+}
+
+impl PqlType {
+    // The type as it is written, with the fields of structs indented by `depth` when there is one.
+    fn written(&self, depth: Option<usize>) -> String {
+        match self {
+            Self::List(element) => format!("LIST<{}>", element.written(depth)),
+            Self::Struct(fields) if fields.is_empty() => "STRUCT<>".to_owned(),
+            Self::Struct(fields) => {
+                let inner = depth.map(|depth| depth + 1);
+                let fields = fields
+                    .iter()
+                    .map(|(key, t)| format!("{}: {}", field_name(key), t.written(inner)));
+                match depth {
+                    Some(depth) => {
+                        let indent = |depth: usize| "  ".repeat(depth);
+                        let fields = fields
+                            .map(|field| format!("{}{}", indent(depth + 1), field))
+                            .collect::<Vec<_>>();
+                        format!("STRUCT<\n{}\n{}>", fields.join(",\n"), indent(depth))
+                    }
+                    None => format!("STRUCT<{}>", fields.collect::<Vec<_>>().join(", ")),
+                }
+            }
+            Self::Union(members) if members.is_empty() => "NOTHING".to_owned(),
+            Self::Union(members) => {
+                let members = members.iter().map(|t| t.written(depth)).collect::<Vec<_>>();
+                format!("UNION<{}>", members.join(", "))
+            }
+            t => t.name().to_uppercase(),
+        }
+    }
+}
+
+// A field name as it is written in a query, quoted unless it is an identifier.
+fn field_name(key: &str) -> String {
+    let is_identifier = key
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if is_identifier {
+        key.to_owned()
+    } else {
+        format!("'{}'", key.replace('\'', "''"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::PqlType;
+    use crate::sql::Selector;
+    use crate::value::json_value;
+
+    #[test]
+    fn types_of_values() -> anyhow::Result<()> {
+        let value = json_value::to_pqlvalue(serde_json::from_str(
+            r#"[
+                { "id": 1, "name": "a", "tags": ["x"] },
+                { "id": 2.5, "tags": [], "first name": null }
+            ]"#,
+        )?)?;
+        let t = PqlType::of(&value);
+        assert_eq!(
+            t.to_string(),
+            "LIST<STRUCT<id: UNION<INT, FLOAT>, name: UNION<STRING, MISSING>, \
+             tags: LIST<STRING>, 'first name': UNION<NULL, MISSING>>>"
+        );
+
+        let element = t.elements();
+        assert!(element.path(&Selector::from_str("name")?).is_optional());
+        assert_eq!(
+            element.path(&Selector::from_str("tags")?),
+            PqlType::List(Box::new(PqlType::String))
+        );
+        assert!(element
+            .path(&Selector::from_str("nmae")?)
+            .is_always_missing());
+        assert!(t.path(&Selector::from_str("nmae")?).is_always_missing());
+        assert!(!PqlType::Any
+            .path(&Selector::from_str("a.b")?)
+            .is_always_missing());
+        Ok(())
+    }
+}
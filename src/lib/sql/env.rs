@@ -1,4 +1,5 @@
 use std::collections::VecDeque;
+use std::sync::Arc;
 
 use indexmap::IndexMap as Map;
 
@@ -7,20 +8,35 @@ use crate::sql::Selector;
 use crate::sql::SelectorNode;
 use crate::value::PqlValue;
 
+/// The names a query can refer to. A binding tuple is an `Env` nested in the tuple it was
+/// produced from, so that the tuples of a join share the variables they have in common.
 #[derive(Debug, Default, Clone)]
 pub struct Env {
     data: Map<String, Expr>,
+    outer: Option<Arc<Env>>,
 }
 
 impl From<PqlValue> for Env {
     fn from(value: PqlValue) -> Self {
         let mut env = Self::default();
-        env.insert("", &Expr::from(value));
+        env.bind("", value);
         env
     }
 }
 
 impl Env {
+    /// An empty environment in which the names of `outer` are still visible.
+    pub fn nested(outer: Arc<Env>) -> Self {
+        Self {
+            data: Map::new(),
+            outer: Some(outer),
+        }
+    }
+
+    pub fn bind(&mut self, alias: &str, value: PqlValue) -> Option<Expr> {
+        self.data.insert(alias.to_string(), Expr::Value(value))
+    }
+
     pub fn insert(&mut self, alias: &str, expr: &Expr) -> Option<Expr> {
         self.data.insert(alias.to_string(), expr.to_owned())
     }
@@ -36,7 +52,35 @@ impl Env {
     }
 
     pub fn get(&self, key: &str) -> Option<Expr> {
-        self.data.get(key).map(|e| e.to_owned())
+        self.get_ref(key).map(|e| e.to_owned())
+    }
+
+    pub fn get_ref(&self, key: &str) -> Option<&Expr> {
+        match self.data.get(key) {
+            Some(expr) => Some(expr),
+            None => self.outer.as_ref()?.get_ref(key),
+        }
+    }
+
+    /// Every name in scope with what it is bound to, the outermost first.
+    pub fn variables(&self) -> Map<&str, &Expr> {
+        let mut variables = match &self.outer {
+            Some(outer) => outer.variables(),
+            None => Map::new(),
+        };
+        for (name, expr) in &self.data {
+            variables.insert(name, expr);
+        }
+        variables
+    }
+
+    /// The names bound in this environment itself, without those of the tuples it is nested in.
+    pub fn bindings(&self) -> impl Iterator<Item = (&str, &Expr)> {
+        self.data.iter().map(|(name, expr)| (name.as_str(), expr))
+    }
+
+    pub fn remove(&mut self, key: &str) -> Option<Expr> {
+        self.data.remove(key)
     }
 
     pub fn get_mut(&mut self, key: &str) -> Option<&mut Expr> {
@@ -45,13 +89,13 @@ impl Env {
 
     pub fn get_by_selector(&self, selector: &Selector) -> PqlValue {
         if let Some((head, tail)) = selector.split_first() {
-            if let Some(expr) = self.get(head.to_string().as_str()) {
+            if let Some(expr) = self.get_ref(head.to_string().as_str()) {
                 match expr {
                     Expr::Value(value) => {
                         let v = if tail.data.len() > 0 {
                             value.select_by_selector(&tail)
                         } else {
-                            value
+                            value.to_owned()
                         };
                         v
                     }
@@ -0,0 +1,339 @@
+use parse_display::Display;
+
+use crate::value::PqlValue;
+
+/// How an operator or a function treats values it is not defined for, such as `'abc' + 1` or
+/// `UPPER(1)`, as PartiQL defines it.
+///
+/// Either way, an operand that is MISSING makes the result MISSING, and one that is NULL makes
+/// it NULL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EvalMode {
+    /// The result is MISSING, and a comparison is false.
+    #[default]
+    Permissive,
+    /// The query stops with an `EvalError`.
+    Strict,
+}
+
+/// Why an expression has no value.
+#[derive(Display, Debug, Clone, PartialEq, Eq)]
+pub enum EvalError {
+    /// An operator or function applied to values of types it is not defined for.
+    #[display("type error: {op} is not defined for {operands}")]
+    Type { op: String, operands: String },
+    /// A division or remainder by zero, of any numeric type.
+    #[display("division by zero")]
+    DivisionByZero,
+    #[display("{0} is not supported")]
+    Unsupported(String),
+}
+
+impl std::error::Error for EvalError {}
+
+impl EvalError {
+    /// The type error of `op` applied to `operands`.
+    pub fn types(op: &str, operands: &[&PqlValue]) -> Self {
+        let operands = operands
+            .iter()
+            .map(|value| value.type_name())
+            .collect::<Vec<_>>();
+        Self::Type {
+            op: op.to_owned(),
+            operands: operands.join(" and "),
+        }
+    }
+}
+
+impl EvalMode {
+    /// The value of `result`, which is MISSING for an error in permissive mode.
+    pub fn value(self, result: Result<PqlValue, EvalError>) -> anyhow::Result<PqlValue> {
+        match (result, self) {
+            (Ok(value), _) => Ok(value),
+            (Err(_), Self::Permissive) => Ok(PqlValue::Missing),
+            (Err(err), Self::Strict) => Err(err.into()),
+        }
+    }
+
+    /// Whether `result` holds, which it does not for an error in permissive mode.
+    pub fn holds(self, result: Result<bool, EvalError>) -> anyhow::Result<bool> {
+        match (result, self) {
+            (Ok(holds), _) => Ok(holds),
+            (Err(_), Self::Permissive) => Ok(false),
+            (Err(err), Self::Strict) => Err(err.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+    use chrono::{DateTime, NaiveDate};
+
+    use super::{EvalError, EvalMode};
+    use crate::planner::evaluate_rows_in;
+    use crate::sql::{Env, Expr, Func, Sql, WhereCond};
+    use crate::value::datetime::{DateTimePart, PqlTime};
+    use crate::value::PqlValue;
+
+    // A value of each type.
+    fn values() -> anyhow::Result<Vec<PqlValue>> {
+        Ok(vec![
+            PqlValue::Missing,
+            PqlValue::Null,
+            PqlValue::from(2),
+            PqlValue::from(2.5),
+            PqlValue::Decimal(BigDecimal::from_str("1.5")?),
+            PqlValue::from("abc"),
+            PqlValue::Symbol("sym".to_owned()),
+            PqlValue::Boolean(true),
+            timestamp()?,
+            PqlValue::Blob(vec![1]),
+            PqlValue::from_str("{ 'a': 1 }")?,
+        ])
+    }
+
+    fn timestamp() -> anyhow::Result<PqlValue> {
+        Ok(PqlValue::DateTime(DateTime::parse_from_rfc3339(
+            "2021-07-01T09:30:00Z",
+        )?))
+    }
+
+    fn is_number(value: &PqlValue) -> bool {
+        matches!(
+            value,
+            PqlValue::Int(_) | PqlValue::Float(_) | PqlValue::Decimal(_)
+        )
+    }
+
+    fn eval(expr: &Expr, mode: EvalMode) -> anyhow::Result<PqlValue> {
+        expr.to_owned().eval_in(&Env::default(), mode)
+    }
+
+    fn strict_error(expr: &Expr) -> Option<EvalError> {
+        eval(expr, EvalMode::Strict)
+            .err()
+            .and_then(|err| err.downcast::<EvalError>().ok())
+    }
+
+    #[test]
+    fn arithmetic_of_each_type_pair() -> anyhow::Result<()> {
+        type Make = fn(Box<Expr>, Box<Expr>) -> Expr;
+        let ops: Vec<(&str, Make)> = vec![
+            ("+", Expr::Add),
+            ("-", Expr::Sub),
+            ("*", Expr::Mul),
+            ("/", Expr::Div),
+            ("%", Expr::Rem),
+            ("^", Expr::Exp),
+        ];
+        for (op, make) in ops {
+            for left in values()? {
+                for right in values()? {
+                    let expr = make(
+                        Box::new(Expr::Value(left.to_owned())),
+                        Box::new(Expr::Value(right.to_owned())),
+                    );
+                    let permissive = eval(&expr, EvalMode::Permissive)?;
+                    let case = format!("{:?} {} {:?}", left, op, right);
+                    if left == PqlValue::Missing || right == PqlValue::Missing {
+                        assert_eq!(permissive, PqlValue::Missing, "{}", case);
+                        assert_eq!(
+                            eval(&expr, EvalMode::Strict)?,
+                            PqlValue::Missing,
+                            "{}",
+                            case
+                        );
+                    } else if left == PqlValue::Null || right == PqlValue::Null {
+                        assert_eq!(permissive, PqlValue::Null, "{}", case);
+                        assert_eq!(eval(&expr, EvalMode::Strict)?, PqlValue::Null, "{}", case);
+                    } else if is_number(&left) && is_number(&right) {
+                        assert!(is_number(&permissive), "{}", case);
+                        assert_eq!(eval(&expr, EvalMode::Strict)?, permissive, "{}", case);
+                    } else {
+                        assert_eq!(permissive, PqlValue::Missing, "{}", case);
+                        assert!(
+                            matches!(strict_error(&expr), Some(EvalError::Type { .. })),
+                            "{}",
+                            case
+                        );
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn division_by_zero() -> anyhow::Result<()> {
+        let zero_int = PqlValue::from(0);
+        let zero_decimal = PqlValue::Decimal(BigDecimal::from(0));
+        let zero_float = PqlValue::from(0.);
+        for (make, zero) in [
+            (Expr::Div as fn(Box<Expr>, Box<Expr>) -> Expr, &zero_int),
+            (Expr::Div, &zero_decimal),
+            (Expr::Div, &zero_float),
+            (Expr::Rem, &zero_int),
+            (Expr::Rem, &zero_decimal),
+            (Expr::Rem, &zero_float),
+        ] {
+            let expr = make(
+                Box::new(Expr::Value(PqlValue::from(1))),
+                Box::new(Expr::Value(zero.to_owned())),
+            );
+            assert_eq!(eval(&expr, EvalMode::Permissive)?, PqlValue::Missing);
+            assert_eq!(strict_error(&expr), Some(EvalError::DivisionByZero));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn functions_of_each_type() -> anyhow::Result<()> {
+        let timestamp = timestamp()?;
+        for value in values()? {
+            let arg = || Expr::Value(value.to_owned());
+            let cases = vec![
+                (Func::Upper(arg()), matches!(value, PqlValue::Str(_))),
+                (Func::Extract(DateTimePart::Year, arg()), value == timestamp),
+                (
+                    Func::DateAdd(DateTimePart::Day, Expr::Value(PqlValue::from(1)), arg()),
+                    value == timestamp,
+                ),
+                (
+                    Func::DateDiff(DateTimePart::Day, arg(), Expr::Value(timestamp.to_owned())),
+                    value == timestamp,
+                ),
+                (
+                    Func::ToString(arg(), Expr::Value(PqlValue::from("yyyy"))),
+                    value == timestamp,
+                ),
+            ];
+            for (func, is_defined) in cases {
+                let case = format!("{:?}", func);
+                let expr = Expr::Func(Box::new(func));
+                let permissive = eval(&expr, EvalMode::Permissive)?;
+                match &value {
+                    PqlValue::Missing | PqlValue::Null => {
+                        assert_eq!(permissive, value, "{}", case);
+                        assert_eq!(eval(&expr, EvalMode::Strict)?, value, "{}", case);
+                    }
+                    _ if is_defined => {
+                        assert_ne!(permissive, PqlValue::Missing, "{}", case);
+                        assert_eq!(eval(&expr, EvalMode::Strict)?, permissive, "{}", case);
+                    }
+                    _ => {
+                        assert_eq!(permissive, PqlValue::Missing, "{}", case);
+                        assert!(
+                            matches!(strict_error(&expr), Some(EvalError::Type { .. })),
+                            "{}",
+                            case
+                        );
+                    }
+                }
+            }
+        }
+
+        // Patterns with fields the value does not have are type errors too.
+        let date = PqlValue::Date(NaiveDate::from_ymd_opt(2021, 7, 1).unwrap_or_default());
+        let time = PqlValue::Time(PqlTime::from_str("09:30:00+01:00")?);
+        for (value, pattern) in [(date, "HH:mm"), (time, "HH:mm XXX")] {
+            let expr = Expr::Func(Box::new(Func::ToString(
+                Expr::Value(value),
+                Expr::Value(PqlValue::from(pattern)),
+            )));
+            assert_eq!(eval(&expr, EvalMode::Permissive)?, PqlValue::Missing);
+            assert!(matches!(strict_error(&expr), Some(EvalError::Type { .. })));
+        }
+
+        let count = Expr::Func(Box::new(Func::Count(Expr::Value(PqlValue::from(1)))));
+        assert_eq!(eval(&count, EvalMode::Permissive)?, PqlValue::Missing);
+        assert!(matches!(
+            strict_error(&count),
+            Some(EvalError::Unsupported(_))
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn comparisons_of_each_type() -> anyhow::Result<()> {
+        let env = Env::default();
+        for value in values()? {
+            let expr = Expr::Value(value.to_owned());
+            let number = PqlValue::from(1.);
+            let conds = vec![
+                (
+                    WhereCond::Lt {
+                        expr: expr.to_owned(),
+                        right: number.to_owned(),
+                    },
+                    is_number(&value),
+                ),
+                (
+                    WhereCond::Ge {
+                        expr: expr.to_owned(),
+                        right: number.to_owned(),
+                    },
+                    is_number(&value),
+                ),
+                (
+                    WhereCond::Like {
+                        expr: expr.to_owned(),
+                        right: "a%".to_owned(),
+                    },
+                    matches!(value, PqlValue::Str(_)),
+                ),
+                // Any two values are equal or not.
+                (
+                    WhereCond::Eq {
+                        expr: expr.to_owned(),
+                        right: number.to_owned(),
+                    },
+                    true,
+                ),
+            ];
+            for (cond, is_defined) in conds {
+                let case = format!("{:?}", cond);
+                let permissive = cond.eval_in(&env, EvalMode::Permissive)?;
+                let strict = cond.eval_in(&env, EvalMode::Strict);
+                match &value {
+                    PqlValue::Missing | PqlValue::Null if !matches!(cond, WhereCond::Eq { .. }) => {
+                        assert!(!permissive, "{}", case);
+                        assert!(!strict?, "{}", case);
+                    }
+                    _ if is_defined => assert_eq!(strict?, permissive, "{}", case),
+                    _ => {
+                        assert!(!permissive, "{}", case);
+                        assert!(strict.is_err(), "{}", case);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn query_from_text() -> anyhow::Result<()> {
+        let run = |mode: EvalMode| -> anyhow::Result<Vec<PqlValue>> {
+            let sql = Sql::from_str("SELECT 'abc' + 1 AS w, a")?;
+            let rows = vec![PqlValue::from_str("{ 'a': 1 }")];
+            let mut emitted = vec![];
+            evaluate_rows_in(sql, rows.into_iter(), mode, |row| {
+                emitted.push(row);
+                Ok(())
+            })?;
+            Ok(emitted)
+        };
+        assert_eq!(
+            run(EvalMode::Permissive)?,
+            vec![PqlValue::from_str("{ 'a': 1 }")?]
+        );
+        let err = run(EvalMode::Strict).unwrap_err();
+        assert_eq!(
+            err.downcast::<EvalError>()?,
+            EvalError::types("+", &[&PqlValue::from("abc"), &PqlValue::from(1.)])
+        );
+        Ok(())
+    }
+}
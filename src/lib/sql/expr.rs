@@ -6,6 +6,7 @@ use ordered_float::OrderedFloat;
 
 use crate::parser;
 use crate::sql::Env;
+use crate::sql::EvalMode;
 use crate::sql::Func;
 use crate::sql::Selector;
 use crate::sql::Sql;
@@ -104,7 +105,7 @@ impl Expr {
             Self::Selector(path) => Self::Selector(path.expand_fullpath2(&env)),
             Expr::Value(_) => self.to_owned(),
             Expr::Star => todo!(),
-            Expr::Func(_) => todo!(),
+            Expr::Func(func) => Expr::Func(Box::new(func.expand_fullpath(env))),
             Self::Add(left, right) => Self::Add(
                 Box::new((*left).expand_fullpath(&env)),
                 Box::new((*right).expand_fullpath(&env)),
@@ -133,20 +134,47 @@ impl Expr {
         }
     }
 
+    /// The value of the expression in `env`, which is MISSING where it is not defined.
     pub fn eval(self, env: &Env) -> PqlValue {
-        match self.to_owned() {
+        self.eval_in(env, EvalMode::Permissive)
+            .unwrap_or(PqlValue::Missing)
+    }
+
+    /// The value of the expression in `env`, with the type errors of its operators and
+    /// functions handled as `mode` says.
+    pub fn eval_in(self, env: &Env, mode: EvalMode) -> anyhow::Result<PqlValue> {
+        let value = match self {
             Self::Value(value) => value,
-            Self::Selector(selector) => selector.evaluate(&env),
+            Self::Selector(selector) => selector.evaluate(env),
             Self::Star => todo!(),
-            Self::Func(_) => todo!(),
+            Self::Func(box func) => func.eval_in(env, mode)?,
             Self::Sql(_) => todo!(),
-            Self::Add(box expr1, box expr2) => (expr1).eval(&env) + (expr2).eval(&env),
-            Self::Sub(box expr1, box expr2) => (expr1).eval(&env) - (expr2).eval(&env),
-            Self::Mul(box expr1, box expr2) => (expr1).eval(&env) * (expr2).eval(&env),
-            Self::Div(box expr1, box expr2) => (expr1).eval(&env) / (expr2).eval(&env),
-            Self::Rem(box expr1, box expr2) => (expr1).eval(&env) % (expr2).eval(&env),
-            Self::Exp(box expr1, box expr2) => (expr1).eval(&env).powf((expr2).eval(&env)),
-        }
+            Self::Add(box left, box right) => mode.value(
+                left.eval_in(env, mode)?
+                    .checked_add(right.eval_in(env, mode)?),
+            )?,
+            Self::Sub(box left, box right) => mode.value(
+                left.eval_in(env, mode)?
+                    .checked_sub(right.eval_in(env, mode)?),
+            )?,
+            Self::Mul(box left, box right) => mode.value(
+                left.eval_in(env, mode)?
+                    .checked_mul(right.eval_in(env, mode)?),
+            )?,
+            Self::Div(box left, box right) => mode.value(
+                left.eval_in(env, mode)?
+                    .checked_div(right.eval_in(env, mode)?),
+            )?,
+            Self::Rem(box left, box right) => mode.value(
+                left.eval_in(env, mode)?
+                    .checked_rem(right.eval_in(env, mode)?),
+            )?,
+            Self::Exp(box left, box right) => mode.value(
+                left.eval_in(env, mode)?
+                    .checked_powf(right.eval_in(env, mode)?),
+            )?,
+        };
+        Ok(value)
     }
 
     pub fn source_field_name_set(&self, env: &Env) -> HashSet<String> {
@@ -187,6 +215,7 @@ impl Expr {
                 let b = expr2.source_field_name_set(&env);
                 a.union(&b).map(String::from).collect::<HashSet<_>>()
             }
+            Expr::Func(box func) => func.source_field_name_set(env),
             _ => {
                 dbg!(&self);
                 todo!();
@@ -194,12 +223,36 @@ impl Expr {
         }
     }
 
+    /// The top-level fields of the input this expression reads, or `None` if that is not known statically.
+    pub fn source_columns(&self, env: &Env) -> Option<HashSet<String>> {
+        match self {
+            Self::Selector(selector) => {
+                // A path to a whole row reads all of it.
+                let column = selector.expand_fullpath2(env).data.front()?.to_string();
+                Some(collect! { as HashSet<String>: column })
+            }
+            Self::Value(_) => Some(HashSet::default()),
+            Self::Func(func) => func.source_columns(env),
+            Self::Add(left, right)
+            | Self::Sub(left, right)
+            | Self::Mul(left, right)
+            | Self::Div(left, right)
+            | Self::Rem(left, right)
+            | Self::Exp(left, right) => {
+                let mut columns = left.source_columns(env)?;
+                columns.extend(right.source_columns(env)?);
+                Some(columns)
+            }
+            Self::Star | Self::Sql(_) => None,
+        }
+    }
+
     pub fn to_path(&self) -> Option<Selector> {
         match self.to_owned() {
             Self::Value(_value) => None,
             Self::Selector(selector) => Some(selector),
             Self::Star => todo!(),
-            Self::Func(_) => todo!(),
+            Self::Func(box func) => func.to_path(),
             Self::Sql(_) => todo!(),
             Self::Add(box expr1, box expr2) => match (expr1.to_path(), expr2.to_path()) {
                 (Some(s1), Some(s2)) => Some(s1.intersect(&s2)),
@@ -39,15 +39,17 @@ impl Field {
         value
     }
 
-    pub fn rename(self) -> (String, Expr) {
-        if let Some(alias) = self.alias {
-            (alias, self.expr)
-        } else {
-            let alias = match &self.expr {
-                Expr::Selector(selector) => selector.to_vec().last().unwrap().to_string(),
-                _ => todo!(),
-            };
-            (alias, self.expr)
-        }
+    /// The name of the field in the output: its alias, the last step of its path,
+    /// or `_n` for the `n`th item of SELECT, counted from 1.
+    pub fn rename(self, position: usize) -> (String, Expr) {
+        let alias = match (self.alias, &self.expr) {
+            (Some(alias), _) => alias,
+            (None, Expr::Selector(selector)) => match selector.last() {
+                Some(last) => last,
+                None => format!("_{}", position),
+            },
+            (None, _) => format!("_{}", position),
+        };
+        (alias, self.expr)
     }
 }
@@ -1,7 +1,260 @@
+use std::collections::HashSet;
+use std::convert::TryFrom;
+
+use crate::sql::Env;
+use crate::sql::EvalError;
+use crate::sql::EvalMode;
 use crate::sql::Expr;
+use crate::sql::Selector;
+use crate::value::datetime::{self, DateTimePart};
+use crate::value::PqlValue;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Func {
     Count(Expr),
     Upper(Expr),
+    Extract(DateTimePart, Expr),
+    DateAdd(DateTimePart, Expr, Expr),
+    DateDiff(DateTimePart, Expr, Expr),
+    ToString(Expr, Expr),
+    ToTimestamp(Expr, Option<Expr>),
+    UtcNow,
+}
+
+impl Func {
+    fn args(&self) -> Vec<&Expr> {
+        match self {
+            Self::Count(expr) | Self::Upper(expr) | Self::Extract(_, expr) => vec![expr],
+            Self::DateAdd(_, a, b) | Self::DateDiff(_, a, b) | Self::ToString(a, b) => vec![a, b],
+            Self::ToTimestamp(a, b) => std::iter::once(a).chain(b.iter()).collect(),
+            Self::UtcNow => vec![],
+        }
+    }
+
+    pub fn expand_fullpath(&self, env: &Env) -> Self {
+        match self {
+            Self::Count(expr) => Self::Count(expr.expand_fullpath(env)),
+            Self::Upper(expr) => Self::Upper(expr.expand_fullpath(env)),
+            Self::Extract(part, expr) => Self::Extract(*part, expr.expand_fullpath(env)),
+            Self::DateAdd(part, a, b) => {
+                Self::DateAdd(*part, a.expand_fullpath(env), b.expand_fullpath(env))
+            }
+            Self::DateDiff(part, a, b) => {
+                Self::DateDiff(*part, a.expand_fullpath(env), b.expand_fullpath(env))
+            }
+            Self::ToString(a, b) => Self::ToString(a.expand_fullpath(env), b.expand_fullpath(env)),
+            Self::ToTimestamp(a, b) => Self::ToTimestamp(
+                a.expand_fullpath(env),
+                b.as_ref().map(|b| b.expand_fullpath(env)),
+            ),
+            Self::UtcNow => Self::UtcNow,
+        }
+    }
+
+    pub fn source_field_name_set(&self, env: &Env) -> HashSet<String> {
+        self.args()
+            .into_iter()
+            .map(|expr| expr.source_field_name_set(env))
+            .fold(HashSet::default(), |acc, x| {
+                acc.union(&x).map(String::from).collect::<HashSet<_>>()
+            })
+    }
+
+    pub fn source_columns(&self, env: &Env) -> Option<HashSet<String>> {
+        self.args()
+            .into_iter()
+            .try_fold(HashSet::default(), |mut acc, expr| {
+                acc.extend(expr.source_columns(env)?);
+                Some(acc)
+            })
+    }
+
+    pub fn to_path(&self) -> Option<Selector> {
+        self.args()
+            .into_iter()
+            .filter_map(|expr| expr.to_path())
+            .fold(None, |acc: Option<Selector>, path| match acc {
+                Some(acc) => Some(acc.intersect(&path)),
+                None => Some(path),
+            })
+    }
+
+    pub fn eval(self, env: &Env) -> PqlValue {
+        self.eval_in(env, EvalMode::Permissive)
+            .unwrap_or(PqlValue::Missing)
+    }
+
+    /// The value of the function in `env`. An argument it is not defined for is a type error,
+    /// handled as `mode` says.
+    pub fn eval_in(self, env: &Env, mode: EvalMode) -> anyhow::Result<PqlValue> {
+        match self {
+            Self::Count(_) => mode.value(Err(EvalError::Unsupported(
+                "COUNT outside of an aggregate query".to_owned(),
+            ))),
+            Self::Upper(expr) => map_value(expr.eval_in(env, mode)?, mode, &|value| match value {
+                PqlValue::Str(s) => Ok(PqlValue::Str(s.to_uppercase())),
+                value => Err(EvalError::types("UPPER", &[&value])),
+            }),
+            Self::Extract(part, expr) => map_value(expr.eval_in(env, mode)?, mode, &|value| {
+                defined("EXTRACT", datetime::extract(part, &value), &[&value])
+            }),
+            Self::DateAdd(part, quantity, expr) => zip_values(
+                quantity.eval_in(env, mode)?,
+                expr.eval_in(env, mode)?,
+                mode,
+                &|quantity, value| match i64::try_from(quantity.to_owned()) {
+                    Ok(n) => defined(
+                        "DATE_ADD",
+                        datetime::date_add(part, n, &value),
+                        &[&quantity, &value],
+                    ),
+                    Err(_) => Err(EvalError::types("DATE_ADD", &[&quantity, &value])),
+                },
+            ),
+            Self::DateDiff(part, from, to) => zip_values(
+                from.eval_in(env, mode)?,
+                to.eval_in(env, mode)?,
+                mode,
+                &|from, to| {
+                    defined(
+                        "DATE_DIFF",
+                        datetime::date_diff(part, &from, &to),
+                        &[&from, &to],
+                    )
+                },
+            ),
+            Self::ToString(expr, pattern) => zip_values(
+                expr.eval_in(env, mode)?,
+                pattern.eval_in(env, mode)?,
+                mode,
+                &|value, pattern| match &pattern {
+                    PqlValue::Str(s) => {
+                        defined("TO_STRING", datetime::to_string(&value, s), &[&value, &pattern])
+                    }
+                    _ => Err(EvalError::types("TO_STRING", &[&value, &pattern])),
+                },
+            ),
+            Self::ToTimestamp(expr, None) => map_value(expr.eval_in(env, mode)?, mode, &|value| {
+                defined("TO_TIMESTAMP", datetime::to_timestamp(&value, None), &[&value])
+            }),
+            Self::ToTimestamp(expr, Some(pattern)) => zip_values(
+                expr.eval_in(env, mode)?,
+                pattern.eval_in(env, mode)?,
+                mode,
+                &|value, pattern| match &pattern {
+                    PqlValue::Str(s) => defined(
+                        "TO_TIMESTAMP",
+                        datetime::to_timestamp(&value, Some(s)),
+                        &[&value, &pattern],
+                    ),
+                    _ => Err(EvalError::types("TO_TIMESTAMP", &[&value, &pattern])),
+                },
+            ),
+            Self::UtcNow => Ok(datetime::utcnow()),
+        }
+    }
+}
+
+// The result of a function, which is not defined for `args` when it is MISSING.
+fn defined(name: &str, result: PqlValue, args: &[&PqlValue]) -> Result<PqlValue, EvalError> {
+    match result {
+        PqlValue::Missing => Err(EvalError::types(name, args)),
+        result => Ok(result),
+    }
+}
+
+// Selectors evaluate to one array per path, so functions apply element-wise like arithmetic does.
+// A MISSING or NULL argument is the result, whatever the function.
+fn map_value(
+    value: PqlValue,
+    mode: EvalMode,
+    f: &dyn Fn(PqlValue) -> Result<PqlValue, EvalError>,
+) -> anyhow::Result<PqlValue> {
+    match value {
+        PqlValue::Array(array) => array
+            .into_iter()
+            .map(|value| map_value(value, mode, f))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map(PqlValue::Array),
+        PqlValue::Missing | PqlValue::Null => Ok(value),
+        _ => mode.value(f(value)),
+    }
+}
+
+fn zip_values(
+    a: PqlValue,
+    b: PqlValue,
+    mode: EvalMode,
+    f: &dyn Fn(PqlValue, PqlValue) -> Result<PqlValue, EvalError>,
+) -> anyhow::Result<PqlValue> {
+    match (a, b) {
+        (PqlValue::Array(array_a), PqlValue::Array(array_b)) => array_a
+            .into_iter()
+            .zip(array_b)
+            .map(|(a, b)| zip_values(a, b, mode, f))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map(PqlValue::Array),
+        (PqlValue::Array(array), b) => array
+            .into_iter()
+            .map(|a| zip_values(a, b.to_owned(), mode, f))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map(PqlValue::Array),
+        (a, PqlValue::Array(array)) => array
+            .into_iter()
+            .map(|b| zip_values(a.to_owned(), b, mode, f))
+            .collect::<anyhow::Result<Vec<_>>>()
+            .map(PqlValue::Array),
+        (PqlValue::Missing, _) | (_, PqlValue::Missing) => Ok(PqlValue::Missing),
+        (PqlValue::Null, _) | (_, PqlValue::Null) => Ok(PqlValue::Null),
+        (a, b) => mode.value(f(a, b)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::lang::Lang;
+    use crate::planner::evaluate;
+    use crate::sql::Sql;
+
+    #[test]
+    fn datetime_functions_in_select() -> anyhow::Result<()> {
+        let data = Lang::from_as_toml(
+            r#"
+[[events]]
+name = "release"
+at = 2021-07-01T09:30:00.5+09:00
+
+[[events]]
+name = "freeze"
+at = 2021-12-24T18:00:00Z
+"#,
+        )?
+        .data;
+        let sql = Sql::from_str(
+            r#"
+SELECT
+    e.name AS name,
+    EXTRACT(YEAR FROM e.at) AS y,
+    TO_STRING(DATE_ADD(MONTH, 1, e.at), 'yyyy-MM-dd') AS next,
+    DATE_DIFF(DAY, TIMESTAMP '2021-01-01T00:00:00Z', e.at) AS days
+FROM events AS e
+"#,
+        )?;
+
+        assert_eq!(
+            evaluate(sql, data),
+            Lang::from_as_json(
+                r#"
+[
+    { "name": "release", "y": 2021, "next": "2021-08-01", "days": 181 },
+    { "name": "freeze", "y": 2021, "next": "2022-01-24", "days": 357 }
+]
+"#
+            )?
+            .data
+        );
+        Ok(())
+    }
 }
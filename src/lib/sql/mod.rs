@@ -1,4 +1,5 @@
 mod env;
+mod eval_mode;
 mod expr;
 mod field;
 mod func;
@@ -8,12 +9,15 @@ mod utils;
 mod where_cond;
 
 pub use env::Env;
+pub use eval_mode::{EvalError, EvalMode};
 pub use expr::Expr;
 pub use field::Field;
 pub use func::Func;
 pub use selector::Selector;
 pub use selector::SelectorNode;
 pub use sql::Sql;
+pub use sql::Statement;
+pub use sql::DOCS;
 pub use where_cond::re_from_str;
 pub use where_cond::WhereCond;
 
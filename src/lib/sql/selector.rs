@@ -165,10 +165,13 @@ impl Selector {
 
     fn rec_get_full_path(&self, env: &Env, trace_path: &mut Selector) {
         if let Some((first, tail)) = self.split_first() {
-            if let Some(alias_path) = env.get_as_selector(&first.to_string()) {
-                alias_path.rec_get_full_path(env, trace_path)
-            } else {
-                (*trace_path).data.push_back(first);
+            match env.get_as_selector(&first.to_string()) {
+                // A path that starts with the alias it is bound to, as in `FROM orders`, is full.
+                Some(alias_path) if alias_path.data.front() == Some(&first) => {
+                    trace_path.data.extend(alias_path.data)
+                }
+                Some(alias_path) => alias_path.rec_get_full_path(env, trace_path),
+                None => trace_path.data.push_back(first),
             }
             if tail.data.len() > 0 {
                 let tail_path = Selector::from(tail);
@@ -221,13 +224,13 @@ impl Selector {
 
     pub fn evaluate(&self, env: &Env) -> PqlValue {
         if let Some((head, tail)) = self.expand_fullpath(&env).split_first() {
-            if let Some(expr) = env.get(head.to_string().as_str()) {
+            if let Some(expr) = env.get_ref(head.to_string().as_str()) {
                 match expr {
                     Expr::Value(value) => {
                         let v = if tail.data.len() > 0 {
                             value.select_by_selector(&tail)
                         } else {
-                            value
+                            value.to_owned()
                         };
                         v
                     }
@@ -1,11 +1,19 @@
+use std::collections::HashSet;
 use std::str::FromStr;
 
 use crate::parser;
 pub use crate::sql::clause::Limit;
 pub use crate::sql::clause::OrderBy;
+use crate::sql::Env;
+use crate::sql::Expr;
 use crate::sql::Field;
+use crate::sql::Selector;
+use crate::sql::SelectorNode;
 pub use crate::sql::WhereCond;
 
+/// The name under which FROM reads the whole input, as in `SELECT d.name FROM docs d`.
+pub const DOCS: &str = "docs";
+
 #[derive(Debug, Default, Clone, PartialEq)]
 pub struct Sql {
     pub select_clause: Vec<Field>,
@@ -23,3 +31,107 @@ impl FromStr for Sql {
         parser::select_statement::from_str(s)
     }
 }
+
+/// A query, or `EXPLAIN` followed by a query, which asks for its plan instead of its result.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Statement {
+    Query(Sql),
+    Explain(Sql),
+}
+
+impl FromStr for Statement {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        parser::select_statement::statement_from_str(s)
+    }
+}
+
+impl Sql {
+    /// Whether the first source of the FROM clause is the whole input, named `docs`.
+    pub fn reads_docs(&self) -> bool {
+        match self.from_clause.first().map(|field| &field.expr) {
+            Some(Expr::Selector(selector)) => {
+                matches!(selector.to_vec().as_slice(), [SelectorNode::String(name)] if name == DOCS)
+            }
+            _ => false,
+        }
+    }
+
+    /// The top-level fields of each input row the query reads, so that columnar sources can skip the rest.
+    /// `None` means every field may be read, as with `SELECT *`, a subquery, or a FROM clause that
+    /// does not read the rows of the input.
+    pub fn source_columns(&self) -> Option<HashSet<String>> {
+        if !self.from_clause.is_empty() && !self.reads_docs() {
+            return None;
+        }
+        let mut env = Env::default();
+        for field in self.from_clause.iter().chain(self.left_join_clause.iter()) {
+            if let Some(alias) = &field.alias {
+                env.insert(alias, &field.expr);
+            }
+        }
+
+        let mut columns = HashSet::default();
+        if self.reads_docs() {
+            // A path from `docs`, or from a variable over it, starts at a field of a row, unless
+            // the rows have a field `docs`, which FROM then reads instead.
+            env.insert(DOCS, &Expr::Selector(Selector::default()));
+            columns.insert(DOCS.to_owned());
+        }
+        for field in &self.select_clause {
+            columns.extend(field.expr.source_columns(&env)?);
+        }
+        if let Some(cond) = &self.where_clause {
+            for expr in cond.exprs() {
+                columns.extend(expr.source_columns(&env)?);
+            }
+        }
+        if let Some(orderby) = &self.orderby {
+            let label = Expr::Selector(Selector::from_str(&orderby.label).ok()?);
+            columns.extend(label.source_columns(&env)?);
+        }
+        Some(columns)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+    use std::str::FromStr;
+
+    use super::Sql;
+
+    #[test]
+    fn source_columns() -> anyhow::Result<()> {
+        let sql = Sql::from_str("SELECT name, UPPER(city) AS city WHERE price = 1")?;
+        let expected = vec!["name", "city", "price"]
+            .into_iter()
+            .map(String::from)
+            .collect::<HashSet<_>>();
+        assert_eq!(sql.source_columns(), Some(expected));
+
+        let sql = Sql::from_str("SELECT * WHERE price = 1")?;
+        assert_eq!(sql.source_columns(), None);
+
+        let columns = |names: &[&str]| {
+            let columns = names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<HashSet<_>>();
+            Some(columns)
+        };
+        let sql =
+            Sql::from_str("SELECT r.name, t FROM docs r, r.tags t WHERE r.price > 1 ORDER BY qty")?;
+        assert_eq!(
+            sql.source_columns(),
+            columns(&["docs", "name", "tags", "price", "qty"])
+        );
+        let sql = Sql::from_str("SELECT r FROM docs r")?;
+        assert_eq!(sql.source_columns(), None);
+        // A FROM clause over anything but the input reads no columns of its own.
+        let sql = Sql::from_str("SELECT r.name FROM rows r")?;
+        assert_eq!(sql.source_columns(), None);
+        Ok(())
+    }
+}
@@ -1,4 +1,8 @@
+use std::cmp::Ordering;
+
 use crate::sql::Env;
+use crate::sql::EvalError;
+use crate::sql::EvalMode;
 use crate::sql::Expr;
 use crate::sql::Selector;
 use crate::value::PqlValue;
@@ -7,7 +11,13 @@ use crate::value::PqlValue;
 pub enum WhereCond {
     Eq { expr: Expr, right: PqlValue },
     Neq { expr: Expr, right: PqlValue },
+    Lt { expr: Expr, right: PqlValue },
+    Le { expr: Expr, right: PqlValue },
+    Gt { expr: Expr, right: PqlValue },
+    Ge { expr: Expr, right: PqlValue },
     Like { expr: Expr, right: String },
+    /// Two expressions of equal values, such as the keys of a join in `o.user_id = u.id`.
+    EqExpr { expr: Expr, right: Expr },
 }
 
 impl Default for WhereCond {
@@ -22,26 +32,110 @@ impl Default for WhereCond {
 impl WhereCond {
     pub fn as_expr(&self) -> Expr {
         match &self {
-            Self::Eq { expr, right: _ } => expr.to_owned(),
-            Self::Neq { expr, right: _ } => expr.to_owned(),
+            Self::Eq { expr, right: _ }
+            | Self::Neq { expr, right: _ }
+            | Self::Lt { expr, right: _ }
+            | Self::Le { expr, right: _ }
+            | Self::Gt { expr, right: _ }
+            | Self::Ge { expr, right: _ } => expr.to_owned(),
             Self::Like { expr, right: _ } => expr.to_owned(),
+            Self::EqExpr { expr, right: _ } => expr.to_owned(),
         }
     }
 
-    pub fn expand_fullpath(self, env: &Env) -> Self {
+    /// Every expression the condition evaluates.
+    pub fn exprs(&self) -> Vec<&Expr> {
+        match self {
+            Self::EqExpr { expr, right } => vec![expr, right],
+            Self::Eq { expr, .. }
+            | Self::Neq { expr, .. }
+            | Self::Lt { expr, .. }
+            | Self::Le { expr, .. }
+            | Self::Gt { expr, .. }
+            | Self::Ge { expr, .. }
+            | Self::Like { expr, .. } => vec![expr],
+        }
+    }
+
+    /// Rewrites each expression of the condition with `f`.
+    pub fn map_exprs(self, f: impl Fn(Expr) -> Expr) -> Self {
         match self {
             Self::Eq { expr, right } => Self::Eq {
-                expr: expr.expand_fullpath(env),
+                expr: f(expr),
+                right,
+            },
+            Self::Neq { expr, right } => Self::Neq {
+                expr: f(expr),
                 right,
             },
-            Self::Neq { expr, right } => Self::Eq {
-                expr: expr.expand_fullpath(env),
+            Self::Lt { expr, right } => Self::Lt {
+                expr: f(expr),
+                right,
+            },
+            Self::Le { expr, right } => Self::Le {
+                expr: f(expr),
+                right,
+            },
+            Self::Gt { expr, right } => Self::Gt {
+                expr: f(expr),
+                right,
+            },
+            Self::Ge { expr, right } => Self::Ge {
+                expr: f(expr),
                 right,
             },
             Self::Like { expr, right } => Self::Like {
-                expr: expr.expand_fullpath(env),
+                expr: f(expr),
                 right,
             },
+            Self::EqExpr { expr, right } => Self::EqExpr {
+                expr: f(expr),
+                right: f(right),
+            },
+        }
+    }
+
+    pub fn expand_fullpath(self, env: &Env) -> Self {
+        self.map_exprs(|expr| expr.expand_fullpath(env))
+    }
+
+    /// Whether the binding tuple `env` satisfies the condition. Values of types that do not
+    /// compare, such as a string and a number, satisfy no comparison.
+    pub fn eval(&self, env: &Env) -> bool {
+        self.eval_in(env, EvalMode::Permissive).unwrap_or(false)
+    }
+
+    /// Whether the binding tuple `env` satisfies the condition. Comparing values of types that
+    /// do not compare is a type error, handled as `mode` says; a MISSING or NULL value satisfies
+    /// no comparison either way.
+    pub fn eval_in(&self, env: &Env, mode: EvalMode) -> anyhow::Result<bool> {
+        let compare = |op: &str, expr: &Expr, right: &PqlValue, is_match: fn(Ordering) -> bool| {
+            let value = expr.to_owned().eval_in(env, mode)?;
+            mode.holds(match (value.compare(right), &value, right) {
+                (Some(ordering), _, _) => Ok(is_match(ordering)),
+                (None, PqlValue::Missing | PqlValue::Null, _)
+                | (None, _, PqlValue::Missing | PqlValue::Null) => Ok(false),
+                (None, value, right) => Err(EvalError::types(op, &[value, right])),
+            })
+        };
+        match self {
+            Self::Eq { expr, right } => Ok(expr.to_owned().eval_in(env, mode)?.equals(right)),
+            Self::Neq { expr, right } => Ok(!expr.to_owned().eval_in(env, mode)?.equals(right)),
+            Self::Lt { expr, right } => compare("<", expr, right, |o| o == Ordering::Less),
+            Self::Le { expr, right } => compare("<=", expr, right, |o| o != Ordering::Greater),
+            Self::Gt { expr, right } => compare(">", expr, right, |o| o == Ordering::Greater),
+            Self::Ge { expr, right } => compare(">=", expr, right, |o| o != Ordering::Less),
+            Self::Like { expr, right } => match expr.to_owned().eval_in(env, mode)? {
+                PqlValue::Str(string) => Ok(re_from_str(right).is_match(&string)),
+                PqlValue::Missing | PqlValue::Null => Ok(false),
+                value => mode.holds(Err(EvalError::types("LIKE", &[&value]))),
+            },
+            Self::EqExpr { expr, right } => {
+                // As with keys in SQL, a missing or null key equals nothing.
+                let value = expr.to_owned().eval_in(env, mode)?;
+                Ok(!matches!(value, PqlValue::Missing | PqlValue::Null)
+                    && value.equals(&right.to_owned().eval_in(env, mode)?))
+            }
         }
     }
 
@@ -62,3 +156,40 @@ pub fn re_from_str(pattern: &str) -> regex::Regex {
     let re = regex::Regex::new(&regex_pattern).unwrap();
     re
 }
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::planner::evaluate;
+    use crate::sql::Sql;
+    use crate::value::{json_value, partiql_value, PqlValue};
+
+    #[test]
+    fn equal_numbers_of_any_type() -> anyhow::Result<()> {
+        // Integers as JSON has them, which a literal such as `1`, a float, equals.
+        let data = json_value::to_pqlvalue(serde_json::from_str(
+            r#"[{ "a": 1, "b": 1 }, { "a": 2, "b": 2.5 }]"#,
+        )?)?;
+        let run = |sql: &str| -> anyhow::Result<PqlValue> {
+            Ok(evaluate(Sql::from_str(sql)?, data.to_owned()))
+        };
+        let rows = |json: &str| -> anyhow::Result<PqlValue> {
+            json_value::to_pqlvalue(serde_json::from_str(json)?)
+        };
+        assert_eq!(run("SELECT a WHERE a = 1")?, rows(r#"[{ "a": 1 }]"#)?);
+        assert_eq!(run("SELECT a WHERE a != 1")?, rows(r#"[{ "a": 2 }]"#)?);
+        assert_eq!(
+            run("SELECT x.a AS a FROM docs x, docs y WHERE x.a = y.b")?,
+            rows(r#"[{ "a": 1 }]"#)?
+        );
+
+        let data = partiql_value::from_str("<< { 'a': 1 }, { 'a': 1.0 }, { 'a': 2 } >>")?;
+        let sql = Sql::from_str("SELECT a WHERE a = 1")?;
+        assert_eq!(
+            evaluate(sql, data.unbag()),
+            partiql_value::from_str("[{ 'a': 1 }, { 'a': 1.0 }]")?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,597 @@
+//! Apache Arrow record batches and the Arrow IPC file format, read into and written from rows of `PqlValue`.
+//!
+//! Structs are read as objects and lists as arrays. On output the schema is inferred from the rows;
+//! a column whose values have no common Arrow type is written as text.
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::io::Cursor;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use arrow::array::*;
+use arrow::buffer::{NullBuffer, OffsetBuffer};
+use arrow::datatypes::*;
+use arrow::ipc::reader::FileReader;
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::RecordBatch;
+use arrow::temporal_conversions as temporal;
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::{BigDecimal, ToPrimitive};
+use chrono::prelude::*;
+use indexmap::IndexMap as Map;
+use ordered_float::OrderedFloat;
+
+use crate::value::datetime::{self, PqlTime};
+use crate::value::json_value;
+use crate::value::PqlValue;
+
+/// The magic bytes an Arrow IPC file starts with.
+pub const MAGIC: [u8; 6] = *b"ARROW1";
+
+// Timestamps are written in UTC, so that values with different offsets share a column.
+const UTC: &str = "+00:00";
+
+// Date32 counts days from 1970-01-01.
+const EPOCH_DAYS_FROM_CE: i32 = 719_163;
+
+/// Reads an Arrow IPC file. Only the `columns` given are decoded, or all of them for `None`.
+pub fn from_bytes(input: &[u8], columns: Option<&HashSet<String>>) -> anyhow::Result<PqlValue> {
+    let schema = FileReader::try_new(Cursor::new(input), None)?.schema();
+    let projection = columns.map(|columns| projection(&schema, columns));
+    let reader = FileReader::try_new(Cursor::new(input), projection)?;
+    let batches = reader.collect::<Result<Vec<_>, _>>()?;
+    Ok(PqlValue::bag(from_batches(&batches)))
+}
+
+/// Writes rows as an Arrow IPC file with an inferred schema.
+pub fn to_bytes(value: &PqlValue) -> anyhow::Result<Vec<u8>> {
+    let batch = to_batch(value)?;
+    let mut buf = Vec::new();
+    let mut writer = FileWriter::try_new(&mut buf, &batch.schema())?;
+    writer.write(&batch)?;
+    writer.finish()?;
+    drop(writer);
+    Ok(buf)
+}
+
+/// The indices of the top-level fields named in `columns`.
+pub fn projection(schema: &Schema, columns: &HashSet<String>) -> Vec<usize> {
+    schema
+        .fields()
+        .iter()
+        .enumerate()
+        .filter(|(_, field)| columns.contains(field.name()))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+pub fn from_batches(batches: &[RecordBatch]) -> Vec<PqlValue> {
+    batches
+        .iter()
+        .flat_map(|batch| {
+            let schema = batch.schema();
+            (0..batch.num_rows())
+                .map(|i| {
+                    let row = schema
+                        .fields()
+                        .iter()
+                        .zip(batch.columns())
+                        .map(|(field, column)| (field.name().to_owned(), value_at(column, i)))
+                        .collect::<Map<_, _>>();
+                    PqlValue::Object(row)
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+fn value_at(array: &dyn Array, i: usize) -> PqlValue {
+    if array.is_null(i) {
+        return PqlValue::Null;
+    }
+    match array.data_type() {
+        DataType::Null => PqlValue::Null,
+        DataType::Boolean => PqlValue::Boolean(array.as_boolean().value(i)),
+        DataType::Int8 => PqlValue::Int(array.as_primitive::<Int8Type>().value(i).into()),
+        DataType::Int16 => PqlValue::Int(array.as_primitive::<Int16Type>().value(i).into()),
+        DataType::Int32 => PqlValue::Int(array.as_primitive::<Int32Type>().value(i).into()),
+        DataType::Int64 => PqlValue::Int(array.as_primitive::<Int64Type>().value(i)),
+        DataType::UInt8 => PqlValue::Int(array.as_primitive::<UInt8Type>().value(i).into()),
+        DataType::UInt16 => PqlValue::Int(array.as_primitive::<UInt16Type>().value(i).into()),
+        DataType::UInt32 => PqlValue::Int(array.as_primitive::<UInt32Type>().value(i).into()),
+        DataType::UInt64 => {
+            let v = array.as_primitive::<UInt64Type>().value(i);
+            i64::try_from(v).map_or_else(|_| PqlValue::Decimal(BigDecimal::from(v)), PqlValue::Int)
+        }
+        DataType::Float16 => float(array.as_primitive::<Float16Type>().value(i).to_f64()),
+        DataType::Float32 => float(array.as_primitive::<Float32Type>().value(i).into()),
+        DataType::Float64 => float(array.as_primitive::<Float64Type>().value(i)),
+        DataType::Decimal128(_, scale) => {
+            let v = array.as_primitive::<Decimal128Type>().value(i);
+            PqlValue::Decimal(BigDecimal::new(BigInt::from(v), *scale as i64))
+        }
+        DataType::Decimal256(_, scale) => {
+            let v = array.as_primitive::<Decimal256Type>().value(i);
+            BigInt::from_str(&v.to_string()).map_or(PqlValue::Null, |v| {
+                PqlValue::Decimal(BigDecimal::new(v, *scale as i64))
+            })
+        }
+        DataType::Date32 => date(temporal::date32_to_datetime(
+            array.as_primitive::<Date32Type>().value(i),
+        )),
+        DataType::Date64 => date(temporal::date64_to_datetime(
+            array.as_primitive::<Date64Type>().value(i),
+        )),
+        DataType::Timestamp(unit, tz) => {
+            let naive = match unit {
+                TimeUnit::Second => temporal::as_datetime::<TimestampSecondType>(
+                    array.as_primitive::<TimestampSecondType>().value(i),
+                ),
+                TimeUnit::Millisecond => temporal::as_datetime::<TimestampMillisecondType>(
+                    array.as_primitive::<TimestampMillisecondType>().value(i),
+                ),
+                TimeUnit::Microsecond => temporal::as_datetime::<TimestampMicrosecondType>(
+                    array.as_primitive::<TimestampMicrosecondType>().value(i),
+                ),
+                TimeUnit::Nanosecond => temporal::as_datetime::<TimestampNanosecondType>(
+                    array.as_primitive::<TimestampNanosecondType>().value(i),
+                ),
+            };
+            // Named time zones other than UTC are read as UTC.
+            let offset = tz
+                .as_deref()
+                .and_then(|tz| datetime::parse_offset(tz).ok())
+                .unwrap_or_else(|| Utc.fix());
+            naive.map_or(PqlValue::Null, |naive| {
+                PqlValue::DateTime(offset.from_utc_datetime(&naive))
+            })
+        }
+        DataType::Time32(unit) => {
+            let v = match unit {
+                TimeUnit::Second => array.as_primitive::<Time32SecondType>().value(i),
+                _ => array.as_primitive::<Time32MillisecondType>().value(i),
+            };
+            time(match unit {
+                TimeUnit::Second => temporal::time32s_to_time(v),
+                _ => temporal::time32ms_to_time(v),
+            })
+        }
+        DataType::Time64(unit) => {
+            let v = match unit {
+                TimeUnit::Microsecond => array.as_primitive::<Time64MicrosecondType>().value(i),
+                _ => array.as_primitive::<Time64NanosecondType>().value(i),
+            };
+            time(match unit {
+                TimeUnit::Microsecond => temporal::time64us_to_time(v),
+                _ => temporal::time64ns_to_time(v),
+            })
+        }
+        DataType::Utf8 => PqlValue::from(array.as_string::<i32>().value(i)),
+        DataType::LargeUtf8 => PqlValue::from(array.as_string::<i64>().value(i)),
+        DataType::Utf8View => PqlValue::from(array.as_string_view().value(i)),
+        DataType::Binary => PqlValue::Blob(array.as_binary::<i32>().value(i).to_vec()),
+        DataType::LargeBinary => PqlValue::Blob(array.as_binary::<i64>().value(i).to_vec()),
+        DataType::BinaryView => PqlValue::Blob(array.as_binary_view().value(i).to_vec()),
+        DataType::FixedSizeBinary(_) => {
+            PqlValue::Blob(array.as_fixed_size_binary().value(i).to_vec())
+        }
+        DataType::Struct(fields) => {
+            let array = array.as_struct();
+            let object = fields
+                .iter()
+                .zip(array.columns())
+                .map(|(field, column)| (field.name().to_owned(), value_at(column, i)))
+                .collect::<Map<_, _>>();
+            PqlValue::Object(object)
+        }
+        DataType::List(_) => elements(&array.as_list::<i32>().value(i)),
+        DataType::LargeList(_) => elements(&array.as_list::<i64>().value(i)),
+        DataType::FixedSizeList(_, _) => elements(&array.as_fixed_size_list().value(i)),
+        DataType::Map(_, _) => {
+            let entries = array.as_map().value(i);
+            let object = (0..entries.len())
+                .map(|j| {
+                    let key = match value_at(entries.column(0), j) {
+                        PqlValue::Str(key) => key,
+                        key => json_value::from_pqlvalue(key).to_string(),
+                    };
+                    (key, value_at(entries.column(1), j))
+                })
+                .collect::<Map<_, _>>();
+            PqlValue::Object(object)
+        }
+        // Dictionaries and anything else are read through the type they cast to.
+        DataType::Dictionary(_, value_type) => arrow::compute::cast(&array.slice(i, 1), value_type)
+            .map_or(PqlValue::Null, |array| value_at(&array, 0)),
+        _ => arrow::compute::cast(&array.slice(i, 1), &DataType::Utf8)
+            .map_or(PqlValue::Null, |array| value_at(&array, 0)),
+    }
+}
+
+fn float(f: f64) -> PqlValue {
+    PqlValue::Float(OrderedFloat(f))
+}
+
+fn date(datetime: Option<NaiveDateTime>) -> PqlValue {
+    datetime.map_or(PqlValue::Null, |datetime| PqlValue::Date(datetime.date()))
+}
+
+fn time(time: Option<NaiveTime>) -> PqlValue {
+    time.map_or(PqlValue::Null, |time| {
+        PqlValue::Time(PqlTime { time, offset: None })
+    })
+}
+
+fn elements(array: &ArrayRef) -> PqlValue {
+    PqlValue::Array((0..array.len()).map(|i| value_at(array, i)).collect())
+}
+
+/// Builds one record batch out of rows, inferring its schema. A value that is not an array is a single row.
+pub fn to_batch(value: &PqlValue) -> anyhow::Result<RecordBatch> {
+    let rows = match value.to_owned().unbag() {
+        PqlValue::Array(array) => array,
+        value => vec![value],
+    };
+    // Rows that are not objects are put in a column of their own.
+    let rows = rows
+        .into_iter()
+        .map(|row| match strip(&row) {
+            PqlValue::Object(_) => row,
+            _ => PqlValue::Object(vec![("_1".to_owned(), row)].into_iter().collect()),
+        })
+        .collect::<Vec<_>>();
+
+    let fields = match rows.iter().map(infer).fold(DataType::Null, merge) {
+        DataType::Struct(fields) => fields,
+        _ => Fields::empty(),
+    };
+    let columns = fields
+        .iter()
+        .map(|field| {
+            let values = rows
+                .iter()
+                .map(|row| get(row, field.name()))
+                .collect::<Vec<_>>();
+            build(&values, field.data_type())
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let options = RecordBatchOptions::new().with_row_count(Some(rows.len()));
+    Ok(RecordBatch::try_new_with_options(
+        Arc::new(Schema::new(fields)),
+        columns,
+        &options,
+    )?)
+}
+
+fn strip(value: &PqlValue) -> &PqlValue {
+    match value {
+        PqlValue::Annotated(_, value) => strip(value),
+        value => value,
+    }
+}
+
+fn get<'a>(value: &'a PqlValue, key: &str) -> &'a PqlValue {
+    match strip(value) {
+        PqlValue::Object(object) => object.get(key).unwrap_or(&PqlValue::Null),
+        _ => &PqlValue::Null,
+    }
+}
+
+fn infer(value: &PqlValue) -> DataType {
+    match strip(value) {
+        PqlValue::Missing | PqlValue::Null => DataType::Null,
+        PqlValue::Boolean(_) => DataType::Boolean,
+        PqlValue::Int(_) => DataType::Int64,
+        PqlValue::Float(_) => DataType::Float64,
+        PqlValue::Decimal(decimal) => match decimal.as_bigint_and_exponent().1 {
+            scale if scale > DECIMAL128_MAX_SCALE as i64 => DataType::Utf8,
+            scale => DataType::Decimal128(DECIMAL128_MAX_PRECISION, scale.max(0) as i8),
+        },
+        PqlValue::Str(_) | PqlValue::Symbol(_) => DataType::Utf8,
+        PqlValue::Blob(_) | PqlValue::Clob(_) => DataType::Binary,
+        PqlValue::DateTime(_) => DataType::Timestamp(TimeUnit::Microsecond, Some(UTC.into())),
+        PqlValue::Date(_) => DataType::Date32,
+        PqlValue::Time(_) => DataType::Time64(TimeUnit::Microsecond),
+        PqlValue::Array(array) => DataType::List(Arc::new(Field::new(
+            "item",
+            array.iter().map(infer).fold(DataType::Null, merge),
+            true,
+        ))),
+        PqlValue::Object(object) => DataType::Struct(
+            object
+                .iter()
+                .filter(|(_, v)| **v != PqlValue::Missing)
+                .map(|(k, v)| Field::new(k, infer(v), true))
+                .collect(),
+        ),
+        PqlValue::Annotated(_, _) => unreachable!(),
+    }
+}
+
+/// The narrowest type both values can be written as.
+fn merge(a: DataType, b: DataType) -> DataType {
+    match (a, b) {
+        (a, b) if a == b => a,
+        (DataType::Null, t) | (t, DataType::Null) => t,
+        (DataType::Int64, DataType::Float64) | (DataType::Float64, DataType::Int64) => {
+            DataType::Float64
+        }
+        (DataType::Decimal128(p, a), DataType::Decimal128(_, b)) => {
+            DataType::Decimal128(p, a.max(b))
+        }
+        (DataType::Int64, t @ DataType::Decimal128(_, _))
+        | (t @ DataType::Decimal128(_, _), DataType::Int64) => t,
+        (DataType::List(a), DataType::List(b)) => DataType::List(Arc::new(Field::new(
+            "item",
+            merge(a.data_type().to_owned(), b.data_type().to_owned()),
+            true,
+        ))),
+        (DataType::Struct(a), DataType::Struct(b)) => {
+            let mut fields = a
+                .iter()
+                .map(|field| (field.name().to_owned(), field.data_type().to_owned()))
+                .collect::<Map<_, _>>();
+            for field in b.iter() {
+                let t = fields.get(field.name()).cloned().unwrap_or(DataType::Null);
+                fields.insert(
+                    field.name().to_owned(),
+                    merge(t, field.data_type().to_owned()),
+                );
+            }
+            DataType::Struct(
+                fields
+                    .into_iter()
+                    .map(|(name, t)| Field::new(name, t, true))
+                    .collect(),
+            )
+        }
+        _ => DataType::Utf8,
+    }
+}
+
+fn build(values: &[&PqlValue], data_type: &DataType) -> anyhow::Result<ArrayRef> {
+    let values = values.iter().map(|v| strip(v)).collect::<Vec<_>>();
+    let array: ArrayRef = match data_type {
+        DataType::Null => Arc::new(NullArray::new(values.len())),
+        DataType::Boolean => Arc::new(
+            values
+                .iter()
+                .map(|v| match v {
+                    PqlValue::Boolean(b) => Some(*b),
+                    _ => None,
+                })
+                .collect::<BooleanArray>(),
+        ),
+        DataType::Int64 => Arc::new(
+            values
+                .iter()
+                .map(|v| match v {
+                    PqlValue::Int(i) => Some(*i),
+                    _ => None,
+                })
+                .collect::<Int64Array>(),
+        ),
+        DataType::Float64 => Arc::new(
+            values
+                .iter()
+                .map(|v| match v {
+                    PqlValue::Int(i) => Some(*i as f64),
+                    PqlValue::Float(OrderedFloat(f)) => Some(*f),
+                    _ => None,
+                })
+                .collect::<Float64Array>(),
+        ),
+        DataType::Decimal128(precision, scale) => Arc::new(
+            values
+                .iter()
+                .map(|v| {
+                    let decimal = match v {
+                        PqlValue::Int(i) => BigDecimal::from(*i),
+                        PqlValue::Decimal(decimal) => decimal.to_owned(),
+                        _ => return None,
+                    };
+                    decimal
+                        .with_scale(*scale as i64)
+                        .as_bigint_and_exponent()
+                        .0
+                        .to_i128()
+                })
+                .collect::<Decimal128Array>()
+                .with_precision_and_scale(*precision, *scale)?,
+        ),
+        DataType::Utf8 => Arc::new(
+            values
+                .iter()
+                .map(|v| match v {
+                    PqlValue::Missing | PqlValue::Null => None,
+                    PqlValue::Str(s) | PqlValue::Symbol(s) => Some(s.to_owned()),
+                    v => Some(json_value::from_pqlvalue((*v).to_owned()).to_string()),
+                })
+                .collect::<StringArray>(),
+        ),
+        DataType::Binary => Arc::new(
+            values
+                .iter()
+                .map(|v| match v {
+                    PqlValue::Blob(bytes) | PqlValue::Clob(bytes) => Some(bytes.as_slice()),
+                    _ => None,
+                })
+                .collect::<BinaryArray>(),
+        ),
+        DataType::Timestamp(TimeUnit::Microsecond, _) => Arc::new(
+            values
+                .iter()
+                .map(|v| match v {
+                    PqlValue::DateTime(datetime) => Some(datetime.timestamp_micros()),
+                    _ => None,
+                })
+                .collect::<TimestampMicrosecondArray>()
+                .with_timezone(UTC),
+        ),
+        DataType::Date32 => Arc::new(
+            values
+                .iter()
+                .map(|v| match v {
+                    PqlValue::Date(date) => Some(date.num_days_from_ce() - EPOCH_DAYS_FROM_CE),
+                    _ => None,
+                })
+                .collect::<Date32Array>(),
+        ),
+        DataType::Time64(TimeUnit::Microsecond) => Arc::new(
+            values
+                .iter()
+                .map(|v| match v {
+                    PqlValue::Time(PqlTime { time, .. }) => Some(
+                        time.num_seconds_from_midnight() as i64 * 1_000_000
+                            + time.nanosecond() as i64 / 1_000,
+                    ),
+                    _ => None,
+                })
+                .collect::<Time64MicrosecondArray>(),
+        ),
+        DataType::List(field) => {
+            let lists = values
+                .iter()
+                .map(|v| match v {
+                    PqlValue::Array(array) => Some(array),
+                    _ => None,
+                })
+                .collect::<Vec<_>>();
+            let items = lists
+                .iter()
+                .flatten()
+                .flat_map(|array| array.iter())
+                .collect::<Vec<_>>();
+            Arc::new(ListArray::try_new(
+                field.to_owned(),
+                OffsetBuffer::from_lengths(lists.iter().map(|list| list.map_or(0, |l| l.len()))),
+                build(&items, field.data_type())?,
+                Some(NullBuffer::from(
+                    lists.iter().map(Option::is_some).collect::<Vec<_>>(),
+                )),
+            )?)
+        }
+        DataType::Struct(fields) => {
+            let columns = fields
+                .iter()
+                .map(|field| {
+                    let children = values
+                        .iter()
+                        .map(|v| get(v, field.name()))
+                        .collect::<Vec<_>>();
+                    build(&children, field.data_type())
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?;
+            let valid = values
+                .iter()
+                .map(|v| matches!(v, PqlValue::Object(_)))
+                .collect::<Vec<_>>();
+            if fields.is_empty() {
+                Arc::new(StructArray::new_empty_fields(
+                    values.len(),
+                    Some(NullBuffer::from(valid)),
+                ))
+            } else {
+                Arc::new(StructArray::try_new(
+                    fields.to_owned(),
+                    columns,
+                    Some(NullBuffer::from(valid)),
+                )?)
+            }
+        }
+        data_type => anyhow::bail!("cannot write arrow type {}", data_type),
+    };
+    Ok(array)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{from_bytes, to_bytes};
+    use crate::lang::Lang;
+    use crate::value::partiql_value;
+    use crate::value::PqlValue;
+
+    #[test]
+    fn round_trip() -> anyhow::Result<()> {
+        let data = Lang::from_as_toml(
+            r#"
+[[rows]]
+id = 1
+name = "a"
+at = 2021-07-01T09:30:00.5Z
+on = 2021-07-01
+size = { w = 1.5, h = 2 }
+tags = ["x", "y"]
+
+[[rows]]
+id = 2
+at = 2021-12-24T18:00:00Z
+on = 2021-12-24
+size = { w = 3.0 }
+tags = []
+"#,
+        )?
+        .data
+        .get("rows")
+        .unwrap();
+        let bytes = to_bytes(&data)?;
+
+        let expected = partiql_value::from_str(
+            r#"<<
+                {
+                    'id': 1, 'name': 'a',
+                    'at': TIMESTAMP '2021-07-01T09:30:00.5Z', 'on': DATE '2021-07-01',
+                    'size': { 'w': 1.5e0, 'h': 2 }, 'tags': ['x', 'y']
+                },
+                {
+                    'id': 2, 'name': null,
+                    'at': TIMESTAMP '2021-12-24T18:00:00Z', 'on': DATE '2021-12-24',
+                    'size': { 'w': 3e0, 'h': null }, 'tags': []
+                }
+            >>"#,
+        )?;
+        assert_eq!(from_bytes(&bytes, None)?, expected);
+
+        let columns = ["id", "tags"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<HashSet<_>>();
+        let projected = from_bytes(&bytes, Some(&columns))?.unbag();
+        assert_eq!(
+            projected,
+            Lang::from_as_json(r#"[{ "id": 1, "tags": ["x", "y"] }, { "id": 2, "tags": [] }]"#)?
+                .data
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn mixed_columns_are_text() -> anyhow::Result<()> {
+        let data = Lang::from_as_json(r#"[{ "v": 1 }, { "v": "a" }, { "v": [true] }]"#)?.data;
+        let read = from_bytes(&to_bytes(&data)?, None)?.unbag();
+        assert_eq!(
+            read,
+            PqlValue::Array(vec![
+                PqlValue::Object(
+                    vec![("v".to_owned(), PqlValue::from("1"))]
+                        .into_iter()
+                        .collect()
+                ),
+                PqlValue::Object(
+                    vec![("v".to_owned(), PqlValue::from("a"))]
+                        .into_iter()
+                        .collect()
+                ),
+                PqlValue::Object(
+                    vec![("v".to_owned(), PqlValue::from("[true]"))]
+                        .into_iter()
+                        .collect()
+                ),
+            ])
+        );
+        Ok(())
+    }
+}
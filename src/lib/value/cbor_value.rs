@@ -0,0 +1,231 @@
+//! CBOR, read into and written from `PqlValue` through `ciborium::value::Value`.
+//!
+//! Timestamps, dates, bignums and decimal fractions use their standard tags.
+//! A sequence of several top-level values is read as a bag.
+
+use std::convert::TryFrom;
+
+use bigdecimal::num_bigint::{BigInt, Sign};
+use bigdecimal::BigDecimal;
+use chrono::prelude::*;
+use ciborium::value::{Integer, Value};
+use indexmap::IndexMap as Map;
+use ordered_float::OrderedFloat;
+
+use crate::value::datetime::{self, format_timestamp};
+use crate::value::PqlValue;
+
+const DATETIME: u64 = 0;
+const EPOCH: u64 = 1;
+const POSITIVE_BIGNUM: u64 = 2;
+const NEGATIVE_BIGNUM: u64 = 3;
+const DECIMAL_FRACTION: u64 = 4;
+const DATE: u64 = 1004;
+
+/// The self-described CBOR tag some encoders put at the start of a file.
+pub const MAGIC: [u8; 3] = [0xd9, 0xd9, 0xf7];
+
+pub fn from_bytes(input: &[u8]) -> anyhow::Result<PqlValue> {
+    let mut rest = input;
+    let mut values = vec![];
+    while !rest.is_empty() {
+        let value = ciborium::de::from_reader::<Value, _>(&mut rest)?;
+        values.push(to_pqlvalue(value)?);
+    }
+    match values.len() {
+        1 => Ok(values.remove(0)),
+        _ => Ok(PqlValue::bag(values)),
+    }
+}
+
+/// Writes `value` after the self-described tag, which tells CBOR apart from MessagePack.
+pub fn to_bytes(value: &PqlValue) -> anyhow::Result<Vec<u8>> {
+    let mut buf = MAGIC.to_vec();
+    ciborium::ser::into_writer(&from_pqlvalue(value.to_owned()), &mut buf)?;
+    Ok(buf)
+}
+
+pub fn to_pqlvalue(value: Value) -> anyhow::Result<PqlValue> {
+    let pqlv = match value {
+        Value::Null => PqlValue::Null,
+        Value::Bool(boolean) => PqlValue::Boolean(boolean),
+        Value::Integer(int) => {
+            let int = i128::from(int);
+            match i64::try_from(int) {
+                Ok(int) => PqlValue::Int(int),
+                Err(_) => PqlValue::Decimal(BigDecimal::from(BigInt::from(int))),
+            }
+        }
+        Value::Float(float) => PqlValue::Float(OrderedFloat(float)),
+        Value::Text(string) => PqlValue::Str(string),
+        Value::Bytes(bytes) => PqlValue::Blob(bytes),
+        Value::Tag(DATETIME, value) => match *value {
+            Value::Text(string) => PqlValue::DateTime(datetime::parse_timestamp(&string)?),
+            value => anyhow::bail!("invalid date/time string: {:?}", value),
+        },
+        Value::Tag(EPOCH, value) => {
+            let secs = match to_pqlvalue(*value)? {
+                PqlValue::Int(int) => int as f64,
+                PqlValue::Float(OrderedFloat(float)) => float,
+                value => anyhow::bail!("invalid epoch-based date/time: {:?}", value),
+            };
+            let datetime = Utc
+                .timestamp_opt(
+                    secs.floor() as i64,
+                    ((secs - secs.floor()) * 1e9).round() as u32,
+                )
+                .single()
+                .ok_or_else(|| anyhow::anyhow!("timestamp out of range"))?;
+            PqlValue::DateTime(datetime.with_timezone(&Utc.fix()))
+        }
+        Value::Tag(tag @ POSITIVE_BIGNUM, value) | Value::Tag(tag @ NEGATIVE_BIGNUM, value) => {
+            PqlValue::Decimal(BigDecimal::from(bignum(tag, *value)?))
+        }
+        Value::Tag(DECIMAL_FRACTION, value) => match *value {
+            Value::Array(array) => match <[Value; 2]>::try_from(array) {
+                Ok([Value::Integer(exponent), mantissa]) => {
+                    let mantissa = match mantissa {
+                        Value::Integer(int) => BigInt::from(i128::from(int)),
+                        Value::Tag(tag, value) => bignum(tag, *value)?,
+                        value => anyhow::bail!("invalid decimal fraction: {:?}", value),
+                    };
+                    let scale = -i64::try_from(i128::from(exponent))?;
+                    PqlValue::Decimal(BigDecimal::new(mantissa, scale))
+                }
+                _ => anyhow::bail!("invalid decimal fraction"),
+            },
+            value => anyhow::bail!("invalid decimal fraction: {:?}", value),
+        },
+        Value::Tag(DATE, value) => match *value {
+            Value::Text(string) => PqlValue::Date(datetime::parse_date(&string)?),
+            value => anyhow::bail!("invalid full-date string: {:?}", value),
+        },
+        // Other tags, including the self-described tag of MAGIC, carry no meaning here.
+        Value::Tag(_, value) => to_pqlvalue(*value)?,
+        Value::Array(array) => PqlValue::Array(
+            array
+                .into_iter()
+                .map(to_pqlvalue)
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        ),
+        Value::Map(map) => PqlValue::Object(
+            map.into_iter()
+                .map(|(k, v)| {
+                    let key = match k {
+                        Value::Text(string) => string,
+                        key => match to_pqlvalue(key)? {
+                            PqlValue::Int(int) => int.to_string(),
+                            key => key.to_jsonc()?,
+                        },
+                    };
+                    Ok((key, to_pqlvalue(v)?))
+                })
+                .collect::<anyhow::Result<Map<_, _>>>()?,
+        ),
+        value => anyhow::bail!("unsupported CBOR value: {:?}", value),
+    };
+    Ok(pqlv)
+}
+
+/// Converts to a `ciborium::value::Value` for output. MISSING fields are dropped.
+pub fn from_pqlvalue(pqlv: PqlValue) -> Value {
+    match pqlv {
+        PqlValue::Missing | PqlValue::Null => Value::Null,
+        PqlValue::Str(string) | PqlValue::Symbol(string) => Value::Text(string),
+        PqlValue::Boolean(boolean) => Value::Bool(boolean),
+        PqlValue::Int(int) => Value::from(int),
+        PqlValue::Float(OrderedFloat(float)) => Value::Float(float),
+        PqlValue::Decimal(decimal) => {
+            let (mantissa, scale) = decimal.as_bigint_and_exponent();
+            if scale == 0 {
+                integer(mantissa)
+            } else {
+                let fraction = vec![Value::from(-scale), integer(mantissa)];
+                Value::Tag(DECIMAL_FRACTION, Box::new(Value::Array(fraction)))
+            }
+        }
+        PqlValue::DateTime(datetime) => {
+            Value::Tag(DATETIME, Box::new(Value::Text(format_timestamp(&datetime))))
+        }
+        PqlValue::Date(date) => Value::Tag(DATE, Box::new(Value::Text(date.to_string()))),
+        PqlValue::Time(time) => Value::Text(time.to_string()),
+        PqlValue::Blob(bytes) | PqlValue::Clob(bytes) => Value::Bytes(bytes),
+        PqlValue::Annotated(_, value) => from_pqlvalue(*value),
+        PqlValue::Array(array) => Value::Array(array.into_iter().map(from_pqlvalue).collect()),
+        PqlValue::Object(map) => Value::Map(
+            map.into_iter()
+                .filter(|(_, v)| v != &PqlValue::Missing)
+                .map(|(k, v)| (Value::Text(k), from_pqlvalue(v)))
+                .collect(),
+        ),
+    }
+}
+
+// Integers beyond the 64-bit range of a CBOR integer are written as bignums.
+fn integer(int: BigInt) -> Value {
+    let native = i128::try_from(&int)
+        .ok()
+        .and_then(|int| Integer::try_from(int).ok());
+    match native {
+        Some(int) => Value::Integer(int),
+        None => {
+            let (tag, n) = match int.sign() {
+                Sign::Minus => (NEGATIVE_BIGNUM, -int - 1),
+                _ => (POSITIVE_BIGNUM, int),
+            };
+            Value::Tag(tag, Box::new(Value::Bytes(n.to_bytes_be().1)))
+        }
+    }
+}
+
+fn bignum(tag: u64, value: Value) -> anyhow::Result<BigInt> {
+    let n = match value {
+        Value::Bytes(bytes) => BigInt::from_bytes_be(Sign::Plus, &bytes),
+        value => anyhow::bail!("invalid bignum: {:?}", value),
+    };
+    match tag {
+        NEGATIVE_BIGNUM => Ok(-n - 1),
+        _ => Ok(n),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_bytes, to_bytes, MAGIC};
+    use crate::lang::Lang;
+    use crate::value::partiql_value;
+    use crate::value::PqlValue;
+
+    #[test]
+    fn round_trip() -> anyhow::Result<()> {
+        let data = partiql_value::from_str(
+            r#"{
+                'id': 1, 'price': 1.50, 'huge': -123456789012345678901234567890, 'tiny': 0.000000000000000000000000000001,
+                'ratio': 2.5e-1, 'name': 'apple', 'ok': true, 'none': null,
+                'at': TIMESTAMP '2021-07-01T09:30:00.25+09:00', 'on': DATE '2021-07-01',
+                'tags': ['a', 2], 'raw': `{{AAEC}}`
+            }"#,
+        )?;
+        let bytes = to_bytes(&data)?;
+        assert_eq!(from_bytes(&bytes)?, data);
+        Ok(())
+    }
+
+    #[test]
+    fn reads_sequences() -> anyhow::Result<()> {
+        // {"a": 1} followed by a self-described [true, null]
+        let bytes = [
+            0xa1, 0x61, b'a', 0x01, MAGIC[0], MAGIC[1], MAGIC[2], 0x82, 0xf5, 0xf6,
+        ];
+        let expected = PqlValue::bag(vec![
+            Lang::from_as_json(r#"{ "a": 1 }"#)?.data,
+            Lang::from_as_json("[true, null]")?.data,
+        ]);
+        assert_eq!(from_bytes(&bytes)?, expected);
+        assert_eq!(
+            to_bytes(&Lang::from_as_json(r#"{ "a": 1 }"#)?.data)?,
+            [&MAGIC[..], &bytes[..4]].concat()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+//! CSV and TSV, read as a bag of rows and written with nested values flattened into dotted columns.
+
+use indexmap::IndexMap as Map;
+use ordered_float::OrderedFloat;
+
+use crate::value::json_value;
+use crate::value::PqlValue;
+
+/// The dialect of a CSV or TSV text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvOptions {
+    /// Field delimiter. `None` means `,` for CSV and a tab for TSV.
+    pub delimiter: Option<u8>,
+    pub quote: u8,
+    /// Whether the first record names the columns. Without it, columns are named `_1`, `_2`, ...
+    pub has_headers: bool,
+    /// Whether to read fields that look like numbers, booleans or empty as such, rather than as strings.
+    pub infer_types: bool,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: None,
+            quote: b'"',
+            has_headers: true,
+            infer_types: true,
+        }
+    }
+}
+
+impl CsvOptions {
+    /// The same dialect, tab-separated unless another delimiter was chosen.
+    pub fn or_tab(&self) -> Self {
+        Self {
+            delimiter: Some(self.delimiter.unwrap_or(b'\t')),
+            ..self.to_owned()
+        }
+    }
+
+    fn delimiter(&self) -> u8 {
+        self.delimiter.unwrap_or(b',')
+    }
+}
+
+pub fn from_str(input: &str, options: &CsvOptions) -> anyhow::Result<PqlValue> {
+    let mut reader = csv::ReaderBuilder::new()
+        .delimiter(options.delimiter())
+        .quote(options.quote)
+        .has_headers(options.has_headers)
+        .from_reader(input.as_bytes());
+    let headers = if options.has_headers {
+        reader
+            .headers()?
+            .iter()
+            .map(String::from)
+            .collect::<Vec<_>>()
+    } else {
+        Vec::default()
+    };
+
+    let records = reader.records().collect::<Result<Vec<_>, _>>()?;
+    let width = records.iter().map(|record| record.len()).max().unwrap_or(0);
+    let kinds = (0..width)
+        .map(|i| {
+            records
+                .iter()
+                .filter_map(|record| record.get(i))
+                .map(Kind::of)
+                .fold(Kind::Null, Kind::merge)
+        })
+        .collect::<Vec<_>>();
+
+    let rows = records
+        .iter()
+        .map(|record| {
+            let row = record
+                .iter()
+                .enumerate()
+                .map(|(i, field)| {
+                    let name = headers
+                        .get(i)
+                        .cloned()
+                        .unwrap_or_else(|| format!("_{}", i + 1));
+                    let value = if options.infer_types {
+                        kinds[i].read(field)
+                    } else {
+                        PqlValue::from(field)
+                    };
+                    (name, value)
+                })
+                .collect::<Map<_, _>>();
+            PqlValue::Object(row)
+        })
+        .collect::<Vec<_>>();
+    Ok(PqlValue::bag(rows))
+}
+
+/// The type inferred for a column: the narrowest one all of its non-empty fields can be read as.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Null,
+    Boolean,
+    Int,
+    Float,
+    Str,
+}
+
+impl Kind {
+    // Numbers with leading zeros, such as zip codes, are not numbers.
+    fn of(field: &str) -> Self {
+        let digits = field.trim_start_matches(['-', '+']);
+        let leading_zero = digits.len() > 1 && digits.starts_with('0') && !digits.starts_with("0.");
+        let numeric = digits.starts_with(|c: char| c.is_ascii_digit() || c == '.');
+        match field {
+            "" => Self::Null,
+            "true" | "TRUE" | "True" | "false" | "FALSE" | "False" => Self::Boolean,
+            _ if leading_zero || !numeric => Self::Str,
+            _ if field.parse::<i64>().is_ok() => Self::Int,
+            _ if field.parse::<f64>().is_ok() => Self::Float,
+            _ => Self::Str,
+        }
+    }
+
+    fn merge(self, other: Self) -> Self {
+        match (self, other) {
+            (a, b) if a == b => a,
+            (Self::Null, kind) | (kind, Self::Null) => kind,
+            (Self::Int, Self::Float) | (Self::Float, Self::Int) => Self::Float,
+            _ => Self::Str,
+        }
+    }
+
+    fn read(self, field: &str) -> PqlValue {
+        match self {
+            _ if field.is_empty() => PqlValue::Null,
+            Self::Str => PqlValue::from(field),
+            Self::Boolean => PqlValue::Boolean(field.eq_ignore_ascii_case("true")),
+            Self::Int => field.parse().map_or(PqlValue::Null, PqlValue::Int),
+            Self::Float => field
+                .parse()
+                .map_or(PqlValue::Null, |f| PqlValue::Float(OrderedFloat(f))),
+            Self::Null => PqlValue::Null,
+        }
+    }
+}
+
+/// Writes one record per row. Columns are the flattened keys of all rows, in the order they first appear.
+pub fn to_string(value: &PqlValue, options: &CsvOptions) -> anyhow::Result<String> {
+    let rows = match value.to_owned().unbag() {
+        PqlValue::Array(array) => array,
+        value => vec![value],
+    };
+    let rows = rows
+        .into_iter()
+        .map(|row| {
+            let mut columns = Map::new();
+            match row {
+                PqlValue::Object(_) => flatten("", row, &mut columns),
+                _ => flatten("_1", row, &mut columns),
+            }
+            columns
+        })
+        .collect::<Vec<_>>();
+    let mut headers = Map::<&str, ()>::new();
+    for row in &rows {
+        headers.extend(row.keys().map(|k| (k.as_str(), ())));
+    }
+
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(options.delimiter())
+        .quote(options.quote)
+        .from_writer(vec![]);
+    if options.has_headers {
+        writer.write_record(headers.keys())?;
+    }
+    for row in &rows {
+        writer.write_record(
+            headers
+                .keys()
+                .map(|k| row.get(*k).map(String::as_str).unwrap_or_default()),
+        )?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+fn flatten(prefix: &str, value: PqlValue, columns: &mut Map<String, String>) {
+    let key = |k: &str| {
+        if prefix.is_empty() {
+            k.to_owned()
+        } else {
+            format!("{}.{}", prefix, k)
+        }
+    };
+    match value {
+        PqlValue::Missing => {}
+        PqlValue::Annotated(_, value) => flatten(prefix, *value, columns),
+        PqlValue::Object(map) => {
+            for (k, v) in map {
+                flatten(&key(&k), v, columns);
+            }
+        }
+        PqlValue::Array(array) => {
+            for (i, v) in array.into_iter().enumerate() {
+                flatten(&key(&i.to_string()), v, columns);
+            }
+        }
+        value => {
+            let field = match json_value::from_pqlvalue(value) {
+                serde_json::Value::Null => String::default(),
+                serde_json::Value::String(s) => s,
+                json => json.to_string(),
+            };
+            columns.insert(prefix.to_owned(), field);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{from_str, to_string, CsvOptions};
+    use crate::lang::Lang;
+    use crate::value::PqlValue;
+
+    #[test]
+    fn reads_dialects() -> anyhow::Result<()> {
+        let expected = Lang::from_as_json(
+            r#"[
+                { "zip": "01234", "n": 1, "x": 1.5, "ok": true, "note": "a;b", "none": null },
+                { "zip": "99999", "n": -2, "x": 0.5, "ok": false, "note": "c", "none": null }
+            ]"#,
+        )?
+        .data;
+        let csv = "zip,n,x,ok,note,none\n01234,1,1.5,true,\"a;b\",\n99999,-2,.5,false,c,\n";
+        let data = from_str(csv, &CsvOptions::default())?;
+        assert!(data.is_bag());
+        assert_eq!(data.unbag(), expected);
+
+        let options = CsvOptions {
+            delimiter: Some(b';'),
+            quote: b'\'',
+            has_headers: false,
+            infer_types: false,
+        };
+        let data = from_str("a;'b;c'\n1;2\n", &options)?;
+        assert_eq!(
+            data.unbag(),
+            PqlValue::from_str(r#"[{ "_1": "a", "_2": "b;c" }, { "_1": "1", "_2": "2" }]"#)?
+        );
+
+        let tsv = from_str("a\tb\n1\tx y\n", &CsvOptions::default().or_tab())?;
+        assert_eq!(
+            tsv.unbag(),
+            Lang::from_as_json(r#"[{ "a": 1, "b": "x y" }]"#)?.data
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn writes_flattened_columns() -> anyhow::Result<()> {
+        let data = Lang::from_as_json(
+            r#"[
+                { "name": "a", "size": { "w": 1, "h": 2 }, "tags": ["x", "y"] },
+                { "name": "b,c", "size": { "w": 3 }, "extra": null }
+            ]"#,
+        )?
+        .data;
+        assert_eq!(
+            to_string(&data, &CsvOptions::default())?,
+            "name,size.w,size.h,tags.0,tags.1,extra\na,1,2,x,y,\n\"b,c\",3,,,,\n"
+        );
+        let options = CsvOptions {
+            has_headers: false,
+            ..CsvOptions::default().or_tab()
+        };
+        assert_eq!(
+            to_string(&data, &options)?,
+            "a\t1\t2\tx\ty\t\nb,c\t3\t\t\t\t\n"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,468 @@
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+
+use bigdecimal::BigDecimal;
+use chrono::prelude::*;
+use chrono::{Duration, FixedOffset, Months, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat};
+use parse_display::{Display, FromStr};
+use serde::{Serialize, Serializer};
+
+use crate::value::PqlValue;
+
+/// A `TIME` value, with an offset when it is `TIME WITH TIME ZONE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PqlTime {
+    pub time: NaiveTime,
+    pub offset: Option<FixedOffset>,
+}
+
+impl PqlTime {
+    fn offset_seconds(&self) -> Option<i32> {
+        self.offset.map(|offset| offset.local_minus_utc())
+    }
+}
+
+impl Ord for PqlTime {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.time, self.offset_seconds()).cmp(&(other.time, other.offset_seconds()))
+    }
+}
+
+impl PartialOrd for PqlTime {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for PqlTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.time.format("%H:%M:%S%.f"))?;
+        if let Some(offset) = self.offset {
+            write!(f, "{}", offset)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for PqlTime {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let s = s.trim();
+        if let Ok(time) = NaiveTime::parse_from_str(s, "%H:%M:%S%.f") {
+            return Ok(Self { time, offset: None });
+        }
+        let (time, offset) = match s.strip_suffix('Z') {
+            Some(time) => (time, "+00:00"),
+            None => s.split_at(s.rfind(['+', '-']).unwrap_or(s.len())),
+        };
+        let time = NaiveTime::parse_from_str(time, "%H:%M:%S%.f")?;
+        let offset = parse_offset(offset)?;
+        Ok(Self {
+            time,
+            offset: Some(offset),
+        })
+    }
+}
+
+impl Serialize for PqlTime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+pub(crate) fn parse_offset(s: &str) -> anyhow::Result<FixedOffset> {
+    let sign = match s.chars().next() {
+        Some('+') => 1,
+        Some('-') => -1,
+        _ => anyhow::bail!("invalid offset: {}", s),
+    };
+    let (hours, minutes) = s[1..].split_at(s[1..].find(':').unwrap_or(2).min(s.len() - 1));
+    let hours = hours.parse::<i32>()?;
+    let minutes = minutes.trim_start_matches(':');
+    let minutes = if minutes.is_empty() {
+        0
+    } else {
+        minutes.parse::<i32>()?
+    };
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+        .ok_or_else(|| anyhow::anyhow!("invalid offset: {}", s))
+}
+
+/// Parses a `TIMESTAMP` as written in RFC 3339 / ISO 8601.
+/// A timestamp without an offset, or a bare date, is taken to be in UTC.
+pub fn parse_timestamp(s: &str) -> anyhow::Result<DateTime<FixedOffset>> {
+    let s = s.trim();
+    if let Ok(datetime) = DateTime::parse_from_rfc3339(s) {
+        return Ok(datetime);
+    }
+    for fmt in &["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f", "%Y-%m-%dT%H:%M"] {
+        if let Ok(datetime) = NaiveDateTime::parse_from_str(s, fmt) {
+            return in_utc(&datetime)
+                .ok_or_else(|| anyhow::anyhow!("invalid timestamp: {}", s));
+        }
+    }
+    if let Ok(datetime) = DateTime::parse_from_str(s, "%Y-%m-%d %H:%M:%S%.f%:z") {
+        return Ok(datetime);
+    }
+    let date = parse_date(s)?;
+    start_of_day(&date).ok_or_else(|| anyhow::anyhow!("invalid timestamp: {}", s))
+}
+
+// A naive date and time, taken to be in UTC.
+fn in_utc(datetime: &NaiveDateTime) -> Option<DateTime<FixedOffset>> {
+    Some(FixedOffset::east_opt(0)?.from_utc_datetime(datetime))
+}
+
+// The timestamp of midnight UTC on `date`.
+fn start_of_day(date: &NaiveDate) -> Option<DateTime<FixedOffset>> {
+    in_utc(&date.and_hms_opt(0, 0, 0)?)
+}
+
+pub fn parse_date(s: &str) -> anyhow::Result<NaiveDate> {
+    Ok(NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d")?)
+}
+
+pub fn format_timestamp(datetime: &DateTime<FixedOffset>) -> String {
+    datetime.to_rfc3339_opts(SecondsFormat::AutoSi, true)
+}
+
+pub fn serialize_timestamp<S: Serializer>(
+    datetime: &DateTime<FixedOffset>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format_timestamp(datetime))
+}
+
+#[derive(Display, FromStr, Debug, Clone, Copy, PartialEq)]
+#[display(style = "snake_case")]
+pub enum DateTimePart {
+    Year,
+    Month,
+    Day,
+    Hour,
+    Minute,
+    Second,
+    TimezoneHour,
+    TimezoneMinute,
+}
+
+fn as_timestamp(value: &PqlValue) -> Option<DateTime<FixedOffset>> {
+    match value {
+        PqlValue::DateTime(datetime) => Some(*datetime),
+        PqlValue::Date(date) => start_of_day(date),
+        _ => None,
+    }
+}
+
+/// `EXTRACT(part FROM value)`. SECOND keeps its fraction as a decimal.
+pub fn extract(part: DateTimePart, value: &PqlValue) -> PqlValue {
+    let (date, time, offset) = match value {
+        PqlValue::DateTime(datetime) => (
+            Some(datetime.naive_local().date()),
+            Some(datetime.time()),
+            Some(*datetime.offset()),
+        ),
+        PqlValue::Date(date) => (Some(*date), None, None),
+        PqlValue::Time(time) => (None, Some(time.time), time.offset),
+        _ => return PqlValue::Missing,
+    };
+    let extracted = match part {
+        DateTimePart::Year => date.map(|d| d.year() as i64),
+        DateTimePart::Month => date.map(|d| d.month() as i64),
+        DateTimePart::Day => date.map(|d| d.day() as i64),
+        DateTimePart::Hour => time.map(|t| t.hour() as i64),
+        DateTimePart::Minute => time.map(|t| t.minute() as i64),
+        DateTimePart::Second => {
+            return match time {
+                Some(t) => PqlValue::Decimal(seconds(t.second(), t.nanosecond())),
+                None => PqlValue::Missing,
+            }
+        }
+        DateTimePart::TimezoneHour => offset.map(|o| (o.local_minus_utc() / 3600) as i64),
+        DateTimePart::TimezoneMinute => offset.map(|o| (o.local_minus_utc() % 3600 / 60) as i64),
+    };
+    extracted.map(PqlValue::Int).unwrap_or(PqlValue::Missing)
+}
+
+/// `DATE_ADD(part, quantity, value)`. Adding months clamps to the end of the month.
+pub fn date_add(part: DateTimePart, quantity: i64, value: &PqlValue) -> PqlValue {
+    let datetime = match as_timestamp(value) {
+        Some(datetime) => datetime,
+        None => return PqlValue::Missing,
+    };
+    let added = match part {
+        DateTimePart::Year => add_months(datetime, quantity.saturating_mul(12)),
+        DateTimePart::Month => add_months(datetime, quantity),
+        DateTimePart::Day => datetime.checked_add_signed(Duration::days(quantity)),
+        DateTimePart::Hour => datetime.checked_add_signed(Duration::hours(quantity)),
+        DateTimePart::Minute => datetime.checked_add_signed(Duration::minutes(quantity)),
+        DateTimePart::Second => datetime.checked_add_signed(Duration::seconds(quantity)),
+        _ => None,
+    };
+    match (added, value) {
+        (Some(added), PqlValue::Date(_)) => PqlValue::Date(added.naive_local().date()),
+        (Some(added), _) => PqlValue::DateTime(added),
+        (None, _) => PqlValue::Missing,
+    }
+}
+
+// Whole and fractional seconds as a decimal with no trailing zeros, as `30.5` rather than
+// `30.500000000`.
+fn seconds(second: u32, nanosecond: u32) -> BigDecimal {
+    let (mut fraction, mut scale) = (i64::from(nanosecond), 9);
+    while scale > 0 && fraction % 10 == 0 {
+        fraction /= 10;
+        scale -= 1;
+    }
+    BigDecimal::from(i64::from(second)) + BigDecimal::new(fraction.into(), scale)
+}
+
+fn add_months(datetime: DateTime<FixedOffset>, months: i64) -> Option<DateTime<FixedOffset>> {
+    let local = datetime.naive_local();
+    let date = if months >= 0 {
+        local.date().checked_add_months(Months::new(u32::try_from(months).ok()?))
+    } else {
+        local.date().checked_sub_months(Months::new(u32::try_from(-months).ok()?))
+    }?;
+    datetime
+        .offset()
+        .from_local_datetime(&date.and_time(local.time()))
+        .single()
+}
+
+/// `DATE_DIFF(part, from, to)`: the number of whole `part`s from `from` to `to`.
+pub fn date_diff(part: DateTimePart, from: &PqlValue, to: &PqlValue) -> PqlValue {
+    let (from, to) = match (as_timestamp(from), as_timestamp(to)) {
+        (Some(from), Some(to)) => (from, to),
+        _ => return PqlValue::Missing,
+    };
+    let diff = match part {
+        DateTimePart::Year => months_between(from, to) / 12,
+        DateTimePart::Month => months_between(from, to),
+        DateTimePart::Day => (to - from).num_days(),
+        DateTimePart::Hour => (to - from).num_hours(),
+        DateTimePart::Minute => (to - from).num_minutes(),
+        DateTimePart::Second => (to - from).num_seconds(),
+        _ => return PqlValue::Missing,
+    };
+    PqlValue::Int(diff)
+}
+
+fn months_between(from: DateTime<FixedOffset>, to: DateTime<FixedOffset>) -> i64 {
+    let (from, to) = (from.naive_utc(), to.naive_utc());
+    let months =
+        (to.year() as i64 - from.year() as i64) * 12 + to.month() as i64 - from.month() as i64;
+    // Only count the last month once it has fully elapsed.
+    let (from_rest, to_rest) = ((from.day(), from.time()), (to.day(), to.time()));
+    if months > 0 && to_rest < from_rest {
+        months - 1
+    } else if months < 0 && to_rest > from_rest {
+        months + 1
+    } else {
+        months
+    }
+}
+
+/// Translates a PartiQL/Java style pattern such as `yyyy-MM-dd'T'HH:mm:ss` into a chrono one.
+pub fn to_chrono_format(pattern: &str) -> String {
+    let chars = pattern.chars().collect::<Vec<_>>();
+    let mut fmt = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let n = chars[i..].iter().take_while(|&&x| x == c).count();
+        match c {
+            '\'' => {
+                let literal = chars[i + 1..]
+                    .iter()
+                    .take_while(|&&x| x != '\'')
+                    .collect::<String>();
+                fmt.push_str(&literal.replace('%', "%%"));
+                i += literal.chars().count() + 2;
+                continue;
+            }
+            'y' if n == 2 => fmt.push_str("%y"),
+            'y' => fmt.push_str("%Y"),
+            'M' if n >= 4 => fmt.push_str("%B"),
+            'M' if n == 3 => fmt.push_str("%b"),
+            'M' if n == 2 => fmt.push_str("%m"),
+            'M' => fmt.push_str("%-m"),
+            'd' if n >= 2 => fmt.push_str("%d"),
+            'd' => fmt.push_str("%-d"),
+            'H' if n >= 2 => fmt.push_str("%H"),
+            'H' => fmt.push_str("%-H"),
+            'h' if n >= 2 => fmt.push_str("%I"),
+            'h' => fmt.push_str("%-I"),
+            'm' if n >= 2 => fmt.push_str("%M"),
+            'm' => fmt.push_str("%-M"),
+            's' if n >= 2 => fmt.push_str("%S"),
+            's' => fmt.push_str("%-S"),
+            'S' if n <= 3 => fmt.push_str("%3f"),
+            'S' if n <= 6 => fmt.push_str("%6f"),
+            'S' => fmt.push_str("%9f"),
+            'a' => fmt.push_str("%p"),
+            'X' | 'x' if n >= 3 => fmt.push_str("%:z"),
+            'X' | 'x' => fmt.push_str("%z"),
+            '%' => fmt.push_str(&"%%".repeat(n)),
+            _ => fmt.push_str(&chars[i..i + n].iter().collect::<String>()),
+        }
+        i += n;
+    }
+    fmt
+}
+
+/// `TO_STRING(value, pattern)`. A pattern with fields the value does not have, such as hours of
+/// a DATE or the offset of a TIME, has no string.
+pub fn to_string(value: &PqlValue, pattern: &str) -> PqlValue {
+    use std::fmt::Write;
+
+    let fmt = to_chrono_format(pattern);
+    let mut s = String::new();
+    let written = match value {
+        PqlValue::DateTime(datetime) => write!(s, "{}", datetime.format(&fmt)),
+        PqlValue::Date(date) => write!(s, "{}", date.format(&fmt)),
+        PqlValue::Time(time) => write!(s, "{}", time.time.format(&fmt)),
+        _ => return PqlValue::Missing,
+    };
+    match written {
+        Ok(()) => PqlValue::Str(s),
+        Err(fmt::Error) => PqlValue::Missing,
+    }
+}
+
+/// `TO_TIMESTAMP(string [, pattern])`
+pub fn to_timestamp(value: &PqlValue, pattern: Option<&str>) -> PqlValue {
+    let s = match value {
+        PqlValue::Str(s) => s,
+        PqlValue::DateTime(_) => return value.to_owned(),
+        _ => return PqlValue::Missing,
+    };
+    let parsed = match pattern {
+        None => parse_timestamp(s).ok(),
+        Some(pattern) => {
+            let fmt = to_chrono_format(pattern);
+            DateTime::parse_from_str(s, &fmt)
+                .ok()
+                .or_else(|| {
+                    NaiveDateTime::parse_from_str(s, &fmt)
+                        .ok()
+                        .and_then(|naive| in_utc(&naive))
+                })
+                .or_else(|| {
+                    NaiveDate::parse_from_str(s, &fmt)
+                        .ok()
+                        .and_then(|date| start_of_day(&date))
+                })
+        }
+    };
+    parsed.map(PqlValue::DateTime).unwrap_or(PqlValue::Missing)
+}
+
+/// `UTCNOW()`
+pub fn utcnow() -> PqlValue {
+    in_utc(&Utc::now().naive_utc())
+        .map(PqlValue::DateTime)
+        .unwrap_or(PqlValue::Missing)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+
+    use super::{
+        date_add, date_diff, extract, format_timestamp, parse_date, parse_timestamp, to_string,
+        to_timestamp, DateTimePart, PqlTime,
+    };
+    use crate::value::PqlValue;
+
+    #[test]
+    fn keeps_offset_and_precision() -> anyhow::Result<()> {
+        let ts = parse_timestamp("2021-07-01T12:34:56.789+09:00")?;
+        assert_eq!(format_timestamp(&ts), "2021-07-01T12:34:56.789+09:00");
+
+        let time = PqlTime::from_str("23:59:30.5-05:30")?;
+        assert_eq!(time.to_string(), "23:59:30.500-05:30");
+        assert_eq!(PqlTime::from_str("08:00:00")?.offset, None);
+        Ok(())
+    }
+
+    #[test]
+    fn extract_parts() -> anyhow::Result<()> {
+        let ts = PqlValue::DateTime(parse_timestamp("2021-07-01T12:34:56.25+09:30")?);
+        assert_eq!(extract(DateTimePart::Year, &ts), PqlValue::from(2021));
+        assert_eq!(extract(DateTimePart::Minute, &ts), PqlValue::from(34));
+        assert_eq!(
+            extract(DateTimePart::Second, &ts),
+            PqlValue::Decimal(BigDecimal::from_str("56.25")?)
+        );
+        let seconds = |s: &str| -> anyhow::Result<String> {
+            match extract(DateTimePart::Second, &PqlValue::DateTime(parse_timestamp(s)?)) {
+                PqlValue::Decimal(seconds) => Ok(seconds.to_string()),
+                value => anyhow::bail!("not a decimal: {:?}", value),
+            }
+        };
+        assert_eq!(seconds("2021-07-01T12:34:30.5Z")?, "30.5");
+        assert_eq!(seconds("2021-07-01T12:34:30Z")?, "30");
+        assert_eq!(seconds("2021-07-01T12:34:30.000000001Z")?, "30.000000001");
+        assert_eq!(extract(DateTimePart::TimezoneHour, &ts), PqlValue::from(9));
+        assert_eq!(extract(DateTimePart::TimezoneMinute, &ts), PqlValue::from(30));
+
+        let date = PqlValue::Date(parse_date("2021-07-01")?);
+        assert_eq!(extract(DateTimePart::Hour, &date), PqlValue::Missing);
+        Ok(())
+    }
+
+    #[test]
+    fn add_and_diff() -> anyhow::Result<()> {
+        let ts = PqlValue::DateTime(parse_timestamp("2021-01-31T00:00:00Z")?);
+        assert_eq!(
+            date_add(DateTimePart::Month, 1, &ts),
+            PqlValue::DateTime(parse_timestamp("2021-02-28T00:00:00Z")?)
+        );
+
+        let date = PqlValue::Date(parse_date("2021-07-01")?);
+        assert_eq!(
+            date_add(DateTimePart::Day, -1, &date),
+            PqlValue::Date(parse_date("2021-06-30")?)
+        );
+
+        let later = PqlValue::DateTime(parse_timestamp("2022-01-30T00:00:00Z")?);
+        assert_eq!(date_diff(DateTimePart::Month, &ts, &later), PqlValue::from(11));
+        assert_eq!(date_diff(DateTimePart::Year, &ts, &later), PqlValue::from(0));
+        assert_eq!(date_diff(DateTimePart::Day, &ts, &later), PqlValue::from(364));
+        Ok(())
+    }
+
+    #[test]
+    fn format_and_parse() -> anyhow::Result<()> {
+        let ts = PqlValue::DateTime(parse_timestamp("2021-07-01T08:05:09Z")?);
+        assert_eq!(
+            to_string(&ts, "yyyy-MM-dd'T'HH:mm:ss"),
+            PqlValue::from("2021-07-01T08:05:09")
+        );
+        assert_eq!(
+            to_string(&ts, "MMMM d, yyyy"),
+            PqlValue::from("July 1, 2021")
+        );
+        // A DATE has no hours, and a TIME no offset.
+        let date = PqlValue::Date(parse_date("2021-07-01")?);
+        assert_eq!(to_string(&date, "HH:mm"), PqlValue::Missing);
+        let time = PqlValue::Time(PqlTime::from_str("09:30:00+01:00")?);
+        assert_eq!(to_string(&time, "HH:mm XXX"), PqlValue::Missing);
+        assert_eq!(to_string(&time, "HH:mm"), PqlValue::from("09:30"));
+        assert_eq!(
+            to_timestamp(&PqlValue::from("01/07/2021 08:05:09"), Some("dd/MM/yyyy HH:mm:ss")),
+            ts
+        );
+        assert_eq!(
+            to_timestamp(&PqlValue::from("2021-07-01T08:05:09Z"), None),
+            ts
+        );
+        Ok(())
+    }
+}
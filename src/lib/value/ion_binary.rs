@@ -0,0 +1,654 @@
+//! Amazon Ion binary, read into and written from `PqlValue` with the same mapping as `ion_value`.
+//!
+//! Only local symbol tables are supported; a stream importing a shared symbol table is rejected.
+
+use std::convert::TryFrom;
+
+use bigdecimal::num_bigint::{BigInt, Sign};
+use bigdecimal::BigDecimal;
+use chrono::prelude::*;
+use indexmap::IndexMap as Map;
+use indexmap::IndexSet;
+use num_traits::{ToPrimitive, Zero};
+use ordered_float::OrderedFloat;
+
+use crate::value::ion_value::{self, MISSING, TIME};
+use crate::value::{bounded_decimal, PqlValue};
+
+pub const VERSION_MARKER: [u8; 4] = [0xE0, 0x01, 0x00, 0xEA];
+
+const SYSTEM_SYMBOLS: [&str; 10] = [
+    "$0",
+    "$ion",
+    "$ion_1_0",
+    "$ion_symbol_table",
+    "name",
+    "version",
+    "imports",
+    "symbols",
+    "max_id",
+    "$ion_shared_symbol_table",
+];
+const ION_SYMBOL_TABLE: usize = 3;
+const SYMBOLS: usize = 7;
+// The most digits of a fraction of a second that are read from a timestamp.
+const MAX_FRACTION_DIGITS: i64 = 64;
+
+/// Reads an Ion binary stream. A stream of several top-level values is read as an array.
+pub fn from_bytes(bytes: &[u8]) -> anyhow::Result<PqlValue> {
+    let mut reader = Reader {
+        bytes,
+        pos: 0,
+        symbols: system_symbols(),
+    };
+    let mut values = Vec::new();
+    while reader.pos < bytes.len() {
+        if bytes[reader.pos..].starts_with(&VERSION_MARKER) {
+            reader.pos += VERSION_MARKER.len();
+            reader.symbols = system_symbols();
+            continue;
+        }
+        match reader.value()? {
+            Some(PqlValue::Annotated(annotations, table))
+                if annotations.first().map(String::as_str) == Some("$ion_symbol_table") =>
+            {
+                reader.load_symbol_table(*table)?
+            }
+            Some(value) => values.push(value),
+            None => {}
+        }
+    }
+
+    if values.len() == 1 {
+        Ok(values.remove(0))
+    } else {
+        Ok(PqlValue::Array(values))
+    }
+}
+
+/// Writes a single Ion binary value, preceded by the local symbol table it needs.
+pub fn to_bytes(value: &PqlValue) -> Vec<u8> {
+    let mut symbols = system_symbols().into_iter().collect::<IndexSet<_>>();
+    let system_len = symbols.len();
+    collect_symbols(value, &mut symbols);
+
+    let mut out = VERSION_MARKER.to_vec();
+    let writer = Writer { symbols };
+    if writer.symbols.len() > system_len {
+        let table = writer
+            .symbols
+            .iter()
+            .skip(system_len)
+            .map(|s| PqlValue::Str(s.to_owned()))
+            .collect::<Vec<_>>();
+        let mut map = Map::new();
+        map.insert(SYSTEM_SYMBOLS[SYMBOLS].to_owned(), PqlValue::Array(table));
+        let table = PqlValue::Annotated(
+            vec![SYSTEM_SYMBOLS[ION_SYMBOL_TABLE].to_owned()],
+            Box::new(PqlValue::Object(map)),
+        );
+        writer.write(&table, &mut out);
+    }
+    writer.write(value, &mut out);
+    out
+}
+
+fn system_symbols() -> Vec<String> {
+    SYSTEM_SYMBOLS.iter().map(|s| s.to_string()).collect()
+}
+
+fn collect_symbols(value: &PqlValue, symbols: &mut IndexSet<String>) {
+    match value {
+        PqlValue::Missing => {
+            symbols.insert(MISSING.to_owned());
+        }
+        PqlValue::Time(_) => {
+            symbols.insert(TIME.to_owned());
+        }
+        PqlValue::Symbol(s) => {
+            symbols.insert(s.to_owned());
+        }
+        PqlValue::Annotated(annotations, value) => {
+            symbols.extend(annotations.iter().cloned());
+            collect_symbols(value, symbols);
+        }
+        PqlValue::Array(array) => array.iter().for_each(|v| collect_symbols(v, symbols)),
+        PqlValue::Object(map) => {
+            for (k, v) in map.iter().filter(|(_, v)| **v != PqlValue::Missing) {
+                symbols.insert(k.to_owned());
+                collect_symbols(v, symbols);
+            }
+        }
+        _ => {}
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    symbols: Vec<String>,
+}
+
+impl<'a> Reader<'a> {
+    fn byte(&mut self) -> anyhow::Result<u8> {
+        let byte = *self
+            .bytes
+            .get(self.pos)
+            .ok_or_else(|| anyhow::anyhow!("unexpected end of ion binary"))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        let end = self.end(len)?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(bytes)
+    }
+
+    // The position `len` bytes on, which must be within the stream.
+    fn end(&self, len: usize) -> anyhow::Result<usize> {
+        match self.pos.checked_add(len) {
+            Some(end) if end <= self.bytes.len() => Ok(end),
+            _ => anyhow::bail!("unexpected end of ion binary"),
+        }
+    }
+
+    // The bytes left before `end`, which a read must not have gone past.
+    fn remaining(&self, end: usize) -> anyhow::Result<usize> {
+        end.checked_sub(self.pos)
+            .ok_or_else(|| anyhow::anyhow!("malformed ion value length"))
+    }
+
+    fn var_uint(&mut self) -> anyhow::Result<usize> {
+        let mut value = 0usize;
+        loop {
+            let byte = self.byte()?;
+            value = value
+                .checked_mul(0x80)
+                .ok_or_else(|| anyhow::anyhow!("ion integer is too large"))?
+                | (byte & 0x7F) as usize;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+        }
+    }
+
+    // Returns the magnitude with its sign, keeping `-0` apart from `0`.
+    fn var_int(&mut self) -> anyhow::Result<(bool, i64)> {
+        let byte = self.byte()?;
+        let negative = byte & 0x40 != 0;
+        let mut magnitude = (byte & 0x3F) as i64;
+        let mut end = byte & 0x80 != 0;
+        while !end {
+            let byte = self.byte()?;
+            magnitude = magnitude
+                .checked_mul(0x80)
+                .ok_or_else(|| anyhow::anyhow!("ion integer is too large"))?
+                | (byte & 0x7F) as i64;
+            end = byte & 0x80 != 0;
+        }
+        Ok((negative, if negative { -magnitude } else { magnitude }))
+    }
+
+    fn symbol(&self, sid: usize) -> String {
+        self.symbols
+            .get(sid)
+            .cloned()
+            .unwrap_or_else(|| format!("${}", sid))
+    }
+
+    fn load_symbol_table(&mut self, table: PqlValue) -> anyhow::Result<()> {
+        let imports = table.to_owned().get("imports");
+        match imports {
+            Some(PqlValue::Symbol(s)) if s == SYSTEM_SYMBOLS[ION_SYMBOL_TABLE] => {}
+            None => self.symbols = system_symbols(),
+            Some(_) => anyhow::bail!("ion shared symbol tables are not supported"),
+        }
+        if let Some(PqlValue::Array(symbols)) = table.get("symbols") {
+            for symbol in symbols {
+                match symbol {
+                    PqlValue::Str(s) => self.symbols.push(s),
+                    _ => self.symbols.push(format!("${}", self.symbols.len())),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads one value; `None` is NOP padding.
+    fn value(&mut self) -> anyhow::Result<Option<PqlValue>> {
+        let descriptor = self.byte()?;
+        let (t, l) = (descriptor >> 4, descriptor & 0x0F);
+        if l == 0x0F {
+            return Ok(Some(PqlValue::Null));
+        }
+        if t == 0x1 {
+            return Ok(Some(PqlValue::Boolean(l == 1)));
+        }
+        let len = match (t, l) {
+            (0xD, 1) => self.var_uint()?,
+            (_, 14) => self.var_uint()?,
+            _ => l as usize,
+        };
+        let end = self.end(len)?;
+
+        let value = match t {
+            0x0 => {
+                self.pos = end;
+                return Ok(None);
+            }
+            0x2 => ion_value::from_bigint(self.uint(len)?),
+            0x3 => ion_value::from_bigint(-self.uint(len)?),
+            0x4 => match len {
+                0 => PqlValue::Float(OrderedFloat(0.)),
+                4 => {
+                    let bytes = <[u8; 4]>::try_from(self.take(4)?)?;
+                    PqlValue::Float(OrderedFloat(f32::from_be_bytes(bytes) as f64))
+                }
+                8 => {
+                    let bytes = <[u8; 8]>::try_from(self.take(8)?)?;
+                    PqlValue::Float(OrderedFloat(f64::from_be_bytes(bytes)))
+                }
+                _ => anyhow::bail!("invalid ion float length: {}", len),
+            },
+            0x5 if len == 0 => PqlValue::Decimal(BigDecimal::zero()),
+            0x5 => {
+                let (_, exponent) = self.var_int()?;
+                let coefficient = self.int(self.remaining(end)?)?;
+                PqlValue::Decimal(bounded_decimal(BigDecimal::new(coefficient, -exponent))?)
+            }
+            0x6 => self.timestamp(end)?,
+            0x7 => {
+                let sid = self.uint(len)?.to_usize().unwrap_or(0);
+                PqlValue::Symbol(self.symbol(sid))
+            }
+            0x8 => PqlValue::Str(String::from_utf8(self.take(len)?.to_vec())?),
+            0x9 => PqlValue::Clob(self.take(len)?.to_vec()),
+            0xA => PqlValue::Blob(self.take(len)?.to_vec()),
+            0xB | 0xC => {
+                let mut array = Vec::new();
+                while self.pos < end {
+                    array.extend(self.value()?);
+                    self.remaining(end)?;
+                }
+                PqlValue::Array(array)
+            }
+            0xD => {
+                let mut map = Map::new();
+                while self.pos < end {
+                    let key = self.var_uint()?;
+                    if let Some(value) = self.value()? {
+                        map.insert(self.symbol(key), value);
+                    }
+                    self.remaining(end)?;
+                }
+                PqlValue::Object(map)
+            }
+            0xE => {
+                let annotations_len = self.var_uint()?;
+                let annotations_end = self.end(annotations_len)?;
+                self.remaining(end)?;
+                if annotations_end > end {
+                    anyhow::bail!("malformed ion value length");
+                }
+                let mut annotations = Vec::new();
+                while self.pos < annotations_end {
+                    let sid = self.var_uint()?;
+                    annotations.push(self.symbol(sid));
+                    self.remaining(annotations_end)?;
+                }
+                let value = self.value()?.unwrap_or(PqlValue::Null);
+                ion_value::annotate(annotations, value)
+            }
+            _ => anyhow::bail!("invalid ion type descriptor: {:#04x}", descriptor),
+        };
+        if self.pos != end {
+            anyhow::bail!("malformed ion value length");
+        }
+        Ok(Some(value))
+    }
+
+    fn uint(&mut self, len: usize) -> anyhow::Result<BigInt> {
+        Ok(BigInt::from_bytes_be(Sign::Plus, self.take(len)?))
+    }
+
+    // A fixed-length signed integer in sign-magnitude form.
+    fn int(&mut self, len: usize) -> anyhow::Result<BigInt> {
+        let bytes = self.take(len)?;
+        match bytes.split_first() {
+            Some((first, rest)) => {
+                let mut magnitude = vec![first & 0x7F];
+                magnitude.extend_from_slice(rest);
+                let sign = if first & 0x80 != 0 {
+                    Sign::Minus
+                } else {
+                    Sign::Plus
+                };
+                Ok(BigInt::from_bytes_be(sign, &magnitude))
+            }
+            None => Ok(BigInt::zero()),
+        }
+    }
+
+    // Timestamp fields are stored in UTC along with the offset in minutes.
+    fn timestamp(&mut self, end: usize) -> anyhow::Result<PqlValue> {
+        let invalid = || anyhow::anyhow!("invalid ion timestamp");
+        let (_, offset) = self.var_int()?;
+        let year = i32::try_from(self.var_uint()?).map_err(|_| invalid())?;
+        let mut fields = Vec::new();
+        while self.remaining(end)? > 0 && fields.len() < 5 {
+            fields.push(u32::try_from(self.var_uint()?).map_err(|_| invalid())?);
+        }
+        let nanos = if self.remaining(end)? > 0 {
+            let (_, exponent) = self.var_int()?;
+            let coefficient = self.int(self.remaining(end)?)?;
+            // A fraction of a second is less than one, so its exponent is negative.
+            if !(-MAX_FRACTION_DIGITS..=0).contains(&exponent) {
+                return Err(invalid());
+            }
+            let nanos = BigDecimal::new(coefficient, -exponent - 9);
+            nanos.to_u32().unwrap_or(0)
+        } else {
+            0
+        };
+
+        let field = |i: usize, default: u32| fields.get(i).copied().unwrap_or(default);
+        let date = NaiveDate::from_ymd_opt(year, field(0, 1), field(1, 1)).ok_or_else(invalid)?;
+        if fields.len() < 3 {
+            return Ok(PqlValue::Date(date));
+        }
+        let time = NaiveTime::from_hms_nano_opt(field(2, 0), field(3, 0), field(4, 0), nanos)
+            .ok_or_else(invalid)?;
+        let offset = i32::try_from(offset)
+            .ok()
+            .and_then(|minutes| minutes.checked_mul(60))
+            .and_then(FixedOffset::east_opt)
+            .ok_or_else(|| anyhow::anyhow!("invalid ion timestamp offset"))?;
+        Ok(PqlValue::DateTime(
+            offset.from_utc_datetime(&date.and_time(time)),
+        ))
+    }
+}
+
+struct Writer {
+    symbols: IndexSet<String>,
+}
+
+impl Writer {
+    fn sid(&self, symbol: &str) -> usize {
+        self.symbols.get_index_of(symbol).unwrap_or(0)
+    }
+
+    fn write(&self, value: &PqlValue, out: &mut Vec<u8>) {
+        match value {
+            PqlValue::Missing => self.write(
+                &PqlValue::Annotated(vec![MISSING.to_owned()], Box::new(PqlValue::Null)),
+                out,
+            ),
+            PqlValue::Null => out.push(0x0F),
+            PqlValue::Boolean(b) => out.push(0x10 | *b as u8),
+            PqlValue::Int(0) => out.push(0x20),
+            PqlValue::Int(i) => {
+                let magnitude = uint_bytes(&BigInt::from(i.unsigned_abs()));
+                header(out, if *i < 0 { 0x3 } else { 0x2 }, magnitude.len());
+                out.extend(magnitude);
+            }
+            PqlValue::Float(OrderedFloat(f)) if f.to_bits() == 0 => out.push(0x40),
+            PqlValue::Float(OrderedFloat(f)) => {
+                out.push(0x48);
+                out.extend_from_slice(&f.to_be_bytes());
+            }
+            // Integers too large for i64 are kept as decimals with no fraction; write them back as ints.
+            PqlValue::Decimal(d) if d.as_bigint_and_exponent().1 == 0 && d.to_i64().is_none() => {
+                let (int, _) = d.as_bigint_and_exponent();
+                let magnitude = uint_bytes(&int);
+                header(out, if int.sign() == Sign::Minus { 0x3 } else { 0x2 }, magnitude.len());
+                out.extend(magnitude);
+            }
+            PqlValue::Decimal(d) => {
+                let (coefficient, scale) = d.as_bigint_and_exponent();
+                if coefficient.is_zero() && scale == 0 {
+                    out.push(0x50);
+                } else {
+                    let mut body = Vec::new();
+                    var_int(-scale, &mut body);
+                    body.extend(int_bytes(&coefficient));
+                    header(out, 0x5, body.len());
+                    out.extend(body);
+                }
+            }
+            PqlValue::DateTime(datetime) => {
+                let utc = datetime.naive_utc();
+                let mut body = Vec::new();
+                var_int(datetime.offset().local_minus_utc() as i64 / 60, &mut body);
+                var_uint(utc.year() as usize, &mut body);
+                for field in &[utc.month(), utc.day(), utc.hour(), utc.minute(), utc.second()] {
+                    var_uint(*field as usize, &mut body);
+                }
+                let mut nanos = utc.nanosecond() as i64;
+                if nanos > 0 {
+                    let mut exponent = -9;
+                    while nanos % 10 == 0 {
+                        nanos /= 10;
+                        exponent += 1;
+                    }
+                    var_int(exponent, &mut body);
+                    body.extend(int_bytes(&BigInt::from(nanos)));
+                }
+                header(out, 0x6, body.len());
+                out.extend(body);
+            }
+            PqlValue::Date(date) => {
+                // A date has no offset, which Ion writes as the unknown offset `-00:00`.
+                let mut body = vec![0xC0];
+                var_uint(date.year() as usize, &mut body);
+                var_uint(date.month() as usize, &mut body);
+                var_uint(date.day() as usize, &mut body);
+                header(out, 0x6, body.len());
+                out.extend(body);
+            }
+            PqlValue::Time(time) => self.write(
+                &PqlValue::Annotated(
+                    vec![TIME.to_owned()],
+                    Box::new(PqlValue::Str(time.to_string())),
+                ),
+                out,
+            ),
+            PqlValue::Str(s) => {
+                header(out, 0x8, s.len());
+                out.extend_from_slice(s.as_bytes());
+            }
+            PqlValue::Symbol(s) => {
+                let sid = uint_bytes(&BigInt::from(self.sid(s)));
+                header(out, 0x7, sid.len());
+                out.extend(sid);
+            }
+            PqlValue::Clob(bytes) => {
+                header(out, 0x9, bytes.len());
+                out.extend_from_slice(bytes);
+            }
+            PqlValue::Blob(bytes) => {
+                header(out, 0xA, bytes.len());
+                out.extend_from_slice(bytes);
+            }
+            PqlValue::Annotated(annotations, value) => {
+                let mut sids = Vec::new();
+                for annotation in annotations {
+                    var_uint(self.sid(annotation), &mut sids);
+                }
+                let mut body = Vec::new();
+                var_uint(sids.len(), &mut body);
+                body.extend(sids);
+                self.write(value, &mut body);
+                header(out, 0xE, body.len());
+                out.extend(body);
+            }
+            PqlValue::Array(array) => {
+                let mut body = Vec::new();
+                array.iter().for_each(|v| self.write(v, &mut body));
+                header(out, 0xB, body.len());
+                out.extend(body);
+            }
+            PqlValue::Object(map) => {
+                let mut body = Vec::new();
+                for (k, v) in map.iter().filter(|(_, v)| **v != PqlValue::Missing) {
+                    var_uint(self.sid(k), &mut body);
+                    self.write(v, &mut body);
+                }
+                header(out, 0xD, body.len());
+                out.extend(body);
+            }
+        }
+    }
+}
+
+fn header(out: &mut Vec<u8>, t: u8, len: usize) {
+    if len < 14 {
+        out.push((t << 4) | len as u8);
+    } else {
+        out.push((t << 4) | 14);
+        var_uint(len, out);
+    }
+}
+
+fn var_uint(mut value: usize, out: &mut Vec<u8>) {
+    let mut groups = vec![(value & 0x7F) as u8 | 0x80];
+    value >>= 7;
+    while value > 0 {
+        groups.push((value & 0x7F) as u8);
+        value >>= 7;
+    }
+    out.extend(groups.into_iter().rev());
+}
+
+fn var_int(value: i64, out: &mut Vec<u8>) {
+    let mut magnitude = value.unsigned_abs();
+    let mut groups = vec![(magnitude & 0x7F) as u8];
+    magnitude >>= 7;
+    while magnitude > 0 {
+        groups.push((magnitude & 0x7F) as u8);
+        magnitude >>= 7;
+    }
+    // The first byte only has room for six bits next to the sign.
+    if groups.last().is_some_and(|&group| group & 0x40 != 0) {
+        groups.push(0);
+    }
+    groups[0] |= 0x80;
+    if value < 0 {
+        *groups.last_mut().unwrap() |= 0x40;
+    }
+    out.extend(groups.into_iter().rev());
+}
+
+fn uint_bytes(int: &BigInt) -> Vec<u8> {
+    match int.to_bytes_be() {
+        (Sign::NoSign, _) => Vec::new(),
+        (_, bytes) => bytes,
+    }
+}
+
+fn int_bytes(int: &BigInt) -> Vec<u8> {
+    let mut bytes = uint_bytes(int);
+    if bytes.first().is_some_and(|b| b & 0x80 != 0) {
+        bytes.insert(0, 0);
+    }
+    if int.sign() == Sign::Minus {
+        bytes[0] |= 0x80;
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_bytes, to_bytes};
+    use crate::value::{ion_value, PqlValue};
+
+    #[test]
+    fn round_trip() -> anyhow::Result<()> {
+        let value = ion_value::from_str(
+            r#"
+{
+  a: 1, b: -300, c: 0x7fff_ffff_ffff_ffff_ff, d: -12.345, e: 0d0, f: 1.5e0, g: -0e0,
+  h: 2021-07-01T09:30:00.5+09:00, i: 2021-07-01, j: $partiql_time::"09:30:00",
+  big: 123456789012345678901234567890, k: "text", l: sym, m: {{ aGVsbG8= }}, n: {{ "raw" }}, o: celsius::21.5,
+  p: [true, false, null, $partiql_missing::null], q: {}, r: { x: [] },
+  long_field_name_to_need_a_length_prefix: "a string longer than thirteen bytes"
+}
+"#,
+        )?;
+        let bytes = to_bytes(&value);
+        assert_eq!(&bytes[..4], &[0xE0, 0x01, 0x00, 0xEA]);
+        assert_eq!(from_bytes(&bytes)?, value);
+        Ok(())
+    }
+
+    #[test]
+    fn reads_hand_encoded_stream() -> anyhow::Result<()> {
+        // $ion_1_0 $ion_symbol_table::{ symbols: ["title"] } { name: "ion", $10: 7 }
+        let bytes = [
+            0xE0, 0x01, 0x00, 0xEA, // version marker
+            0xEB, 0x81, 0x83, 0xD8, 0x87, 0xB6, 0x85, b't', b'i', b't', b'l', b'e', // symbol table
+            0xD8, 0x84, 0x83, b'i', b'o', b'n', 0x8A, 0x21, 0x07, // struct
+        ];
+        assert_eq!(
+            from_bytes(&bytes)?,
+            ion_value::from_str(r#"{ name: "ion", title: 7 }"#)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_truncated_and_malformed_streams() -> anyhow::Result<()> {
+        let value = ion_value::from_str(
+            r#"{ a: 1, d: -12.345, h: 2021-07-01T09:30:00.5+09:00, k: "text", o: celsius::21.5 }"#,
+        )?;
+        // Cut short after its symbol table, the stream has no values; anywhere else, it is an error.
+        let bytes = to_bytes(&value);
+        for len in 5..bytes.len() {
+            match from_bytes(&bytes[..len]) {
+                Ok(PqlValue::Array(values)) if values.is_empty() => {}
+                read => assert!(read.is_err(), "{:02x?}", &bytes[..len]),
+            }
+        }
+
+        let malformed: &[&[u8]] = &[
+            // A decimal whose exponent runs past its length.
+            &[0x51, 0x01, 0x80],
+            // A timestamp whose fraction runs past its length.
+            &[
+                0x69, 0x80, 0x0F, 0xD0, 0x81, 0x81, 0x80, 0x80, 0x80, 0x41, 0xC1,
+            ],
+            // A length whose VarUInt overflows.
+            &[
+                0x2E, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0xFF,
+            ],
+            // A length that reaches past the end of the stream.
+            &[0x2E, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0xFF],
+            // A decimal exponent whose VarInt overflows.
+            &[
+                0x5B, 0x3F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0x7F, 0xFF, 0x01,
+            ],
+            // Annotations longer than the stream.
+            &[0xE3, 0x85, 0x84, 0x21, 0x01],
+            // A list whose element runs past its length.
+            &[0xB1, 0x21, 0x01],
+            // A timestamp with a year out of range.
+            &[0x66, 0x80, 0x7F, 0x7F, 0x7F, 0x7F, 0xFF],
+            // A timestamp with a fraction of more than a second.
+            &[
+                0x6A, 0x80, 0x0F, 0xD0, 0x81, 0x81, 0x80, 0x80, 0x80, 0x80, 0x02,
+            ],
+            // A length cut short after its type descriptor.
+            &[0x0F, 0xFF, 0xFE],
+            // A decimal whose exponent is too large to be written out.
+            &[
+                0xE0, 0x01, 0x00, 0xEA, 0x56, 0x07, 0x7F, 0x7F, 0x7F, 0xFF, 0x01,
+            ],
+        ];
+        for bytes in malformed {
+            assert!(from_bytes(bytes).is_err(), "{:02x?}", bytes);
+        }
+        Ok(())
+    }
+}
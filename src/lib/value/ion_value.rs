@@ -0,0 +1,651 @@
+//! Amazon Ion text, read into and written from `PqlValue`.
+//!
+//! PartiQL values that Ion has no type for are written with the annotations PartiQL itself uses:
+//! `$partiql_missing::null` for MISSING and `$partiql_time::"09:30:00"` for TIME.
+//! S-expressions are read as arrays. Bags are lists annotated `$partiql_bag`, as `PqlValue::bag` keeps them.
+
+use std::str::FromStr;
+
+use bigdecimal::num_bigint::BigInt;
+use bigdecimal::BigDecimal;
+use chrono::prelude::*;
+use nom::branch::alt;
+use nom::bytes::complete::{is_not, tag, take_until, take_while, take_while1};
+use nom::character::complete::{char, multispace1};
+use nom::combinator::{cut, map, map_opt, opt, recognize, verify};
+use nom::multi::{many0, many0_count, many1, separated_list0};
+use nom::sequence::{delimited, pair, preceded, terminated, tuple};
+use nom::IResult;
+use num_traits::ToPrimitive;
+use ordered_float::OrderedFloat;
+
+use crate::value::datetime::{self, PqlTime};
+use crate::value::{bounded_decimal, PqlValue};
+
+pub const MISSING: &str = "$partiql_missing";
+pub const TIME: &str = "$partiql_time";
+const DATE: &str = "$partiql_date";
+pub const BAG: &str = "$partiql_bag";
+
+/// Parses an Ion text stream. A stream of several top-level values is read as an array.
+pub fn from_str(input: &str) -> anyhow::Result<PqlValue> {
+    match terminated(many0(preceded(ws, value)), ws)(input) {
+        Ok(("", values)) => {
+            let mut values = values
+                .into_iter()
+                .filter(|value| !is_system_value(value))
+                .collect::<Vec<_>>();
+            if values.len() == 1 {
+                Ok(values.remove(0))
+            } else {
+                Ok(PqlValue::Array(values))
+            }
+        }
+        Ok((rest, _)) => anyhow::bail!("unexpected ion text: {}", truncate(rest)),
+        Err(err) => anyhow::bail!("fail to parse ion: {}", err),
+    }
+}
+
+/// Writes Ion text, indented unless `compact`.
+pub fn to_string(value: &PqlValue, compact: bool) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, if compact { None } else { Some(0) });
+    out
+}
+
+// Version markers and symbol tables at the top level are not data.
+fn is_system_value(value: &PqlValue) -> bool {
+    match value {
+        PqlValue::Symbol(s) => s == "$ion_1_0",
+        PqlValue::Annotated(annotations, _) => annotations[0] == "$ion_symbol_table",
+        _ => false,
+    }
+}
+
+fn truncate(s: &str) -> &str {
+    &s[..s.char_indices().nth(32).map_or(s.len(), |(i, _)| i)]
+}
+
+/// Applies the annotations read in front of a value, turning PartiQL's own annotations back into values.
+pub fn annotate(mut annotations: Vec<String>, value: PqlValue) -> PqlValue {
+    if annotations.is_empty() {
+        return value;
+    }
+    let value = match (annotations[0].as_str(), value) {
+        (MISSING, PqlValue::Null) => PqlValue::Missing,
+        (TIME, PqlValue::Str(s)) => match PqlTime::from_str(&s) {
+            Ok(time) => PqlValue::Time(time),
+            Err(_) => return PqlValue::Annotated(annotations, Box::new(PqlValue::Str(s))),
+        },
+        (DATE, PqlValue::DateTime(datetime)) => PqlValue::Date(datetime.naive_local().date()),
+        (DATE, value @ PqlValue::Date(_)) => value,
+        (_, value) => return PqlValue::Annotated(annotations, Box::new(value)),
+    };
+    annotations.remove(0);
+    annotate(annotations, value)
+}
+
+fn ws(input: &str) -> IResult<&str, usize> {
+    many0_count(alt((
+        multispace1,
+        recognize(pair(tag("//"), opt(is_not("\r\n")))),
+        recognize(tuple((tag("/*"), take_until("*/"), tag("*/")))),
+    )))(input)
+}
+
+fn value(input: &str) -> IResult<&str, PqlValue> {
+    let (input, annotations) = many0(terminated(
+        preceded(ws, symbol_text),
+        preceded(ws, tag("::")),
+    ))(input)?;
+    let (input, value) = preceded(ws, bare_value)(input)?;
+
+    Ok((input, annotate(annotations, value)))
+}
+
+fn bare_value(input: &str) -> IResult<&str, PqlValue> {
+    alt((
+        keyword_or_symbol,
+        map(tag("+inf"), |_| PqlValue::Float(OrderedFloat(f64::INFINITY))),
+        map(tag("-inf"), |_| {
+            PqlValue::Float(OrderedFloat(f64::NEG_INFINITY))
+        }),
+        number_or_timestamp,
+        map(long_strings, PqlValue::Str),
+        map(short_string, PqlValue::Str),
+        map(quoted_symbol, PqlValue::Symbol),
+        lob,
+        structure,
+        list,
+        sexp,
+    ))(input)
+}
+
+fn identifier(input: &str) -> IResult<&str, &str> {
+    recognize(pair(
+        take_while1(|c: char| c.is_ascii_alphabetic() || c == '_' || c == '$'),
+        take_while(|c: char| c.is_ascii_alphanumeric() || c == '_' || c == '$'),
+    ))(input)
+}
+
+fn keyword_or_symbol(input: &str) -> IResult<&str, PqlValue> {
+    let (rest, ident) = identifier(input)?;
+    match ident {
+        // Typed nulls such as `null.int` are all NULL to PartiQL.
+        "null" => map(opt(preceded(char('.'), identifier)), |_| PqlValue::Null)(rest),
+        "true" => Ok((rest, PqlValue::Boolean(true))),
+        "false" => Ok((rest, PqlValue::Boolean(false))),
+        "nan" => Ok((rest, PqlValue::Float(OrderedFloat(f64::NAN)))),
+        _ => Ok((rest, PqlValue::Symbol(ident.to_owned()))),
+    }
+}
+
+fn symbol_text(input: &str) -> IResult<&str, String> {
+    alt((
+        verify(map(identifier, String::from), |s: &str| {
+            !matches!(s, "null" | "true" | "false" | "nan")
+        }),
+        quoted_symbol,
+    ))(input)
+}
+
+fn number_or_timestamp(input: &str) -> IResult<&str, PqlValue> {
+    map_opt(
+        recognize(pair(
+            pair(opt(char('-')), take_while1(|c: char| c.is_ascii_digit())),
+            take_while(|c: char| c.is_ascii_alphanumeric() || "_.+-:".contains(c)),
+        )),
+        |token: &str| timestamp(token).or_else(|| number(token)),
+    )(input)
+}
+
+fn number(token: &str) -> Option<PqlValue> {
+    let token = token.replace('_', "");
+    let (negative, digits) = match token.strip_prefix('-') {
+        Some(digits) => (true, digits.to_ascii_lowercase()),
+        None => (false, token.to_ascii_lowercase()),
+    };
+    let radix = if digits.starts_with("0x") {
+        Some(16)
+    } else if digits.starts_with("0b") {
+        Some(2)
+    } else {
+        None
+    };
+    if let Some(radix) = radix {
+        let int = BigInt::parse_bytes(&digits.as_bytes()[2..], radix)?;
+        return Some(from_bigint(if negative { -int } else { int }));
+    }
+
+    if digits.contains('e') {
+        token.parse::<f64>().ok().map(|f| PqlValue::Float(OrderedFloat(f)))
+    } else if digits.contains('d') || digits.contains('.') {
+        let decimal = token.to_ascii_lowercase().replace('d', "e");
+        let decimal = decimal.replace(".e", "e");
+        BigDecimal::from_str(decimal.trim_end_matches('.'))
+            .ok()
+            .and_then(|decimal| bounded_decimal(decimal).ok())
+            .map(PqlValue::Decimal)
+    } else {
+        BigInt::from_str(&token).ok().map(from_bigint)
+    }
+}
+
+pub fn from_bigint(int: BigInt) -> PqlValue {
+    match int.to_i64() {
+        Some(int) => PqlValue::Int(int),
+        None => PqlValue::Decimal(BigDecimal::from(int)),
+    }
+}
+
+// An Ion timestamp is at least a year followed by `T`, e.g. `2007T`, `2007-02-23` or `2007-02-23T12:14Z`.
+// Timestamps without a time of day are read as dates.
+fn timestamp(token: &str) -> Option<PqlValue> {
+    let head = token.get(..5)?;
+    if !(head[..4].chars().all(|c| c.is_ascii_digit()) && (head.ends_with('-') || head.ends_with('T')))
+    {
+        return None;
+    }
+    let (date, clock) = match token.find('T') {
+        Some(i) => (&token[..i], &token[i + 1..]),
+        None => (token, ""),
+    };
+    let mut parts = date.split('-');
+    let year = parts.next()?.parse::<i32>().ok()?;
+    let month = parts.next().map_or(Some(1), |s| s.parse::<u32>().ok())?;
+    let day = parts.next().map_or(Some(1), |s| s.parse::<u32>().ok())?;
+    let date = NaiveDate::from_ymd_opt(year, month, day)?;
+    if clock.is_empty() {
+        return Some(PqlValue::Date(date));
+    }
+
+    let (clock, offset) = match clock.strip_suffix('Z') {
+        Some(clock) => (clock, "+00:00"),
+        None => clock.split_at(clock.rfind(['+', '-'])?),
+    };
+    // `-00:00` is an unknown offset, which is read as UTC.
+    let offset = datetime::parse_offset(offset).ok()?;
+    let time = match clock.len() {
+        5 => NaiveTime::parse_from_str(clock, "%H:%M"),
+        _ => NaiveTime::parse_from_str(clock, "%H:%M:%S%.f"),
+    }
+    .ok()?;
+    offset
+        .from_local_datetime(&date.and_time(time))
+        .single()
+        .map(PqlValue::DateTime)
+}
+
+fn short_string(input: &str) -> IResult<&str, String> {
+    quoted('"')(input)
+}
+
+fn quoted_symbol(input: &str) -> IResult<&str, String> {
+    quoted('\'')(input)
+}
+
+fn quoted(quote: char) -> impl Fn(&str) -> IResult<&str, String> {
+    move |input: &str| {
+        let (input, _) = char(quote)(input)?;
+        let mut escaped = false;
+        for (i, c) in input.char_indices() {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                _ if c == quote => {
+                    return match unescape(&input[..i]) {
+                        Some(s) => Ok((&input[i + 1..], s)),
+                        None => Err(failure(input)),
+                    };
+                }
+                _ => {}
+            }
+        }
+        Err(failure(input))
+    }
+}
+
+fn long_strings(input: &str) -> IResult<&str, String> {
+    let long_string = preceded(
+        tag("'''"),
+        cut(terminated(
+            map_opt(long_string_body, unescape),
+            tag("'''"),
+        )),
+    );
+    map(many1(preceded(ws, long_string)), |parts| parts.concat())(input)
+}
+
+fn long_string_body(input: &str) -> IResult<&str, &str> {
+    let mut escaped = false;
+    for (i, c) in input.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '\'' if input[i..].starts_with("'''") => return Ok((&input[i..], &input[..i])),
+            _ => {}
+        }
+    }
+    Err(failure(input))
+}
+
+fn failure(input: &str) -> nom::Err<nom::error::Error<&str>> {
+    nom::Err::Failure(nom::error::Error::new(input, nom::error::ErrorKind::Escaped))
+}
+
+fn unescape(raw: &str) -> Option<String> {
+    let mut out = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        let c = match chars.next()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            'a' => '\x07',
+            'b' => '\x08',
+            'f' => '\x0c',
+            'v' => '\x0b',
+            // An escaped line break continues the line.
+            '\n' => continue,
+            '\r' => {
+                if chars.as_str().starts_with('\n') {
+                    chars.next();
+                }
+                continue;
+            }
+            'x' => hex_char(&mut chars, 2)?,
+            'u' => hex_char(&mut chars, 4)?,
+            'U' => hex_char(&mut chars, 8)?,
+            c => c,
+        };
+        out.push(c);
+    }
+    Some(out)
+}
+
+fn hex_char(chars: &mut std::str::Chars, len: usize) -> Option<char> {
+    let hex = chars.as_str().get(..len)?;
+    let code = u32::from_str_radix(hex, 16).ok()?;
+    for _ in 0..len {
+        chars.next();
+    }
+    std::char::from_u32(code)
+}
+
+fn lob(input: &str) -> IResult<&str, PqlValue> {
+    delimited(
+        tag("{{"),
+        cut(delimited(
+            ws,
+            alt((
+                map(alt((long_strings, short_string)), |s| {
+                    PqlValue::Clob(s.chars().map(|c| c as u8).collect())
+                }),
+                map_opt(
+                    take_while(|c: char| {
+                        c.is_ascii_alphanumeric() || "+/=".contains(c) || c.is_whitespace()
+                    }),
+                    |s: &str| {
+                        let s = s.split_whitespace().collect::<String>();
+                        base64::decode(s).ok().map(PqlValue::Blob)
+                    },
+                ),
+            )),
+            ws,
+        )),
+        tag("}}"),
+    )(input)
+}
+
+fn structure(input: &str) -> IResult<&str, PqlValue> {
+    let field = tuple((
+        preceded(ws, alt((long_strings, short_string, symbol_text))),
+        preceded(ws, char(':')),
+        value,
+    ));
+    map(
+        preceded(
+            char('{'),
+            cut(terminated(
+                terminated(
+                    separated_list0(preceded(ws, char(',')), field),
+                    opt(preceded(ws, char(','))),
+                ),
+                preceded(ws, char('}')),
+            )),
+        ),
+        |fields| {
+            PqlValue::Object(
+                fields
+                    .into_iter()
+                    .map(|(key, _, value)| (key, value))
+                    .collect(),
+            )
+        },
+    )(input)
+}
+
+fn list(input: &str) -> IResult<&str, PqlValue> {
+    map(
+        preceded(
+            char('['),
+            cut(terminated(
+                terminated(
+                    separated_list0(preceded(ws, char(',')), value),
+                    opt(preceded(ws, char(','))),
+                ),
+                preceded(ws, char(']')),
+            )),
+        ),
+        PqlValue::Array,
+    )(input)
+}
+
+fn sexp(input: &str) -> IResult<&str, PqlValue> {
+    let operator = map(
+        take_while1(|c: char| "!#%&*+-./;<=>?@^`|~".contains(c)),
+        |s: &str| PqlValue::Symbol(s.to_owned()),
+    );
+    map(
+        preceded(
+            char('('),
+            cut(terminated(
+                many0(preceded(ws, alt((value, operator)))),
+                preceded(ws, char(')')),
+            )),
+        ),
+        PqlValue::Array,
+    )(input)
+}
+
+fn write_value(out: &mut String, value: &PqlValue, indent: Option<usize>) {
+    match value {
+        PqlValue::Missing => out.push_str("$partiql_missing::null"),
+        PqlValue::Null => out.push_str("null"),
+        PqlValue::Str(s) => write_string(out, s),
+        PqlValue::Boolean(b) => out.push_str(&b.to_string()),
+        PqlValue::Int(i) => out.push_str(&i.to_string()),
+        PqlValue::Float(OrderedFloat(f)) if f.is_nan() => out.push_str("nan"),
+        PqlValue::Float(OrderedFloat(f)) if f.is_infinite() => {
+            out.push_str(if *f > 0. { "+inf" } else { "-inf" })
+        }
+        PqlValue::Float(OrderedFloat(f)) => out.push_str(&format!("{:e}", f)),
+        PqlValue::Decimal(d) => match d.as_bigint_and_exponent() {
+            (_, scale) if scale > 0 => out.push_str(&d.to_string()),
+            // Integers too large for i64 are kept as decimals with no fraction; write them back as ints.
+            (int, 0) if d.to_i64().is_none() => out.push_str(&int.to_string()),
+            (int, scale) => out.push_str(&format!("{}d{}", int, -scale)),
+        },
+        PqlValue::DateTime(datetime) => out.push_str(&datetime::format_timestamp(datetime)),
+        PqlValue::Date(date) => out.push_str(&date.to_string()),
+        PqlValue::Time(time) => {
+            out.push_str("$partiql_time::");
+            write_string(out, &time.to_string());
+        }
+        PqlValue::Symbol(s) => write_symbol(out, s),
+        PqlValue::Blob(bytes) => out.push_str(&format!("{{{{{}}}}}", base64::encode(bytes))),
+        PqlValue::Clob(bytes) => {
+            out.push_str("{{\"");
+            for &b in bytes {
+                match b {
+                    b'"' | b'\\' => out.push_str(&format!("\\{}", b as char)),
+                    0x20..=0x7e => out.push(b as char),
+                    _ => out.push_str(&format!("\\x{:02x}", b)),
+                }
+            }
+            out.push_str("\"}}");
+        }
+        PqlValue::Annotated(annotations, value) => {
+            for annotation in annotations {
+                write_symbol(out, annotation);
+                out.push_str("::");
+            }
+            write_value(out, value, indent);
+        }
+        PqlValue::Array(array) => {
+            write_container(out, '[', ']', array.iter(), indent, |out, v, indent| {
+                write_value(out, v, indent)
+            })
+        }
+        PqlValue::Object(map) => {
+            let fields = map.iter().filter(|(_, v)| **v != PqlValue::Missing);
+            write_container(out, '{', '}', fields, indent, |out, (k, v), indent| {
+                write_symbol(out, k);
+                out.push_str(if indent.is_some() { ": " } else { ":" });
+                write_value(out, v, indent)
+            })
+        }
+    }
+}
+
+fn write_container<T>(
+    out: &mut String,
+    open: char,
+    close: char,
+    items: impl Iterator<Item = T>,
+    indent: Option<usize>,
+    write_item: impl Fn(&mut String, T, Option<usize>),
+) {
+    out.push(open);
+    let mut empty = true;
+    for (i, item) in items.enumerate() {
+        empty = false;
+        if i > 0 {
+            out.push(',');
+        }
+        if let Some(indent) = indent {
+            out.push('\n');
+            out.push_str(&"  ".repeat(indent + 1));
+        }
+        write_item(out, item, indent.map(|indent| indent + 1));
+    }
+    if let (Some(indent), false) = (indent, empty) {
+        out.push('\n');
+        out.push_str(&"  ".repeat(indent));
+    }
+    out.push(close);
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('"');
+    write_escaped(out, s, '"');
+    out.push('"');
+}
+
+fn write_symbol(out: &mut String, s: &str) {
+    let is_identifier = matches!(identifier(s), Ok(("", _)))
+        && !matches!(s, "null" | "true" | "false" | "nan");
+    if is_identifier {
+        out.push_str(s);
+    } else {
+        out.push('\'');
+        write_escaped(out, s, '\'');
+        out.push('\'');
+    }
+}
+
+fn write_escaped(out: &mut String, s: &str, quote: char) {
+    for c in s.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            _ if c == quote => {
+                out.push('\\');
+                out.push(c);
+            }
+            _ if c.is_control() => out.push_str(&format!("\\u{:04x}", c as u32)),
+            _ => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+    use indexmap::IndexMap as Map;
+
+    use super::{from_str, to_string};
+    use crate::value::datetime;
+    use crate::value::PqlValue;
+
+    #[test]
+    fn reads_ion_types() -> anyhow::Result<()> {
+        let value = from_str(
+            r#"
+// a comment
+{
+  price: 19.99,
+  qty: 3,
+  ratio: 1.5e0,
+  big: 0x7fff_ffff_ffff_ffff_ff,
+  at: 2021-07-01T09:30:00.5+09:00,
+  on: 2021-07-01T,
+  status: active,
+  'quoted key': "a\tb",
+  long: '''one ''' /* joined */ '''two''',
+  data: {{ aGVsbG8= }},
+  text: {{ "raw" }},
+  temp: celsius::21.5,
+  gone: $partiql_missing::null,
+  nothing: null.int,
+  sexp: (+ 1 2),
+}
+"#,
+        )?;
+
+        let mut expected = Map::new();
+        let d = |s: &str| PqlValue::Decimal(BigDecimal::from_str(s).unwrap());
+        expected.insert("price".to_owned(), d("19.99"));
+        expected.insert("qty".to_owned(), PqlValue::from(3));
+        expected.insert("ratio".to_owned(), PqlValue::from(1.5));
+        expected.insert("big".to_owned(), d("2361183241434822606847"));
+        expected.insert(
+            "at".to_owned(),
+            PqlValue::DateTime(datetime::parse_timestamp("2021-07-01T09:30:00.5+09:00")?),
+        );
+        expected.insert(
+            "on".to_owned(),
+            PqlValue::Date(datetime::parse_date("2021-07-01")?),
+        );
+        expected.insert("status".to_owned(), PqlValue::Symbol("active".to_owned()));
+        expected.insert("quoted key".to_owned(), PqlValue::from("a\tb"));
+        expected.insert("long".to_owned(), PqlValue::from("one two"));
+        expected.insert("data".to_owned(), PqlValue::Blob(b"hello".to_vec()));
+        expected.insert("text".to_owned(), PqlValue::Clob(b"raw".to_vec()));
+        expected.insert(
+            "temp".to_owned(),
+            PqlValue::Annotated(vec!["celsius".to_owned()], Box::new(d("21.5"))),
+        );
+        expected.insert("gone".to_owned(), PqlValue::Missing);
+        expected.insert("nothing".to_owned(), PqlValue::Null);
+        expected.insert(
+            "sexp".to_owned(),
+            PqlValue::Array(vec![
+                PqlValue::Symbol("+".to_owned()),
+                PqlValue::from(1),
+                PqlValue::from(2),
+            ]),
+        );
+        assert_eq!(value, PqlValue::Object(expected));
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() -> anyhow::Result<()> {
+        let input = r#"{a:1,b:[123456789012345678901234567890,19.99,5d0,1.5e0,"x\"y"],c:'two words'::sym,d:{{aGVsbG8=}},e:{{"r\x00w"}},f:2021-07-01T09:30:00.500+09:00,g:2021-07-01,h:$partiql_time::"09:30:00",i:[$partiql_missing::null,nan,-inf]}"#;
+        let value = from_str(input)?;
+        assert_eq!(to_string(&value, true), input);
+        assert_eq!(from_str(&to_string(&value, false))?, value);
+        Ok(())
+    }
+
+    #[test]
+    fn top_level_stream_is_array() -> anyhow::Result<()> {
+        assert_eq!(
+            from_str("1 2 3")?,
+            PqlValue::Array(vec![PqlValue::from(1), PqlValue::from(2), PqlValue::from(3)])
+        );
+        assert!(from_str("{a:").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_decimals_too_large_to_write() -> anyhow::Result<()> {
+        assert_eq!(
+            from_str("1d400")?,
+            PqlValue::Decimal(BigDecimal::from_str("1e400")?)
+        );
+        assert!(from_str("1d99999999999").is_err());
+        assert!(from_str("1d-99999999999").is_err());
+        Ok(())
+    }
+}
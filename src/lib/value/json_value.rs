@@ -1,10 +1,13 @@
 use std::collections::{BTreeMap, BTreeSet};
+use std::str::FromStr;
 
+use bigdecimal::BigDecimal;
 use indexmap::IndexMap as Map;
 use ordered_float::OrderedFloat;
 use serde_derive::{Deserialize, Serialize};
 
-use crate::value::PqlValue;
+use crate::value::datetime::format_timestamp;
+use crate::value::{bounded_decimal, decimal_to_f64, PqlValue};
 
 #[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 #[serde(untagged)]
@@ -65,7 +68,14 @@ impl From<PqlValue> for JsonValue {
             PqlValue::Boolean(boolean) => Self::Boolean(boolean),
             PqlValue::Float(float) => Self::Num(float),
             PqlValue::Int(int) => Self::Num(OrderedFloat(int as f64)),
-            PqlValue::DateTime(datetime) => Self::Str(datetime.to_rfc3339()),
+            PqlValue::Decimal(decimal) => Self::Num(OrderedFloat(decimal_to_f64(&decimal))),
+            PqlValue::DateTime(datetime) => Self::Str(format_timestamp(&datetime)),
+            PqlValue::Date(date) => Self::Str(date.to_string()),
+            PqlValue::Time(time) => Self::Str(time.to_string()),
+            PqlValue::Symbol(symbol) => Self::Str(symbol),
+            PqlValue::Blob(bytes) => Self::Str(base64::encode(bytes)),
+            PqlValue::Clob(bytes) => Self::Str(String::from_utf8_lossy(&bytes).into_owned()),
+            PqlValue::Annotated(_, value) => Self::from(*value),
             PqlValue::Array(array) => {
                 Self::Array(array.into_iter().map(Self::from).collect::<Vec<_>>())
             }
@@ -78,22 +88,78 @@ impl From<PqlValue> for JsonValue {
     }
 }
 
-pub fn to_pqlvalue(json: serde_json::value::Value) -> PqlValue {
-    match json {
+/// Converts parsed JSON. A number too large for a float is kept as an exact decimal, and is an
+/// error if its exponent is too large to be written out.
+pub fn to_pqlvalue(json: serde_json::value::Value) -> anyhow::Result<PqlValue> {
+    convert(json, false)
+}
+
+/// Same as `to_pqlvalue`, but non-integer numbers become exact `PqlValue::Decimal`s
+/// built from their textual representation, so no precision is lost.
+pub fn to_pqlvalue_as_decimal(json: serde_json::value::Value) -> anyhow::Result<PqlValue> {
+    convert(json, true)
+}
+
+fn convert(json: serde_json::value::Value, as_decimal: bool) -> anyhow::Result<PqlValue> {
+    Ok(match json {
         serde_json::value::Value::Null => PqlValue::Null,
         serde_json::value::Value::String(string) => PqlValue::Str(string),
         serde_json::value::Value::Bool(boolean) => PqlValue::Boolean(boolean),
-        serde_json::value::Value::Number(number) if number.is_f64() => {
+        serde_json::value::Value::Number(number) if number.is_f64() && !as_decimal => {
             PqlValue::Float(OrderedFloat(number.as_f64().unwrap()))
         }
-        serde_json::value::Value::Number(number) => PqlValue::Int(number.as_i64().unwrap()),
-        serde_json::value::Value::Array(array) => {
-            PqlValue::Array(array.into_iter().map(to_pqlvalue).collect::<Vec<_>>())
-        }
+        serde_json::value::Value::Number(number) => match number.as_i64() {
+            Some(int) => PqlValue::Int(int),
+            // Integers beyond i64, and numbers beyond a float, are kept exactly as decimals.
+            None => PqlValue::Decimal(bounded_decimal(BigDecimal::from_str(&number.to_string())?)?),
+        },
+        serde_json::value::Value::Array(array) => PqlValue::Array(
+            array
+                .into_iter()
+                .map(|v| convert(v, as_decimal))
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        ),
         serde_json::value::Value::Object(map) => PqlValue::Object(
             map.into_iter()
-                .map(|(k, v)| (k, to_pqlvalue(v)))
-                .collect::<Map<_, _>>(),
+                .map(|(k, v)| Ok((k, convert(v, as_decimal)?)))
+                .collect::<anyhow::Result<Map<_, _>>>()?,
+        ),
+    })
+}
+
+/// Converts back to a `serde_json::value::Value` for output.
+/// Decimals are written with all of their digits; MISSING fields are dropped.
+pub fn from_pqlvalue(pqlv: PqlValue) -> serde_json::value::Value {
+    match pqlv {
+        PqlValue::Missing => serde_json::value::Value::Null,
+        PqlValue::Null => serde_json::value::Value::Null,
+        PqlValue::Str(string) => serde_json::value::Value::String(string),
+        PqlValue::Boolean(boolean) => serde_json::value::Value::Bool(boolean),
+        PqlValue::Int(int) => serde_json::value::Value::from(int),
+        PqlValue::Float(OrderedFloat(float)) => serde_json::value::Value::from(float),
+        PqlValue::Decimal(decimal) => serde_json::Number::from_str(&decimal.to_string())
+            .map(serde_json::value::Value::Number)
+            .unwrap_or(serde_json::value::Value::Null),
+        PqlValue::DateTime(datetime) => {
+            serde_json::value::Value::String(format_timestamp(&datetime))
+        }
+        PqlValue::Date(date) => serde_json::value::Value::String(date.to_string()),
+        PqlValue::Time(time) => serde_json::value::Value::String(time.to_string()),
+        PqlValue::Symbol(symbol) => serde_json::value::Value::String(symbol),
+        // JSON has no binary type, so blobs are written as base64 text.
+        PqlValue::Blob(bytes) => serde_json::value::Value::String(base64::encode(bytes)),
+        PqlValue::Clob(bytes) => {
+            serde_json::value::Value::String(String::from_utf8_lossy(&bytes).into_owned())
+        }
+        PqlValue::Annotated(_, value) => from_pqlvalue(*value),
+        PqlValue::Array(array) => serde_json::value::Value::Array(
+            array.into_iter().map(from_pqlvalue).collect::<Vec<_>>(),
+        ),
+        PqlValue::Object(map) => serde_json::value::Value::Object(
+            map.into_iter()
+                .filter(|(_, v)| v != &PqlValue::Missing)
+                .map(|(k, v)| (k, from_pqlvalue(v)))
+                .collect(),
         ),
     }
 }
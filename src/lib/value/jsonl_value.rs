@@ -0,0 +1,77 @@
+//! JSON Lines: one JSON value per line, read as the rows of a bag.
+//!
+//! `rows` reads lazily from any `BufRead`, so that `planner::evaluate_rows` can run over
+//! an unbounded stream such as `tail -f app.log`.
+
+use std::io::BufRead;
+
+use crate::value::json_value;
+use crate::value::PqlValue;
+
+/// Parses one line, or returns `None` for a blank one.
+pub fn from_line(line: &str) -> anyhow::Result<Option<PqlValue>> {
+    if line.trim().is_empty() {
+        return Ok(None);
+    }
+    let json = serde_json::from_str::<serde_json::value::Value>(line)?;
+    Ok(Some(json_value::to_pqlvalue(json)?))
+}
+
+/// Reads rows one line at a time. Errors carry the line number they occurred on.
+pub fn rows<R: BufRead>(reader: R) -> impl Iterator<Item = anyhow::Result<PqlValue>> {
+    reader.lines().enumerate().filter_map(|(i, line)| {
+        let row = line
+            .map_err(anyhow::Error::from)
+            .and_then(|line| from_line(&line));
+        match row {
+            Ok(row) => row.map(Ok),
+            Err(err) => Some(Err(err.context(format!("line {}", i + 1)))),
+        }
+    })
+}
+
+/// Parses a whole JSON Lines text into a bag of its rows.
+pub fn from_str(input: &str) -> anyhow::Result<PqlValue> {
+    let rows = rows(input.as_bytes()).collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(PqlValue::bag(rows))
+}
+
+pub fn to_line(value: &PqlValue) -> anyhow::Result<String> {
+    Ok(value.to_jsonc()?)
+}
+
+/// Writes each row of an array or bag on its own line. Any other value takes a single line.
+pub fn to_string(value: &PqlValue) -> anyhow::Result<String> {
+    match value.to_owned().unbag() {
+        PqlValue::Array(rows) => Ok(rows
+            .iter()
+            .map(to_line)
+            .collect::<anyhow::Result<Vec<_>>>()?
+            .join("\n")),
+        value => to_line(&value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_str, rows, to_string};
+    use crate::value::PqlValue;
+
+    #[test]
+    fn reads_rows_as_bag() -> anyhow::Result<()> {
+        let input = "{\"a\":1}\n\n{\"a\":[2,3]}\n";
+        let data = from_str(input)?;
+        assert!(data.is_bag());
+        assert_eq!(to_string(&data)?, "{\"a\":1}\n{\"a\":[2,3]}");
+        assert_eq!(to_string(&PqlValue::from(1))?, "1");
+        Ok(())
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let mut rows = rows("{\"a\":1}\n{\"a\":\n".as_bytes());
+        assert!(rows.next().unwrap().is_ok());
+        let err = rows.next().unwrap().unwrap_err();
+        assert!(format!("{:#}", err).starts_with("line 2: "), "{:#}", err);
+    }
+}
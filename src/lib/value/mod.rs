@@ -1,12 +1,29 @@
+#[cfg(feature = "columnar")]
+pub mod arrow_value;
+pub mod cbor_value;
+pub mod csv_value;
+pub mod datetime;
+pub mod ion_binary;
+pub mod ion_value;
 pub mod json_value;
+pub mod jsonl_value;
+pub mod msgpack_value;
+#[cfg(feature = "columnar")]
+pub mod parquet_value;
+pub mod partiql_value;
 mod pql_value;
 mod pql_vector;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_value;
 
 #[cfg(feature = "table")]
 pub mod table;
-mod toml_value;
+pub mod tabular_value;
+pub mod toml_value;
+pub mod xml_value;
+pub mod yaml_value;
 
 pub use json_value::{BJsonValue, JsonValue};
 pub use pql_value::{BPqlValue, PqlValue};
+pub(crate) use pql_value::{bounded_decimal, decimal_to_f64, float_to_decimal};
 pub use pql_vector::PqlVector;
-pub use toml_value::TomlValue;
@@ -0,0 +1,177 @@
+//! MessagePack, read into and written from `PqlValue` through `rmpv::Value`.
+//!
+//! Timestamps use the standard timestamp extension (type -1), which has no offset,
+//! so they are read back in UTC. A stream of several top-level values is read as a bag.
+
+use std::convert::{TryFrom, TryInto};
+
+use bigdecimal::BigDecimal;
+use chrono::prelude::*;
+use indexmap::IndexMap as Map;
+use num_traits::ToPrimitive;
+use ordered_float::OrderedFloat;
+use rmpv::Value;
+
+use crate::value::{decimal_to_f64, PqlValue};
+
+const TIMESTAMP: i8 = -1;
+
+pub fn from_bytes(input: &[u8]) -> anyhow::Result<PqlValue> {
+    let mut rest = input;
+    let mut values = vec![];
+    while !rest.is_empty() {
+        let value = rmpv::decode::read_value(&mut rest)?;
+        values.push(to_pqlvalue(value)?);
+    }
+    match values.len() {
+        1 => Ok(values.remove(0)),
+        _ => Ok(PqlValue::bag(values)),
+    }
+}
+
+pub fn to_bytes(value: &PqlValue) -> anyhow::Result<Vec<u8>> {
+    let mut buf = Vec::new();
+    rmpv::encode::write_value(&mut buf, &from_pqlvalue(value.to_owned()))?;
+    Ok(buf)
+}
+
+pub fn to_pqlvalue(value: Value) -> anyhow::Result<PqlValue> {
+    let pqlv = match value {
+        Value::Nil => PqlValue::Null,
+        Value::Boolean(boolean) => PqlValue::Boolean(boolean),
+        Value::Integer(int) => match (int.as_i64(), int.as_u64()) {
+            (Some(int), _) => PqlValue::Int(int),
+            (None, Some(uint)) => PqlValue::Decimal(BigDecimal::from(uint)),
+            _ => unreachable!(),
+        },
+        Value::F32(float) => PqlValue::Float(OrderedFloat(float as f64)),
+        Value::F64(float) => PqlValue::Float(OrderedFloat(float)),
+        Value::String(string) if string.is_str() => {
+            PqlValue::Str(string.into_str().unwrap_or_default())
+        }
+        // Strings that are not valid UTF-8 are kept as bytes.
+        Value::String(string) => PqlValue::Clob(string.into_bytes()),
+        Value::Binary(bytes) => PqlValue::Blob(bytes),
+        Value::Ext(TIMESTAMP, bytes) => PqlValue::DateTime(timestamp(&bytes)?),
+        Value::Ext(_, bytes) => PqlValue::Blob(bytes),
+        Value::Array(array) => PqlValue::Array(
+            array
+                .into_iter()
+                .map(to_pqlvalue)
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        ),
+        Value::Map(map) => PqlValue::Object(
+            map.into_iter()
+                .map(|(k, v)| {
+                    let key = match k {
+                        Value::String(string) => String::from_utf8_lossy(string.as_bytes()).into(),
+                        key => key.to_string(),
+                    };
+                    Ok((key, to_pqlvalue(v)?))
+                })
+                .collect::<anyhow::Result<Map<_, _>>>()?,
+        ),
+    };
+    Ok(pqlv)
+}
+
+/// Converts to a `rmpv::Value` for output. MISSING fields are dropped.
+pub fn from_pqlvalue(pqlv: PqlValue) -> Value {
+    match pqlv {
+        PqlValue::Missing | PqlValue::Null => Value::Nil,
+        PqlValue::Str(string) | PqlValue::Symbol(string) => Value::from(string),
+        PqlValue::Boolean(boolean) => Value::Boolean(boolean),
+        PqlValue::Int(int) => Value::from(int),
+        PqlValue::Float(OrderedFloat(float)) => Value::F64(float),
+        // MessagePack has no decimal type: integers are kept when they fit, others become floats.
+        PqlValue::Decimal(decimal) => {
+            match (decimal.is_integer(), decimal.to_i64(), decimal.to_u64()) {
+                (true, Some(int), _) => Value::from(int),
+                (true, None, Some(uint)) => Value::from(uint),
+                _ => Value::F64(decimal_to_f64(&decimal)),
+            }
+        }
+        PqlValue::DateTime(datetime) => Value::Ext(TIMESTAMP, timestamp_bytes(&datetime)),
+        PqlValue::Date(date) => Value::from(date.to_string()),
+        PqlValue::Time(time) => Value::from(time.to_string()),
+        PqlValue::Blob(bytes) | PqlValue::Clob(bytes) => Value::Binary(bytes),
+        PqlValue::Annotated(_, value) => from_pqlvalue(*value),
+        PqlValue::Array(array) => Value::Array(array.into_iter().map(from_pqlvalue).collect()),
+        PqlValue::Object(map) => Value::Map(
+            map.into_iter()
+                .filter(|(_, v)| v != &PqlValue::Missing)
+                .map(|(k, v)| (Value::from(k), from_pqlvalue(v)))
+                .collect(),
+        ),
+    }
+}
+
+// The timestamp extension comes in 32, 64 and 96 bit forms.
+fn timestamp(bytes: &[u8]) -> anyhow::Result<DateTime<FixedOffset>> {
+    let (secs, nsecs) = match bytes.len() {
+        4 => (i64::from(u32::from_be_bytes(bytes.try_into()?)), 0),
+        8 => {
+            let data = u64::from_be_bytes(bytes.try_into()?);
+            ((data & 0x3_ffff_ffff) as i64, (data >> 34) as u32)
+        }
+        12 => {
+            let nsecs = u32::from_be_bytes(bytes[..4].try_into()?);
+            (i64::from_be_bytes(bytes[4..].try_into()?), nsecs)
+        }
+        len => anyhow::bail!("invalid timestamp extension of {} bytes", len),
+    };
+    let datetime = Utc
+        .timestamp_opt(secs, nsecs)
+        .single()
+        .ok_or_else(|| anyhow::anyhow!("timestamp out of range"))?;
+    Ok(datetime.with_timezone(&Utc.fix()))
+}
+
+fn timestamp_bytes(datetime: &DateTime<FixedOffset>) -> Vec<u8> {
+    let (secs, nsecs) = (datetime.timestamp(), datetime.timestamp_subsec_nanos());
+    match u32::try_from(secs) {
+        Ok(secs) if nsecs == 0 => secs.to_be_bytes().to_vec(),
+        _ if secs >> 34 == 0 => ((u64::from(nsecs) << 34) | secs as u64)
+            .to_be_bytes()
+            .to_vec(),
+        _ => [&nsecs.to_be_bytes()[..], &secs.to_be_bytes()[..]].concat(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_bytes, to_bytes};
+    use crate::lang::Lang;
+    use crate::value::partiql_value;
+    use crate::value::PqlValue;
+
+    #[test]
+    fn round_trip() -> anyhow::Result<()> {
+        let data = partiql_value::from_str(
+            r#"{
+                'id': 1, 'big': 18446744073709551615, 'price': 1.5e0, 'name': 'apple', 'ok': true, 'none': null,
+                'at': TIMESTAMP '2021-07-01T09:30:00.25+09:00', 'epoch': TIMESTAMP '1970-01-01T00:00:01Z',
+                'far': TIMESTAMP '2600-01-01T00:00:00Z', 'tags': ['a', 2], 'raw': `{{AAEC}}`
+            }"#,
+        )?;
+        let bytes = to_bytes(&data)?;
+        assert_eq!(from_bytes(&bytes)?, data);
+        Ok(())
+    }
+
+    #[test]
+    fn reads_messages() -> anyhow::Result<()> {
+        // {"a": 1} followed by [true, nil]
+        let bytes = [0x81, 0xa1, b'a', 0x01, 0x92, 0xc3, 0xc0];
+        let expected = PqlValue::bag(vec![
+            Lang::from_as_json(r#"{ "a": 1 }"#)?.data,
+            Lang::from_as_json("[true, null]")?.data,
+        ]);
+        assert_eq!(from_bytes(&bytes)?, expected);
+        assert_eq!(
+            to_bytes(&Lang::from_as_json(r#"{ "a": 1 }"#)?.data)?,
+            bytes[..4]
+        );
+        Ok(())
+    }
+}
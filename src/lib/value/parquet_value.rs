@@ -0,0 +1,63 @@
+//! Apache Parquet files, read into and written from rows of `PqlValue` through Arrow record batches.
+
+use std::collections::HashSet;
+
+use bytes::Bytes;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::{ArrowWriter, ProjectionMask};
+
+use crate::value::arrow_value;
+use crate::value::PqlValue;
+
+/// The magic bytes a Parquet file starts with.
+pub const MAGIC: [u8; 4] = *b"PAR1";
+
+/// Reads a Parquet file. Only the `columns` given are read from disk, or all of them for `None`.
+pub fn from_bytes(input: &[u8], columns: Option<&HashSet<String>>) -> anyhow::Result<PqlValue> {
+    let mut builder = ParquetRecordBatchReaderBuilder::try_new(Bytes::copy_from_slice(input))?;
+    if let Some(columns) = columns {
+        let projection = arrow_value::projection(builder.schema(), columns);
+        let mask = ProjectionMask::roots(builder.parquet_schema(), projection);
+        builder = builder.with_projection(mask);
+    }
+    let batches = builder.build()?.collect::<Result<Vec<_>, _>>()?;
+    Ok(PqlValue::bag(arrow_value::from_batches(&batches)))
+}
+
+/// Writes rows as a Parquet file with an inferred schema.
+pub fn to_bytes(value: &PqlValue) -> anyhow::Result<Vec<u8>> {
+    let batch = arrow_value::to_batch(value)?;
+    let mut buf = Vec::new();
+    let mut writer = ArrowWriter::try_new(&mut buf, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{from_bytes, to_bytes, MAGIC};
+    use crate::value::partiql_value;
+
+    #[test]
+    fn round_trip() -> anyhow::Result<()> {
+        let data = partiql_value::from_str(
+            r#"<<
+                { 'id': 1, 'price': 1.50, 'at': TIMESTAMP '2021-07-01T09:30:00Z', 'tags': ['a'], 'none': null },
+                { 'id': 2, 'price': 20.25, 'at': TIMESTAMP '2021-12-24T18:00:00+09:00', 'tags': [], 'none': null }
+            >>"#,
+        )?;
+        let bytes = to_bytes(&data)?;
+        assert!(bytes.starts_with(&MAGIC));
+        assert_eq!(from_bytes(&bytes, None)?, data);
+
+        let columns = vec!["price".to_owned()].into_iter().collect::<HashSet<_>>();
+        assert_eq!(
+            from_bytes(&bytes, Some(&columns))?,
+            partiql_value::from_str("<< { 'price': 1.50 }, { 'price': 20.25 } >>")?
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,222 @@
+//! PartiQL value text, the notation of `samples/*.env`, read into and written from `PqlValue`.
+//!
+//! Bags are written as `<< >>` and MISSING as `missing`. Values PartiQL has no literal for,
+//! such as symbols, lobs, NaN or other annotated values, are written as Ion between backticks.
+
+use bigdecimal::ToPrimitive;
+use ordered_float::OrderedFloat;
+
+use crate::value::datetime;
+use crate::value::ion_value;
+use crate::value::PqlValue;
+
+/// Parses PartiQL value text, keeping bags and telling INT, DECIMAL and FLOAT apart.
+pub fn from_str(input: &str) -> anyhow::Result<PqlValue> {
+    crate::pqlir_parser::pql_value_with_bags(input)
+}
+
+/// Writes PartiQL value text, indented unless `compact`.
+pub fn to_string(value: &PqlValue, compact: bool) -> String {
+    let mut out = String::new();
+    write_value(&mut out, value, if compact { None } else { Some(0) });
+    out
+}
+
+fn write_value(out: &mut String, value: &PqlValue, indent: Option<usize>) {
+    match value {
+        PqlValue::Missing => out.push_str("missing"),
+        PqlValue::Null => out.push_str("null"),
+        PqlValue::Str(s) => write_string(out, s),
+        PqlValue::Boolean(b) => out.push_str(&b.to_string()),
+        PqlValue::Int(i) => out.push_str(&i.to_string()),
+        PqlValue::Float(OrderedFloat(f)) if f.is_finite() => out.push_str(&format!("{:e}", f)),
+        PqlValue::Decimal(d) => match d.as_bigint_and_exponent() {
+            (_, scale) if scale > 0 => out.push_str(&d.to_string()),
+            (int, 0) if d.to_i64().is_none() => out.push_str(&int.to_string()),
+            // A trailing dot keeps a whole DECIMAL from being read back as an INT.
+            (int, 0) => out.push_str(&format!("{}.", int)),
+            _ => write_ion(out, value),
+        },
+        PqlValue::DateTime(datetime) => {
+            out.push_str("TIMESTAMP ");
+            write_string(out, &datetime::format_timestamp(datetime));
+        }
+        PqlValue::Date(date) => {
+            out.push_str("DATE ");
+            write_string(out, &date.to_string());
+        }
+        PqlValue::Time(time) => {
+            out.push_str(match time.offset {
+                Some(_) => "TIME WITH TIME ZONE ",
+                None => "TIME ",
+            });
+            write_string(out, &time.to_string());
+        }
+        PqlValue::Annotated(_, array) if value.is_bag() => {
+            if let PqlValue::Array(array) = array.as_ref() {
+                write_container(out, "<<", ">>", array.iter(), indent, write_value)
+            }
+        }
+        PqlValue::Array(array) => write_container(out, "[", "]", array.iter(), indent, write_value),
+        PqlValue::Object(map) => {
+            let fields = map.iter().filter(|(_, v)| **v != PqlValue::Missing);
+            write_container(out, "{", "}", fields, indent, |out, (k, v), indent| {
+                write_string(out, k);
+                out.push_str(if indent.is_some() { ": " } else { ":" });
+                write_value(out, v, indent)
+            })
+        }
+        PqlValue::Float(_)
+        | PqlValue::Symbol(_)
+        | PqlValue::Blob(_)
+        | PqlValue::Clob(_)
+        | PqlValue::Annotated(_, _) => write_ion(out, value),
+    }
+}
+
+fn write_container<T>(
+    out: &mut String,
+    open: &str,
+    close: &str,
+    items: impl Iterator<Item = T>,
+    indent: Option<usize>,
+    write_item: impl Fn(&mut String, T, Option<usize>),
+) {
+    out.push_str(open);
+    let mut empty = true;
+    for (i, item) in items.enumerate() {
+        empty = false;
+        if i > 0 {
+            out.push(',');
+        }
+        if let Some(indent) = indent {
+            out.push('\n');
+            out.push_str(&"  ".repeat(indent + 1));
+        }
+        write_item(out, item, indent.map(|indent| indent + 1));
+    }
+    if let (Some(indent), false) = (indent, empty) {
+        out.push('\n');
+        out.push_str(&"  ".repeat(indent));
+    }
+    out.push_str(close);
+}
+
+fn write_string(out: &mut String, s: &str) {
+    out.push('\'');
+    for c in s.chars() {
+        match c {
+            '\'' => out.push_str("''"),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            _ => out.push(c),
+        }
+    }
+    out.push('\'');
+}
+
+// Backticks can only appear in Ion strings and symbols, where they may be escaped.
+fn write_ion(out: &mut String, value: &PqlValue) {
+    out.push('`');
+    out.push_str(&ion_value::to_string(value, true).replace('`', "\\x60"));
+    out.push('`');
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bigdecimal::BigDecimal;
+    use indexmap::IndexMap as Map;
+    use ordered_float::OrderedFloat;
+
+    use super::{from_str, to_string};
+    use crate::value::datetime;
+    use crate::value::PqlValue;
+
+    #[test]
+    fn round_trip() -> anyhow::Result<()> {
+        let value = PqlValue::Object(
+            vec![
+                (
+                    "bag".to_owned(),
+                    PqlValue::bag(vec![
+                        PqlValue::Int(1),
+                        PqlValue::Missing,
+                        PqlValue::bag(vec![]),
+                    ]),
+                ),
+                ("null".to_owned(), PqlValue::Null),
+                ("str".to_owned(), PqlValue::from("it's a \\ \n 'quote'")),
+                ("bool".to_owned(), PqlValue::Boolean(false)),
+                ("int".to_owned(), PqlValue::Int(-42)),
+                ("float".to_owned(), PqlValue::Float(OrderedFloat(1.5))),
+                ("whole_float".to_owned(), PqlValue::Float(OrderedFloat(2.))),
+                ("nan".to_owned(), PqlValue::Float(OrderedFloat(f64::NAN))),
+                (
+                    "inf".to_owned(),
+                    PqlValue::Float(OrderedFloat(f64::NEG_INFINITY)),
+                ),
+                (
+                    "decimal".to_owned(),
+                    PqlValue::Decimal(BigDecimal::from_str("1.50")?),
+                ),
+                (
+                    "whole_decimal".to_owned(),
+                    PqlValue::Decimal(BigDecimal::from(5)),
+                ),
+                (
+                    "huge".to_owned(),
+                    PqlValue::Decimal(BigDecimal::from_str("123456789012345678901234567890")?),
+                ),
+                (
+                    "scaled".to_owned(),
+                    PqlValue::Decimal(BigDecimal::new(12.into(), -3)),
+                ),
+                (
+                    "timestamp".to_owned(),
+                    PqlValue::DateTime(datetime::parse_timestamp("2021-07-01T09:30:00.5+09:00")?),
+                ),
+                (
+                    "date".to_owned(),
+                    PqlValue::Date(datetime::parse_date("2021-07-01")?),
+                ),
+                ("time".to_owned(), PqlValue::Time("09:30:00".parse()?)),
+                (
+                    "time_tz".to_owned(),
+                    PqlValue::Time("09:30:00.25-05:00".parse()?),
+                ),
+                ("symbol".to_owned(), PqlValue::Symbol("a`b".to_owned())),
+                ("blob".to_owned(), PqlValue::Blob(vec![0, 1, 255])),
+                ("clob".to_owned(), PqlValue::Clob(b"text".to_vec())),
+                (
+                    "annotated".to_owned(),
+                    PqlValue::Annotated(vec!["unit".to_owned()], Box::new(PqlValue::Int(3))),
+                ),
+                ("array".to_owned(), PqlValue::Array(vec![PqlValue::Null])),
+                ("object".to_owned(), PqlValue::Object(Map::new())),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        for compact in [false, true] {
+            let text = to_string(&value, compact);
+            assert_eq!(from_str(&text)?, value, "{}", text);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn writes_samples_layout() -> anyhow::Result<()> {
+        let value = from_str("<< { 'id': 4, 'title': 'Dev Mgr', 'gone': missing } >>")?;
+        assert_eq!(
+            to_string(&value, false),
+            "<<\n  {\n    'id': 4,\n    'title': 'Dev Mgr'\n  }\n>>"
+        );
+        assert_eq!(to_string(&value, true), "<<{'id':4,'title':'Dev Mgr'}>>");
+        Ok(())
+    }
+}
@@ -1,21 +1,24 @@
 use std::cmp::PartialOrd;
-use std::collections::{BTreeMap, BTreeSet};
+use std::collections::BTreeMap;
 use std::convert::TryFrom;
 use std::ops::{Add, Div, Mul, Neg, Rem, Sub};
 use std::str::FromStr;
 
+use bigdecimal::BigDecimal;
 use chrono::prelude::*;
-use chrono::serde::ts_seconds;
 use indexmap::IndexMap as Map;
+use num_traits::{ToPrimitive, Zero};
 use ordered_float::OrderedFloat;
 use rayon::prelude::*;
+use serde::Serializer;
 use serde_derive::{Deserialize, Serialize};
 
+use crate::sql::EvalError;
 use crate::sql::Selector;
 use crate::sql::SelectorNode;
-use crate::value::PqlVector;
+use crate::value::datetime::{serialize_timestamp, PqlTime};
 
-#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+#[derive(Debug, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 #[serde(untagged)]
 pub enum BPqlValue {
     #[serde(skip_serializing)]
@@ -24,10 +27,24 @@ pub enum BPqlValue {
     Str(String),
     Boolean(bool),
     Float(OrderedFloat<f64>),
+    #[serde(serialize_with = "serialize_decimal", skip_deserializing)]
+    Decimal(BigDecimal),
     Int(i64),
-    #[serde(with = "ts_seconds")]
-    DateTime(DateTime<Utc>),
-    Array(BTreeSet<Self>),
+    #[serde(serialize_with = "serialize_timestamp", skip_deserializing)]
+    DateTime(DateTime<FixedOffset>),
+    #[serde(skip_deserializing)]
+    Date(NaiveDate),
+    #[serde(skip_deserializing)]
+    Time(PqlTime),
+    #[serde(skip_deserializing)]
+    Symbol(String),
+    #[serde(serialize_with = "serialize_lob", skip_deserializing)]
+    Blob(Vec<u8>),
+    #[serde(serialize_with = "serialize_lob", skip_deserializing)]
+    Clob(Vec<u8>),
+    #[serde(serialize_with = "serialize_annotated", skip_deserializing)]
+    Annotated(Vec<String>, Box<Self>),
+    Array(Vec<Self>),
     Object(BTreeMap<String, Self>),
 }
 
@@ -40,9 +57,23 @@ impl From<PqlValue> for BPqlValue {
             PqlValue::Boolean(b) => Self::Boolean(b),
             PqlValue::Int(i) => Self::Int(i),
             PqlValue::Float(f) => Self::Float(f),
+            PqlValue::Decimal(d) => Self::Decimal(d),
             PqlValue::DateTime(t) => Self::DateTime(t),
-            PqlValue::Array(_) => todo!(),
-            PqlValue::Object(_) => todo!(),
+            PqlValue::Date(d) => Self::Date(d),
+            PqlValue::Time(t) => Self::Time(t),
+            PqlValue::Symbol(s) => Self::Symbol(s),
+            PqlValue::Blob(b) => Self::Blob(b),
+            PqlValue::Clob(c) => Self::Clob(c),
+            PqlValue::Annotated(annotations, value) => {
+                Self::Annotated(annotations, Box::new(Self::from(*value)))
+            }
+            PqlValue::Array(array) => Self::Array(array.into_iter().map(Self::from).collect()),
+            PqlValue::Object(object) => Self::Object(
+                object
+                    .into_iter()
+                    .map(|(key, value)| (key, Self::from(value)))
+                    .collect(),
+            ),
         }
     }
 }
@@ -57,12 +88,54 @@ pub enum PqlValue {
     Boolean(bool),
     Int(i64),
     Float(OrderedFloat<f64>),
-    #[serde(with = "ts_seconds")]
-    DateTime(DateTime<Utc>),
+    /// An exact, arbitrary-precision decimal, as `DECIMAL` in PartiQL/Ion.
+    #[serde(serialize_with = "serialize_decimal", skip_deserializing)]
+    Decimal(BigDecimal),
+    /// `TIMESTAMP`, keeping its original offset and sub-second precision.
+    #[serde(serialize_with = "serialize_timestamp", skip_deserializing)]
+    DateTime(DateTime<FixedOffset>),
+    #[serde(skip_deserializing)]
+    Date(NaiveDate),
+    #[serde(skip_deserializing)]
+    Time(PqlTime),
+    /// An Ion symbol, kept apart from strings so it is written back as a symbol.
+    #[serde(skip_deserializing)]
+    Symbol(String),
+    #[serde(serialize_with = "serialize_lob", skip_deserializing)]
+    Blob(Vec<u8>),
+    /// Character data of unknown encoding, as Ion `clob`.
+    #[serde(serialize_with = "serialize_lob", skip_deserializing)]
+    Clob(Vec<u8>),
+    /// A value with Ion annotations such as `celsius::21.5`. Paths look through the annotations.
+    #[serde(serialize_with = "serialize_annotated", skip_deserializing)]
+    Annotated(Vec<String>, Box<Self>),
     Array(Vec<Self>),
     Object(Map<String, Self>),
 }
 
+// Formats without an exact decimal type receive an integer when it fits, else the nearest float.
+// JSON output keeps every digit through `json_value::from_pqlvalue`.
+fn serialize_decimal<S: Serializer>(d: &BigDecimal, serializer: S) -> Result<S::Ok, S::Error> {
+    match (d.is_integer(), d.to_i64(), d.to_u64()) {
+        (true, Some(int), _) => serializer.serialize_i64(int),
+        (true, None, Some(uint)) => serializer.serialize_u64(uint),
+        _ => serializer.serialize_f64(decimal_to_f64(d)),
+    }
+}
+
+fn serialize_lob<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bytes(bytes)
+}
+
+// Formats without annotations receive the bare value.
+fn serialize_annotated<S: Serializer, T: serde::Serialize>(
+    _annotations: &[String],
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    value.serialize(serializer)
+}
+
 impl Default for PqlValue {
     fn default() -> Self {
         Self::Null
@@ -101,6 +174,12 @@ impl From<f64> for PqlValue {
     }
 }
 
+impl From<BigDecimal> for PqlValue {
+    fn from(d: BigDecimal) -> Self {
+        Self::Decimal(d)
+    }
+}
+
 impl From<Vec<PqlValue>> for PqlValue {
     fn from(v: Vec<PqlValue>) -> Self {
         Self::Array(v)
@@ -126,6 +205,7 @@ impl PqlValue {
     pub fn get(self, key: &str) -> Option<Self> {
         match self {
             Self::Object(map) => map.get(key).map(|v| v.to_owned()),
+            Self::Annotated(_, value) => value.get(key),
             _ => None,
         }
     }
@@ -151,6 +231,7 @@ impl PqlValue {
             (Self::Object(map), SelectorNode::String(key_s)) => {
                 map.get(&key_s).map(|v| v.to_owned())
             }
+            (Self::Annotated(_, value), _) => value.select_by_key(key),
             _ => None,
         }
     }
@@ -158,6 +239,7 @@ impl PqlValue {
     pub fn get_mut_by_selectornode(&mut self, node: &SelectorNode) -> Option<&mut Self> {
         match (self, node.to_owned()) {
             (Self::Object(map), SelectorNode::String(key_s)) => map.get_mut(&key_s),
+            (Self::Annotated(_, value), _) => value.get_mut_by_selectornode(node),
             _ => None,
         }
     }
@@ -205,11 +287,17 @@ impl PqlValue {
                     Self::Array(new_array)
                 }
             }
+            Self::Annotated(_, value) if !selector.data.is_empty() => {
+                value.select_by_selector(selector)
+            }
             _ => self.to_owned(),
         }
     }
 
     pub fn get_mut_by_selector(&mut self, selector: &Selector) -> Option<&mut Self> {
+        if selector.data.is_empty() {
+            return Some(self);
+        }
         match self {
             Self::Object(_map) => {
                 if let Some((key, tail)) = selector.split_first() {
@@ -241,6 +329,7 @@ impl PqlValue {
                     todo!()
                 }
             }
+            Self::Annotated(_, value) => value.get_mut_by_selector(selector),
             _ => Some(self),
         }
     }
@@ -262,11 +351,59 @@ impl PqlValue {
     }
 
     pub fn to_jsonp(&self) -> serde_json::Result<String> {
-        serde_json::to_string_pretty(self)
+        serde_json::to_string_pretty(&crate::value::json_value::from_pqlvalue(self.to_owned()))
     }
 
     pub fn to_jsonc(&self) -> serde_json::Result<String> {
-        serde_json::to_string(self)
+        serde_json::to_string(&crate::value::json_value::from_pqlvalue(self.to_owned()))
+    }
+
+    pub fn sort_keys(self) -> Self {
+        match self {
+            Self::Array(array) => Self::Array(array.into_iter().map(Self::sort_keys).collect()),
+            Self::Object(map) => {
+                let mut map = map
+                    .into_iter()
+                    .map(|(k, v)| (k, v.sort_keys()))
+                    .collect::<Map<_, _>>();
+                map.sort_keys();
+                Self::Object(map)
+            }
+            _ => self,
+        }
+    }
+
+    /// A bag, kept as an array annotated the way PartiQL writes bags in Ion.
+    pub fn bag(values: Vec<Self>) -> Self {
+        Self::Annotated(
+            vec![crate::value::ion_value::BAG.to_owned()],
+            Box::new(Self::Array(values)),
+        )
+    }
+
+    pub fn is_bag(&self) -> bool {
+        match self {
+            Self::Annotated(annotations, value) => {
+                annotations == &[crate::value::ion_value::BAG] && matches!(**value, Self::Array(_))
+            }
+            _ => false,
+        }
+    }
+
+    /// Turns every bag into a plain array.
+    pub fn unbag(self) -> Self {
+        let is_bag = self.is_bag();
+        match self {
+            Self::Annotated(_, value) if is_bag => value.unbag(),
+            Self::Annotated(annotations, value) => {
+                Self::Annotated(annotations, Box::new(value.unbag()))
+            }
+            Self::Array(array) => Self::Array(array.into_iter().map(Self::unbag).collect()),
+            Self::Object(map) => {
+                Self::Object(map.into_iter().map(|(k, v)| (k, v.unbag())).collect())
+            }
+            _ => self,
+        }
     }
 
     pub fn into_array(self) -> Self {
@@ -295,159 +432,255 @@ impl PqlValue {
 impl Neg for PqlValue {
     type Output = Self;
     fn neg(self) -> Self::Output {
-        match self {
-            Self::Int(a) => Self::Int(-a),
-            Self::Float(a) => Self::Float(-a),
-            _ => todo!(),
-        }
+        self.checked_neg().unwrap_or(Self::Missing)
     }
 }
 
 impl Add for PqlValue {
     type Output = Self;
     fn add(self, other: Self) -> Self::Output {
-        match (self, other) {
-            (Self::Int(a), Self::Int(b)) => Self::Int(a + b),
-            (Self::Int(a), Self::Float(b)) => Self::Float(OrderedFloat(a as f64) + b),
-            (Self::Float(a), Self::Int(b)) => Self::Float(a + OrderedFloat(b as f64)),
-            (Self::Float(a), Self::Float(b)) => Self::Float(a + b),
-            (Self::Array(array_a), Self::Array(array_b)) => {
-                let (vec_a, vec_b) = (PqlVector(array_a), PqlVector(array_b));
-                PqlValue::from(vec_a + vec_b)
-            }
-            (Self::Array(array), val) => {
-                let n = array.len();
-                let (vec_a, vec_b) = (PqlVector(array), PqlVector(vec![val; n]));
-                PqlValue::from(vec_a + vec_b)
-            }
-            (val, Self::Array(array)) => {
-                let n = array.len();
-                let (vec_a, vec_b) = (PqlVector(vec![val; n]), PqlVector(array));
-                PqlValue::from(vec_a + vec_b)
-            }
-            _ => todo!(),
-        }
+        self.checked_add(other).unwrap_or(Self::Missing)
     }
 }
 
 impl Sub for PqlValue {
     type Output = Self;
     fn sub(self, other: Self) -> Self::Output {
-        match (self, other) {
-            (Self::Int(a), Self::Int(b)) => Self::Int(a - b),
-            (Self::Int(a), Self::Float(b)) => Self::Float(OrderedFloat(a as f64) - b),
-            (Self::Float(a), Self::Int(b)) => Self::Float(a - OrderedFloat(b as f64)),
-            (Self::Float(a), Self::Float(b)) => Self::Float(a - b),
-            (Self::Array(array_a), Self::Array(array_b)) => {
-                let (vec_a, vec_b) = (PqlVector(array_a), PqlVector(array_b));
-                PqlValue::from(vec_a - vec_b)
-            }
-            (Self::Array(array), val) => {
-                let n = array.len();
-                let (vec_a, vec_b) = (PqlVector(array), PqlVector(vec![val; n]));
-                PqlValue::from(vec_a - vec_b)
-            }
-            (val, Self::Array(array)) => {
-                let n = array.len();
-                let (vec_a, vec_b) = (PqlVector(vec![val; n]), PqlVector(array));
-                PqlValue::from(vec_a - vec_b)
-            }
-            _ => todo!(),
-        }
+        self.checked_sub(other).unwrap_or(Self::Missing)
     }
 }
 
 impl Mul for PqlValue {
     type Output = Self;
     fn mul(self, other: Self) -> Self::Output {
-        match (self.to_owned(), other.to_owned()) {
-            (Self::Int(a), Self::Int(b)) => Self::Int(a * b),
-            (Self::Int(a), Self::Float(b)) => Self::Float(OrderedFloat(a as f64) * b),
-            (Self::Float(a), Self::Int(b)) => Self::Float(a * OrderedFloat(b as f64)),
-            (Self::Float(a), Self::Float(b)) => Self::Float(a * b),
-            (Self::Array(array_a), Self::Array(array_b)) => {
-                let (vec_a, vec_b) = (PqlVector(array_a), PqlVector(array_b));
-                PqlValue::from(vec_a * vec_b)
-            }
-            (Self::Array(array), val) => {
-                let n = array.len();
-                let (vec_a, vec_b) = (PqlVector(array), PqlVector(vec![val; n]));
-                PqlValue::from(vec_a * vec_b)
-            }
-            (val, Self::Array(array)) => {
-                let n = array.len();
-                let (vec_a, vec_b) = (PqlVector(vec![val; n]), PqlVector(array));
-                PqlValue::from(vec_a * vec_b)
-            }
-            _ => todo!(),
-        }
+        self.checked_mul(other).unwrap_or(Self::Missing)
     }
 }
 
 impl Div for PqlValue {
     type Output = Self;
     fn div(self, other: Self) -> Self::Output {
-        match (self, other) {
-            (Self::Int(a), Self::Int(b)) => Self::Float(OrderedFloat(a as f64 / b as f64)),
-            (Self::Int(a), Self::Float(b)) => Self::Float(OrderedFloat(a as f64) / b),
-            (Self::Float(a), Self::Int(b)) => Self::Float(a / OrderedFloat(b as f64)),
-            (Self::Float(a), Self::Float(b)) => Self::Float(a / b),
-            (Self::Array(array_a), Self::Array(array_b)) => {
-                let (vec_a, vec_b) = (PqlVector(array_a), PqlVector(array_b));
-                PqlValue::from(vec_a / vec_b)
-            }
-            (Self::Array(array), val) => {
-                let n = array.len();
-                let (vec_a, vec_b) = (PqlVector(array), PqlVector(vec![val; n]));
-                PqlValue::from(vec_a / vec_b)
-            }
-            (val, Self::Array(array)) => {
-                let n = array.len();
-                let (vec_a, vec_b) = (PqlVector(vec![val; n]), PqlVector(array));
-                PqlValue::from(vec_a / vec_b)
-            }
-            _ => todo!(),
-        }
+        self.checked_div(other).unwrap_or(Self::Missing)
     }
 }
 
 impl Rem for PqlValue {
     type Output = Self;
     fn rem(self, other: Self) -> Self::Output {
-        match (self, other) {
-            (Self::Int(a), Self::Int(b)) => Self::from(a % b),
-            (Self::Int(a), Self::Float(OrderedFloat(b))) => Self::from(a as f64 % b),
-            (Self::Float(OrderedFloat(a)), Self::Int(b)) => Self::from(a % b as f64),
-            (Self::Float(OrderedFloat(a)), Self::Float(OrderedFloat(b))) => Self::from(a % b),
-            (Self::Array(array_a), Self::Array(array_b)) => {
-                let (vec_a, vec_b) = (PqlVector(array_a), PqlVector(array_b));
-                PqlValue::from(vec_a % vec_b)
-            }
-            (Self::Array(array), val) => {
-                let n = array.len();
-                let (vec_a, vec_b) = (PqlVector(array), PqlVector(vec![val; n]));
-                PqlValue::from(vec_a % vec_b)
-            }
-            (val, Self::Array(array)) => {
-                let n = array.len();
-                let (vec_a, vec_b) = (PqlVector(vec![val; n]), PqlVector(array));
-                PqlValue::from(vec_a % vec_b)
-            }
-            _ => todo!(),
-        }
+        self.checked_rem(other).unwrap_or(Self::Missing)
     }
 }
 
+// The arithmetic operators are defined for numbers. MISSING and NULL operands propagate, the
+// annotations of an operand are looked through, and an array applies the operator to each of
+// its elements. Any other operand is a type error.
 impl PqlValue {
+    pub fn checked_neg(self) -> Result<Self, EvalError> {
+        match self {
+            Self::Missing | Self::Null => Ok(self),
+            Self::Annotated(_, value) => value.checked_neg(),
+            Self::Array(array) => array
+                .into_iter()
+                .map(Self::checked_neg)
+                .collect::<Result<Vec<_>, _>>()
+                .map(Self::Array),
+            Self::Int(a) => Ok(a
+                .checked_neg()
+                .map(Self::Int)
+                .unwrap_or_else(|| Self::Decimal(-BigDecimal::from(a)))),
+            Self::Float(a) => Ok(Self::Float(-a)),
+            Self::Decimal(a) => Ok(Self::Decimal(-a)),
+            value => Err(EvalError::types("-", &[&value])),
+        }
+    }
+
+    pub fn checked_add(self, other: Self) -> Result<Self, EvalError> {
+        self.binary(other, "+", |a, b| match (a, b) {
+            (Self::Int(a), Self::Int(b)) => Some(
+                a.checked_add(b)
+                    .map(Self::Int)
+                    .unwrap_or_else(|| Self::Decimal(BigDecimal::from(a) + BigDecimal::from(b))),
+            ),
+            (Self::Decimal(a), Self::Decimal(b)) => Some(Self::Decimal(a + b)),
+            (Self::Int(a), Self::Decimal(b)) => Some(Self::Decimal(BigDecimal::from(a) + b)),
+            (Self::Decimal(a), Self::Int(b)) => Some(Self::Decimal(a + BigDecimal::from(b))),
+            (a, b) => Some(Self::Float(a.to_float()? + b.to_float()?)),
+        })
+    }
+
+    pub fn checked_sub(self, other: Self) -> Result<Self, EvalError> {
+        self.binary(other, "-", |a, b| match (a, b) {
+            (Self::Int(a), Self::Int(b)) => Some(
+                a.checked_sub(b)
+                    .map(Self::Int)
+                    .unwrap_or_else(|| Self::Decimal(BigDecimal::from(a) - BigDecimal::from(b))),
+            ),
+            (Self::Decimal(a), Self::Decimal(b)) => Some(Self::Decimal(a - b)),
+            (Self::Int(a), Self::Decimal(b)) => Some(Self::Decimal(BigDecimal::from(a) - b)),
+            (Self::Decimal(a), Self::Int(b)) => Some(Self::Decimal(a - BigDecimal::from(b))),
+            (a, b) => Some(Self::Float(a.to_float()? - b.to_float()?)),
+        })
+    }
+
+    pub fn checked_mul(self, other: Self) -> Result<Self, EvalError> {
+        self.binary(other, "*", |a, b| match (a, b) {
+            (Self::Int(a), Self::Int(b)) => Some(
+                a.checked_mul(b)
+                    .map(Self::Int)
+                    .unwrap_or_else(|| Self::Decimal(BigDecimal::from(a) * BigDecimal::from(b))),
+            ),
+            (Self::Decimal(a), Self::Decimal(b)) => Some(Self::Decimal(a * b)),
+            (Self::Int(a), Self::Decimal(b)) => Some(Self::Decimal(BigDecimal::from(a) * b)),
+            (Self::Decimal(a), Self::Int(b)) => Some(Self::Decimal(a * BigDecimal::from(b))),
+            (a, b) => Some(Self::Float(a.to_float()? * b.to_float()?)),
+        })
+    }
+
+    /// Divides as `/`. Two INTs are divided as floats.
+    pub fn checked_div(self, other: Self) -> Result<Self, EvalError> {
+        self.binary(other, "/", |a, b| match (a, b) {
+            (Self::Decimal(a), Self::Decimal(b)) => Some(Self::Decimal(a / b)),
+            (Self::Int(a), Self::Decimal(b)) => Some(Self::Decimal(BigDecimal::from(a) / b)),
+            (Self::Decimal(a), Self::Int(b)) => Some(Self::Decimal(a / BigDecimal::from(b))),
+            (a, b) => Some(Self::Float(a.to_float()? / b.to_float()?)),
+        })
+    }
+
+    pub fn checked_rem(self, other: Self) -> Result<Self, EvalError> {
+        self.binary(other, "%", |a, b| match (a, b) {
+            // `i64::MIN % -1` overflows although the remainder is 0.
+            (Self::Int(a), Self::Int(b)) => Some(Self::from(a.checked_rem(b).unwrap_or(0))),
+            (Self::Decimal(a), Self::Decimal(b)) => Some(Self::Decimal(a % b)),
+            (Self::Int(a), Self::Decimal(b)) => Some(Self::Decimal(BigDecimal::from(a) % b)),
+            (Self::Decimal(a), Self::Int(b)) => Some(Self::Decimal(a % BigDecimal::from(b))),
+            (a, b) => Some(Self::Float(a.to_float()? % b.to_float()?)),
+        })
+    }
+
+    pub fn checked_powf(self, other: Self) -> Result<Self, EvalError> {
+        self.binary(other, "^", |a, b| {
+            Some(Self::from(a.to_float()?.0.powf(b.to_float()?.0)))
+        })
+    }
+
     pub fn powf(self, other: Self) -> Self {
-        let (a, b) = match (self, other) {
-            (Self::Int(a), Self::Int(b)) => (a as f64, b as f64),
-            (Self::Int(a), Self::Float(OrderedFloat(b))) => (a as f64, b),
-            (Self::Float(OrderedFloat(a)), Self::Int(b)) => (a, b as f64),
-            (Self::Float(OrderedFloat(a)), Self::Float(OrderedFloat(b))) => (a, b),
-            _ => todo!(),
+        self.checked_powf(other).unwrap_or(Self::Missing)
+    }
+
+    // Applies `op` to two numbers, which it has no value for if either is not a number.
+    fn binary(
+        self,
+        other: Self,
+        name: &str,
+        op: fn(Self, Self) -> Option<Self>,
+    ) -> Result<Self, EvalError> {
+        let each = |pairs: Vec<(Self, Self)>| {
+            pairs
+                .into_iter()
+                .map(|(a, b)| a.binary(b, name, op))
+                .collect::<Result<Vec<_>, _>>()
+                .map(Self::Array)
         };
-        Self::from(a.powf(b))
+        match (self, other) {
+            (Self::Missing, _) | (_, Self::Missing) => Ok(Self::Missing),
+            (Self::Null, _) | (_, Self::Null) => Ok(Self::Null),
+            (Self::Annotated(_, a), b) => a.binary(b, name, op),
+            (a, Self::Annotated(_, b)) => a.binary(*b, name, op),
+            (Self::Array(a), Self::Array(b)) => each(a.into_iter().zip(b).collect()),
+            (Self::Array(a), b) => each(a.into_iter().map(|a| (a, b.to_owned())).collect()),
+            (a, Self::Array(b)) => each(b.into_iter().map(|b| (a.to_owned(), b)).collect()),
+            (a, b) if a.is_number() && b.is_number() => {
+                let (a, b) = widen(a, b);
+                if matches!(name, "/" | "%") && b.is_zero() {
+                    return Err(EvalError::DivisionByZero);
+                }
+                Ok(op(a, b).expect("numbers have a value"))
+            }
+            (a, b) => Err(EvalError::types(name, &[&a, &b])),
+        }
+    }
+
+    fn to_float(&self) -> Option<OrderedFloat<f64>> {
+        match self {
+            Self::Int(int) => Some(OrderedFloat(*int as f64)),
+            Self::Float(float) => Some(*float),
+            Self::Decimal(decimal) => Some(OrderedFloat(decimal_to_f64(decimal))),
+            _ => None,
+        }
+    }
+
+    // A divisor of zero is an error whatever its type, since a literal such as `0` is a float.
+    fn is_zero(&self) -> bool {
+        match self {
+            Self::Int(int) => *int == 0,
+            Self::Float(float) => float.0 == 0.,
+            Self::Decimal(decimal) => decimal.is_zero(),
+            _ => false,
+        }
+    }
+
+    /// The name of the type of the value, as in a type error.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Missing => "missing",
+            Self::Null => "null",
+            Self::Str(_) => "string",
+            Self::Boolean(_) => "bool",
+            Self::Int(_) => "int",
+            Self::Float(_) => "float",
+            Self::Decimal(_) => "decimal",
+            Self::DateTime(_) => "timestamp",
+            Self::Date(_) => "date",
+            Self::Time(_) => "time",
+            Self::Symbol(_) => "symbol",
+            Self::Blob(_) => "blob",
+            Self::Clob(_) => "clob",
+            Self::Annotated(_, value) => value.type_name(),
+            Self::Array(_) => "list",
+            Self::Object(_) => "struct",
+        }
+    }
+}
+
+// A float applied to a decimal as the decimal it is written as, so that a literal such as `19.99`,
+// a float, is as exact as the decimals it is applied to.
+fn widen(a: PqlValue, b: PqlValue) -> (PqlValue, PqlValue) {
+    let decimal = |float: OrderedFloat<f64>| match float_to_decimal(float.0) {
+        Some(decimal) => PqlValue::Decimal(decimal),
+        None => PqlValue::Float(float),
+    };
+    match (a, b) {
+        (PqlValue::Float(a), b @ PqlValue::Decimal(_)) => (decimal(a), b),
+        (a @ PqlValue::Decimal(_), PqlValue::Float(b)) => (a, decimal(b)),
+        (a, b) => (a, b),
+    }
+}
+
+// The largest exponent, either way, of a decimal read from input, as in IEEE 754 decimal128. A
+// decimal beyond it would be expanded to as many digits as its exponent to be written out.
+const MAX_DECIMAL_EXPONENT: u64 = 6144;
+
+/// `decimal`, or an error if its exponent is too large to be written out.
+pub(crate) fn bounded_decimal(decimal: BigDecimal) -> anyhow::Result<BigDecimal> {
+    let (_, scale) = decimal.as_bigint_and_exponent();
+    if scale.unsigned_abs() > MAX_DECIMAL_EXPONENT {
+        anyhow::bail!("the exponent of a decimal is out of range: {}", -scale);
+    }
+    Ok(decimal)
+}
+
+/// The float nearest to `d`. Through the text, since `BigDecimal::to_f64` does not round
+/// correctly.
+pub(crate) fn decimal_to_f64(d: &BigDecimal) -> f64 {
+    d.to_string().parse().unwrap_or(f64::NAN)
+}
+
+/// The decimal a finite float is written as, such as `19.99` for the float nearest to it.
+pub(crate) fn float_to_decimal(f: f64) -> Option<BigDecimal> {
+    if f.is_finite() {
+        BigDecimal::from_str(&f.to_string()).ok()
+    } else {
+        None
     }
 }
 
@@ -457,11 +690,65 @@ impl TryFrom<PqlValue> for i64 {
         match value {
             PqlValue::Int(int) => Ok(int),
             PqlValue::Float(OrderedFloat(f)) => Ok(f as i64),
+            PqlValue::Decimal(d) => d
+                .to_i64()
+                .ok_or_else(|| anyhow::anyhow!("out of range for i64")),
             _ => anyhow::bail!("not numeric"),
         }
     }
 }
 
+impl PqlValue {
+    /// The order of two values of the same kind, with numbers compared by value whatever
+    /// their type. Values of different kinds, such as a string and a number, have no order.
+    pub fn compare(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        match (self, other) {
+            (Self::Annotated(_, value), other) => value.compare(other),
+            (value, Self::Annotated(_, other)) => value.compare(other),
+            (Self::Int(a), Self::Int(b)) => Some(a.cmp(b)),
+            (Self::Decimal(a), Self::Decimal(b)) => a.partial_cmp(b),
+            (Self::Int(a), Self::Decimal(b)) => BigDecimal::from(*a).partial_cmp(b),
+            (Self::Decimal(a), Self::Int(b)) => a.partial_cmp(&BigDecimal::from(*b)),
+            // A float is compared to a decimal as it is written, as in arithmetic.
+            (Self::Decimal(a), Self::Float(b)) => match float_to_decimal(b.0) {
+                Some(b) => a.partial_cmp(&b),
+                None => Some(OrderedFloat(decimal_to_f64(a)).cmp(b)),
+            },
+            (Self::Float(_), Self::Decimal(_)) => other.compare(self).map(std::cmp::Ordering::reverse),
+            (a, b) if a.is_number() && b.is_number() => {
+                let float = |value: &Self| match value {
+                    Self::Int(int) => OrderedFloat(*int as f64),
+                    Self::Float(float) => *float,
+                    Self::Decimal(decimal) => OrderedFloat(decimal_to_f64(decimal)),
+                    _ => unreachable!(),
+                };
+                Some(float(a).cmp(&float(b)))
+            }
+            (Self::Str(a), Self::Str(b)) | (Self::Symbol(a), Self::Symbol(b)) => Some(a.cmp(b)),
+            (Self::Boolean(a), Self::Boolean(b)) => Some(a.cmp(b)),
+            (Self::DateTime(a), Self::DateTime(b)) => Some(a.cmp(b)),
+            (Self::Date(a), Self::Date(b)) => Some(a.cmp(b)),
+            (Self::Time(a), Self::Time(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+
+    /// Whether the values are equal, as `=` tells: numbers by what they are worth whatever their
+    /// type, so that `1 = 1e0`, and any other values as they are.
+    pub fn equals(&self, other: &Self) -> bool {
+        match (self, other) {
+            (a, b) if a.is_number() && b.is_number() => {
+                a.compare(b) == Some(std::cmp::Ordering::Equal)
+            }
+            (a, b) => a == b,
+        }
+    }
+
+    fn is_number(&self) -> bool {
+        matches!(self, Self::Int(_) | Self::Float(_) | Self::Decimal(_))
+    }
+}
+
 impl PartialOrd for PqlValue {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
         let v1 = BPqlValue::from(self.to_owned());
@@ -475,6 +762,7 @@ mod tests {
     use std::collections::VecDeque;
     use std::str::FromStr;
 
+    use bigdecimal::BigDecimal;
     use ordered_float::OrderedFloat;
 
     use crate::planner::LogicalPlan;
@@ -494,8 +782,88 @@ mod tests {
         );
         assert_eq!(
             PqlValue::Float(OrderedFloat(1.)) / PqlValue::Float(OrderedFloat(0.)),
-            PqlValue::Float(OrderedFloat(f64::INFINITY))
+            PqlValue::Missing
+        );
+    }
+
+    #[test]
+    fn decimal_exact_arithmetic() -> anyhow::Result<()> {
+        let d = |s: &str| PqlValue::Decimal(BigDecimal::from_str(s).unwrap());
+
+        assert_eq!(d("0.1") + d("0.2"), d("0.3"));
+        assert_eq!(d("19.99") * PqlValue::from(3), d("59.97"));
+        assert_eq!(PqlValue::from(10) - d("0.01"), d("9.99"));
+        assert_eq!(d("1") / d("8"), d("0.125"));
+        assert_eq!(d("7.5") % PqlValue::from(2), d("1.5"));
+        assert_eq!(d("1") / PqlValue::from(0), PqlValue::Missing);
+        assert_eq!(-d("1.5"), d("-1.5"));
+        // A float, as a literal is, is applied to a decimal as the decimal it is written as.
+        assert_eq!(d("0.5") + PqlValue::from(0.25), d("0.75"));
+        assert_eq!(d("19.99") * PqlValue::from(3.), d("59.97"));
+        assert_eq!(PqlValue::from(0.1) + d("0.2"), d("0.3"));
+        assert!(d("19.99").equals(&PqlValue::from(19.99)));
+        assert!(d("0.3").equals(&(PqlValue::from(0.1) + d("0.2"))));
+        assert_eq!(
+            d("0.1").compare(&PqlValue::from(0.1000000000000001)),
+            Some(std::cmp::Ordering::Less)
+        );
+        // A decimal that is not exact as a float is written as the float nearest to it.
+        assert_eq!(d("19.99").to_json()?, "19.99");
+
+        Ok(())
+    }
+
+    #[test]
+    fn int_overflow_promotes_to_decimal() -> anyhow::Result<()> {
+        let d = |s: &str| PqlValue::Decimal(BigDecimal::from_str(s).unwrap());
+
+        assert_eq!(
+            PqlValue::from(i64::MAX) + PqlValue::from(1),
+            d("9223372036854775808")
         );
+        assert_eq!(
+            PqlValue::from(i64::MIN) - PqlValue::from(1),
+            d("-9223372036854775809")
+        );
+        assert_eq!(
+            PqlValue::from(i64::MAX) * PqlValue::from(i64::MAX),
+            d("85070591730234615847396907784232501249")
+        );
+        assert_eq!(-PqlValue::from(i64::MIN), d("9223372036854775808"));
+        assert_eq!(
+            PqlValue::from(i64::MIN) % PqlValue::from(-1),
+            PqlValue::from(0)
+        );
+        assert_eq!(PqlValue::from(1) % PqlValue::from(0), PqlValue::Missing);
+        assert_eq!(PqlValue::from(2) + PqlValue::from(3), PqlValue::from(5));
+
+        Ok(())
+    }
+
+    #[test]
+    fn decimal_from_json() -> anyhow::Result<()> {
+        let lang = crate::lang::Lang::from_as_json_decimal(
+            r#"[{ "price": 19.99 }, { "price": 0.01 }, { "price": 3 }]"#,
+        )?;
+        let prices: Vec<PqlValue> = lang.data.select_by_selector(&Selector::from("price")).into();
+        let total = prices
+            .into_iter()
+            .fold(PqlValue::from(0), |acc, v| acc + v);
+
+        assert_eq!(total, PqlValue::Decimal(BigDecimal::from_str("23.00")?));
+        assert_eq!(total.to_jsonc()?, "23.00");
+        Ok(())
+    }
+
+    #[test]
+    fn bounds_json_exponents() -> anyhow::Result<()> {
+        let json = |s: &str| crate::value::json_value::to_pqlvalue(serde_json::from_str(s)?);
+        // A number beyond a float is a decimal, unless it is too large to be written out.
+        assert_eq!(json("1e400")?, PqlValue::Decimal(BigDecimal::from_str("1e400")?));
+        assert!(json("[1e99999999999]").is_err());
+        assert!(crate::lang::Lang::from_as_json_decimal("[1e-99999999999]").is_err());
+        assert!(crate::lang::Lang::from_as_json_decimal("[1.5e10]").is_ok());
+        Ok(())
     }
 
     #[test]
@@ -563,9 +931,10 @@ mod tests {
         let sql = Sql::from_str(
             r#"
 SELECT
-    dat.n + 3 AS n3,
-    4 + dat.n  AS n4,
-    dat.n + dat.n  AS nn,
+    d.n + 3 AS n3,
+    4 + d.n  AS n4,
+    d.n + d.n  AS nn
+FROM dat AS d
     "#,
         )?;
         let plan = LogicalPlan::from(sql);
@@ -618,9 +987,10 @@ SELECT
         let sql = Sql::from_str(
             r#"
 SELECT
-    dat.n - 3 AS n3,
-    4 - dat.n  AS n4,
-    dat.n - dat.n  AS nn,
+    d.n - 3 AS n3,
+    4 - d.n  AS n4,
+    d.n - d.n  AS nn
+FROM dat AS d
     "#,
         )?;
         let plan = LogicalPlan::from(sql);
@@ -674,9 +1044,10 @@ SELECT
         let sql = Sql::from_str(
             r#"
 SELECT
-    dat.n * 3 AS n3,
-    4* dat.n  AS n4,
-    dat.n* dat.n  AS nn,
+    d.n * 3 AS n3,
+    4 * d.n  AS n4,
+    d.n * d.n  AS nn
+FROM dat AS d
     "#,
         )?;
         let plan = LogicalPlan::from(sql);
@@ -729,9 +1100,10 @@ SELECT
         let sql = Sql::from_str(
             r#"
 SELECT
-    dat.n / 3 AS n3,
-    4 / dat.n  AS n4,
-    dat.n / dat.n  AS nn,
+    d.n / 3 AS n3,
+    4 / d.n  AS n4,
+    d.n / d.n  AS nn
+FROM dat AS d
     "#,
         )?;
         let plan = LogicalPlan::from(sql);
@@ -0,0 +1,104 @@
+//! SQLite databases, read as a tuple with a bag of rows for each table and view.
+//!
+//! Values keep their storage class: INTEGER is read as an integer, REAL as a float, TEXT as a
+//! string and BLOB as a blob, whatever the declared type of their column.
+
+use std::path::Path;
+
+use indexmap::IndexMap as Map;
+use ordered_float::OrderedFloat;
+use rusqlite::types::ValueRef;
+use rusqlite::{Connection, OpenFlags};
+
+use crate::value::PqlValue;
+
+/// Opens a database file read-only and reads it with `from_connection`.
+pub fn from_path(path: &Path) -> anyhow::Result<PqlValue> {
+    let conn = Connection::open_with_flags(
+        path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?;
+    from_connection(&conn)
+}
+
+/// Reads every table and view, except SQLite's own, as a field named after it.
+pub fn from_connection(conn: &Connection) -> anyhow::Result<PqlValue> {
+    let mut statement = conn.prepare(
+        "SELECT name FROM sqlite_master WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' ORDER BY rowid",
+    )?;
+    let names = statement
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut tables = Map::new();
+    for name in names {
+        let rows = rows(conn, &name)?;
+        tables.insert(name, PqlValue::bag(rows));
+    }
+    Ok(PqlValue::Object(tables))
+}
+
+fn rows(conn: &Connection, table: &str) -> anyhow::Result<Vec<PqlValue>> {
+    let mut statement =
+        conn.prepare(&format!("SELECT * FROM \"{}\"", table.replace('"', "\"\"")))?;
+    let columns = statement
+        .column_names()
+        .into_iter()
+        .map(String::from)
+        .collect::<Vec<_>>();
+    let mut rows = statement.query([])?;
+    let mut values = vec![];
+    while let Some(row) = rows.next()? {
+        let mut map = Map::new();
+        for (i, column) in columns.iter().enumerate() {
+            map.insert(column.to_owned(), to_pqlvalue(row.get_ref(i)?));
+        }
+        values.push(PqlValue::Object(map));
+    }
+    Ok(values)
+}
+
+fn to_pqlvalue(value: ValueRef) -> PqlValue {
+    match value {
+        ValueRef::Null => PqlValue::Null,
+        ValueRef::Integer(int) => PqlValue::Int(int),
+        ValueRef::Real(real) => PqlValue::Float(OrderedFloat(real)),
+        ValueRef::Text(text) => PqlValue::Str(String::from_utf8_lossy(text).into_owned()),
+        ValueRef::Blob(blob) => PqlValue::Blob(blob.to_vec()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rusqlite::Connection;
+
+    use super::from_connection;
+    use crate::value::partiql_value;
+
+    #[test]
+    fn reads_tables() -> anyhow::Result<()> {
+        let conn = Connection::open_in_memory()?;
+        conn.execute_batch(
+            r#"
+            CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT, score REAL, avatar BLOB);
+            CREATE TABLE "order items" (user_id INTEGER, total INTEGER);
+            CREATE VIEW names AS SELECT name FROM users;
+            INSERT INTO users VALUES (1, 'alice', 1.5e0, x'0102'), (2, 'bob', NULL, NULL);
+            INSERT INTO "order items" VALUES (1, 30), (2, 10);
+            "#,
+        )?;
+
+        let expected = partiql_value::from_str(
+            r#"{
+                'users': <<
+                    { 'id': 1, 'name': 'alice', 'score': 1.5e0, 'avatar': `{{AQI=}}` },
+                    { 'id': 2, 'name': 'bob', 'score': null, 'avatar': null }
+                >>,
+                'order items': << { 'user_id': 1, 'total': 30 }, { 'user_id': 2, 'total': 10 } >>,
+                'names': << { 'name': 'alice' }, { 'name': 'bob' } >>
+            }"#,
+        )?;
+        assert_eq!(from_connection(&conn)?, expected);
+        Ok(())
+    }
+}
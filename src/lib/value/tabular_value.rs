@@ -0,0 +1,263 @@
+//! Query results rendered as tables: Markdown, HTML and aligned plain text, for output only.
+//!
+//! Each row is a record, and the columns are the keys of all rows in the order they first
+//! appear. A row that is not a tuple is a single column `_1`. Nested values are written as
+//! compact JSON, and a MISSING field is left blank.
+
+use indexmap::IndexMap as Map;
+use unicode_width::UnicodeWidthStr;
+
+use crate::value::json_value;
+use crate::value::PqlValue;
+
+struct Grid {
+    headers: Vec<String>,
+    rows: Vec<Vec<String>>,
+    /// Whether every value of a column is a number, so that it is aligned to the right.
+    numeric: Vec<bool>,
+}
+
+impl From<&PqlValue> for Grid {
+    fn from(value: &PqlValue) -> Self {
+        let rows = match value.to_owned().unbag() {
+            PqlValue::Array(array) => array,
+            value => vec![value],
+        };
+        let rows = rows
+            .into_iter()
+            .map(|row| match row {
+                PqlValue::Object(map) => map,
+                value => {
+                    let mut map = Map::new();
+                    map.insert("_1".to_owned(), value);
+                    map
+                }
+            })
+            .collect::<Vec<_>>();
+        let mut columns = Map::<&str, bool>::new();
+        for row in &rows {
+            for (k, v) in row {
+                let is_number = matches!(
+                    v,
+                    PqlValue::Int(_)
+                        | PqlValue::Float(_)
+                        | PqlValue::Decimal(_)
+                        | PqlValue::Missing
+                );
+                let numeric = columns.entry(k.as_str()).or_insert(true);
+                *numeric = *numeric && is_number;
+            }
+        }
+
+        Self {
+            headers: columns.keys().map(|k| k.to_string()).collect(),
+            rows: rows
+                .iter()
+                .map(|row| {
+                    columns
+                        .keys()
+                        .map(|k| row.get(*k).map(cell).unwrap_or_default())
+                        .collect()
+                })
+                .collect(),
+            numeric: columns.values().copied().collect(),
+        }
+    }
+}
+
+fn cell(value: &PqlValue) -> String {
+    if value == &PqlValue::Missing {
+        return String::default();
+    }
+    match json_value::from_pqlvalue(value.to_owned()) {
+        serde_json::Value::String(s) => s,
+        json => json.to_string(),
+    }
+}
+
+/// Writes a GitHub Flavored Markdown table, with `|` escaped and line breaks as `<br>`.
+pub fn to_markdown(value: &PqlValue) -> String {
+    let grid = Grid::from(value);
+    if grid.headers.is_empty() {
+        return String::default();
+    }
+    let escape = |s: &str| {
+        s.replace('|', "\\|")
+            .replace("\r\n", "<br>")
+            .replace('\n', "<br>")
+    };
+    let headers = grid.headers.iter().map(|s| escape(s)).collect::<Vec<_>>();
+    let rows = grid
+        .rows
+        .iter()
+        .map(|row| row.iter().map(|s| escape(s)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let widths = widths(&headers, &rows, 3);
+
+    let line = |cells: &[String]| {
+        let cells = cells
+            .iter()
+            .zip(&widths)
+            .zip(&grid.numeric)
+            .map(|((s, width), numeric)| pad(s, *width, *numeric))
+            .collect::<Vec<_>>();
+        format!("| {} |\n", cells.join(" | "))
+    };
+    let rule = widths
+        .iter()
+        .zip(&grid.numeric)
+        .map(|(width, numeric)| match numeric {
+            true => format!("{}:", "-".repeat(width - 1)),
+            false => "-".repeat(*width),
+        })
+        .collect::<Vec<_>>();
+
+    let mut output = line(&headers);
+    output.push_str(&format!("| {} |\n", rule.join(" | ")));
+    for row in &rows {
+        output.push_str(&line(row));
+    }
+    output
+}
+
+/// Writes an HTML `<table>` with a header row.
+pub fn to_html(value: &PqlValue) -> String {
+    let grid = Grid::from(value);
+    let escape = |s: &str| {
+        s.replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    };
+    let row = |tag: &str, cells: &[String]| {
+        let cells = cells
+            .iter()
+            .map(|s| format!("<{0}>{1}</{0}>", tag, escape(s)))
+            .collect::<String>();
+        format!("    <tr>{}</tr>\n", cells)
+    };
+
+    let mut output = String::from("<table>\n");
+    if !grid.headers.is_empty() {
+        output.push_str("  <thead>\n");
+        output.push_str(&row("th", &grid.headers));
+        output.push_str("  </thead>\n  <tbody>\n");
+        for cells in &grid.rows {
+            output.push_str(&row("td", cells));
+        }
+        output.push_str("  </tbody>\n");
+    }
+    output.push_str("</table>\n");
+    output
+}
+
+/// Writes columns aligned for a terminal, the header underlined, with line breaks as spaces.
+pub fn to_table(value: &PqlValue) -> String {
+    let grid = Grid::from(value);
+    if grid.headers.is_empty() {
+        return String::default();
+    }
+    let rows = grid
+        .rows
+        .iter()
+        .map(|row| row.iter().map(|s| s.replace(['\r', '\n'], " ")).collect())
+        .collect::<Vec<Vec<_>>>();
+    let widths = widths(&grid.headers, &rows, 1);
+
+    let line = |cells: &[String]| {
+        let cells = cells
+            .iter()
+            .zip(&widths)
+            .zip(&grid.numeric)
+            .map(|((s, width), numeric)| pad(s, *width, *numeric))
+            .collect::<Vec<_>>();
+        format!("{}\n", cells.join("  ").trim_end())
+    };
+    let rule = widths
+        .iter()
+        .map(|width| "-".repeat(*width))
+        .collect::<Vec<_>>();
+
+    let mut output = line(&grid.headers);
+    output.push_str(&format!("{}\n", rule.join("  ")));
+    for row in &rows {
+        output.push_str(&line(row));
+    }
+    output
+}
+
+fn widths(headers: &[String], rows: &[Vec<String>], min: usize) -> Vec<usize> {
+    headers
+        .iter()
+        .enumerate()
+        .map(|(i, header)| {
+            rows.iter()
+                .map(|row| row[i].width())
+                .chain([header.width(), min])
+                .max()
+                .unwrap_or(min)
+        })
+        .collect()
+}
+
+fn pad(s: &str, width: usize, right: bool) -> String {
+    let padding = " ".repeat(width.saturating_sub(s.width()));
+    if right {
+        format!("{}{}", padding, s)
+    } else {
+        format!("{}{}", s, padding)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_html, to_markdown, to_table};
+    use crate::value::partiql_value;
+
+    #[test]
+    fn renders_tables() -> anyhow::Result<()> {
+        let rows = partiql_value::from_str(
+            r#"<<
+                { 'name': 'a|b', 'n': 1, 'tags': ['x', 'y'] },
+                { 'name': '名前', 'n': 10.5, 'note': null },
+                { 'name': '<c>' }
+            >>"#,
+        )?;
+
+        assert_eq!(
+            to_markdown(&rows),
+            r#"| name |    n | tags      | note |
+| ---- | ---: | --------- | ---- |
+| a\|b |    1 | ["x","y"] |      |
+| 名前 | 10.5 |           | null |
+| <c>  |      |           |      |
+"#
+        );
+
+        assert_eq!(
+            to_table(&rows),
+            "name     n  tags       note
+----  ----  ---------  ----
+a|b      1  [\"x\",\"y\"]
+名前  10.5             null
+<c>
+"
+        );
+
+        assert_eq!(
+            to_html(&rows),
+            r#"<table>
+  <thead>
+    <tr><th>name</th><th>n</th><th>tags</th><th>note</th></tr>
+  </thead>
+  <tbody>
+    <tr><td>a|b</td><td>1</td><td>[&quot;x&quot;,&quot;y&quot;]</td><td></td></tr>
+    <tr><td>名前</td><td>10.5</td><td></td><td>null</td></tr>
+    <tr><td>&lt;c&gt;</td><td></td><td></td><td></td></tr>
+  </tbody>
+</table>
+"#
+        );
+        Ok(())
+    }
+}
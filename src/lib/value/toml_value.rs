@@ -1,63 +1,355 @@
-use chrono::prelude::*;
-use chrono::serde::ts_seconds;
+//! TOML, read into `PqlValue` through `toml::Value` and written by the emitter below.
+//!
+//! The writer keeps the order of keys: a table or array of tables gets its own section when
+//! only other sections follow it, and is written inline otherwise. TOML has no null, so nulls
+//! are written by a `NullPolicy`, and MISSING fields are left out.
+
+use std::fmt::Write;
+use std::str::FromStr;
+
 use indexmap::IndexMap as Map;
-use serde_derive::{Deserialize, Serialize};
-
-use crate::value::PqlValue;
-
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-#[serde(untagged)]
-pub enum TomlValue {
-    #[serde(skip_serializing)]
-    Null,
-    Str(String),
-    Boolean(bool),
-    Float(f64),
-    Int(i64),
-    #[serde(with = "ts_seconds")]
-    DateTime(DateTime<Utc>),
-    Array(Vec<Self>),
-    Object(Map<String, Self>),
-}
-
-impl From<PqlValue> for TomlValue {
-    fn from(pqlv: PqlValue) -> Self {
-        match pqlv {
-            PqlValue::Missing => unreachable!(),
-            PqlValue::Null => Self::Null,
-            PqlValue::Str(string) => Self::Str(string),
-            PqlValue::Boolean(boolean) => Self::Boolean(boolean),
-            PqlValue::Float(float) => Self::Float(float.into_inner()),
-            PqlValue::Int(int) => Self::Int(int),
-            PqlValue::DateTime(datetime) => Self::DateTime(datetime),
-            PqlValue::Array(array) => Self::Array(
-                array
-                    .into_iter()
-                    .filter_map(|v| match v {
-                        PqlValue::Null => None,
-                        PqlValue::Missing => None,
-                        _ => Some(Self::from(v)),
+use num_traits::ToPrimitive;
+use ordered_float::OrderedFloat;
+use parse_display::{Display, FromStr};
+use toml::value::{Datetime, Table};
+
+use crate::value::datetime::{format_timestamp, parse_date, parse_timestamp, PqlTime};
+use crate::value::{decimal_to_f64, PqlValue};
+
+/// How nulls are written to TOML, which has no null.
+#[derive(Display, FromStr, Debug, Clone, Copy, PartialEq)]
+#[display(style = "snake_case")]
+pub enum NullPolicy {
+    /// Leave null fields out of their table. A null in an array is an error,
+    /// since leaving it out would move the items after it.
+    Omit,
+    /// Write nulls as empty strings.
+    Empty,
+    /// Fail on any null.
+    Error,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TomlOptions {
+    pub nulls: NullPolicy,
+    /// The key to write a top-level value that is not a tuple under, such as the rows of a query.
+    /// Without it, such a value is an error, since a TOML document is a table.
+    pub root_key: Option<String>,
+}
+
+impl Default for TomlOptions {
+    fn default() -> Self {
+        Self {
+            nulls: NullPolicy::Omit,
+            root_key: None,
+        }
+    }
+}
+
+pub fn to_string(value: &PqlValue, options: &TomlOptions) -> anyhow::Result<String> {
+    let value = match (value.to_owned().unbag(), &options.root_key) {
+        (PqlValue::Object(map), _) => PqlValue::Object(map),
+        (value, Some(key)) => {
+            let mut map = Map::new();
+            map.insert(key.to_owned(), value);
+            PqlValue::Object(map)
+        }
+        (_, None) => anyhow::bail!(
+            "a TOML document is a table, so a top-level value that is not a tuple needs a root key to be written under"
+        ),
+    };
+    let mut output = String::new();
+    if let Some(toml::Value::Table(table)) = from_pqlvalue(value, options.nulls)? {
+        write_table(&mut output, &mut vec![], &table)?;
+    }
+    Ok(output)
+}
+
+/// Converts to a `toml::Value` by `nulls`, or `None` for a null or MISSING that is left out.
+pub fn from_pqlvalue(pqlv: PqlValue, nulls: NullPolicy) -> anyhow::Result<Option<toml::Value>> {
+    let value = match pqlv {
+        PqlValue::Missing | PqlValue::Null => match nulls {
+            NullPolicy::Omit => return Ok(None),
+            NullPolicy::Empty => toml::Value::String(String::default()),
+            NullPolicy::Error => anyhow::bail!("TOML has no null"),
+        },
+        PqlValue::Str(string) | PqlValue::Symbol(string) => toml::Value::String(string),
+        PqlValue::Boolean(boolean) => toml::Value::Boolean(boolean),
+        PqlValue::Float(float) => toml::Value::Float(float.into_inner()),
+        PqlValue::Int(int) => toml::Value::Integer(int),
+        // TOML has no decimal type: integers are kept when they fit, others become floats.
+        PqlValue::Decimal(decimal) => match (decimal.is_integer(), decimal.to_i64()) {
+            (true, Some(int)) => toml::Value::Integer(int),
+            _ => toml::Value::Float(decimal_to_f64(&decimal)),
+        },
+        PqlValue::DateTime(datetime) => datetime_or_string(format_timestamp(&datetime)),
+        PqlValue::Date(date) => datetime_or_string(date.to_string()),
+        // TOML only has local times, so a time with an offset is kept as a string.
+        PqlValue::Time(time) if time.offset.is_some() => toml::Value::String(time.to_string()),
+        PqlValue::Time(time) => datetime_or_string(time.to_string()),
+        PqlValue::Blob(bytes) => toml::Value::String(base64::encode(bytes)),
+        PqlValue::Clob(bytes) => toml::Value::String(String::from_utf8_lossy(&bytes).into_owned()),
+        PqlValue::Annotated(_, value) => return from_pqlvalue(*value, nulls),
+        PqlValue::Array(array) => toml::Value::Array(
+            array
+                .into_iter()
+                .map(|v| {
+                    from_pqlvalue(v, nulls)?.ok_or_else(|| {
+                        anyhow::anyhow!("TOML has no null, and one cannot be left out of an array")
                     })
-                    .collect::<Vec<_>>(),
-            ),
-            PqlValue::Object(map) => Self::Object({
-                let mut paris = vec![];
-                let mut paris_for_map = vec![];
-                for (k, v) in map.into_iter() {
-                    match v {
-                        PqlValue::Missing => {}
-                        PqlValue::Null => {}
-                        PqlValue::Object(_) => {
-                            paris_for_map.push((k, Self::from(v)));
-                        }
-                        _ => {
-                            paris.push((k, Self::from(v)));
-                        }
-                    }
+                })
+                .collect::<anyhow::Result<Vec<_>>>()?,
+        ),
+        PqlValue::Object(map) => {
+            let mut table = Table::new();
+            for (k, v) in map {
+                if v == PqlValue::Missing {
+                    continue;
+                }
+                if let Some(v) = from_pqlvalue(v, nulls)? {
+                    table.insert(k, v);
                 }
-                paris.append(&mut paris_for_map);
-                paris.into_iter().collect::<Map<_, _>>()
-            }),
+            }
+            toml::Value::Table(table)
+        }
+    };
+    Ok(Some(value))
+}
+
+fn datetime_or_string(s: String) -> toml::Value {
+    match Datetime::from_str(&s) {
+        Ok(datetime) => toml::Value::Datetime(datetime),
+        Err(_) => toml::Value::String(s),
+    }
+}
+
+fn write_table(output: &mut String, path: &mut Vec<String>, table: &Table) -> std::fmt::Result {
+    let sections = table.values().rev().take_while(|v| is_section(v)).count();
+    let inline_len = table.len() - sections;
+    for (k, v) in table.iter().take(inline_len) {
+        writeln!(output, "{} = {}", key(k), inline(v))?;
+    }
+    for (k, v) in table.iter().skip(inline_len) {
+        path.push(key(k));
+        match v {
+            toml::Value::Table(table) => {
+                // A table holding nothing but sections is implied by their headers.
+                if table.is_empty() || !table.values().all(is_section) {
+                    write_header(output, &format!("[{}]", path.join(".")))?;
+                }
+                write_table(output, path, table)?;
+            }
+            toml::Value::Array(array) => {
+                for table in array.iter().filter_map(toml::Value::as_table) {
+                    write_header(output, &format!("[[{}]]", path.join(".")))?;
+                    write_table(output, path, table)?;
+                }
+            }
+            _ => unreachable!(),
+        }
+        path.pop();
+    }
+    Ok(())
+}
+
+fn write_header(output: &mut String, header: &str) -> std::fmt::Result {
+    if !output.is_empty() {
+        writeln!(output)?;
+    }
+    writeln!(output, "{}", header)
+}
+
+// Whether a value is written as a section of its own when it comes last in its table.
+fn is_section(value: &toml::Value) -> bool {
+    match value {
+        toml::Value::Table(_) => true,
+        toml::Value::Array(array) => !array.is_empty() && array.iter().all(toml::Value::is_table),
+        _ => false,
+    }
+}
+
+fn inline(value: &toml::Value) -> String {
+    match value {
+        toml::Value::String(string) => quote(string),
+        toml::Value::Integer(int) => int.to_string(),
+        toml::Value::Float(float) if float.is_nan() => "nan".to_owned(),
+        toml::Value::Float(float) if float.is_infinite() => {
+            (if *float > 0.0 { "inf" } else { "-inf" }).to_owned()
         }
+        // Debug always writes a fraction or an exponent, so the float is not read back as an integer.
+        toml::Value::Float(float) => format!("{:?}", float),
+        toml::Value::Boolean(boolean) => boolean.to_string(),
+        toml::Value::Datetime(datetime) => datetime.to_string(),
+        toml::Value::Array(array) => format!(
+            "[{}]",
+            array.iter().map(inline).collect::<Vec<_>>().join(", ")
+        ),
+        toml::Value::Table(table) if table.is_empty() => "{}".to_owned(),
+        toml::Value::Table(table) => format!(
+            "{{ {} }}",
+            table
+                .iter()
+                .map(|(k, v)| format!("{} = {}", key(k), inline(v)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+fn key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        key.to_owned()
+    } else {
+        quote(key)
+    }
+}
+
+fn quote(s: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04X}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+pub fn to_pqlvalue(toml: toml::Value) -> PqlValue {
+    match toml {
+        toml::Value::String(string) => PqlValue::Str(string),
+        toml::Value::Integer(int) => PqlValue::Int(int),
+        toml::Value::Float(float) => PqlValue::Float(OrderedFloat(float)),
+        toml::Value::Boolean(boolean) => PqlValue::Boolean(boolean),
+        toml::Value::Datetime(datetime) => datetime_to_pqlvalue(&datetime),
+        toml::Value::Array(array) => {
+            PqlValue::Array(array.into_iter().map(to_pqlvalue).collect::<Vec<_>>())
+        }
+        toml::Value::Table(table) => PqlValue::Object(
+            table
+                .into_iter()
+                .map(|(k, v)| (k, to_pqlvalue(v)))
+                .collect::<Map<_, _>>(),
+        ),
+    }
+}
+
+// Offset and local date-times become TIMESTAMP, local dates DATE and local times TIME.
+fn datetime_to_pqlvalue(datetime: &Datetime) -> PqlValue {
+    let s = datetime.to_string();
+    let value = match (&datetime.date, &datetime.time) {
+        (Some(_), Some(_)) => parse_timestamp(&s).map(PqlValue::DateTime),
+        (Some(_), None) => parse_date(&s).map(PqlValue::Date),
+        _ => PqlTime::from_str(&s).map(PqlValue::Time),
+    };
+    value.unwrap_or(PqlValue::Str(s))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{to_string, NullPolicy, TomlOptions};
+    use crate::lang::Lang;
+    use crate::value::partiql_value;
+
+    #[test]
+    fn keeps_key_order() -> anyhow::Result<()> {
+        let data = partiql_value::from_str(
+            r#"{
+                'name': 'app', 'owner': { 'name': 'a b', 'since': DATE '2021-07-01' }, 'version': 2,
+                'server': { 'host': 'x', 'at': TIMESTAMP '2021-07-01T09:30:00+09:00', 'ratio': 1e0 },
+                'db': { 'replica': { 'port': 5433 } }
+            }"#,
+        )?;
+        let expected = r#"name = "app"
+owner = { name = "a b", since = 2021-07-01 }
+version = 2
+
+[server]
+host = "x"
+at = 2021-07-01T09:30:00+09:00
+ratio = 1.0
+
+[db.replica]
+port = 5433
+"#;
+        let output = to_string(&data, &TomlOptions::default())?;
+        assert_eq!(output, expected);
+        assert_eq!(Lang::from_as_toml(&output)?.data, data);
+        Ok(())
+    }
+
+    #[test]
+    fn writes_arrays_of_tables() -> anyhow::Result<()> {
+        let data = partiql_value::from_str(
+            r#"{
+                'fruits': [
+                    { 'name': 'apple', 'varieties': [{ 'name': 'fuji' }, { 'name': 'jazz' }] },
+                    { 'name': 'banana', 'grid': [[{ 'x': 1 }], []], 'tags': ['a "b"'] }
+                ]
+            }"#,
+        )?;
+        let expected = r#"[[fruits]]
+name = "apple"
+
+[[fruits.varieties]]
+name = "fuji"
+
+[[fruits.varieties]]
+name = "jazz"
+
+[[fruits]]
+name = "banana"
+grid = [[{ x = 1 }], []]
+tags = ["a \"b\""]
+"#;
+        let output = to_string(&data, &TomlOptions::default())?;
+        assert_eq!(output, expected);
+        assert_eq!(Lang::from_as_toml(&output)?.data, data);
+        Ok(())
+    }
+
+    #[test]
+    fn nulls_and_root_key() -> anyhow::Result<()> {
+        let rows = partiql_value::from_str("<< { 'a': 1, 'b': null }, { 'a': 2, 'b': 'x' } >>")?;
+        let err = to_string(&rows, &TomlOptions::default()).unwrap_err();
+        assert!(err.to_string().contains("root key"), "{}", err);
+
+        let options = TomlOptions {
+            root_key: Some("rows".to_owned()),
+            ..TomlOptions::default()
+        };
+        assert_eq!(
+            to_string(&rows, &options)?,
+            "[[rows]]\na = 1\n\n[[rows]]\na = 2\nb = \"x\"\n"
+        );
+
+        let options = TomlOptions {
+            nulls: NullPolicy::Empty,
+            root_key: Some("rows".to_owned()),
+        };
+        assert_eq!(
+            to_string(&rows, &options)?,
+            "[[rows]]\na = 1\nb = \"\"\n\n[[rows]]\na = 2\nb = \"x\"\n"
+        );
+
+        let options = TomlOptions {
+            nulls: NullPolicy::Error,
+            root_key: Some("rows".to_owned()),
+        };
+        assert!(to_string(&rows, &options).is_err());
+
+        let array = partiql_value::from_str("{ 'a': [1, null] }")?;
+        assert!(to_string(&array, &TomlOptions::default()).is_err());
+        Ok(())
     }
 }
@@ -0,0 +1,298 @@
+//! XML, read into and written from `PqlValue` with a fixed mapping:
+//!
+//! - A document is a tuple with one field, named after its root element.
+//! - An element holding only text is that text as a string, and an empty element is `null`.
+//! - Any other element is a tuple of its attributes as `@name` fields, its child elements as
+//!   fields named after them, and its text as a `#text` field.
+//! - Child elements of the same name are gathered into an array, in document order.
+//! - Names keep their namespace prefix, as in `dc:title`, and `xmlns` declarations are kept as
+//!   attributes, so that documents round-trip. Prefixes are not resolved to namespace URIs.
+//!
+//! Text is trimmed, and the text around child elements is joined with a space.
+//! Comments, processing instructions and the XML declaration are skipped.
+//!
+//! So `<catalog><book id="1">A</book><book id="2">B</book></catalog>` is
+//! `{ 'catalog': { 'book': [{ '@id': '1', '#text': 'A' }, { '@id': '2', '#text': 'B' }] } }`,
+//! and `SELECT b.@id FROM catalog.book b` selects the ids.
+
+use indexmap::IndexMap as Map;
+use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+use quick_xml::{Reader, Writer};
+
+use crate::value::json_value;
+use crate::value::PqlValue;
+
+pub const ATTRIBUTE_PREFIX: char = '@';
+pub const TEXT: &str = "#text";
+/// The root element written for a value that is not a tuple with a single field.
+pub const ROOT: &str = "root";
+/// The element written for each item of an array that is not the value of a field.
+pub const ITEM: &str = "item";
+
+#[derive(Default)]
+struct Element {
+    name: String,
+    fields: Map<String, PqlValue>,
+    text: Vec<String>,
+}
+
+impl Element {
+    fn start<B: std::io::BufRead>(start: &BytesStart, reader: &Reader<B>) -> anyhow::Result<Self> {
+        let mut fields = Map::new();
+        for attribute in start.attributes() {
+            let attribute = attribute?;
+            let key = format!("{}{}", ATTRIBUTE_PREFIX, reader.decode(attribute.key)?);
+            let value = attribute.unescape_and_decode_value(reader)?;
+            fields.insert(key, PqlValue::Str(value));
+        }
+        Ok(Self {
+            name: reader.decode(start.name())?.to_owned(),
+            fields,
+            text: vec![],
+        })
+    }
+
+    fn push_text(&mut self, text: String) {
+        let text = text.trim();
+        if !text.is_empty() {
+            self.text.push(text.to_owned());
+        }
+    }
+
+    fn push_child(&mut self, name: String, value: PqlValue) {
+        match self.fields.get_mut(&name) {
+            Some(PqlValue::Array(array)) => array.push(value),
+            Some(first) => *first = PqlValue::Array(vec![first.to_owned(), value]),
+            None => {
+                self.fields.insert(name, value);
+            }
+        }
+    }
+
+    fn end(self) -> (String, PqlValue) {
+        let text = self.text.join(" ");
+        let value = match (self.fields.is_empty(), text.is_empty()) {
+            (true, true) => PqlValue::Null,
+            (true, false) => PqlValue::Str(text),
+            (false, _) => {
+                let mut fields = self.fields;
+                if !text.is_empty() {
+                    fields.insert(TEXT.to_owned(), PqlValue::Str(text));
+                }
+                PqlValue::Object(fields)
+            }
+        };
+        (self.name, value)
+    }
+}
+
+pub fn from_str(input: &str) -> anyhow::Result<PqlValue> {
+    let mut reader = Reader::from_str(input);
+    reader.check_end_names(true);
+    let mut buf = vec![];
+    let mut stack = vec![Element::default()];
+    loop {
+        match reader.read_event(&mut buf)? {
+            Event::Start(start) => stack.push(Element::start(&start, &reader)?),
+            Event::Empty(start) => {
+                let (name, value) = Element::start(&start, &reader)?.end();
+                stack.last_mut().unwrap().push_child(name, value);
+            }
+            Event::End(_) => {
+                let (name, value) = stack.pop().unwrap().end();
+                match stack.last_mut() {
+                    Some(parent) => parent.push_child(name, value),
+                    None => anyhow::bail!("unexpected end tag </{}>", name),
+                }
+            }
+            Event::Text(text) | Event::CData(text) => {
+                let text = text.unescape_and_decode(&reader)?;
+                stack.last_mut().unwrap().push_text(text)
+            }
+            Event::Eof => break,
+            Event::Comment(_) | Event::Decl(_) | Event::PI(_) | Event::DocType(_) => {}
+        }
+        buf.clear();
+    }
+
+    let document = match stack.as_slice() {
+        [document] => document,
+        [.., element] => anyhow::bail!("unclosed element <{}>", element.name),
+        [] => unreachable!(),
+    };
+    anyhow::ensure!(
+        document.fields.len() == 1 && !matches!(document.fields[0], PqlValue::Array(_)),
+        "expected a single root element"
+    );
+    anyhow::ensure!(
+        document.text.is_empty(),
+        "unexpected text outside of the root element"
+    );
+    Ok(PqlValue::Object(document.fields.to_owned()))
+}
+
+/// Writes XML, indented unless `compact`, by the mapping of `from_str`.
+pub fn to_string(value: &PqlValue, compact: bool) -> anyhow::Result<String> {
+    let mut writer = if compact {
+        Writer::new(vec![])
+    } else {
+        Writer::new_with_indent(vec![], b' ', 2)
+    };
+    match value.to_owned().unbag() {
+        PqlValue::Object(map) if map.len() == 1 && !matches!(map[0], PqlValue::Array(_)) => {
+            let (name, value) = map.into_iter().next().unwrap();
+            write_element(&mut writer, &name, value)?;
+        }
+        value => write_element(&mut writer, ROOT, value)?,
+    }
+    Ok(String::from_utf8(writer.into_inner())?)
+}
+
+fn write_element(writer: &mut Writer<Vec<u8>>, name: &str, value: PqlValue) -> anyhow::Result<()> {
+    let mut start = BytesStart::borrowed_name(name.as_bytes());
+    match value {
+        PqlValue::Missing => {}
+        PqlValue::Annotated(_, value) => write_element(writer, name, *value)?,
+        PqlValue::Null => writer.write_event(Event::Empty(start))?,
+        PqlValue::Object(map) => {
+            let (attributes, children): (Vec<_>, Vec<_>) = map
+                .into_iter()
+                .filter(|(_, v)| v != &PqlValue::Missing)
+                .partition(|(k, _)| k.starts_with(ATTRIBUTE_PREFIX));
+            for (k, v) in &attributes {
+                let v = text(v.to_owned());
+                start.push_attribute((&k[ATTRIBUTE_PREFIX.len_utf8()..], v.as_str()));
+            }
+            if children.is_empty() {
+                writer.write_event(Event::Empty(start))?;
+                return Ok(());
+            }
+            writer.write_event(Event::Start(start))?;
+            for (k, v) in children {
+                match v {
+                    v if k == TEXT => {
+                        let v = text(v);
+                        writer.write_event(Event::Text(BytesText::from_plain_str(&v)))?
+                    }
+                    // Repeated elements, unless nested in another array.
+                    PqlValue::Array(array) => {
+                        for v in array {
+                            match v {
+                                PqlValue::Array(_) => {
+                                    let mut items = Map::new();
+                                    items.insert(ITEM.to_owned(), v);
+                                    write_element(writer, &k, PqlValue::Object(items))?
+                                }
+                                v => write_element(writer, &k, v)?,
+                            }
+                        }
+                    }
+                    v => write_element(writer, &k, v)?,
+                }
+            }
+            writer.write_event(Event::End(BytesEnd::borrowed(name.as_bytes())))?;
+        }
+        PqlValue::Array(array) => {
+            let mut items = Map::new();
+            items.insert(ITEM.to_owned(), PqlValue::Array(array));
+            write_element(writer, name, PqlValue::Object(items))?
+        }
+        value => {
+            writer.write_event(Event::Start(start))?;
+            let value = text(value);
+            writer.write_event(Event::Text(BytesText::from_plain_str(&value)))?;
+            writer.write_event(Event::End(BytesEnd::borrowed(name.as_bytes())))?;
+        }
+    }
+    Ok(())
+}
+
+fn text(value: PqlValue) -> String {
+    match json_value::from_pqlvalue(value) {
+        serde_json::Value::Null => String::default(),
+        serde_json::Value::String(s) => s,
+        json => json.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{from_str, to_string};
+    use crate::lang::Lang;
+    use crate::planner::evaluate;
+    use crate::sql::Sql;
+    use crate::value::PqlValue;
+
+    const CATALOG: &str = r#"<?xml version="1.0"?>
+<!-- books -->
+<catalog xmlns:dc="http://purl.org/dc/elements/1.1/">
+  <book id="1" lang="en">
+    <dc:title>Rust &amp; You</dc:title>
+    <price>10</price>
+    <tag>a</tag>
+    <tag>b</tag>
+  </book>
+  <book id="2">
+    <dc:title><![CDATA[<XML>]]></dc:title>
+    <note>see <b>this</b> now</note>
+    <empty/>
+  </book>
+</catalog>"#;
+
+    #[test]
+    fn reads_mapping() -> anyhow::Result<()> {
+        let data = from_str(CATALOG)?;
+        let expected = PqlValue::from_str(
+            r##"{
+                "catalog": {
+                    "@xmlns:dc": "http://purl.org/dc/elements/1.1/",
+                    "book": [
+                        { "@id": "1", "@lang": "en", "dc:title": "Rust & You", "price": "10", "tag": ["a", "b"] },
+                        { "@id": "2", "dc:title": "<XML>", "note": { "b": "this", "#text": "see now" }, "empty": null }
+                    ]
+                }
+            }"##,
+        )?;
+        assert_eq!(data, expected);
+
+        assert!(from_str("<a><b></a>").is_err());
+        assert!(from_str("<a/><b/>").is_err());
+        assert!(from_str("<a/><a/>").is_err());
+        assert!(from_str("hello").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn round_trip() -> anyhow::Result<()> {
+        let data = from_str(CATALOG)?;
+        let compact = to_string(&data, true)?;
+        assert!(compact.starts_with(
+            r#"<catalog xmlns:dc="http://purl.org/dc/elements/1.1/"><book id="1" lang="en"><dc:title>Rust &amp; You</dc:title>"#
+        ));
+        assert_eq!(from_str(&compact)?, data);
+        assert_eq!(from_str(&to_string(&data, false)?)?, data);
+
+        let rows = Lang::from_as_json(r#"[{ "id": 1 }, { "id": 2 }]"#)?.data;
+        assert_eq!(
+            to_string(&rows, true)?,
+            "<root><item><id>1</id></item><item><id>2</id></item></root>"
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn query_attributes() -> anyhow::Result<()> {
+        let sql =
+            Sql::from_str(r#"SELECT b.@id AS id, b."dc:title" AS title FROM catalog.book b"#)?;
+        let result = evaluate(sql, from_str(CATALOG)?);
+        assert_eq!(
+            result,
+            PqlValue::from_str(
+                r#"[{ "id": "1", "title": "Rust & You" }, { "id": "2", "title": "<XML>" }]"#
+            )?
+        );
+        Ok(())
+    }
+}
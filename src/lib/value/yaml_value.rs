@@ -0,0 +1,199 @@
+//! YAML, read through `yaml_rust` and written through `serde_yaml`.
+//!
+//! A stream of several documents is read as a bag of them, and a bag is written as a stream.
+//! Aliases are resolved to a copy of their anchored node, and merge keys (`<<: *base`) to the
+//! fields of the merged mappings that the mapping does not set itself.
+
+use std::collections::HashSet;
+use std::str::FromStr;
+
+use bigdecimal::BigDecimal;
+use indexmap::IndexMap as Map;
+use ordered_float::OrderedFloat;
+use yaml_rust::Yaml;
+
+use crate::value::PqlValue;
+
+const MERGE_KEY: &str = "<<";
+
+pub fn from_str(input: &str) -> anyhow::Result<PqlValue> {
+    let mut docs = yaml_rust::YamlLoader::load_from_str(input)?;
+    match docs.len() {
+        0 => anyhow::bail!("no YAML document"),
+        1 => Ok(to_pqlvalue(docs.remove(0))),
+        _ => Ok(PqlValue::bag(docs.into_iter().map(to_pqlvalue).collect())),
+    }
+}
+
+pub fn to_string(value: &PqlValue) -> anyhow::Result<String> {
+    let docs = if value.is_bag() {
+        match value.to_owned().unbag() {
+            PqlValue::Array(docs) => docs,
+            _ => unreachable!(),
+        }
+    } else {
+        vec![value.to_owned()]
+    };
+    let docs = docs
+        .iter()
+        .map(|doc| {
+            let doc = serde_yaml::to_string(doc)?;
+            Ok(doc.trim_start_matches("---\n").trim_end().to_owned())
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(format!("{}\n", docs.join("\n---\n")))
+}
+
+pub fn to_pqlvalue(yaml: Yaml) -> PqlValue {
+    match yaml {
+        Yaml::Null => PqlValue::Null,
+        Yaml::BadValue => PqlValue::Null,
+        Yaml::String(string) => PqlValue::Str(string),
+        Yaml::Boolean(boolean) => PqlValue::Boolean(boolean),
+        Yaml::Integer(int) => PqlValue::Int(int),
+        Yaml::Real(real) => real_to_pqlvalue(&real),
+        Yaml::Array(array) => {
+            PqlValue::Array(array.into_iter().map(to_pqlvalue).collect::<Vec<_>>())
+        }
+        Yaml::Hash(hash) => {
+            let merge_key = Yaml::String(MERGE_KEY.to_owned());
+            let explicit = hash
+                .keys()
+                .filter(|k| k != &&merge_key)
+                .map(|k| key_to_string(k.to_owned()))
+                .collect::<HashSet<_>>();
+            let mut map = Map::new();
+            for (k, v) in hash {
+                if k != merge_key {
+                    map.insert(key_to_string(k), to_pqlvalue(v));
+                    continue;
+                }
+                // Earlier mappings of a merged sequence take precedence over later ones.
+                let sources = match v {
+                    Yaml::Array(array) => array,
+                    v => vec![v],
+                };
+                for source in sources {
+                    if let PqlValue::Object(source) = to_pqlvalue(source) {
+                        for (k, v) in source {
+                            if !explicit.contains(&k) && !map.contains_key(&k) {
+                                map.insert(k, v);
+                            }
+                        }
+                    }
+                }
+            }
+            PqlValue::Object(map)
+        }
+        Yaml::Alias(_) => PqlValue::Null,
+    }
+}
+
+// yaml-rust keeps the source text of every number that does not fit in i64,
+// so integers of any size are recovered exactly as integral decimals.
+fn real_to_pqlvalue(real: &str) -> PqlValue {
+    let digits = real.trim_start_matches('+');
+    let is_integer = digits
+        .trim_start_matches('-')
+        .chars()
+        .all(|c| c.is_ascii_digit());
+    if is_integer {
+        if let Ok(decimal) = BigDecimal::from_str(digits) {
+            return PqlValue::Decimal(decimal);
+        }
+    }
+    let float = match real {
+        ".inf" | ".Inf" | ".INF" | "+.inf" | "+.Inf" | "+.INF" => f64::INFINITY,
+        "-.inf" | "-.Inf" | "-.INF" => f64::NEG_INFINITY,
+        ".nan" | "NaN" | ".NAN" => f64::NAN,
+        _ => real.parse::<f64>().unwrap_or(f64::NAN),
+    };
+    PqlValue::Float(OrderedFloat(float))
+}
+
+fn key_to_string(key: Yaml) -> String {
+    match key {
+        Yaml::String(string) => string,
+        Yaml::Integer(int) => int.to_string(),
+        Yaml::Real(real) => real,
+        Yaml::Boolean(boolean) => boolean.to_string(),
+        Yaml::Null => String::from("null"),
+        _ => String::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use super::{from_str, to_string};
+    use crate::lang::Lang;
+    use crate::planner::evaluate;
+    use crate::sql::Sql;
+    use crate::value::PqlValue;
+
+    const MANIFESTS: &str = r#"
+kind: Deployment
+metadata:
+  name: web
+  defaults: &defaults
+    app: web
+    tier: front
+  labels:
+    <<: *defaults
+    tier: back
+  selector: *defaults
+---
+kind: Service
+metadata:
+  name: web
+  labels:
+    <<: [{ app: svc }, { app: other, port: 80 }]
+"#;
+
+    #[test]
+    fn reads_streams() -> anyhow::Result<()> {
+        let docs = from_str(MANIFESTS)?;
+        assert!(docs.is_bag());
+        let expected = Lang::from_as_json(
+            r#"[
+                {
+                    "kind": "Deployment",
+                    "metadata": {
+                        "name": "web",
+                        "defaults": { "app": "web", "tier": "front" },
+                        "labels": { "app": "web", "tier": "back" },
+                        "selector": { "app": "web", "tier": "front" }
+                    }
+                },
+                { "kind": "Service", "metadata": { "name": "web", "labels": { "app": "svc", "port": 80 } } }
+            ]"#,
+        )?;
+        assert_eq!(docs.unbag(), expected.data);
+
+        assert!(!from_str("a: 1")?.is_bag());
+        assert!(from_str("").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn query_documents() -> anyhow::Result<()> {
+        let sql = Sql::from_str(
+            "SELECT m.metadata.name AS name, m.metadata.labels.tier AS tier FROM docs m WHERE m.kind = 'Deployment'",
+        )?;
+        let result = evaluate(sql, from_str(MANIFESTS)?);
+        assert_eq!(
+            result,
+            PqlValue::from_str(r#"[{ "name": "web", "tier": "back" }]"#)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn writes_streams() -> anyhow::Result<()> {
+        let docs = from_str("a: 1\n---\n- b\n")?;
+        assert_eq!(to_string(&docs)?, "a: 1\n---\n- b\n");
+        assert_eq!(to_string(&docs.unbag())?, "- a: 1\n- - b\n");
+        Ok(())
+    }
+}
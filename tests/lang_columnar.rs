@@ -0,0 +1,69 @@
+#![cfg(feature = "columnar")]
+
+use piqel::engine;
+use piqel::lang::{Lang, LangType};
+
+const ROWS: &str = r#"[
+  { "name": "apple", "price": 1.5, "qty": 3, "origin": { "country": "jp" } },
+  { "name": "pear", "price": 2.25, "qty": 0, "origin": { "country": "fr" } }
+]"#;
+
+#[test]
+fn query_parquet() -> anyhow::Result<()> {
+    let data = engine::loads(ROWS, "json")?;
+    let bytes = engine::dumps_bytes(data.to_owned(), "parquet")?;
+    let lang = Lang::from_bytes(&bytes)?;
+    assert_eq!(lang.from, LangType::Parquet);
+    assert_eq!(lang.data.unbag(), data);
+
+    let output = engine::evaluate_bytes(
+        "SELECT name, origin.country AS country WHERE name = 'pear'",
+        &bytes,
+        "parquet",
+        "json",
+    )?;
+    assert_eq!(
+        String::from_utf8(output)?,
+        r#"[{"name":"pear","country":"fr"}]"#
+    );
+    Ok(())
+}
+
+#[test]
+fn query_parquet_from_docs() -> anyhow::Result<()> {
+    let bytes = engine::dumps_bytes(engine::loads(ROWS, "json")?, "parquet")?;
+    let output = engine::evaluate_bytes(
+        "SELECT r.name AS name FROM docs r WHERE r.price > 1",
+        &bytes,
+        "parquet",
+        "json",
+    )?;
+    assert_eq!(
+        String::from_utf8(output)?,
+        r#"[{"name":"apple"},{"name":"pear"}]"#
+    );
+
+    let output = engine::evaluate_bytes(
+        "SELECT name, qty ORDER BY qty ASC",
+        &bytes,
+        "parquet",
+        "json",
+    )?;
+    assert_eq!(
+        String::from_utf8(output)?,
+        r#"[{"name":"pear","qty":0},{"name":"apple","qty":3}]"#
+    );
+    Ok(())
+}
+
+#[test]
+fn query_arrow() -> anyhow::Result<()> {
+    let data = engine::loads(ROWS, "json")?;
+    let bytes = engine::dumps_bytes(data.to_owned(), "arrow")?;
+    assert_eq!(Lang::from_bytes(&bytes)?.from, LangType::Arrow);
+
+    let output = engine::evaluate_bytes("SELECT qty", &bytes, "arrow", "parquet")?;
+    let rows = engine::loads_bytes(&output, "parquet")?;
+    assert_eq!(engine::dumps(rows, "json")?, r#"[{"qty":3},{"qty":0}]"#);
+    Ok(())
+}
@@ -0,0 +1,46 @@
+use piqel::engine;
+use piqel::lang::{Lang, LangType};
+use piqel::value::PqlValue;
+
+const ION: &str = r#"
+$ion_1_0
+{
+  hr: {
+    employees: [
+      { id: 3, name: "Bob Smith", salary: 1234.50, hired: 2019-04-01T, team: eng },
+      { id: 4, name: "Susan Smith", salary: 5678.25, hired: 2020-10-15T, team: ops },
+    ],
+  },
+}
+"#;
+
+#[test]
+fn query_ion() -> anyhow::Result<()> {
+    let output = engine::evaluate(
+        "SELECT e.name AS name, e.salary AS salary, e.team AS team FROM hr.employees AS e WHERE e.name = 'Bob Smith'",
+        ION,
+        "ion",
+        "ion",
+    )?;
+    assert_eq!(output, r#"[{name:"Bob Smith",salary:1234.50,team:eng}]"#);
+    Ok(())
+}
+
+#[test]
+fn ion_binary_round_trip() -> anyhow::Result<()> {
+    let data = engine::loads(ION, "ion")?;
+    let bytes = engine::dumps_bytes(data.to_owned(), "ion_binary")?;
+    assert_eq!(engine::loads_bytes(&bytes, "ion_binary")?, data);
+
+    let lang = Lang::from_bytes(&bytes)?;
+    assert_eq!(lang.from, LangType::IonBinary);
+    assert!(lang.to_string(true).is_err());
+
+    let json = engine::dumps(data, "json")?;
+    assert!(json.contains(r#""hired":"2019-04-01""#));
+    assert!(matches!(
+        engine::loads(&json, "json")?,
+        PqlValue::Object(_)
+    ));
+    Ok(())
+}
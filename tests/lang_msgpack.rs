@@ -0,0 +1,37 @@
+use piqel::engine;
+use piqel::lang::{Lang, LangType};
+
+const JSON: &str = r#"[
+  { "id": 3, "name": "Bob Smith", "salary": 1234.5, "tags": ["eng"] },
+  { "id": 4, "name": "Susan Smith", "salary": 5678.25, "tags": [] }
+]"#;
+
+#[test]
+fn msgpack_and_cbor_round_trip() -> anyhow::Result<()> {
+    let data = engine::loads(JSON, "json")?;
+    for format in &["msgpack", "cbor"] {
+        let bytes = engine::dumps_bytes(data.to_owned(), format)?;
+        assert_eq!(engine::loads_bytes(&bytes, format)?, data);
+        assert!(Lang::from_as(JSON, LangType::Msgpack).is_err());
+        assert!(engine::dumps(data.to_owned(), format).is_err());
+    }
+    Ok(())
+}
+
+#[test]
+fn query_msgpack() -> anyhow::Result<()> {
+    let data = engine::loads(JSON, "json")?;
+    let bytes = engine::dumps_bytes(data, "msgpack")?;
+    let output = engine::evaluate_bytes(
+        "SELECT name WHERE name = 'Susan Smith'",
+        &bytes,
+        "msgpack",
+        "cbor",
+    )?;
+    let result = engine::loads_bytes(&output, "cbor")?;
+    assert_eq!(
+        engine::dumps(result, "json")?,
+        r#"[{"name":"Susan Smith"}]"#
+    );
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use std::str::FromStr;
+
+use piqel::engine;
+use piqel::lang::{Lang, LangType};
+
+#[test]
+fn query_partiql_sample() -> anyhow::Result<()> {
+    let input = std::fs::read_to_string("samples/q1.env")?;
+    let sql = std::fs::read_to_string("samples/q1.sql")?;
+    let output = engine::evaluate(&sql, &input, "partiql", "partiql")?;
+    assert_eq!(
+        output,
+        "<<{'id':4,'employeeName':'Susan Smith','title':'Dev Mgr'}>>"
+    );
+    Ok(())
+}
+
+#[test]
+fn partiql_is_detected() -> anyhow::Result<()> {
+    let input = std::fs::read_to_string("samples/q1.env")?;
+    let mut lang = Lang::from_str(&input)?;
+    assert_eq!(lang.from, LangType::Partiql);
+
+    lang.text = String::default();
+    let output = lang.to_string(false)?;
+    assert!(output.starts_with("{\n  'hr': {\n    'employees': <<\n"));
+    assert_eq!(Lang::from_as_partiql(&output)?.data, lang.data);
+
+    let json = engine::dumps(lang.data, "json")?;
+    assert!(json.starts_with(r#"{"hr":{"employees":[{"id":3,"#));
+    Ok(())
+}
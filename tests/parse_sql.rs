@@ -290,3 +290,37 @@ fn q12() -> anyhow::Result<()> {
     );
     Ok(())
 }
+
+#[test]
+fn string_operand() -> anyhow::Result<()> {
+    let sql = Sql::from_str("SELECT 'abc' + 1 AS w, a")?;
+    assert_eq!(
+        sql.select_clause,
+        vec![
+            Field {
+                expr: Expr::Add(
+                    Box::new(Expr::Value(PqlValue::from("abc"))),
+                    Box::new(Expr::from(1.))
+                ),
+                alias: Some("w".to_owned()),
+            },
+            Field::from_str("a")?,
+        ]
+    );
+    Ok(())
+}
+
+#[test]
+fn whole_query_is_parsed() -> anyhow::Result<()> {
+    assert_eq!(
+        Sql::from_str("FROM docs d SELECT d.a")?,
+        Sql::from_str("SELECT d.a FROM docs d")?
+    );
+    assert_eq!(
+        Sql::from_str("SELECT d.a FROM docs d;\n")?,
+        Sql::from_str("SELECT d.a FROM docs d")?
+    );
+    assert!(Sql::from_str("SELECT a WHERE a + 1 > 2 * 3").is_err());
+    assert!(Sql::from_str("SELECT a FROM docs d ORDER a").is_err());
+    Ok(())
+}